@@ -0,0 +1,22 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum ContextCommands {
+    /// Define or replace a context from a filter
+    Define {
+        name: String,
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        filter: Vec<String>,
+    },
+    /// Delete a context
+    Delete { name: String },
+    /// List defined contexts
+    List,
+    /// Show the active context
+    Show,
+    /// Clear the active context
+    None,
+    /// Make a defined context active
+    #[command(external_subcommand)]
+    Use(Vec<String>),
+}
@@ -1,5 +1,7 @@
 mod config;
+mod context;
 mod doc;
+mod project;
 mod state;
 mod sync;
 mod system;
@@ -7,11 +9,13 @@ mod task;
 
 use crate::core::{EddaConfig, EddaError, EddaResult, init_logging, load_config, validate_config};
 
+pub use context::ContextCommands;
 pub use doc::DocCommands;
+pub use project::ProjectCommands;
 pub use state::StateCommands;
 pub use sync::{GitHubSyncCommands, SyncCommands};
-pub use system::{ConfigCommands, SystemCommands};
-pub use task::TaskCommands;
+pub use system::{BackupCommands, ConfigCommands, MigrateCommands, SystemCommands};
+pub use task::{BulkArgs, IntervalCommands, TaskCommands};
 
 use clap::Parser;
 use std::path::PathBuf;
@@ -43,6 +47,10 @@ pub struct Cli {
     #[arg(long)]
     pub verbose: bool,
 
+    /// Context to apply instead of the active one ("none" for no context)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub context: Option<String>,
+
     /// Subcommand to run
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -65,6 +73,26 @@ pub enum Commands {
         #[command(subcommand)]
         subcommand: StateCommands,
     },
+    /// Project commands
+    Project {
+        #[command(subcommand)]
+        subcommand: ProjectCommands,
+    },
+    /// Context management commands
+    Context {
+        #[command(subcommand)]
+        subcommand: ContextCommands,
+    },
+    /// Full-text search over tasks and documents, best matches first
+    Search {
+        /// Words to search for; all must match, and a trailing `*` matches
+        /// a prefix
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        terms: Vec<String>,
+        /// Maximum number of results
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
     /// Query engine
     Query { query: String },
     /// System commands
@@ -99,6 +127,10 @@ pub fn parse_args() -> EddaResult<(Cli, EddaConfig)> {
         config.log_level = "debug".to_string();
     }
 
+    if let Some(context) = &cli.context {
+        config.context = (context != "none").then(|| context.clone());
+    }
+
     // Validate configuration
     validate_config(&config)?;
 
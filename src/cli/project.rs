@@ -0,0 +1,16 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum ProjectCommands {
+    /// List projects as a tree with their open task counts
+    List {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// Show task counts, completion, remaining effort and overdue tasks per
+    /// project, rolled up through the project tree
+    Summary {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+}
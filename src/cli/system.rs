@@ -5,10 +5,22 @@ use std::path::PathBuf;
 pub enum SystemCommands {
     /// Initialize Edda data directory
     Init,
-    /// Create backup of data
-    Backup,
-    /// Restore from backup
-    Restore { backup: PathBuf },
+    /// Back up the database to data_dir/backups and rotate old backups
+    Backup {
+        #[command(subcommand)]
+        subcommand: Option<BackupCommands>,
+        /// Compress the backup with gzip (default: backup.compress)
+        #[arg(long)]
+        compress: bool,
+        /// Encrypt the backup (default: backup.encrypt)
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Restore the database from a backup, saving the current one first
+    Restore {
+        /// Backup file, or the name of one in data_dir/backups
+        backup: PathBuf,
+    },
     /// Configuration management
     Config {
         #[command(subcommand)]
@@ -16,8 +28,50 @@ pub enum SystemCommands {
     },
     /// Show system status
     Status,
-    /// Clean up temporary files
+    /// Run maintenance: generate recurring instances, wake waiting tasks
+    /// and expire tasks past their until date
     Cleanup,
+    /// Database schema migrations
+    Migrate {
+        #[command(subcommand)]
+        subcommand: MigrateCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    /// List backups, newest first
+    List,
+    /// Remove regular backups outside the retention policy
+    Prune {
+        /// Show what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommands {
+    /// Show applied and pending migrations
+    Status,
+    /// Apply pending migrations
+    Up {
+        /// Stop at this schema version instead of the latest
+        #[arg(long)]
+        to: Option<i32>,
+        /// Show what would be applied without changing the database
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Revert applied migrations
+    Down {
+        /// Schema version to revert to (default: the previous version)
+        #[arg(long)]
+        to: Option<i32>,
+        /// Show what would be reverted without changing the database
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -1,31 +1,270 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Subcommand)]
 pub enum TaskCommands {
-    /// Add a new task
-    Add { description: String },
-    /// List tasks
-    List { query: Option<String> },
+    /// Add a new task (e.g. `Fix login project:web +bug priority:H due:fri`)
+    Add {
+        /// Description words, `key:value` attributes and `+tag`s
+        #[arg(required = true)]
+        description: Vec<String>,
+        /// Due date (e.g. `2026-11-01`, `tomorrow`, `eow`, `+3d`)
+        #[arg(long)]
+        due: Option<String>,
+        /// Date the task should be started
+        #[arg(long)]
+        scheduled: Option<String>,
+        /// Hide the task until this date
+        #[arg(long)]
+        wait: Option<String>,
+        /// Date after which a recurring task stops repeating
+        #[arg(long)]
+        until: Option<String>,
+        /// Make the new task a subtask of this task (ID or UUID)
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// List tasks, optionally filtered (e.g. `project:web +bug or priority:H`)
+    List {
+        /// Sort keys replacing the report's, e.g. `urgency` or `due+,project-`
+        #[arg(long)]
+        sort: Option<String>,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// Run a named report (e.g. `next`, `overdue` or one declared in the
+    /// config), optionally narrowed by a filter
+    Report {
+        name: String,
+        /// Sort keys replacing the report's, e.g. `urgency` or `due+,project-`
+        #[arg(long)]
+        sort: Option<String>,
+        /// Show at most this many tasks
+        #[arg(long)]
+        limit: Option<usize>,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// List the available reports
+    Reports,
     /// Get task information
     Get { id: String },
-    /// Modify task
-    Modify {
+    /// Show a task with its subtasks and their rolled-up effort
+    Tree { id: String },
+    /// Show a task's urgency
+    Urgency {
         id: String,
-        field: String,
-        value: String,
+        /// Break the urgency down into the terms that make it up
+        #[arg(long)]
+        explain: bool,
+    },
+    /// Modify tasks (e.g. `modify 3-5 priority:H +bug -later depends:3`);
+    /// bare words replace the description
+    Modify {
+        /// Task IDs, ranges, UUID prefixes or a quoted filter
+        filter: String,
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        modifications: Vec<String>,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
+    /// Mark tasks as done
+    Done {
+        /// Task IDs, ranges (`3-7`), UUID prefixes or a filter
+        #[arg(required = true)]
+        filter: Vec<String>,
+        /// Complete even if dependencies or subtasks are still open
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
+    /// Delete tasks
+    Delete {
+        /// Task IDs, ranges (`3-7`), UUID prefixes or a filter
+        #[arg(required = true)]
+        filter: Vec<String>,
+        #[command(flatten)]
+        bulk: BulkArgs,
     },
-    /// Mark task as done
-    Done { id: String },
-    /// Delete task
-    Delete { id: String },
     /// Start time tracking
-    Start { id: String },
+    Start {
+        /// Task IDs, ranges (`3-7`), UUID prefixes or a filter
+        #[arg(required = true)]
+        filter: Vec<String>,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
     /// Stop time tracking
-    Stop { id: String },
+    Stop {
+        /// Task IDs, ranges (`3-7`), UUID prefixes or a filter
+        #[arg(required = true)]
+        filter: Vec<String>,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
     /// Add annotation
-    Annotate { id: String, note: String },
+    Annotate {
+        /// Task IDs, ranges, UUID prefixes or a quoted filter
+        filter: String,
+        note: String,
+        /// Who is leaving the note; defaults to the `author` setting
+        #[arg(long)]
+        author: Option<String>,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
+    /// Remove an annotation, given by its number in `get` output or its text
+    Denotate { id: String, annotation: String },
+    /// Replace the text of an annotation, given by its number or its text
+    Reannotate {
+        id: String,
+        annotation: String,
+        note: String,
+    },
     /// Add tag
-    Tag { id: String, tag: String },
+    Tag {
+        /// Task IDs, ranges, UUID prefixes or a quoted filter
+        filter: String,
+        tag: String,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
     /// Remove tag
-    Untag { id: String, tag: String },
+    Untag {
+        /// Task IDs, ranges, UUID prefixes or a quoted filter
+        filter: String,
+        tag: String,
+        #[command(flatten)]
+        bulk: BulkArgs,
+    },
+    /// Make a task depend on other tasks (IDs or UUIDs)
+    Depend {
+        id: String,
+        #[arg(required = true)]
+        depends_on: Vec<String>,
+    },
+    /// Remove dependencies from a task (IDs or UUIDs)
+    Undepend {
+        id: String,
+        #[arg(required = true)]
+        depends_on: Vec<String>,
+    },
+    /// Show the most urgent task that is ready to work on (pending,
+    /// unblocked and unclaimed), optionally filtered
+    Next {
+        /// Atomically start the task and hold it for the agent until the
+        /// lease runs out; the claimed task is printed as JSON
+        #[arg(long)]
+        claim: bool,
+        /// Agent claiming the task; defaults to the `author` setting
+        #[arg(long)]
+        agent: Option<String>,
+        /// How long the claim lasts without a heartbeat (e.g. `15m`, `1h`)
+        #[arg(long, default_value = "15m")]
+        lease: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// Extend the lease on a claimed task
+    Heartbeat {
+        id: String,
+        /// Agent holding the claim; defaults to the `author` setting
+        #[arg(long)]
+        agent: Option<String>,
+        /// New lease, counted from now
+        #[arg(long, default_value = "15m")]
+        lease: String,
+    },
+    /// Give up a claim, returning the task to pending
+    Release {
+        id: String,
+        /// Agent holding the claim; defaults to the `author` setting
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    /// List open tasks that are waiting on open dependencies
+    Blocked {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// List open tasks that other open tasks depend on
+    Blocking {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// List pending tasks with no open dependencies (ready to work)
+    Unblocked {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// Report tracked time by day, week, project or tag
+    Timesheet {
+        /// Grouping: day, week, project or tag
+        #[arg(long, default_value = "day")]
+        by: String,
+        /// Only count time from this date on
+        #[arg(long)]
+        from: Option<String>,
+        /// Only count time before this date
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Manage recorded time intervals
+    Interval {
+        #[command(subcommand)]
+        subcommand: IntervalCommands,
+    },
+    /// Revert the most recent task changes
+    Undo {
+        /// Number of changes to revert
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+    /// Show the change history of a task
+    History { id: String },
+    /// Export tasks as Taskwarrior-compatible JSON, optionally filtered
+    Export {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+    },
+    /// Import tasks from Taskwarrior JSON (`task export` output), updating
+    /// tasks that already exist by UUID
+    Import {
+        /// File to read; standard input when omitted or `-`
+        file: Option<PathBuf>,
+    },
+}
+
+/// Options for commands that can change many tasks at once
+#[derive(Args, Clone, Copy, Debug, Default)]
+pub struct BulkArgs {
+    /// Show what would change without saving anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Don't ask for confirmation when many tasks are selected
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+}
+
+#[derive(Subcommand)]
+pub enum IntervalCommands {
+    /// List recorded intervals, optionally for one task
+    List { id: Option<String> },
+    /// Record a finished interval for a task
+    Add {
+        id: String,
+        start: String,
+        end: String,
+    },
+    /// Change the start or end of an interval
+    Modify {
+        interval_id: String,
+        #[arg(long)]
+        start: Option<String>,
+        #[arg(long)]
+        end: Option<String>,
+    },
+    /// Delete an interval
+    Delete { interval_id: String },
 }
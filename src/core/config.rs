@@ -1,5 +1,11 @@
-use crate::core::error::{ConfigError, EddaResult};
+use crate::core::date::DateParser;
+use crate::core::error::{ConfigError, EddaResult, TaskError};
+use crate::core::filter::FilterExpr;
+use crate::core::report::{ReportDefinition, builtin_reports};
+use crate::core::uda::{UdaDefinition, UdaType};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Main configuration structure for Edda
@@ -17,6 +23,10 @@ pub struct EddaConfig {
     #[serde(default = "default_output_format")]
     pub output_format: String,
 
+    /// IANA timezone used to resolve relative dates such as `today` or `eow`
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+
     /// GitHub sync configuration
     #[serde(default)]
     pub github: GitHubConfig,
@@ -24,6 +34,49 @@ pub struct EddaConfig {
     /// Database configuration
     #[serde(default)]
     pub database: DatabaseConfig,
+
+    /// Recurring task configuration
+    #[serde(default)]
+    pub recurrence: RecurrenceConfig,
+
+    /// Bulk command configuration
+    #[serde(default)]
+    pub bulk: BulkConfig,
+
+    /// Subtask cascading rules
+    #[serde(default)]
+    pub subtasks: SubtaskConfig,
+
+    /// Backup format and retention
+    #[serde(default)]
+    pub backup: BackupConfig,
+
+    /// User-defined attributes, declared as `[uda.<name>]` tables
+    #[serde(default)]
+    pub uda: BTreeMap<String, UdaDefinition>,
+
+    /// Urgency coefficients
+    #[serde(default)]
+    pub urgency: UrgencyConfig,
+
+    /// Named reports, declared as `[report.<name>]` tables; these replace
+    /// built-in reports of the same name
+    #[serde(default)]
+    pub report: BTreeMap<String, ReportDefinition>,
+
+    /// Active context, whose filter narrows task listings and reports;
+    /// `EDDA_CONTEXT` or `--context` override it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+
+    /// Context filters by name, declared in the `[contexts]` table
+    #[serde(default)]
+    pub contexts: BTreeMap<String, String>,
+
+    /// Name recorded on new annotations; `EDDA_AUTHOR` or `--author`
+    /// override it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
 }
 
 /// GitHub-specific configuration
@@ -61,14 +114,124 @@ pub struct DatabaseConfig {
     pub max_connections: u32,
 }
 
+/// Recurring task configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurrenceConfig {
+    /// How far ahead (in days) recurring instances are generated
+    #[serde(default = "default_recurrence_horizon_days")]
+    pub horizon_days: u32,
+}
+
+/// Bulk command configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkConfig {
+    /// Ask for confirmation when a command would change more tasks than
+    /// this (0 never asks)
+    #[serde(default = "default_bulk_confirm_threshold")]
+    pub confirm_threshold: usize,
+}
+
+/// How changes to subtasks and their parents cascade
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtaskConfig {
+    /// Complete a parent once all of its subtasks are completed
+    #[serde(default)]
+    pub complete_parent: bool,
+
+    /// Refuse to complete a parent while any of its subtasks is open
+    #[serde(default = "default_true")]
+    pub block_parent: bool,
+
+    /// Delete a task's subtasks along with it
+    #[serde(default = "default_true")]
+    pub delete_children: bool,
+}
+
+/// Backup format and retention
+///
+/// Regular backups are rotated after each new one: the newest backup of each
+/// of the last `keep_daily` days and of each of the last `keep_weekly` ISO
+/// weeks is kept, along with the newest backup overall.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupConfig {
+    /// Compress new backups with gzip
+    #[serde(default)]
+    pub compress: bool,
+
+    /// Encrypt new backups, including the copies taken before migrations
+    /// and restores; the key comes from `key_file`, `EDDA_BACKUP_KEY_FILE`
+    /// or `EDDA_BACKUP_PASSPHRASE`
+    #[serde(default)]
+    pub encrypt: bool,
+
+    /// File holding the backup encryption key (at least 32 bytes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+
+    /// Days to keep a backup for
+    #[serde(default = "default_backup_keep_daily")]
+    pub keep_daily: usize,
+
+    /// Weeks to keep a backup for
+    #[serde(default = "default_backup_keep_weekly")]
+    pub keep_weekly: usize,
+}
+
+/// Urgency coefficients, modelled on Taskwarrior's `urgency.*` settings
+///
+/// Each term's factor (between 0 and 1) is multiplied by its coefficient and
+/// the products are summed; negative coefficients lower urgency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrgencyConfig {
+    /// Due date, rising from 0.2 two weeks out to 1.0 a week overdue
+    pub due: f64,
+    /// Other open tasks depend on this one
+    pub blocking: f64,
+    /// The task waits on open dependencies
+    pub blocked: f64,
+    /// The scheduled date has passed
+    pub scheduled: f64,
+    /// The task is started
+    pub active: f64,
+    /// Age, reaching its full weight at `age_max_days`
+    pub age: f64,
+    pub age_max_days: u32,
+    /// Number of annotations (0.8 for one, 0.9 for two, 1.0 for more)
+    pub annotations: f64,
+    /// Number of tags, scaled like annotations
+    pub tags: f64,
+    /// The task has a project
+    pub project: f64,
+    /// The task is waiting
+    pub waiting: f64,
+    /// Coefficient per priority value (`H`, `M`, `L` or `0`-`9`)
+    pub priority: BTreeMap<String, f64>,
+    /// Coefficient per tag, e.g. `next = 15.0`
+    pub user_tag: BTreeMap<String, f64>,
+    /// Coefficient per project, also applied to its subprojects
+    pub user_project: BTreeMap<String, f64>,
+}
+
 impl Default for EddaConfig {
     fn default() -> Self {
         Self {
             data_dir: default_data_dir(),
             log_level: default_log_level(),
             output_format: default_output_format(),
+            timezone: default_timezone(),
             github: GitHubConfig::default(),
             database: DatabaseConfig::default(),
+            recurrence: RecurrenceConfig::default(),
+            bulk: BulkConfig::default(),
+            subtasks: SubtaskConfig::default(),
+            backup: BackupConfig::default(),
+            uda: BTreeMap::new(),
+            urgency: UrgencyConfig::default(),
+            report: BTreeMap::new(),
+            context: None,
+            contexts: BTreeMap::new(),
+            author: None,
         }
     }
 }
@@ -94,9 +257,162 @@ impl Default for DatabaseConfig {
     }
 }
 
+impl Default for RecurrenceConfig {
+    fn default() -> Self {
+        Self {
+            horizon_days: default_recurrence_horizon_days(),
+        }
+    }
+}
+
+impl Default for BulkConfig {
+    fn default() -> Self {
+        Self {
+            confirm_threshold: default_bulk_confirm_threshold(),
+        }
+    }
+}
+
+impl Default for SubtaskConfig {
+    fn default() -> Self {
+        Self {
+            complete_parent: false,
+            block_parent: true,
+            delete_children: true,
+        }
+    }
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            compress: false,
+            encrypt: false,
+            key_file: None,
+            keep_daily: default_backup_keep_daily(),
+            keep_weekly: default_backup_keep_weekly(),
+        }
+    }
+}
+
+impl Default for UrgencyConfig {
+    fn default() -> Self {
+        Self {
+            due: 12.0,
+            blocking: 8.0,
+            blocked: -5.0,
+            scheduled: 5.0,
+            active: 4.0,
+            age: 2.0,
+            age_max_days: 365,
+            annotations: 1.0,
+            tags: 1.0,
+            project: 1.0,
+            waiting: -3.0,
+            priority: BTreeMap::from([
+                ("H".to_string(), 6.0),
+                ("M".to_string(), 3.9),
+                ("L".to_string(), 1.8),
+            ]),
+            user_tag: BTreeMap::from([("next".to_string(), 15.0)]),
+            user_project: BTreeMap::new(),
+        }
+    }
+}
+
+impl UrgencyConfig {
+    /// Scalar coefficients with their keys
+    pub fn coefficients(&self) -> [(&'static str, f64); 10] {
+        [
+            ("due", self.due),
+            ("blocking", self.blocking),
+            ("blocked", self.blocked),
+            ("scheduled", self.scheduled),
+            ("active", self.active),
+            ("age", self.age),
+            ("annotations", self.annotations),
+            ("tags", self.tags),
+            ("project", self.project),
+            ("waiting", self.waiting),
+        ]
+    }
+
+    /// Coefficient tables with their keys
+    pub fn tables(&self) -> [(&'static str, &BTreeMap<String, f64>); 3] {
+        [
+            ("priority", &self.priority),
+            ("user_tag", &self.user_tag),
+            ("user_project", &self.user_project),
+        ]
+    }
+
+    /// Mutable reference to a scalar coefficient by its key
+    fn coefficient_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "due" => Some(&mut self.due),
+            "blocking" => Some(&mut self.blocking),
+            "blocked" => Some(&mut self.blocked),
+            "scheduled" => Some(&mut self.scheduled),
+            "active" => Some(&mut self.active),
+            "age" => Some(&mut self.age),
+            "annotations" => Some(&mut self.annotations),
+            "tags" => Some(&mut self.tags),
+            "project" => Some(&mut self.project),
+            "waiting" => Some(&mut self.waiting),
+            _ => None,
+        }
+    }
+
+    /// Coefficient tables keyed by priority, tag or project
+    fn table_mut(&mut self, name: &str) -> Option<&mut BTreeMap<String, f64>> {
+        match name {
+            "priority" => Some(&mut self.priority),
+            "user_tag" => Some(&mut self.user_tag),
+            "user_project" => Some(&mut self.user_project),
+            _ => None,
+        }
+    }
+}
+
 impl EddaConfig {
+    /// Date parser resolving relative expressions in the configured timezone
+    pub fn date_parser(&self) -> DateParser {
+        DateParser::new(self.timezone.parse::<Tz>().unwrap_or(Tz::UTC))
+    }
+
+    /// Built-in reports merged with the ones declared in the config
+    pub fn reports(&self) -> BTreeMap<String, ReportDefinition> {
+        let mut reports = builtin_reports();
+        reports.extend(self.report.clone());
+        reports
+    }
+
+    /// Filter of the active context, if one is set
+    pub fn context_filter(&self) -> Option<&str> {
+        self.context
+            .as_ref()
+            .and_then(|name| self.contexts.get(name))
+            .map(String::as_str)
+    }
+
+    /// Define or replace a context after checking its name and filter
+    pub fn define_context(&mut self, name: &str, filter: &str) -> EddaResult<()> {
+        validate_context(name, filter, self)?;
+        self.contexts.insert(name.to_string(), filter.to_string());
+        Ok(())
+    }
+
     /// Set a configuration value by key
     pub fn set_value(&mut self, key: &str, value: &str) -> EddaResult<()> {
+        if let Some(report_key) = key.strip_prefix("report.") {
+            return self.set_report_value(report_key, value);
+        }
+        if let Some(uda_key) = key.strip_prefix("uda.") {
+            return self.set_uda_value(uda_key, value);
+        }
+        if let Some(urgency_key) = key.strip_prefix("urgency.") {
+            return self.set_urgency_value(urgency_key, value);
+        }
         match key {
             "data_dir" => {
                 self.data_dir = PathBuf::from(value);
@@ -121,6 +437,15 @@ impl EddaConfig {
                 }
                 self.output_format = value.to_string();
             }
+            "timezone" => {
+                value.parse::<Tz>().map_err(|_| ConfigError::Validation {
+                    message: format!("Invalid timezone: {}", value),
+                })?;
+                self.timezone = value.to_string();
+            }
+            "author" => {
+                self.author = (!value.trim().is_empty()).then(|| value.trim().to_string());
+            }
             "database.url" => {
                 self.database.url = value.to_string();
             }
@@ -133,6 +458,56 @@ impl EddaConfig {
             "github.repository" => {
                 self.github.repository = Some(value.to_string());
             }
+            "recurrence.horizon_days" => {
+                let days = value.parse::<u32>().map_err(|_| ConfigError::Validation {
+                    message: format!("Invalid horizon_days value: {}", value),
+                })?;
+                self.recurrence.horizon_days = days;
+            }
+            "bulk.confirm_threshold" => {
+                let threshold = value
+                    .parse::<usize>()
+                    .map_err(|_| ConfigError::Validation {
+                        message: format!("Invalid confirm_threshold value: {}", value),
+                    })?;
+                self.bulk.confirm_threshold = threshold;
+            }
+            "backup.compress" | "backup.encrypt" => {
+                let enabled = value.parse::<bool>().map_err(|_| ConfigError::Validation {
+                    message: format!("Invalid {key} value: {value} (expected true or false)"),
+                })?;
+                if key == "backup.compress" {
+                    self.backup.compress = enabled;
+                } else {
+                    self.backup.encrypt = enabled;
+                }
+            }
+            "backup.key_file" => {
+                self.backup.key_file =
+                    (!value.trim().is_empty()).then(|| PathBuf::from(value.trim()));
+            }
+            "backup.keep_daily" | "backup.keep_weekly" => {
+                let keep = value
+                    .parse::<usize>()
+                    .map_err(|_| ConfigError::Validation {
+                        message: format!("Invalid {key} value: {value}"),
+                    })?;
+                if key == "backup.keep_daily" {
+                    self.backup.keep_daily = keep;
+                } else {
+                    self.backup.keep_weekly = keep;
+                }
+            }
+            "subtasks.complete_parent" | "subtasks.block_parent" | "subtasks.delete_children" => {
+                let enabled = value.parse::<bool>().map_err(|_| ConfigError::Validation {
+                    message: format!("Invalid {key} value: {value} (expected true or false)"),
+                })?;
+                match key {
+                    "subtasks.complete_parent" => self.subtasks.complete_parent = enabled,
+                    "subtasks.block_parent" => self.subtasks.block_parent = enabled,
+                    _ => self.subtasks.delete_children = enabled,
+                }
+            }
             "github.sync_interval" => {
                 let interval = value.parse::<u64>().map_err(|_| ConfigError::Validation {
                     message: format!("Invalid sync_interval value: {}", value),
@@ -176,15 +551,190 @@ impl EddaConfig {
         Ok(())
     }
 
+    /// Set `uda.<name>.type` or `uda.<name>.values` (comma-separated),
+    /// declaring the attribute if needed
+    fn set_uda_value(&mut self, uda_key: &str, value: &str) -> EddaResult<()> {
+        let unknown = || ConfigError::Validation {
+            message: format!("Unknown configuration key: uda.{}", uda_key),
+        };
+        let (name, field) = uda_key.rsplit_once('.').ok_or_else(unknown)?;
+        let mut definition = self.uda.get(name).cloned().unwrap_or(UdaDefinition {
+            kind: UdaType::String,
+            values: Vec::new(),
+        });
+        match field {
+            "type" => {
+                definition.kind = value.parse().map_err(declaration_error)?;
+            }
+            "values" => {
+                definition.values = value
+                    .split(',')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+            }
+            _ => return Err(unknown().into()),
+        }
+        definition.validate(name).map_err(declaration_error)?;
+        self.uda.insert(name.to_string(), definition);
+        Ok(())
+    }
+
+    /// Set `urgency.<term>`, `urgency.age_max_days` or an entry of one of
+    /// the coefficient tables such as `urgency.user_tag.next`
+    fn set_urgency_value(&mut self, urgency_key: &str, value: &str) -> EddaResult<()> {
+        let unknown = || ConfigError::Validation {
+            message: format!("Unknown configuration key: urgency.{}", urgency_key),
+        };
+        if urgency_key == "age_max_days" {
+            self.urgency.age_max_days = value.parse().map_err(|_| ConfigError::Validation {
+                message: format!("Invalid age_max_days value: {}", value),
+            })?;
+            return Ok(());
+        }
+
+        let coefficient = value
+            .parse::<f64>()
+            .ok()
+            .filter(|c| c.is_finite())
+            .ok_or_else(|| ConfigError::Validation {
+                message: format!("Invalid urgency coefficient: {}", value),
+            })?;
+        if let Some((table, name)) = urgency_key.split_once('.') {
+            let table = self.urgency.table_mut(table).ok_or_else(unknown)?;
+            table.insert(name.to_string(), coefficient);
+        } else {
+            *self
+                .urgency
+                .coefficient_mut(urgency_key)
+                .ok_or_else(unknown)? = coefficient;
+        }
+        Ok(())
+    }
+
+    /// Set a field of `report.<name>`, starting from the built-in report of
+    /// that name if there is one; `columns`, `sort` and `widths`
+    /// (`column=width` pairs) are comma-separated and an empty `limit`
+    /// removes the limit
+    fn set_report_value(&mut self, report_key: &str, value: &str) -> EddaResult<()> {
+        let unknown = || ConfigError::Validation {
+            message: format!("Unknown configuration key: report.{}", report_key),
+        };
+        let (name, field) = report_key.rsplit_once('.').ok_or_else(unknown)?;
+        let mut report = self.reports().remove(name).unwrap_or_default();
+        let list = || -> Vec<String> {
+            value
+                .split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+        match field {
+            "description" => report.description = value.to_string(),
+            "filter" => report.filter = value.to_string(),
+            "columns" => report.columns = list(),
+            "sort" => report.sort = list(),
+            "limit" if value.is_empty() => report.limit = None,
+            "limit" => {
+                report.limit = Some(value.parse().map_err(|_| ConfigError::Validation {
+                    message: format!("Invalid report limit: {}", value),
+                })?);
+            }
+            "widths" => {
+                report.widths = list()
+                    .iter()
+                    .map(|pair| {
+                        pair.split_once('=')
+                            .and_then(|(column, width)| {
+                                Some((column.trim().to_string(), width.trim().parse().ok()?))
+                            })
+                            .ok_or_else(|| ConfigError::Validation {
+                                message: format!("Invalid report width: {}", pair),
+                            })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(unknown().into()),
+        }
+        report
+            .validate(name, &self.uda)
+            .map_err(declaration_error)?;
+        self.report.insert(name.to_string(), report);
+        Ok(())
+    }
+
     /// Get a configuration value by key
     pub fn get_value(&self, key: &str) -> Option<String> {
+        if let Some((name, field)) = key.strip_prefix("report.").and_then(|k| k.rsplit_once('.')) {
+            let report = self.reports().remove(name)?;
+            return match field {
+                "description" => Some(report.description),
+                "filter" => Some(report.filter),
+                "columns" => Some(report.columns.join(",")),
+                "sort" => Some(report.sort.join(",")),
+                "limit" => report.limit.map(|limit| limit.to_string()),
+                "widths" => Some(
+                    report
+                        .widths
+                        .iter()
+                        .map(|(column, width)| format!("{column}={width}"))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+                _ => None,
+            };
+        }
+        if let Some(urgency_key) = key.strip_prefix("urgency.") {
+            if urgency_key == "age_max_days" {
+                return Some(self.urgency.age_max_days.to_string());
+            }
+            let value = match urgency_key.split_once('.') {
+                Some((table, name)) => self
+                    .urgency
+                    .tables()
+                    .into_iter()
+                    .find(|(key, _)| *key == table)
+                    .and_then(|(_, table)| table.get(name).copied()),
+                None => self
+                    .urgency
+                    .coefficients()
+                    .into_iter()
+                    .find(|(key, _)| *key == urgency_key)
+                    .map(|(_, coefficient)| coefficient),
+            };
+            return value.map(|c| c.to_string());
+        }
+        if let Some((name, field)) = key.strip_prefix("uda.").and_then(|k| k.rsplit_once('.')) {
+            let definition = self.uda.get(name)?;
+            return match field {
+                "type" => Some(definition.kind.to_string()),
+                "values" => Some(definition.values.join(",")),
+                _ => None,
+            };
+        }
         match key {
             "data_dir" => Some(self.data_dir.to_string_lossy().to_string()),
             "log_level" => Some(self.log_level.clone()),
             "output_format" => Some(self.output_format.clone()),
+            "timezone" => Some(self.timezone.clone()),
+            "author" => self.author.clone(),
             "database.url" => Some(self.database.url.clone()),
             "database.max_connections" => Some(self.database.max_connections.to_string()),
             "github.repository" => self.github.repository.clone(),
+            "recurrence.horizon_days" => Some(self.recurrence.horizon_days.to_string()),
+            "bulk.confirm_threshold" => Some(self.bulk.confirm_threshold.to_string()),
+            "backup.compress" => Some(self.backup.compress.to_string()),
+            "backup.encrypt" => Some(self.backup.encrypt.to_string()),
+            "backup.key_file" => self
+                .backup
+                .key_file
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            "backup.keep_daily" => Some(self.backup.keep_daily.to_string()),
+            "backup.keep_weekly" => Some(self.backup.keep_weekly.to_string()),
+            "subtasks.complete_parent" => Some(self.subtasks.complete_parent.to_string()),
+            "subtasks.block_parent" => Some(self.subtasks.block_parent.to_string()),
+            "subtasks.delete_children" => Some(self.subtasks.delete_children.to_string()),
             "github.sync_interval" => Some(self.github.sync_interval.to_string()),
             "github.sync_mode" => Some(self.github.sync_mode.clone()),
             "github.project_ids" => Some(
@@ -207,6 +757,15 @@ impl EddaConfig {
     }
 }
 
+/// Report an invalid UDA or report declaration as a configuration error
+fn declaration_error(error: TaskError) -> ConfigError {
+    let message = match error {
+        TaskError::Validation { message } => message,
+        other => other.to_string(),
+    };
+    ConfigError::Validation { message }
+}
+
 /// Load configuration from file and environment variables
 pub fn load_config(config_path: Option<PathBuf>) -> EddaResult<EddaConfig> {
     let mut config = if let Some(path) = config_path {
@@ -232,6 +791,24 @@ pub fn load_config(config_path: Option<PathBuf>) -> EddaResult<EddaConfig> {
     Ok(config)
 }
 
+/// Path of the configuration file `load_config` reads, or the default path
+/// when there is none yet
+pub fn config_file_path(config_path: Option<PathBuf>) -> PathBuf {
+    config_path
+        .or_else(find_config_file)
+        .unwrap_or_else(get_default_config_path)
+}
+
+/// Load a configuration file as written, without environment overrides,
+/// for commands that change and save it
+pub fn load_stored_config(path: &PathBuf) -> EddaResult<EddaConfig> {
+    if path.exists() {
+        load_config_from_file(path)
+    } else {
+        Ok(EddaConfig::default())
+    }
+}
+
 /// Save configuration to file
 pub fn save_config(config: &EddaConfig, config_path: Option<PathBuf>) -> EddaResult<()> {
     let path = config_path.unwrap_or_else(get_default_config_path);
@@ -305,8 +882,39 @@ fn override_from_env(config: &mut EddaConfig) {
     if let Ok(db_url) = std::env::var("EDDA_DATABASE_URL") {
         config.database.url = db_url;
     }
+
+    // Lets agents sharing a config each work in their own context
+    if let Ok(context) = std::env::var("EDDA_CONTEXT") {
+        config.context = (!context.is_empty() && context != "none").then_some(context);
+    }
+
+    if let Ok(author) = std::env::var("EDDA_AUTHOR") {
+        config.author = (!author.trim().is_empty()).then(|| author.trim().to_string());
+    }
 }
 
+/// Check a context's name and that its filter parses
+fn validate_context(name: &str, filter: &str, config: &EddaConfig) -> EddaResult<()> {
+    let mut chars = name.chars();
+    let is_identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !is_identifier || RESERVED_CONTEXT_NAMES.contains(&name) {
+        return Err(ConfigError::Validation {
+            message: format!("Invalid context name: {name}"),
+        }
+        .into());
+    }
+    FilterExpr::from_args_with(&[filter.to_string()], config.date_parser(), &config.uda).map_err(
+        |e| ConfigError::Validation {
+            message: format!("Invalid filter for context {name}: {e}"),
+        },
+    )?;
+    Ok(())
+}
+
+/// Names taken by `edda context` subcommands
+const RESERVED_CONTEXT_NAMES: [&str; 5] = ["define", "delete", "list", "show", "none"];
+
 /// Validate configuration
 pub fn validate_config(config: &EddaConfig) -> EddaResult<()> {
     // Validate data directory
@@ -334,6 +942,14 @@ pub fn validate_config(config: &EddaConfig) -> EddaResult<()> {
         .into());
     }
 
+    // Validate timezone
+    if config.timezone.parse::<Tz>().is_err() {
+        return Err(ConfigError::Validation {
+            message: format!("Invalid timezone: {}", config.timezone),
+        }
+        .into());
+    }
+
     // Validate GitHub sync mode
     let valid_sync_modes = ["issues", "projects", "both"];
     if !valid_sync_modes.contains(&config.github.sync_mode.as_str()) {
@@ -354,6 +970,47 @@ pub fn validate_config(config: &EddaConfig) -> EddaResult<()> {
         }
     }
 
+    // Validate user-defined attributes
+    for (name, definition) in &config.uda {
+        definition.validate(name).map_err(declaration_error)?;
+    }
+
+    // Validate report declarations
+    for (name, report) in &config.report {
+        report
+            .validate(name, &config.uda)
+            .map_err(declaration_error)?;
+    }
+
+    // Validate contexts
+    for (name, filter) in &config.contexts {
+        validate_context(name, filter, config)?;
+    }
+    if let Some(context) = &config.context
+        && !config.contexts.contains_key(context)
+    {
+        return Err(ConfigError::Validation {
+            message: format!("Unknown context: {context}"),
+        }
+        .into());
+    }
+
+    // Validate urgency coefficients
+    let urgency = &config.urgency;
+    let tables = urgency.tables();
+    if urgency
+        .coefficients()
+        .into_iter()
+        .map(|(_, coefficient)| coefficient)
+        .chain(tables.iter().flat_map(|(_, table)| table.values().copied()))
+        .any(|c| !c.is_finite())
+    {
+        return Err(ConfigError::Validation {
+            message: "Urgency coefficients must be finite numbers".to_string(),
+        }
+        .into());
+    }
+
     // Validate column_mapping
     for (column_name, task_status) in &config.github.column_mapping {
         if column_name.is_empty() || task_status.is_empty() {
@@ -430,6 +1087,10 @@ fn default_output_format() -> String {
     "text".to_string()
 }
 
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
 fn default_sync_interval() -> u64 {
     300 // 5 minutes
 }
@@ -454,6 +1115,26 @@ fn default_max_connections() -> u32 {
     5
 }
 
+fn default_recurrence_horizon_days() -> u32 {
+    7
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_bulk_confirm_threshold() -> usize {
+    3
+}
+
+fn default_backup_keep_daily() -> usize {
+    7
+}
+
+fn default_backup_keep_weekly() -> usize {
+    4
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,6 +1153,198 @@ mod tests {
         assert_eq!(config.github.sync_mode, "issues");
         assert_eq!(config.github.project_ids.len(), 0);
         assert_eq!(config.github.column_mapping.len(), 3);
+        assert_eq!(config.recurrence.horizon_days, 7);
+        assert_eq!(config.bulk.confirm_threshold, 3);
+    }
+
+    #[test]
+    fn test_set_get_timezone() {
+        let mut config = EddaConfig::default();
+        assert_eq!(config.get_value("timezone"), Some("UTC".to_string()));
+        config.set_value("timezone", "Europe/Stockholm").unwrap();
+        assert_eq!(config.timezone, "Europe/Stockholm");
+        assert!(config.set_value("timezone", "Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_set_get_author() {
+        let mut config = EddaConfig::default();
+        assert_eq!(config.get_value("author"), None);
+        config.set_value("author", "agent-a").unwrap();
+        assert_eq!(config.get_value("author"), Some("agent-a".to_string()));
+        config.set_value("author", "").unwrap();
+        assert_eq!(config.author, None);
+    }
+
+    #[test]
+    fn test_set_get_recurrence_horizon() {
+        let mut config = EddaConfig::default();
+        config.set_value("recurrence.horizon_days", "14").unwrap();
+        assert_eq!(config.recurrence.horizon_days, 14);
+        assert_eq!(
+            config.get_value("recurrence.horizon_days"),
+            Some("14".to_string())
+        );
+        assert!(config.set_value("recurrence.horizon_days", "soon").is_err());
+    }
+
+    #[test]
+    fn test_set_get_backup_settings() {
+        let mut config = EddaConfig::default();
+        assert_eq!(config.backup.keep_daily, 7);
+        config.set_value("backup.keep_weekly", "8").unwrap();
+        config.set_value("backup.compress", "true").unwrap();
+        assert_eq!(
+            config.get_value("backup.keep_weekly"),
+            Some("8".to_string())
+        );
+        assert!(config.backup.compress);
+        assert!(config.set_value("backup.keep_daily", "-1").is_err());
+        config
+            .set_value("backup.key_file", "/keys/edda.key")
+            .unwrap();
+        assert_eq!(
+            config.get_value("backup.key_file"),
+            Some("/keys/edda.key".to_string())
+        );
+        config.set_value("backup.key_file", "").unwrap();
+        assert_eq!(config.backup.key_file, None);
+    }
+
+    #[test]
+    fn test_set_get_subtask_rules() {
+        let mut config = EddaConfig::default();
+        assert!(config.subtasks.block_parent && !config.subtasks.complete_parent);
+        config
+            .set_value("subtasks.complete_parent", "true")
+            .unwrap();
+        assert!(config.subtasks.complete_parent);
+        assert_eq!(
+            config.get_value("subtasks.complete_parent"),
+            Some("true".to_string())
+        );
+        assert!(config.set_value("subtasks.block_parent", "maybe").is_err());
+
+        let config: EddaConfig = toml::from_str("[subtasks]\ndelete_children = false\n").unwrap();
+        assert!(!config.subtasks.delete_children && config.subtasks.block_parent);
+    }
+
+    #[test]
+    fn test_set_get_urgency_coefficients() {
+        let mut config = EddaConfig::default();
+        assert_eq!(config.get_value("urgency.due"), Some("12".to_string()));
+        config.set_value("urgency.blocked", "-8.5").unwrap();
+        assert_eq!(config.urgency.blocked, -8.5);
+        config.set_value("urgency.user_project.web", "3").unwrap();
+        assert_eq!(
+            config.get_value("urgency.user_project.web"),
+            Some("3".to_string())
+        );
+        config.set_value("urgency.age_max_days", "90").unwrap();
+        assert_eq!(config.urgency.age_max_days, 90);
+        assert!(config.set_value("urgency.due", "soon").is_err());
+        assert!(config.set_value("urgency.colour", "1").is_err());
+        assert!(config.set_value("urgency.user_colour.red", "1").is_err());
+
+        let config: EddaConfig = toml::from_str("[urgency]\ndue = 20.0\n").unwrap();
+        assert_eq!(config.urgency.due, 20.0);
+        assert_eq!(config.urgency.user_tag["next"], 15.0);
+    }
+
+    #[test]
+    fn test_report_declarations() {
+        let config: EddaConfig = toml::from_str(
+            r#"
+            [report.next]
+            filter = "status:pending +next"
+            columns = ["id", "description"]
+
+            [report.triage]
+            filter = "+bug"
+            sort = ["priority-"]
+            widths = { description = 40 }
+            "#,
+        )
+        .unwrap();
+        assert!(validate_config(&config).is_ok());
+        let reports = config.reports();
+        assert_eq!(reports["next"].columns, ["id", "description"]);
+        assert_eq!(reports["triage"].widths["description"], 40);
+        assert!(reports.contains_key("overdue"));
+
+        let mut config = EddaConfig::default();
+        config.set_value("report.next.limit", "5").unwrap();
+        assert_eq!(config.report["next"].sort, ["urgency-"]);
+        assert_eq!(config.get_value("report.next.limit"), Some("5".to_string()));
+        config.set_value("report.mine.filter", "+mine").unwrap();
+        assert_eq!(config.reports()["mine"].filter, "+mine");
+        assert!(
+            config
+                .set_value("report.mine.columns", "id,colour")
+                .is_err()
+        );
+        assert!(config.set_value("report.mine.sort", "colour+").is_err());
+        assert!(
+            config
+                .set_value("report.mine.widths", "description")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_context_declarations() {
+        let mut config: EddaConfig = toml::from_str(
+            r#"
+            context = "work"
+
+            [contexts]
+            work = "project:work or +urgent"
+            "#,
+        )
+        .unwrap();
+        assert!(validate_config(&config).is_ok());
+        assert_eq!(config.context_filter(), Some("project:work or +urgent"));
+
+        config.define_context("home", "project:home").unwrap();
+        assert_eq!(config.contexts["home"], "project:home");
+        assert!(config.define_context("none", "+x").is_err());
+        assert!(config.define_context("9lives", "+x").is_err());
+        assert!(config.define_context("broken", "due:whenever").is_err());
+
+        config.context = Some("missing".to_string());
+        assert!(validate_config(&config).is_err());
+        config.context = None;
+        assert_eq!(config.context_filter(), None);
+    }
+
+    #[test]
+    fn test_uda_declarations() {
+        let config: EddaConfig = toml::from_str(
+            r#"
+            [uda.agent_model]
+            type = "enum"
+            values = ["opus", "sonnet"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.uda["agent_model"].kind, UdaType::Enum);
+        assert!(validate_config(&config).is_ok());
+
+        let mut config = EddaConfig::default();
+        config
+            .set_value("uda.estimate_points.type", "number")
+            .unwrap();
+        assert_eq!(
+            config.get_value("uda.estimate_points.type"),
+            Some("number".to_string())
+        );
+        assert!(config.set_value("uda.agent_model.type", "enum").is_err());
+        assert!(config.set_value("uda.due.type", "date").is_err());
+        assert!(
+            config
+                .set_value("uda.estimate_points.color", "red")
+                .is_err()
+        );
     }
 
     #[test]
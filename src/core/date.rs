@@ -0,0 +1,315 @@
+use crate::core::TaskError;
+use chrono::{
+    DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+
+/// Parse a date expression in UTC; see [`DateParser`] for the accepted forms
+pub fn parse_date(input: &str) -> Result<DateTime<Utc>, TaskError> {
+    DateParser::new(Tz::UTC).parse(input)
+}
+
+/// Resolves date expressions relative to a reference time in a timezone
+///
+/// Accepted forms:
+///
+/// ```text
+/// 2026-11-01, 2026-11-01T09:30[:00]      local time in the timezone
+/// 2026-11-01T09:30:00Z (RFC 3339)        absolute
+/// now, today, tomorrow, yesterday
+/// sod, eod, sow, eow, som, eom, soy, eoy start/end of day, week, month, year
+/// monday .. sunday (or mon .. sun)       next such day, at midnight
+/// +3d, -2h, 1w                           offset from now
+/// now+1w, eow-2d, monday+9h              offset from a named date
+/// ```
+///
+/// Offset units are `s`, `min`, `h`, `d`, `w`, `mo` and `y` (long names are
+/// accepted too). Weeks start on Monday; calendar offsets (days and longer)
+/// keep the local wall-clock time across DST changes.
+#[derive(Debug, Clone, Copy)]
+pub struct DateParser {
+    now: DateTime<Utc>,
+    timezone: Tz,
+}
+
+impl DateParser {
+    /// Create a parser resolving relative expressions against the current time
+    pub fn new(timezone: Tz) -> Self {
+        Self::with_now(Utc::now(), timezone)
+    }
+
+    /// Create a parser with a fixed reference time
+    pub fn with_now(now: DateTime<Utc>, timezone: Tz) -> Self {
+        Self { now, timezone }
+    }
+
+    /// Reference time relative expressions are resolved against
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    /// Format the local calendar date of an instant
+    pub fn format_date(&self, date: DateTime<Utc>) -> String {
+        date.with_timezone(&self.timezone)
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+
+    /// Format an instant as local time in the parser's timezone
+    pub fn format(&self, date: DateTime<Utc>) -> String {
+        date.with_timezone(&self.timezone)
+            .format("%Y-%m-%d %H:%M %Z")
+            .to_string()
+    }
+
+    /// Parse a date expression
+    pub fn parse(&self, input: &str) -> Result<DateTime<Utc>, TaskError> {
+        let input = input.trim();
+        let invalid = || TaskError::Validation {
+            message: format!("Invalid date: {input}"),
+        };
+        if input.is_empty() {
+            return Err(invalid());
+        }
+
+        if let Some(date) = self.parse_absolute(input) {
+            return Ok(date);
+        }
+
+        let lower = input.to_lowercase();
+        let split = lower
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| matches!(c, '+' | '-'))
+            .map_or(lower.len(), |(i, _)| i);
+        let (base, offsets) = lower.split_at(split);
+
+        let (mut date, offsets) = match self.named(base) {
+            Some(date) => (date, offsets),
+            // A bare offset such as `+3d` or `2w` is relative to now
+            None => (self.now, lower.as_str()),
+        };
+
+        for (sign, amount, unit) in split_offsets(offsets).ok_or_else(invalid)? {
+            date = self
+                .apply_offset(date, sign * amount, &unit)
+                .ok_or_else(invalid)?;
+        }
+        Ok(date)
+    }
+
+    /// RFC 3339 timestamps and ISO dates or date-times in local time
+    fn parse_absolute(&self, input: &str) -> Option<DateTime<Utc>> {
+        if let Ok(date) = DateTime::parse_from_rfc3339(input) {
+            return Some(date.with_timezone(&Utc));
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+            if let Ok(date) = NaiveDateTime::parse_from_str(input, format) {
+                return self.resolve(date);
+            }
+        }
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| self.resolve(date.and_time(NaiveTime::MIN)))
+    }
+
+    /// Named dates such as `today`, `eow` or `friday`
+    fn named(&self, name: &str) -> Option<DateTime<Utc>> {
+        let local = self.now.with_timezone(&self.timezone).naive_local();
+        let today = local.date();
+        let end_of = |date: NaiveDate| date.and_hms_opt(23, 59, 59);
+        let start_of_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let start_of_month = today.with_day(1)?;
+        let start_of_year = today.with_ordinal(1)?;
+
+        let naive = match name {
+            "now" => return Some(self.now),
+            "today" | "sod" => today.and_time(NaiveTime::MIN),
+            "tomorrow" => today.succ_opt()?.and_time(NaiveTime::MIN),
+            "yesterday" => today.pred_opt()?.and_time(NaiveTime::MIN),
+            "eod" => end_of(today)?,
+            "sow" => start_of_week.and_time(NaiveTime::MIN),
+            "eow" => end_of(start_of_week + Duration::days(6))?,
+            "som" => start_of_month.and_time(NaiveTime::MIN),
+            "eom" => end_of(
+                start_of_month
+                    .checked_add_months(Months::new(1))?
+                    .pred_opt()?,
+            )?,
+            "soy" => start_of_year.and_time(NaiveTime::MIN),
+            "eoy" => end_of(NaiveDate::from_ymd_opt(today.year(), 12, 31)?)?,
+            _ => {
+                let weekday = name.parse::<Weekday>().ok()?;
+                let days_ahead = (weekday.num_days_from_monday() as i64
+                    - today.weekday().num_days_from_monday() as i64)
+                    .rem_euclid(7);
+                let days_ahead = if days_ahead == 0 { 7 } else { days_ahead };
+                (today + Duration::days(days_ahead)).and_time(NaiveTime::MIN)
+            }
+        };
+        self.resolve(naive)
+    }
+
+    fn apply_offset(&self, date: DateTime<Utc>, amount: i64, unit: &str) -> Option<DateTime<Utc>> {
+        // Out-of-range amounts make no duration rather than panicking
+        let exact = match unit {
+            "s" | "sec" | "secs" | "second" | "seconds" => Some(Duration::try_seconds(amount)),
+            "min" | "mins" | "minute" | "minutes" => Some(Duration::try_minutes(amount)),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Some(Duration::try_hours(amount)),
+            _ => None,
+        };
+        if let Some(duration) = exact {
+            return date.checked_add_signed(duration?);
+        }
+
+        let local = date.with_timezone(&self.timezone).naive_local();
+        let shifted = match unit {
+            "d" | "day" | "days" => local.checked_add_signed(Duration::try_days(amount)?)?,
+            "w" | "wk" | "wks" | "week" | "weeks" => {
+                local.checked_add_signed(Duration::try_weeks(amount)?)?
+            }
+            "mo" | "mos" | "month" | "months" => add_months(local, amount)?,
+            "y" | "yr" | "yrs" | "year" | "years" => add_months(local, amount.checked_mul(12)?)?,
+            _ => return None,
+        };
+        self.resolve(shifted)
+    }
+
+    /// Convert a local wall-clock time to UTC, taking the earlier instant when
+    /// the time is ambiguous and skipping forward over DST gaps
+    fn resolve(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        self.timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .or_else(|| {
+                self.timezone
+                    .from_local_datetime(&(naive + Duration::hours(1)))
+                    .earliest()
+            })
+            .map(|date| date.with_timezone(&Utc))
+    }
+}
+
+fn add_months(date: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let count = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months >= 0 {
+        date.checked_add_months(count)
+    } else {
+        date.checked_sub_months(count)
+    }
+}
+
+/// Split `+1w-2d` (or a bare `3d`) into `(sign, amount, unit)` parts; an
+/// empty string has no parts
+fn split_offsets(input: &str) -> Option<Vec<(i64, i64, String)>> {
+    let mut offsets = Vec::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (sign, body) = match rest.as_bytes()[0] {
+            b'+' => (1, &rest[1..]),
+            b'-' => (-1, &rest[1..]),
+            _ if offsets.is_empty() => (1, rest),
+            _ => return None,
+        };
+        let digits = body
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(body.len());
+        let unit_end = body[digits..]
+            .find(['+', '-'])
+            .map(|i| i + digits)
+            .unwrap_or(body.len());
+        let amount = body[..digits].parse::<i64>().ok()?;
+        let unit = &body[digits..unit_end];
+        if unit.is_empty() {
+            return None;
+        }
+        offsets.push((sign, amount, unit.to_string()));
+        rest = &body[unit_end..];
+    }
+    Some(offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_formats() {
+        let expected = Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap();
+        assert_eq!(parse_date("2026-11-01").unwrap(), expected);
+        assert_eq!(parse_date("2026-11-01T00:00:00Z").unwrap(), expected);
+        assert_eq!(parse_date("2026-11-01T02:00:00+02:00").unwrap(), expected);
+        assert_eq!(parse_date("2026-11-01T00:00").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_date_invalid() {
+        assert!(parse_date("").is_err());
+        assert!(parse_date("2026-13-01").is_err());
+        assert!(parse_date("next week").is_err());
+        assert!(parse_date("+3").is_err());
+        assert!(parse_date("today+3x").is_err());
+        // Offsets too large for a duration are errors, not panics
+        assert!(parse_date("+999999999999d").is_err());
+        assert!(parse_date("+9999999999999w").is_err());
+        assert!(parse_date("+99999999999999999s").is_err());
+        assert!(parse_date("-99999999999999999min").is_err());
+    }
+
+    /// Wednesday 2026-03-11 15:30 UTC
+    fn parser(timezone: Tz) -> DateParser {
+        DateParser::with_now(
+            Utc.with_ymd_and_hms(2026, 3, 11, 15, 30, 0).unwrap(),
+            timezone,
+        )
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    #[test]
+    fn test_named_dates() {
+        let p = parser(Tz::UTC);
+        assert_eq!(p.parse("now").unwrap(), utc(2026, 3, 11, 15, 30, 0));
+        assert_eq!(p.parse("today").unwrap(), utc(2026, 3, 11, 0, 0, 0));
+        assert_eq!(p.parse("Tomorrow").unwrap(), utc(2026, 3, 12, 0, 0, 0));
+        assert_eq!(p.parse("eod").unwrap(), utc(2026, 3, 11, 23, 59, 59));
+        assert_eq!(p.parse("sow").unwrap(), utc(2026, 3, 9, 0, 0, 0));
+        assert_eq!(p.parse("eow").unwrap(), utc(2026, 3, 15, 23, 59, 59));
+        assert_eq!(p.parse("eom").unwrap(), utc(2026, 3, 31, 23, 59, 59));
+        assert_eq!(p.parse("eoy").unwrap(), utc(2026, 12, 31, 23, 59, 59));
+        assert_eq!(p.parse("monday").unwrap(), utc(2026, 3, 16, 0, 0, 0));
+        assert_eq!(p.parse("fri").unwrap(), utc(2026, 3, 13, 0, 0, 0));
+        // The same weekday means next week, not today
+        assert_eq!(p.parse("wednesday").unwrap(), utc(2026, 3, 18, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_offsets() {
+        let p = parser(Tz::UTC);
+        assert_eq!(p.parse("+3d").unwrap(), utc(2026, 3, 14, 15, 30, 0));
+        assert_eq!(p.parse("-2h").unwrap(), utc(2026, 3, 11, 13, 30, 0));
+        assert_eq!(p.parse("2w").unwrap(), utc(2026, 3, 25, 15, 30, 0));
+        assert_eq!(p.parse("now+1w").unwrap(), utc(2026, 3, 18, 15, 30, 0));
+        assert_eq!(p.parse("eow-2d").unwrap(), utc(2026, 3, 13, 23, 59, 59));
+        assert_eq!(p.parse("monday+9h").unwrap(), utc(2026, 3, 16, 9, 0, 0));
+        assert_eq!(p.parse("today+1mo-1d").unwrap(), utc(2026, 4, 10, 0, 0, 0));
+        assert_eq!(p.parse("+45min").unwrap(), utc(2026, 3, 11, 16, 15, 0));
+    }
+
+    #[test]
+    fn test_relative_dates_use_timezone() {
+        // 15:30 UTC is already Thursday 02:30 in Auckland (UTC+13)
+        let p = parser(Tz::Pacific__Auckland);
+        assert_eq!(p.parse("today").unwrap(), utc(2026, 3, 11, 11, 0, 0));
+        assert_eq!(p.parse("2026-03-12").unwrap(), utc(2026, 3, 11, 11, 0, 0));
+        assert_eq!(p.parse("fri").unwrap(), utc(2026, 3, 12, 11, 0, 0));
+
+        // Calendar offsets keep wall-clock time across the DST change on 2026-03-08
+        let p = DateParser::with_now(utc(2026, 3, 7, 17, 0, 0), Tz::America__New_York);
+        assert_eq!(p.parse("now+1d").unwrap(), utc(2026, 3, 8, 16, 0, 0));
+        assert_eq!(p.parse("now+24h").unwrap(), utc(2026, 3, 8, 17, 0, 0));
+    }
+}
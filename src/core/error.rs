@@ -45,6 +45,18 @@ pub enum TaskError {
 
     #[error("Storage error: {message}")]
     Storage { message: String },
+
+    #[error("Invalid filter: {message}")]
+    InvalidFilter { message: String },
+
+    #[error("Dependency cycle: {message}")]
+    DependencyCycle { message: String },
+
+    #[error("Task {id} is blocked by open dependencies: {blockers}")]
+    Blocked { id: String, blockers: String },
+
+    #[error("Task {id} has open subtasks: {subtasks}")]
+    OpenSubtasks { id: String, subtasks: String },
 }
 
 /// Storage-specific errors
@@ -0,0 +1,917 @@
+use crate::core::date::DateParser;
+use crate::core::search::fts_query;
+use crate::core::uda::{UdaDefinition, UdaType};
+use crate::core::virtual_tag::{TagClock, VirtualTag};
+use crate::core::{Priority, TaskError, TaskStatus};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// Task attribute that can be referenced by a filter term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAttribute {
+    Description,
+    Project,
+    Status,
+    Priority,
+    Due,
+    Scheduled,
+    Start,
+    End,
+    Entry,
+    Modified,
+    Until,
+    Wait,
+    Uuid,
+    Recurrence,
+}
+
+impl FilterAttribute {
+    /// Look up an attribute by its Taskwarrior name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "description" | "desc" => Some(FilterAttribute::Description),
+            "project" | "proj" => Some(FilterAttribute::Project),
+            "status" => Some(FilterAttribute::Status),
+            "priority" | "pri" => Some(FilterAttribute::Priority),
+            "due" => Some(FilterAttribute::Due),
+            "scheduled" => Some(FilterAttribute::Scheduled),
+            "start" => Some(FilterAttribute::Start),
+            "end" => Some(FilterAttribute::End),
+            "entry" => Some(FilterAttribute::Entry),
+            "modified" => Some(FilterAttribute::Modified),
+            "until" => Some(FilterAttribute::Until),
+            "wait" => Some(FilterAttribute::Wait),
+            "uuid" => Some(FilterAttribute::Uuid),
+            "recur" | "recurrence" => Some(FilterAttribute::Recurrence),
+            _ => None,
+        }
+    }
+
+    /// Database column backing this attribute
+    pub fn column(&self) -> &'static str {
+        match self {
+            FilterAttribute::Description => "description",
+            FilterAttribute::Project => "project",
+            FilterAttribute::Status => "status",
+            FilterAttribute::Priority => "priority",
+            FilterAttribute::Due => "due_date",
+            FilterAttribute::Scheduled => "scheduled_date",
+            FilterAttribute::Start => "start_date",
+            FilterAttribute::End => "end_date",
+            FilterAttribute::Entry => "entry_date",
+            FilterAttribute::Modified => "modified_date",
+            FilterAttribute::Until => "until_date",
+            FilterAttribute::Wait => "wait_date",
+            FilterAttribute::Uuid => "uuid",
+            FilterAttribute::Recurrence => "recurrence",
+        }
+    }
+
+    /// Check if the attribute holds a date
+    pub fn is_date(&self) -> bool {
+        matches!(
+            self,
+            FilterAttribute::Due
+                | FilterAttribute::Scheduled
+                | FilterAttribute::Start
+                | FilterAttribute::End
+                | FilterAttribute::Entry
+                | FilterAttribute::Modified
+                | FilterAttribute::Until
+                | FilterAttribute::Wait
+        )
+    }
+
+    /// Check if the attribute holds free-form text
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            FilterAttribute::Description
+                | FilterAttribute::Project
+                | FilterAttribute::Uuid
+                | FilterAttribute::Recurrence
+        )
+    }
+}
+
+impl std::fmt::Display for FilterAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FilterAttribute::Description => "description",
+            FilterAttribute::Project => "project",
+            FilterAttribute::Status => "status",
+            FilterAttribute::Priority => "priority",
+            FilterAttribute::Due => "due",
+            FilterAttribute::Scheduled => "scheduled",
+            FilterAttribute::Start => "start",
+            FilterAttribute::End => "end",
+            FilterAttribute::Entry => "entry",
+            FilterAttribute::Modified => "modified",
+            FilterAttribute::Until => "until",
+            FilterAttribute::Wait => "wait",
+            FilterAttribute::Uuid => "uuid",
+            FilterAttribute::Recurrence => "recur",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Attribute modifier (the `.before` in `due.before:2026-11-01`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterModifier {
+    Is,
+    Not,
+    Before,
+    After,
+    Contains,
+    Hasnt,
+    StartsWith,
+    EndsWith,
+    /// Full-text match on the search index, with stemming and ranking
+    Matches,
+    None,
+    Any,
+}
+
+impl std::str::FromStr for FilterModifier {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "is" | "equals" => Ok(FilterModifier::Is),
+            "not" | "isnt" => Ok(FilterModifier::Not),
+            "before" | "below" | "under" => Ok(FilterModifier::Before),
+            "after" | "above" | "over" => Ok(FilterModifier::After),
+            "contains" | "has" => Ok(FilterModifier::Contains),
+            "hasnt" => Ok(FilterModifier::Hasnt),
+            "startswith" | "left" => Ok(FilterModifier::StartsWith),
+            "endswith" | "right" => Ok(FilterModifier::EndsWith),
+            "matches" => Ok(FilterModifier::Matches),
+            "none" => Ok(FilterModifier::None),
+            "any" => Ok(FilterModifier::Any),
+            _ => Err(TaskError::InvalidFilter {
+                message: format!("Unknown attribute modifier: {s}"),
+            }),
+        }
+    }
+}
+
+/// Typed value of an attribute filter term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterValue {
+    Text(String),
+    Date(DateTime<Utc>),
+    /// Numeric value in its canonical decimal form, compared as a number
+    Number(String),
+    Empty,
+}
+
+/// A single filter term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterTerm {
+    /// `+tag` (include) or `-tag` (exclude)
+    Tag { tag: String, include: bool },
+    /// `+OVERDUE`, `-BLOCKED` and other tags computed from task state,
+    /// measured against the clock of the parse
+    VirtualTag {
+        tag: VirtualTag,
+        include: bool,
+        clock: TagClock,
+    },
+    /// `attribute[.modifier]:value`
+    Attribute {
+        attribute: FilterAttribute,
+        modifier: FilterModifier,
+        value: FilterValue,
+    },
+    /// `name[.modifier]:value` on a user-defined attribute
+    Uda {
+        name: String,
+        modifier: FilterModifier,
+        value: FilterValue,
+    },
+    /// Bare word, matched against the description
+    Word(String),
+}
+
+/// Parsed filter expression tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    Term(FilterTerm),
+    Not(Box<FilterExpr>),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Parse a filter from command-line arguments, treating each argument as
+    /// one or more whitespace-separated tokens
+    pub fn from_args(args: &[String]) -> Result<Self, TaskError> {
+        Self::from_args_with(args, DateParser::new(chrono_tz::Tz::UTC), &BTreeMap::new())
+    }
+
+    /// Parse a filter from command-line arguments, resolving date values such
+    /// as `due.before:eow` with the given parser and accepting terms on the
+    /// given user-defined attributes
+    pub fn from_args_with(
+        args: &[String],
+        dates: DateParser,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<Self, TaskError> {
+        let mut tokens = Vec::new();
+        for arg in args {
+            tokens.extend(tokenize(arg)?);
+        }
+        Parser::new(tokens, dates, udas).parse()
+    }
+
+    /// Check if any term in the expression references the given attribute
+    pub fn references(&self, attribute: FilterAttribute) -> bool {
+        match self {
+            FilterExpr::Term(FilterTerm::Attribute { attribute: a, .. }) => *a == attribute,
+            FilterExpr::Term(_) => false,
+            FilterExpr::Not(inner) => inner.references(attribute),
+            FilterExpr::And(left, right) | FilterExpr::Or(left, right) => {
+                left.references(attribute) || right.references(attribute)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for FilterExpr {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(
+            tokenize(s)?,
+            DateParser::new(chrono_tz::Tz::UTC),
+            &BTreeMap::new(),
+        )
+        .parse()
+    }
+}
+
+/// Lexical token of a filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+/// Split a filter string into tokens, honoring double and single quotes
+fn tokenize(input: &str) -> Result<Vec<Token>, TaskError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut quote: Option<char> = None;
+    let chars = input.chars();
+
+    let flush = |current: &mut String, quoted: &mut bool, tokens: &mut Vec<Token>| {
+        if current.is_empty() && !*quoted {
+            return;
+        }
+        let word = std::mem::take(current);
+        let token = if *quoted {
+            Token::Term(word)
+        } else {
+            match word.to_lowercase().as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                _ => Token::Term(word),
+            }
+        };
+        *quoted = false;
+        tokens.push(token);
+    };
+
+    for c in chars {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None => match c {
+                '"' | '\'' => {
+                    quote = Some(c);
+                    quoted = true;
+                }
+                '(' => {
+                    flush(&mut current, &mut quoted, &mut tokens);
+                    tokens.push(Token::LParen);
+                }
+                ')' => {
+                    flush(&mut current, &mut quoted, &mut tokens);
+                    tokens.push(Token::RParen);
+                }
+                c if c.is_whitespace() => flush(&mut current, &mut quoted, &mut tokens),
+                c => current.push(c),
+            },
+        }
+    }
+
+    if quote.is_some() {
+        return Err(TaskError::InvalidFilter {
+            message: format!("Unterminated quote in filter: {input}"),
+        });
+    }
+    flush(&mut current, &mut quoted, &mut tokens);
+
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over filter tokens
+///
+/// Grammar (lowest to highest precedence):
+///
+/// ```text
+/// or   := and ("or" and)*
+/// and  := not ("and"? not)*
+/// not  := "not" not | atom
+/// atom := "(" or ")" | term
+/// ```
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    dates: DateParser,
+    udas: &'a BTreeMap<String, UdaDefinition>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        tokens: Vec<Token>,
+        dates: DateParser,
+        udas: &'a BTreeMap<String, UdaDefinition>,
+    ) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            dates,
+            udas,
+        }
+    }
+
+    fn parse(mut self) -> Result<FilterExpr, TaskError> {
+        if self.tokens.is_empty() {
+            return Err(TaskError::InvalidFilter {
+                message: "Filter is empty".to_string(),
+            });
+        }
+
+        let expr = self.parse_or()?;
+        if let Some(token) = self.peek() {
+            return Err(TaskError::InvalidFilter {
+                message: format!("Unexpected token in filter: {token:?}"),
+            });
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpr, TaskError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            let right = self.parse_and()?;
+            left = FilterExpr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr, TaskError> {
+        let mut left = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                // Adjacent terms are implicitly joined with "and"
+                Some(Token::Term(_)) | Some(Token::Not) | Some(Token::LParen) => {}
+                _ => break,
+            }
+            let right = self.parse_not()?;
+            left = FilterExpr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<FilterExpr, TaskError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            let inner = self.parse_not()?;
+            return Ok(FilterExpr::Not(Box::new(inner)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<FilterExpr, TaskError> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(TaskError::InvalidFilter {
+                        message: "Missing closing parenthesis".to_string(),
+                    }),
+                }
+            }
+            Some(Token::Term(term)) => {
+                Ok(FilterExpr::Term(parse_term(&term, &self.dates, self.udas)?))
+            }
+            Some(token) => Err(TaskError::InvalidFilter {
+                message: format!("Unexpected token in filter: {token:?}"),
+            }),
+            None => Err(TaskError::InvalidFilter {
+                message: "Unexpected end of filter".to_string(),
+            }),
+        }
+    }
+}
+
+/// Parse a single term such as `+tag`, `project:web` or `due.before:2026-11-01`
+fn parse_term(
+    term: &str,
+    dates: &DateParser,
+    udas: &BTreeMap<String, UdaDefinition>,
+) -> Result<FilterTerm, TaskError> {
+    if let Some((include, name)) = term
+        .strip_prefix('+')
+        .map(|name| (true, name))
+        .or_else(|| term.strip_prefix('-').map(|name| (false, name)))
+        && let Some(tag) = VirtualTag::from_name(name)
+    {
+        return Ok(FilterTerm::VirtualTag {
+            tag,
+            include,
+            clock: TagClock::new(dates),
+        });
+    }
+    if let Some(tag) = term.strip_prefix('+').filter(|t| !t.is_empty()) {
+        return Ok(FilterTerm::Tag {
+            tag: tag.to_string(),
+            include: true,
+        });
+    }
+    if let Some(tag) = term.strip_prefix('-').filter(|t| !t.is_empty()) {
+        return Ok(FilterTerm::Tag {
+            tag: tag.to_string(),
+            include: false,
+        });
+    }
+
+    let Some((name, raw_value)) = term.split_once(':') else {
+        return Ok(FilterTerm::Word(term.to_string()));
+    };
+
+    let (attribute_name, modifier_name) = match name.split_once('.') {
+        Some((attribute, modifier)) => (attribute, Some(modifier)),
+        None => (name, None),
+    };
+
+    let mut modifier = match modifier_name {
+        Some(m) => m.parse::<FilterModifier>()?,
+        None => FilterModifier::Is,
+    };
+
+    // `project:` with no value means "project is not set", as in Taskwarrior
+    if raw_value.is_empty() {
+        modifier = match modifier {
+            FilterModifier::Is => FilterModifier::None,
+            FilterModifier::Not => FilterModifier::Any,
+            other => other,
+        };
+    }
+
+    let attribute = match FilterAttribute::from_name(attribute_name) {
+        Some(attribute) => attribute,
+        None => {
+            let (name, definition) =
+                udas.get_key_value(attribute_name)
+                    .ok_or_else(|| TaskError::InvalidFilter {
+                        message: format!("Unknown filter attribute: {attribute_name}"),
+                    })?;
+            return parse_uda_term(name, definition, modifier, raw_value, dates);
+        }
+    };
+
+    validate_modifier(attribute, modifier)?;
+
+    let value = match modifier {
+        FilterModifier::None | FilterModifier::Any => FilterValue::Empty,
+        _ if raw_value.is_empty() => {
+            return Err(TaskError::InvalidFilter {
+                message: format!("Missing value for {attribute} filter"),
+            });
+        }
+        FilterModifier::Matches if fts_query(raw_value).is_none() => {
+            return Err(TaskError::InvalidFilter {
+                message: format!("Missing search terms for {attribute} filter"),
+            });
+        }
+        _ => parse_value(attribute, raw_value, dates)?,
+    };
+
+    Ok(FilterTerm::Attribute {
+        attribute,
+        modifier,
+        value,
+    })
+}
+
+/// Parse a term on a user-defined attribute, typing the value by its declaration
+fn parse_uda_term(
+    name: &str,
+    definition: &UdaDefinition,
+    modifier: FilterModifier,
+    raw_value: &str,
+    dates: &DateParser,
+) -> Result<FilterTerm, TaskError> {
+    let is_text = matches!(definition.kind, UdaType::String | UdaType::Enum);
+    let allowed = match modifier {
+        FilterModifier::Is | FilterModifier::Not | FilterModifier::None | FilterModifier::Any => {
+            true
+        }
+        FilterModifier::Before | FilterModifier::After => definition.kind.is_ordered(),
+        // Only task descriptions are in the search index
+        FilterModifier::Matches => false,
+        FilterModifier::Contains
+        | FilterModifier::Hasnt
+        | FilterModifier::StartsWith
+        | FilterModifier::EndsWith => is_text,
+    };
+    if !allowed {
+        return Err(TaskError::InvalidFilter {
+            message: format!("Modifier {modifier:?} is not supported for {name}"),
+        });
+    }
+
+    let value = match modifier {
+        FilterModifier::None | FilterModifier::Any => FilterValue::Empty,
+        _ if raw_value.is_empty() => {
+            return Err(TaskError::InvalidFilter {
+                message: format!("Missing value for {name} filter"),
+            });
+        }
+        // Partial matches aren't checked against the allowed values
+        _ if is_text && modifier != FilterModifier::Is && modifier != FilterModifier::Not => {
+            FilterValue::Text(raw_value.to_string())
+        }
+        _ => {
+            let invalid = |e: TaskError| TaskError::InvalidFilter {
+                message: e.to_string(),
+            };
+            match definition.kind {
+                UdaType::Date => FilterValue::Date(dates.parse(raw_value).map_err(invalid)?),
+                kind => match definition
+                    .parse_value(name, raw_value, dates)
+                    .map_err(invalid)?
+                {
+                    serde_json::Value::String(text) => FilterValue::Text(text),
+                    number if kind.is_ordered() => FilterValue::Number(number.to_string()),
+                    other => FilterValue::Text(other.to_string()),
+                },
+            }
+        }
+    };
+
+    Ok(FilterTerm::Uda {
+        name: name.to_string(),
+        modifier,
+        value,
+    })
+}
+
+/// Reject modifiers that make no sense for the given attribute
+fn validate_modifier(
+    attribute: FilterAttribute,
+    modifier: FilterModifier,
+) -> Result<(), TaskError> {
+    let allowed = match modifier {
+        FilterModifier::Is | FilterModifier::Not | FilterModifier::None | FilterModifier::Any => {
+            true
+        }
+        FilterModifier::Before | FilterModifier::After => attribute.is_date(),
+        FilterModifier::Matches => attribute == FilterAttribute::Description,
+        FilterModifier::Contains
+        | FilterModifier::Hasnt
+        | FilterModifier::StartsWith
+        | FilterModifier::EndsWith => attribute.is_text(),
+    };
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(TaskError::InvalidFilter {
+            message: format!("Modifier {modifier:?} is not supported for {attribute}"),
+        })
+    }
+}
+
+/// Parse and normalize a raw attribute value
+fn parse_value(
+    attribute: FilterAttribute,
+    raw: &str,
+    dates: &DateParser,
+) -> Result<FilterValue, TaskError> {
+    match attribute {
+        FilterAttribute::Status => {
+            let status = raw
+                .parse::<TaskStatus>()
+                .map_err(|e| TaskError::InvalidFilter {
+                    message: e.to_string(),
+                })?;
+            Ok(FilterValue::Text(status.to_string()))
+        }
+        FilterAttribute::Priority => {
+            let priority = raw
+                .parse::<Priority>()
+                .map_err(|e| TaskError::InvalidFilter {
+                    message: e.to_string(),
+                })?;
+            Ok(FilterValue::Text(priority.to_string()))
+        }
+        a if a.is_date() => {
+            let date = dates.parse(raw).map_err(|e| TaskError::InvalidFilter {
+                message: e.to_string(),
+            })?;
+            Ok(FilterValue::Date(date))
+        }
+        _ => Ok(FilterValue::Text(raw.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn term(expr: &FilterExpr) -> &FilterTerm {
+        match expr {
+            FilterExpr::Term(term) => term,
+            other => panic!("expected term, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_filter_tags() {
+        let expr: FilterExpr = "+bug".parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Tag {
+                tag: "bug".to_string(),
+                include: true
+            }
+        );
+
+        let expr: FilterExpr = "-bug".parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Tag {
+                tag: "bug".to_string(),
+                include: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_virtual_tags() {
+        use chrono::TimeZone;
+        let now = Utc.with_ymd_and_hms(2026, 3, 11, 15, 30, 0).unwrap();
+        let dates = DateParser::with_now(now, chrono_tz::Tz::UTC);
+        let args = vec!["-BLOCKED".to_string()];
+        let expr = FilterExpr::from_args_with(&args, dates, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::VirtualTag {
+                tag: VirtualTag::Blocked,
+                include: false,
+                clock: TagClock::new(&dates),
+            }
+        );
+
+        // Only the upper-case names are virtual
+        let expr: FilterExpr = "+overdue".parse().unwrap();
+        assert!(matches!(term(&expr), FilterTerm::Tag { .. }));
+    }
+
+    #[test]
+    fn test_parse_filter_attributes() {
+        let expr: FilterExpr = "status:Pending".parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Status,
+                modifier: FilterModifier::Is,
+                value: FilterValue::Text("pending".to_string()),
+            }
+        );
+
+        let expr: FilterExpr = "priority:high".parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Priority,
+                modifier: FilterModifier::Is,
+                value: FilterValue::Text("H".to_string()),
+            }
+        );
+
+        let expr: FilterExpr = "due.before:2026-11-01".parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Due,
+                modifier: FilterModifier::Before,
+                value: FilterValue::Date(
+                    NaiveDate::from_ymd_opt(2026, 11, 1)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap()
+                        .and_utc()
+                ),
+            }
+        );
+
+        let expr: FilterExpr = "project:".parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Project,
+                modifier: FilterModifier::None,
+                value: FilterValue::Empty,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_quoted_value() {
+        let expr: FilterExpr = r#"description.contains:"login page""#.parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Description,
+                modifier: FilterModifier::Contains,
+                value: FilterValue::Text("login page".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_matches() {
+        let expr: FilterExpr = r#"description.matches:"login token*""#.parse().unwrap();
+        assert_eq!(
+            term(&expr),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Description,
+                modifier: FilterModifier::Matches,
+                value: FilterValue::Text("login token*".to_string()),
+            }
+        );
+        assert!("project.matches:web".parse::<FilterExpr>().is_err());
+        assert!(r#"description.matches:"*""#.parse::<FilterExpr>().is_err());
+    }
+
+    #[test]
+    fn test_parse_filter_implicit_and_precedence() {
+        // "and" binds tighter than "or"
+        let expr: FilterExpr = "project:web +bug or +urgent".parse().unwrap();
+        match expr {
+            FilterExpr::Or(left, right) => {
+                assert!(matches!(*left, FilterExpr::And(_, _)));
+                assert!(matches!(*right, FilterExpr::Term(FilterTerm::Tag { .. })));
+            }
+            other => panic!("expected or, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_filter_parentheses_and_not() {
+        let expr: FilterExpr = "not (project:web or project:api) and +bug".parse().unwrap();
+        match expr {
+            FilterExpr::And(left, right) => {
+                match *left {
+                    FilterExpr::Not(inner) => assert!(matches!(*inner, FilterExpr::Or(_, _))),
+                    other => panic!("expected not, got {other:?}"),
+                }
+                assert!(matches!(*right, FilterExpr::Term(FilterTerm::Tag { .. })));
+            }
+            other => panic!("expected and, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_filter_from_args() {
+        let args = vec!["(project:web".to_string(), "or +bug)".to_string()];
+        let expr = FilterExpr::from_args(&args).unwrap();
+        assert!(matches!(expr, FilterExpr::Or(_, _)));
+        assert!(expr.references(FilterAttribute::Project));
+        assert!(!expr.references(FilterAttribute::Status));
+    }
+
+    #[test]
+    fn test_parse_filter_relative_dates() {
+        use chrono::TimeZone;
+        let now = Utc.with_ymd_and_hms(2026, 3, 11, 15, 30, 0).unwrap();
+        let dates = DateParser::with_now(now, chrono_tz::Tz::UTC);
+        let args = vec!["due.before:eom".to_string(), "wait.after:now".to_string()];
+        let expr = FilterExpr::from_args_with(&args, dates, &BTreeMap::new()).unwrap();
+        let FilterExpr::And(left, right) = expr else {
+            panic!("expected and, got {expr:?}");
+        };
+        assert_eq!(
+            term(&left),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Due,
+                modifier: FilterModifier::Before,
+                value: FilterValue::Date(Utc.with_ymd_and_hms(2026, 3, 31, 23, 59, 59).unwrap()),
+            }
+        );
+        assert_eq!(
+            term(&right),
+            &FilterTerm::Attribute {
+                attribute: FilterAttribute::Wait,
+                modifier: FilterModifier::After,
+                value: FilterValue::Date(now),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_udas() {
+        let udas = BTreeMap::from([
+            (
+                "estimate_points".to_string(),
+                UdaDefinition {
+                    kind: UdaType::Number,
+                    values: Vec::new(),
+                },
+            ),
+            (
+                "agent_model".to_string(),
+                UdaDefinition {
+                    kind: UdaType::Enum,
+                    values: vec!["opus".to_string(), "sonnet".to_string()],
+                },
+            ),
+        ]);
+        let parse = |line: &str| {
+            FilterExpr::from_args_with(
+                &[line.to_string()],
+                DateParser::new(chrono_tz::Tz::UTC),
+                &udas,
+            )
+        };
+
+        assert_eq!(
+            term(&parse("estimate_points.over:3").unwrap()),
+            &FilterTerm::Uda {
+                name: "estimate_points".to_string(),
+                modifier: FilterModifier::After,
+                value: FilterValue::Number("3".to_string()),
+            }
+        );
+        assert_eq!(
+            term(&parse("agent_model:").unwrap()),
+            &FilterTerm::Uda {
+                name: "agent_model".to_string(),
+                modifier: FilterModifier::None,
+                value: FilterValue::Empty,
+            }
+        );
+        assert!(parse("agent_model.startswith:op").is_ok());
+        assert!(parse("agent_model:gpt").is_err());
+        assert!(parse("agent_model.before:opus").is_err());
+        assert!(parse("estimate_points:lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_filter_bare_word() {
+        let expr: FilterExpr = "login".parse().unwrap();
+        assert_eq!(term(&expr), &FilterTerm::Word("login".to_string()));
+    }
+
+    #[test]
+    fn test_parse_filter_errors() {
+        assert!("".parse::<FilterExpr>().is_err());
+        assert!("(project:web".parse::<FilterExpr>().is_err());
+        assert!("project:web)".parse::<FilterExpr>().is_err());
+        assert!("foo:bar".parse::<FilterExpr>().is_err());
+        assert!("status:bogus".parse::<FilterExpr>().is_err());
+        assert!("due.before:yesterday-ish".parse::<FilterExpr>().is_err());
+        assert!("status.before:pending".parse::<FilterExpr>().is_err());
+        assert!("project.contains:".parse::<FilterExpr>().is_err());
+        assert!(r#"description:"unterminated"#.parse::<FilterExpr>().is_err());
+        assert!("+bug or".parse::<FilterExpr>().is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::core::{Task, TaskError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Fields that change on every write and would only add noise to a diff
+const IGNORED_FIELDS: &[&str] = &["modified_date"];
+
+/// Kind of change recorded in the operations journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    Create,
+    Update,
+    Delete,
+}
+
+impl std::fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperationKind::Create => write!(f, "create"),
+            OperationKind::Update => write!(f, "update"),
+            OperationKind::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+impl std::str::FromStr for OperationKind {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(OperationKind::Create),
+            "update" => Ok(OperationKind::Update),
+            "delete" => Ok(OperationKind::Delete),
+            _ => Err(TaskError::Validation {
+                message: format!("Invalid operation kind: {s}"),
+            }),
+        }
+    }
+}
+
+/// One entry in the append-only operations journal
+///
+/// `before` is absent for creates and `after` for deletes. Entries written by
+/// `task undo` point at the entry they reverted through `undoes`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: Option<i64>,
+    pub task_uuid: Uuid,
+    pub kind: OperationKind,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+    pub undoes: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A single field that differs between the before and after snapshots
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl Operation {
+    /// Create a journal entry for a change to a task
+    pub fn new(kind: OperationKind, before: Option<Task>, after: Option<Task>) -> Self {
+        let task_uuid = after
+            .as_ref()
+            .or(before.as_ref())
+            .map(|task| task.uuid)
+            .unwrap_or_default();
+        Self {
+            id: None,
+            task_uuid,
+            kind,
+            before,
+            after,
+            undoes: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Fields that differ between the before and after snapshots
+    pub fn changes(&self) -> Vec<FieldChange> {
+        let to_object = |task: &Option<Task>| match task.as_ref().map(serde_json::to_value) {
+            Some(Ok(Value::Object(map))) => map,
+            _ => serde_json::Map::new(),
+        };
+        let before = to_object(&self.before);
+        let after = to_object(&self.after);
+
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        fields
+            .into_iter()
+            .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+            .filter_map(|field| {
+                let old = before.get(field).cloned().unwrap_or(Value::Null);
+                let new = after.get(field).cloned().unwrap_or(Value::Null);
+                (old != new).then(|| FieldChange {
+                    field: field.clone(),
+                    before: old,
+                    after: new,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_changes() {
+        let before = Task::new("Write docs".to_string());
+        let mut after = before.clone();
+        after.project = Some("docs".to_string());
+        after.add_tag("writing".to_string());
+
+        let operation = Operation::new(OperationKind::Update, Some(before), Some(after));
+        let fields: Vec<String> = operation.changes().into_iter().map(|c| c.field).collect();
+        assert_eq!(fields, ["project", "tags"]);
+    }
+
+    #[test]
+    fn test_create_changes_include_every_set_field() {
+        let task = Task::new("Write docs".to_string());
+        let uuid = task.uuid;
+        let operation = Operation::new(OperationKind::Create, None, Some(task));
+
+        assert_eq!(operation.task_uuid, uuid);
+        let changes = operation.changes();
+        assert!(changes.iter().any(|c| c.field == "description"));
+        assert!(changes.iter().all(|c| c.before.is_null()));
+    }
+}
@@ -1,9 +1,38 @@
 pub mod config;
+pub mod date;
 pub mod error;
+pub mod filter;
+pub mod journal;
 pub mod logging;
+pub mod modification;
+pub mod project;
+pub mod recurrence;
+pub mod report;
+pub mod search;
+pub mod selection;
+pub mod subtask;
 pub mod task;
+pub mod taskwarrior;
+pub mod timesheet;
+pub mod uda;
+pub mod urgency;
+pub mod virtual_tag;
 
 pub use config::{EddaConfig, load_config, save_config, validate_config};
 pub use error::{EddaError, EddaResult, StorageError, TaskError};
+pub use filter::{FilterAttribute, FilterExpr, FilterModifier, FilterTerm, FilterValue};
+pub use journal::{Operation, OperationKind};
 pub use logging::init_logging;
-pub use task::{Annotation, Priority, Task, TaskEngine, TaskStatus};
+pub use modification::{ModifyAttribute, TaskModification};
+pub use project::ProjectSummary;
+pub use recurrence::Recurrence;
+pub use report::{ReportDefinition, builtin_reports};
+pub use search::{SearchEntity, SearchHit};
+pub use selection::{TaskReference, TaskSelection};
+pub use subtask::TaskTree;
+pub use task::{Annotation, LifecycleChanges, Priority, Task, TaskEngine, TaskStatus};
+pub use taskwarrior::ImportSummary;
+pub use timesheet::{TimeInterval, Timesheet, TimesheetGroup};
+pub use uda::{UdaDefinition, UdaType};
+pub use urgency::{Urgency, UrgencyTerm};
+pub use virtual_tag::{TagClock, TaskRelations, VirtualTag};
@@ -0,0 +1,335 @@
+use crate::core::date::DateParser;
+use crate::core::uda::{UdaDefinition, parse_duration};
+use crate::core::{Priority, Recurrence, Task, TaskError, TaskStatus};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// Task attribute that can be set inline with `key:value`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifyAttribute {
+    Description,
+    Project,
+    Status,
+    Priority,
+    Due,
+    Scheduled,
+    Wait,
+    Until,
+    Recurrence,
+    Depends,
+    Parent,
+    Effort,
+}
+
+impl ModifyAttribute {
+    /// Look up an attribute by its Taskwarrior name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "description" | "desc" => Some(ModifyAttribute::Description),
+            "project" | "proj" => Some(ModifyAttribute::Project),
+            "status" => Some(ModifyAttribute::Status),
+            "priority" | "pri" => Some(ModifyAttribute::Priority),
+            "due" => Some(ModifyAttribute::Due),
+            "scheduled" | "sched" => Some(ModifyAttribute::Scheduled),
+            "wait" => Some(ModifyAttribute::Wait),
+            "until" => Some(ModifyAttribute::Until),
+            "recur" | "recurrence" => Some(ModifyAttribute::Recurrence),
+            "depends" | "dep" => Some(ModifyAttribute::Depends),
+            "parent" => Some(ModifyAttribute::Parent),
+            "effort" => Some(ModifyAttribute::Effort),
+            _ => None,
+        }
+    }
+}
+
+/// Changes parsed from Taskwarrior-style arguments such as
+/// `Fix login project:web +bug -later due:fri`
+///
+/// Bare words form the description, `key:value` sets an attribute (an empty
+/// value clears it), and `+tag` / `-tag` add and remove tags. Words whose
+/// `key:` is neither a known attribute nor a declared UDA are kept as
+/// description text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskModification {
+    pub description: Option<String>,
+    pub attributes: Vec<(ModifyAttribute, String)>,
+    /// Raw values for user-defined attributes, typed when applied
+    pub udas: Vec<(String, String)>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+impl TaskModification {
+    /// Parse command-line arguments, one word per argument so quoted values
+    /// such as `"description:New title"` stay intact
+    pub fn from_args(args: &[String], udas: &BTreeMap<String, UdaDefinition>) -> Self {
+        let mut modification = Self::default();
+        let mut words = Vec::new();
+
+        for word in args {
+            if let Some(tag) = word.strip_prefix('+').filter(|t| !t.is_empty()) {
+                modification.add_tags.push(tag.to_string());
+                continue;
+            }
+            if let Some(tag) = word.strip_prefix('-').filter(|t| !t.is_empty()) {
+                modification.remove_tags.push(tag.to_string());
+                continue;
+            }
+            if let Some((name, value)) = word.split_once(':')
+                && let Some(attribute) = ModifyAttribute::from_name(name)
+            {
+                modification.attributes.push((attribute, value.to_string()));
+                continue;
+            }
+            if let Some((name, value)) = word.split_once(':')
+                && udas.contains_key(name)
+            {
+                modification
+                    .udas
+                    .push((name.to_string(), value.to_string()));
+                continue;
+            }
+            words.push(word.as_str());
+        }
+
+        if !words.is_empty() {
+            modification.description = Some(words.join(" "));
+        }
+        modification
+    }
+
+    /// Check if the arguments contained no changes at all
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.attributes.is_empty()
+            && self.udas.is_empty()
+            && self.add_tags.is_empty()
+            && self.remove_tags.is_empty()
+    }
+
+    /// Raw `depends:` values, which must be resolved against storage
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.attributes
+            .iter()
+            .filter(|(attribute, _)| *attribute == ModifyAttribute::Depends)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Raw value of the last `parent:`, which must be resolved against
+    /// storage
+    pub fn parent(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(attribute, _)| *attribute == ModifyAttribute::Parent)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Apply every change except dependencies and the parent to a task, resolving dates
+    /// with the given parser and typing UDA values by their declarations
+    pub fn apply(
+        &self,
+        task: &mut Task,
+        dates: &DateParser,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<(), TaskError> {
+        if let Some(description) = &self.description {
+            task.description = description.clone();
+        }
+
+        for (attribute, value) in &self.attributes {
+            let optional = |value: &String| (!value.is_empty()).then(|| value.clone());
+            match attribute {
+                ModifyAttribute::Description => task.description = value.clone(),
+                ModifyAttribute::Project => task.project = optional(value),
+                ModifyAttribute::Status => task.status = value.parse::<TaskStatus>()?,
+                ModifyAttribute::Priority => {
+                    task.priority = optional(value).map(|p| p.parse::<Priority>()).transpose()?
+                }
+                ModifyAttribute::Due => task.due_date = parse_optional_date(value, dates)?,
+                ModifyAttribute::Scheduled => {
+                    task.scheduled_date = parse_optional_date(value, dates)?
+                }
+                ModifyAttribute::Wait => task.wait_date = parse_optional_date(value, dates)?,
+                ModifyAttribute::Until => task.until_date = parse_optional_date(value, dates)?,
+                ModifyAttribute::Recurrence => {
+                    if !value.is_empty() {
+                        value.parse::<Recurrence>()?;
+                    }
+                    task.recurrence = optional(value);
+                }
+                ModifyAttribute::Depends | ModifyAttribute::Parent => {}
+                ModifyAttribute::Effort => task.effort = parse_effort(value)?,
+            }
+        }
+
+        for (name, value) in &self.udas {
+            if value.is_empty() {
+                task.udas.remove(name);
+                continue;
+            }
+            let definition = udas.get(name).ok_or_else(|| TaskError::Validation {
+                message: format!("Unknown user-defined attribute: {name}"),
+            })?;
+            task.udas
+                .insert(name.clone(), definition.parse_value(name, value, dates)?);
+        }
+
+        for tag in &self.add_tags {
+            task.add_tag(tag.clone());
+        }
+        for tag in &self.remove_tags {
+            task.remove_tag(tag);
+        }
+        task.modified_date = Utc::now();
+        Ok(())
+    }
+}
+
+/// Parse a date value, where an empty value clears the date
+fn parse_optional_date(
+    value: &str,
+    dates: &DateParser,
+) -> Result<Option<DateTime<Utc>>, TaskError> {
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        dates.parse(value).map(Some)
+    }
+}
+
+/// Parse an effort estimate into minutes: a bare number is minutes,
+/// otherwise a duration such as `2h` or `1h30min`; empty clears it
+fn parse_effort(value: &str) -> Result<Option<u32>, TaskError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .or_else(|| parse_duration(value).and_then(|seconds| u32::try_from(seconds / 60).ok()))
+        .map(Some)
+        .ok_or_else(|| TaskError::Validation {
+            message: format!("Invalid effort: {value} (expected minutes or a duration such as 2h)"),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::UdaType;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    fn parse(line: &str) -> TaskModification {
+        TaskModification::from_args(&args(line), &BTreeMap::new())
+    }
+
+    #[test]
+    fn test_quoted_value_keeps_spaces() {
+        let modification =
+            TaskModification::from_args(&["description:New title".to_string()], &BTreeMap::new());
+        assert_eq!(
+            modification.attributes,
+            vec![(ModifyAttribute::Description, "New title".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_inline_attributes() {
+        let modification = parse("Fix login project:web +bug priority:H Re:auth");
+        assert_eq!(
+            modification.description.as_deref(),
+            Some("Fix login Re:auth")
+        );
+        assert_eq!(
+            modification.attributes,
+            vec![
+                (ModifyAttribute::Project, "web".to_string()),
+                (ModifyAttribute::Priority, "H".to_string()),
+            ]
+        );
+        assert_eq!(modification.add_tags, ["bug"]);
+        assert!(modification.remove_tags.is_empty());
+
+        let modification = parse("-later depends:3,-4 parent:2");
+        assert_eq!(modification.description, None);
+        assert_eq!(modification.remove_tags, ["later"]);
+        assert_eq!(modification.dependencies().collect::<Vec<_>>(), ["3,-4"]);
+        assert_eq!(modification.parent(), Some("2"));
+    }
+
+    #[test]
+    fn test_apply_modification() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 11, 15, 30, 0).unwrap();
+        let dates = DateParser::with_now(now, Tz::UTC);
+        let mut task = Task::new("Fix login".to_string());
+        task.project = Some("web".to_string());
+        task.add_tag("later".to_string());
+
+        parse("project: due:fri priority:L +bug -later")
+            .apply(&mut task, &dates, &BTreeMap::new())
+            .unwrap();
+        assert_eq!(task.description, "Fix login");
+        assert_eq!(task.project, None);
+        assert_eq!(task.priority, Some(Priority::Low));
+        assert_eq!(
+            task.due_date,
+            Some(Utc.with_ymd_and_hms(2026, 3, 13, 0, 0, 0).unwrap())
+        );
+        assert!(task.tags.contains("bug"));
+        assert!(!task.tags.contains("later"));
+
+        let invalid = parse("priority:urgent");
+        assert!(invalid.apply(&mut task, &dates, &BTreeMap::new()).is_err());
+
+        parse("effort:1h30min")
+            .apply(&mut task, &dates, &BTreeMap::new())
+            .unwrap();
+        assert_eq!(task.effort, Some(90));
+        parse("effort:45")
+            .apply(&mut task, &dates, &BTreeMap::new())
+            .unwrap();
+        assert_eq!(task.effort, Some(45));
+        assert!(
+            parse("effort:soon")
+                .apply(&mut task, &dates, &BTreeMap::new())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_apply_uda_modification() {
+        let dates = DateParser::new(Tz::UTC);
+        let udas = BTreeMap::from([(
+            "estimate_points".to_string(),
+            UdaDefinition {
+                kind: UdaType::Number,
+                values: Vec::new(),
+            },
+        )]);
+        let mut task = Task::new("Fix login".to_string());
+
+        let modification = TaskModification::from_args(&args("estimate_points:5 points:3"), &udas);
+        assert_eq!(
+            modification.udas,
+            vec![("estimate_points".to_string(), "5".to_string())]
+        );
+        assert_eq!(modification.description.as_deref(), Some("points:3"));
+        modification.apply(&mut task, &dates, &udas).unwrap();
+        assert_eq!(task.udas["estimate_points"], serde_json::Value::from(5));
+
+        let invalid = TaskModification::from_args(&args("estimate_points:many"), &udas);
+        assert!(invalid.apply(&mut task, &dates, &udas).is_err());
+
+        TaskModification::from_args(&args("estimate_points:"), &udas)
+            .apply(&mut task, &dates, &udas)
+            .unwrap();
+        assert!(task.udas.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::core::{Task, TaskError, TaskStatus};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Check that a project name is a dot-separated path such as `web.auth.login`
+pub fn validate_project(project: &str) -> Result<(), TaskError> {
+    if project
+        .split('.')
+        .any(|segment| segment.is_empty() || segment.chars().any(char::is_whitespace))
+    {
+        return Err(TaskError::Validation {
+            message: format!(
+                "Invalid project: {project} (expected dot-separated names such as web.auth)"
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// The project and each of its ancestors, outermost first: `web`,
+/// `web.auth`, `web.auth.login`
+pub fn project_ancestors(project: &str) -> impl Iterator<Item = &str> {
+    project
+        .match_indices('.')
+        .map(|(index, _)| &project[..index])
+        .chain(std::iter::once(project))
+}
+
+/// Check if a project is `ancestor` itself or one of its descendants
+pub fn is_within(project: &str, ancestor: &str) -> bool {
+    project
+        .strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Task counts and remaining effort of a project, including its descendants
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProjectSummary {
+    pub name: String,
+    /// Nesting level, 0 for top-level projects
+    pub depth: usize,
+    /// Pending, in-progress and waiting tasks
+    pub pending: usize,
+    pub completed: usize,
+    pub overdue: usize,
+    /// Percentage of tasks completed
+    pub completion: f64,
+    /// Estimated effort minus effort spent across open tasks, in minutes
+    pub remaining_minutes: u64,
+}
+
+impl ProjectSummary {
+    /// Summarise tasks by project, rolling each task up into every ancestor
+    /// of its project
+    ///
+    /// Deleted tasks, recurring templates and tasks without a project are
+    /// left out. Summaries come in tree order, each project before its
+    /// descendants.
+    pub fn build(tasks: &[Task]) -> Vec<Self> {
+        let mut summaries: BTreeMap<Vec<&str>, ProjectSummary> = BTreeMap::new();
+        for task in tasks {
+            let Some(project) = task.project.as_deref() else {
+                continue;
+            };
+            let open = matches!(
+                task.status,
+                TaskStatus::Pending | TaskStatus::InProgress | TaskStatus::Waiting
+            );
+            if !open && task.status != TaskStatus::Completed {
+                continue;
+            }
+            let remaining = if open {
+                task.effort
+                    .unwrap_or(0)
+                    .saturating_sub(task.effort_spent.unwrap_or(0))
+            } else {
+                0
+            };
+            let overdue = task.is_overdue();
+
+            for name in project_ancestors(project) {
+                let summary = summaries
+                    .entry(name.split('.').collect())
+                    .or_insert_with(|| ProjectSummary {
+                        name: name.to_string(),
+                        depth: name.matches('.').count(),
+                        ..Default::default()
+                    });
+                if open {
+                    summary.pending += 1;
+                } else {
+                    summary.completed += 1;
+                }
+                if overdue {
+                    summary.overdue += 1;
+                }
+                summary.remaining_minutes += u64::from(remaining);
+            }
+        }
+
+        summaries
+            .into_values()
+            .map(|mut summary| {
+                let total = summary.pending + summary.completed;
+                summary.completion = summary.completed as f64 * 100.0 / total as f64;
+                summary
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn task(description: &str, project: &str) -> Task {
+        let mut task = Task::new(description.to_string());
+        task.project = Some(project.to_string());
+        task
+    }
+
+    #[test]
+    fn test_project_paths() {
+        assert!(validate_project("web.auth.login").is_ok());
+        assert!(validate_project("web..auth").is_err());
+        assert!(validate_project(".web").is_err());
+        assert!(validate_project("web auth").is_err());
+
+        assert_eq!(
+            project_ancestors("web.auth.login").collect::<Vec<_>>(),
+            ["web", "web.auth", "web.auth.login"]
+        );
+        assert!(is_within("web.auth", "web"));
+        assert!(is_within("web", "web"));
+        assert!(!is_within("webapp", "web"));
+        assert!(!is_within("web", "web.auth"));
+    }
+
+    #[test]
+    fn test_summaries_roll_up() {
+        let mut login = task("Fix login", "web.auth");
+        login.effort = Some(120);
+        login.effort_spent = Some(45);
+        login.due_date = Some(Utc::now() - Duration::days(1));
+        let mut styles = task("Update styles", "web-ui");
+        styles.effort = Some(30);
+        let mut docs = task("Write docs", "web");
+        docs.status = TaskStatus::Completed;
+        docs.effort = Some(60);
+        let mut old = task("Old idea", "web.auth");
+        old.status = TaskStatus::Deleted;
+        let tasks = vec![
+            login,
+            styles,
+            docs,
+            old,
+            Task::new("No project".to_string()),
+        ];
+
+        let summaries = ProjectSummary::build(&tasks);
+        let names: Vec<&str> = summaries.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["web", "web.auth", "web-ui"]);
+
+        let web = &summaries[0];
+        assert_eq!((web.pending, web.completed, web.overdue), (1, 1, 1));
+        assert_eq!(web.completion, 50.0);
+        assert_eq!(web.remaining_minutes, 75);
+        assert_eq!(summaries[1].depth, 1);
+        assert_eq!(summaries[1].completion, 0.0);
+        assert_eq!(summaries[2].remaining_minutes, 30);
+    }
+}
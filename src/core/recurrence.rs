@@ -0,0 +1,407 @@
+use crate::core::TaskError;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc, Weekday};
+
+/// Upper bound on the number of periods walked when expanding a pattern,
+/// so patterns whose weekday filter never matches can't loop forever
+const MAX_PERIODS: u32 = 100_000;
+
+/// Recurrence mask marker for a pending instance
+pub const MASK_PENDING: char = '-';
+/// Recurrence mask marker for a completed instance
+pub const MASK_COMPLETED: char = '+';
+/// Recurrence mask marker for a deleted instance
+pub const MASK_DELETED: char = 'X';
+/// Recurrence mask marker for a waiting instance
+pub const MASK_WAITING: char = 'W';
+
+/// Base frequency of a recurrence pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Parsed recurrence pattern
+///
+/// Accepts the Taskwarrior-style names (`daily`, `weekly`, `monthly`,
+/// `yearly`, `weekdays`, `biweekly`, `quarterly`), periods such as `every 2w`,
+/// `every 3 days` or `2w`, and a subset of iCalendar RRULEs
+/// (`FREQ`, `INTERVAL`, `BYDAY`, `UNTIL`, `COUNT`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// Restrict occurrences to these weekdays (daily and weekly only)
+    pub weekdays: Vec<Weekday>,
+    pub until: Option<DateTime<Utc>>,
+    pub count: Option<u32>,
+}
+
+impl Recurrence {
+    fn new(frequency: Frequency, interval: u32) -> Self {
+        Self {
+            frequency,
+            interval,
+            weekdays: Vec::new(),
+            until: None,
+            count: None,
+        }
+    }
+
+    /// Iterate over the occurrence dates of this pattern, starting at `anchor`
+    ///
+    /// The n-th item is the due date of the n-th instance, which is what the
+    /// recurrence mask index refers to.
+    pub fn occurrences(&self, anchor: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        let until = self.until;
+        let count = self.count.map(|c| c as usize).unwrap_or(usize::MAX);
+
+        (0..MAX_PERIODS)
+            .flat_map(move |period| self.period_dates(anchor, period))
+            .take_while(move |date| until.is_none_or(|until| *date <= until))
+            .take(count)
+    }
+
+    /// Candidate dates within the given period, in chronological order
+    fn period_dates(&self, anchor: DateTime<Utc>, period: u32) -> Vec<DateTime<Utc>> {
+        let step = period * self.interval;
+        match self.frequency {
+            Frequency::Daily => {
+                let date = anchor + Duration::days(step as i64);
+                if self.matches_weekday(date) {
+                    vec![date]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                vec![anchor + Duration::weeks(step as i64)]
+            }
+            Frequency::Weekly => {
+                let days_from_monday = anchor.weekday().num_days_from_monday() as i64;
+                let week_start =
+                    anchor - Duration::days(days_from_monday) + Duration::weeks(step as i64);
+                let mut dates: Vec<DateTime<Utc>> = self
+                    .weekdays
+                    .iter()
+                    .map(|day| week_start + Duration::days(day.num_days_from_monday() as i64))
+                    .filter(|date| *date >= anchor)
+                    .collect();
+                dates.sort();
+                dates
+            }
+            Frequency::Monthly => anchor
+                .checked_add_months(Months::new(step))
+                .into_iter()
+                .collect(),
+            Frequency::Yearly => anchor
+                .checked_add_months(Months::new(step * 12))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn matches_weekday(&self, date: DateTime<Utc>) -> bool {
+        self.weekdays.is_empty() || self.weekdays.contains(&date.weekday())
+    }
+}
+
+impl std::str::FromStr for Recurrence {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim();
+        let lower = input.to_lowercase();
+
+        if lower.starts_with("rrule:") || lower.starts_with("freq=") {
+            return parse_rrule(input);
+        }
+
+        let named = match lower.as_str() {
+            "daily" => Some(Recurrence::new(Frequency::Daily, 1)),
+            "weekly" => Some(Recurrence::new(Frequency::Weekly, 1)),
+            "biweekly" | "fortnightly" => Some(Recurrence::new(Frequency::Weekly, 2)),
+            "monthly" => Some(Recurrence::new(Frequency::Monthly, 1)),
+            "quarterly" => Some(Recurrence::new(Frequency::Monthly, 3)),
+            "yearly" | "annual" | "annually" => Some(Recurrence::new(Frequency::Yearly, 1)),
+            "weekdays" => Some(Recurrence {
+                weekdays: vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ],
+                ..Recurrence::new(Frequency::Daily, 1)
+            }),
+            _ => None,
+        };
+        if let Some(recurrence) = named {
+            return Ok(recurrence);
+        }
+
+        let period = lower.strip_prefix("every").unwrap_or(&lower).trim();
+        parse_period(period).ok_or_else(|| TaskError::Validation {
+            message: format!("Invalid recurrence pattern: {input}"),
+        })
+    }
+}
+
+/// Parse a period such as `2w`, `3 days` or `month`
+fn parse_period(period: &str) -> Option<Recurrence> {
+    let split = period
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(period.len());
+    let (number, unit) = period.split_at(split);
+    let interval = if number.is_empty() {
+        1
+    } else {
+        number.parse::<u32>().ok()?
+    };
+    if interval == 0 {
+        return None;
+    }
+
+    let frequency = match unit.trim() {
+        "d" | "day" | "days" => Frequency::Daily,
+        "w" | "wk" | "wks" | "week" | "weeks" => Frequency::Weekly,
+        "mo" | "mos" | "month" | "months" => Frequency::Monthly,
+        "y" | "yr" | "yrs" | "year" | "years" => Frequency::Yearly,
+        _ => return None,
+    };
+
+    Some(Recurrence::new(frequency, interval))
+}
+
+/// Parse the supported RRULE subset, e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`
+fn parse_rrule(input: &str) -> Result<Recurrence, TaskError> {
+    let invalid = |message: String| TaskError::Validation { message };
+    let rule = input
+        .split_once(':')
+        .filter(|(prefix, _)| prefix.eq_ignore_ascii_case("rrule"))
+        .map(|(_, rule)| rule)
+        .unwrap_or(input);
+
+    let mut frequency = None;
+    let mut interval = 1;
+    let mut weekdays = Vec::new();
+    let mut until = None;
+    let mut count = None;
+
+    for part in rule.split(';').filter(|p| !p.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("Invalid RRULE part: {part}")))?;
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return Err(invalid(format!("Unsupported RRULE frequency: {value}"))),
+                })
+            }
+            "INTERVAL" => {
+                interval = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|i| *i > 0)
+                    .ok_or_else(|| invalid(format!("Invalid RRULE interval: {value}")))?;
+            }
+            "BYDAY" => {
+                for day in value.split(',') {
+                    weekdays.push(
+                        parse_rrule_weekday(day)
+                            .ok_or_else(|| invalid(format!("Invalid RRULE weekday: {day}")))?,
+                    );
+                }
+            }
+            "UNTIL" => until = Some(parse_rrule_until(value)?),
+            "COUNT" => {
+                count = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| invalid(format!("Invalid RRULE count: {value}")))?,
+                );
+            }
+            other => return Err(invalid(format!("Unsupported RRULE part: {other}"))),
+        }
+    }
+
+    let frequency = frequency.ok_or_else(|| invalid("RRULE is missing FREQ".to_string()))?;
+    if !weekdays.is_empty() && !matches!(frequency, Frequency::Daily | Frequency::Weekly) {
+        return Err(invalid(
+            "RRULE BYDAY is only supported with DAILY or WEEKLY".to_string(),
+        ));
+    }
+
+    Ok(Recurrence {
+        frequency,
+        interval,
+        weekdays,
+        until,
+        count,
+    })
+}
+
+fn parse_rrule_weekday(day: &str) -> Option<Weekday> {
+    match day.trim().to_uppercase().as_str() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Parse an RRULE `UNTIL` value (`YYYYMMDD` or `YYYYMMDDTHHMMSSZ`)
+fn parse_rrule_until(value: &str) -> Result<DateTime<Utc>, TaskError> {
+    let parsed = if value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(|date| date.and_hms_opt(23, 59, 59).unwrap().and_utc())
+    } else {
+        chrono::NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|date| date.and_utc())
+    };
+
+    parsed.ok_or_else(|| TaskError::Validation {
+        message: format!("Invalid RRULE until: {value}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 9, 0, 0).unwrap()
+    }
+
+    fn first(pattern: &str, anchor: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+        pattern
+            .parse::<Recurrence>()
+            .unwrap()
+            .occurrences(anchor)
+            .take(n)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_named_patterns() {
+        assert_eq!(
+            "daily".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Daily, 1)
+        );
+        assert_eq!(
+            "Weekly".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Weekly, 1)
+        );
+        assert_eq!(
+            "quarterly".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Monthly, 3)
+        );
+        assert_eq!("weekdays".parse::<Recurrence>().unwrap().weekdays.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_period_patterns() {
+        assert_eq!(
+            "every 2w".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Weekly, 2)
+        );
+        assert_eq!(
+            "every 3 days".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Daily, 3)
+        );
+        assert_eq!(
+            "6mo".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Monthly, 6)
+        );
+        assert_eq!(
+            "every year".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Yearly, 1)
+        );
+    }
+
+    #[test]
+    fn test_parse_rrule() {
+        let recurrence: Recurrence = "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;COUNT=4"
+            .parse()
+            .unwrap();
+        assert_eq!(recurrence.frequency, Frequency::Weekly);
+        assert_eq!(recurrence.interval, 2);
+        assert_eq!(recurrence.weekdays, vec![Weekday::Mon, Weekday::Thu]);
+        assert_eq!(recurrence.count, Some(4));
+
+        let recurrence: Recurrence = "FREQ=DAILY;UNTIL=20261105".parse().unwrap();
+        assert_eq!(
+            recurrence.until,
+            Some(Utc.with_ymd_and_hms(2026, 11, 5, 23, 59, 59).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_patterns() {
+        assert!("sometimes".parse::<Recurrence>().is_err());
+        assert!("every 0d".parse::<Recurrence>().is_err());
+        assert!("every 2 fortnights".parse::<Recurrence>().is_err());
+        assert!("FREQ=HOURLY".parse::<Recurrence>().is_err());
+        assert!("INTERVAL=2".parse::<Recurrence>().is_err());
+        assert!("FREQ=MONTHLY;BYDAY=MO".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;BYSETPOS=1".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_occurrences_daily_and_weekly() {
+        let anchor = date(2026, 10, 1);
+        assert_eq!(
+            first("every 2d", anchor, 3),
+            vec![date(2026, 10, 1), date(2026, 10, 3), date(2026, 10, 5)]
+        );
+        assert_eq!(
+            first("weekly", anchor, 2),
+            vec![date(2026, 10, 1), date(2026, 10, 8)]
+        );
+    }
+
+    #[test]
+    fn test_occurrences_weekdays_skip_weekend() {
+        // 2026-10-02 is a Friday
+        assert_eq!(
+            first("weekdays", date(2026, 10, 2), 3),
+            vec![date(2026, 10, 2), date(2026, 10, 5), date(2026, 10, 6)]
+        );
+    }
+
+    #[test]
+    fn test_occurrences_monthly_clamps_to_month_end() {
+        assert_eq!(
+            first("monthly", date(2026, 1, 31), 3),
+            vec![date(2026, 1, 31), date(2026, 2, 28), date(2026, 3, 31)]
+        );
+    }
+
+    #[test]
+    fn test_occurrences_rrule_byday_until_count() {
+        // 2026-10-01 is a Thursday
+        assert_eq!(
+            first("FREQ=WEEKLY;BYDAY=MO,TH", date(2026, 10, 1), 3),
+            vec![date(2026, 10, 1), date(2026, 10, 5), date(2026, 10, 8)]
+        );
+
+        let recurrence: Recurrence = "FREQ=DAILY;COUNT=2".parse().unwrap();
+        assert_eq!(recurrence.occurrences(date(2026, 10, 1)).count(), 2);
+
+        let recurrence: Recurrence = "FREQ=DAILY;UNTIL=20261003".parse().unwrap();
+        assert_eq!(recurrence.occurrences(date(2026, 10, 1)).count(), 3);
+    }
+}
@@ -0,0 +1,713 @@
+use crate::core::date::DateParser;
+use crate::core::uda::{UdaDefinition, UdaType};
+use crate::core::{FilterExpr, Priority, Task, TaskError};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Narrowest the description column is squeezed to before other columns
+/// are dropped to fit the terminal
+const MIN_DESCRIPTION_WIDTH: usize = 15;
+
+/// A named report, declared under `[report.<name>]` in the config
+///
+/// ```toml
+/// [report.triage]
+/// description = "Untriaged bugs"
+/// filter = "+bug status:pending"
+/// columns = ["id", "priority", "due.relative", "description"]
+/// sort = ["priority-", "due+"]
+/// limit = 10
+/// widths = { description = 40 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportDefinition {
+    /// One-line summary shown by `task reports`
+    pub description: String,
+    /// Filter expression, combined with any filter given on the command line
+    pub filter: String,
+    /// Columns to show, e.g. `id`, `project`, `due.relative` or a UDA name
+    pub columns: Vec<String>,
+    /// Sort keys: a column with `+` (ascending) or `-` (descending)
+    pub sort: Vec<String>,
+    /// Show at most this many tasks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Maximum width per column
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub widths: BTreeMap<String, usize>,
+}
+
+impl Default for ReportDefinition {
+    fn default() -> Self {
+        Self {
+            description: String::new(),
+            filter: String::new(),
+            columns: strings(&[
+                "id",
+                "status",
+                "project",
+                "priority",
+                "tags",
+                "due.relative",
+                "age",
+                "description",
+            ]),
+            sort: strings(&["id+"]),
+            limit: None,
+            widths: BTreeMap::new(),
+        }
+    }
+}
+
+impl ReportDefinition {
+    /// Check that the filter, columns and sort keys all parse
+    pub fn validate(
+        &self,
+        name: &str,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<(), TaskError> {
+        let context = |error: TaskError| TaskError::Validation {
+            message: format!("Report {name}: {}", error_message(error)),
+        };
+        self.columns(udas).map_err(context)?;
+        self.sort_keys(udas).map_err(context)?;
+        self.filter(DateParser::new(chrono_tz::Tz::UTC), udas)
+            .map_err(context)?;
+        if let Some(column) = self.widths.keys().find(|c| !self.columns.contains(c)) {
+            return Err(TaskError::Validation {
+                message: format!("Report {name}: width given for unused column {column}"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Parsed filter, or `None` when the report shows every task
+    pub fn filter(
+        &self,
+        dates: DateParser,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<Option<FilterExpr>, TaskError> {
+        if self.filter.trim().is_empty() {
+            return Ok(None);
+        }
+        FilterExpr::from_args_with(std::slice::from_ref(&self.filter), dates, udas).map(Some)
+    }
+
+    /// Parsed columns
+    pub fn columns(
+        &self,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<Vec<Column>, TaskError> {
+        if self.columns.is_empty() {
+            return Err(TaskError::Validation {
+                message: "No columns given".to_string(),
+            });
+        }
+        self.columns
+            .iter()
+            .map(|spec| Column::parse(spec, udas))
+            .collect()
+    }
+
+    /// Parsed sort keys
+    pub fn sort_keys(
+        &self,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<Vec<SortKey>, TaskError> {
+        self.sort
+            .iter()
+            .map(|spec| SortKey::parse(spec, udas))
+            .collect()
+    }
+}
+
+/// Reports available without any configuration; `[report.<name>]` tables
+/// replace them or add new ones
+pub fn builtin_reports() -> BTreeMap<String, ReportDefinition> {
+    let open = "(status:pending or status:in_progress)";
+    let report =
+        |description: &str, filter: &str, columns: &[&str], sort: &[&str]| ReportDefinition {
+            description: description.to_string(),
+            filter: filter.to_string(),
+            columns: strings(columns),
+            sort: strings(sort),
+            limit: None,
+            widths: BTreeMap::new(),
+        };
+
+    BTreeMap::from([
+        (
+            "list".to_string(),
+            ReportDefinition {
+                description: "All tasks".to_string(),
+                ..ReportDefinition::default()
+            },
+        ),
+        (
+            "next".to_string(),
+            ReportDefinition {
+                limit: Some(20),
+                ..report(
+                    "Most urgent open tasks",
+                    open,
+                    &[
+                        "id",
+                        "project",
+                        "priority",
+                        "tags",
+                        "due.relative",
+                        "age",
+                        "description",
+                        "urgency",
+                    ],
+                    &["urgency-"],
+                )
+            },
+        ),
+        (
+            "active".to_string(),
+            report(
+                "Started tasks",
+                "status:in_progress",
+                &["id", "start.relative", "project", "priority", "description"],
+                &["start+"],
+            ),
+        ),
+        (
+            "overdue".to_string(),
+            report(
+                "Open tasks past their due date",
+                &format!("{open} and due.before:now"),
+                &["id", "due.relative", "project", "priority", "description"],
+                &["due+"],
+            ),
+        ),
+        (
+            "waiting".to_string(),
+            report(
+                "Tasks hidden until their wait date",
+                "status:waiting",
+                &["id", "wait.relative", "project", "description"],
+                &["wait+"],
+            ),
+        ),
+        (
+            "completed".to_string(),
+            report(
+                "Completed tasks",
+                "status:completed",
+                &["uuid", "end", "project", "priority", "description"],
+                &["end-"],
+            ),
+        ),
+        (
+            "recent".to_string(),
+            report(
+                "Tasks added in the last week",
+                "entry.after:today-7d",
+                &["id", "entry.relative", "status", "project", "description"],
+                &["entry-"],
+            ),
+        ),
+    ])
+}
+
+/// Date attribute shown by a date column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Due,
+    Scheduled,
+    Wait,
+    Until,
+    Start,
+    End,
+    Entry,
+    Modified,
+}
+
+impl DateField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "due" => Some(DateField::Due),
+            "scheduled" => Some(DateField::Scheduled),
+            "wait" => Some(DateField::Wait),
+            "until" => Some(DateField::Until),
+            "start" => Some(DateField::Start),
+            "end" => Some(DateField::End),
+            "entry" => Some(DateField::Entry),
+            "modified" => Some(DateField::Modified),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            DateField::Due => "Due",
+            DateField::Scheduled => "Scheduled",
+            DateField::Wait => "Wait",
+            DateField::Until => "Until",
+            DateField::Start => "Started",
+            DateField::End => "Completed",
+            DateField::Entry => "Added",
+            DateField::Modified => "Modified",
+        }
+    }
+
+    fn value(&self, task: &Task) -> Option<DateTime<Utc>> {
+        match self {
+            DateField::Due => task.due_date,
+            DateField::Scheduled => task.scheduled_date,
+            DateField::Wait => task.wait_date,
+            DateField::Until => task.until_date,
+            DateField::Start => task.start_date,
+            DateField::End => task.end_date,
+            DateField::Entry => Some(task.entry_date),
+            DateField::Modified => Some(task.modified_date),
+        }
+    }
+}
+
+/// A report column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    /// First eight characters of the UUID
+    Uuid,
+    Description,
+    Status,
+    Project,
+    Priority,
+    Tags,
+    /// Number of dependencies
+    Depends,
+    Recur,
+    Urgency,
+    /// Time since the task was added
+    Age,
+    /// A date as `YYYY-MM-DD`, or relative (`2d ago`, `in 3w`) with the
+    /// `.relative` suffix
+    Date {
+        field: DateField,
+        relative: bool,
+    },
+    /// A user-defined attribute
+    Uda {
+        name: String,
+        kind: UdaType,
+    },
+}
+
+impl Column {
+    /// Parse a column name such as `project` or `due.relative`
+    pub fn parse(spec: &str, udas: &BTreeMap<String, UdaDefinition>) -> Result<Self, TaskError> {
+        let (name, style) = match spec.split_once('.') {
+            Some((name, style)) => (name, Some(style)),
+            None => (spec, None),
+        };
+        let column = match (name, style) {
+            ("id", None) => Column::Id,
+            ("uuid", None) => Column::Uuid,
+            ("description", None) => Column::Description,
+            ("status", None) => Column::Status,
+            ("project", None) => Column::Project,
+            ("priority", None) => Column::Priority,
+            ("tags", None) => Column::Tags,
+            ("depends", None) => Column::Depends,
+            ("recur", None) => Column::Recur,
+            ("urgency", None) => Column::Urgency,
+            ("age", None) => Column::Age,
+            (name, None | Some("relative")) if DateField::from_name(name).is_some() => {
+                Column::Date {
+                    field: DateField::from_name(name).unwrap(),
+                    relative: style.is_some(),
+                }
+            }
+            (name, None) if udas.contains_key(name) => Column::Uda {
+                name: name.to_string(),
+                kind: udas[name].kind,
+            },
+            _ => {
+                return Err(TaskError::Validation {
+                    message: format!("Unknown column: {spec}"),
+                });
+            }
+        };
+        Ok(column)
+    }
+
+    /// Header text
+    pub fn label(&self) -> String {
+        match self {
+            Column::Id => "ID".to_string(),
+            Column::Uuid => "UUID".to_string(),
+            Column::Description => "Description".to_string(),
+            Column::Status => "Status".to_string(),
+            Column::Project => "Project".to_string(),
+            Column::Priority => "Pri".to_string(),
+            Column::Tags => "Tags".to_string(),
+            Column::Depends => "Deps".to_string(),
+            Column::Recur => "Recur".to_string(),
+            Column::Urgency => "Urg".to_string(),
+            Column::Age => "Age".to_string(),
+            Column::Date { field, .. } => field.label().to_string(),
+            Column::Uda { name, .. } => name.clone(),
+        }
+    }
+
+    /// Whether cells are right-aligned
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Column::Id | Column::Urgency | Column::Depends | Column::Age
+        ) || matches!(self, Column::Uda { kind, .. } if *kind == UdaType::Number)
+    }
+
+    /// Cell text for a task; `dates` supplies the timezone and current time
+    pub fn render(&self, task: &Task, urgency: f64, dates: &DateParser) -> String {
+        match self {
+            Column::Id => task.id.map(|id| id.to_string()).unwrap_or_default(),
+            Column::Uuid => task.uuid.to_string()[..8].to_string(),
+            Column::Description => task.description.clone(),
+            Column::Status => task.status.to_string(),
+            Column::Project => task.project.clone().unwrap_or_default(),
+            Column::Priority => task
+                .priority
+                .as_ref()
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            Column::Tags => {
+                let mut tags: Vec<&String> = task.tags.iter().collect();
+                tags.sort();
+                tags.into_iter()
+                    .map(|t| t.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+            Column::Depends if task.depends.is_empty() => String::new(),
+            Column::Depends => task.depends.len().to_string(),
+            Column::Recur => task.recurrence.clone().unwrap_or_default(),
+            Column::Urgency if urgency == 0.0 => String::new(),
+            Column::Urgency => format!("{urgency:.1}"),
+            Column::Age => format_age(dates.now() - task.entry_date),
+            Column::Date { field, relative } => match field.value(task) {
+                None => String::new(),
+                Some(date) if *relative => format_relative(date, dates.now()),
+                Some(date) => dates.format_date(date),
+            },
+            Column::Uda { name, .. } => match task.udas.get(name) {
+                None => String::new(),
+                Some(Value::String(text)) => text.clone(),
+                Some(value) => value.to_string(),
+            },
+        }
+    }
+
+    /// Value used for sorting; `None` sorts last in either direction
+    fn sort_value(&self, task: &Task, urgency: f64) -> Option<SortValue> {
+        let text = |s: &str| Some(SortValue::Text(s.to_lowercase()));
+        match self {
+            Column::Id => task.id.map(|id| SortValue::Number(id as f64)),
+            Column::Uuid => text(&task.uuid.to_string()),
+            Column::Description => text(&task.description),
+            Column::Status => text(&task.status.to_string()),
+            Column::Project => task.project.as_deref().and_then(text),
+            Column::Priority => task.priority.clone().map(SortValue::Priority),
+            Column::Tags => {
+                let mut tags: Vec<&String> = task.tags.iter().collect();
+                tags.sort();
+                tags.first().and_then(|tag| text(tag))
+            }
+            Column::Depends => Some(SortValue::Number(task.depends.len() as f64)),
+            Column::Recur => task.recurrence.as_deref().and_then(text),
+            Column::Urgency => Some(SortValue::Number(urgency)),
+            // Oldest first when ascending
+            Column::Age => Some(SortValue::Date(-task.entry_date.timestamp())),
+            Column::Date { field, .. } => field.value(task).map(|d| SortValue::Date(d.timestamp())),
+            Column::Uda { name, .. } => match task.udas.get(name)? {
+                Value::Number(n) => n.as_f64().map(SortValue::Number),
+                Value::String(s) => text(s),
+                other => text(&other.to_string()),
+            },
+        }
+    }
+}
+
+/// Comparable form of a cell
+#[derive(Debug, Clone, PartialEq)]
+enum SortValue {
+    Number(f64),
+    Text(String),
+    Priority(Priority),
+    Date(i64),
+}
+
+impl SortValue {
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (SortValue::Priority(a), SortValue::Priority(b)) => {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            }
+            (SortValue::Date(a), SortValue::Date(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// A report sort key such as `urgency-` or `due+`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: Column,
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Parse a sort key; without a `+` or `-` suffix urgency sorts
+    /// descending and everything else ascending
+    pub fn parse(spec: &str, udas: &BTreeMap<String, UdaDefinition>) -> Result<Self, TaskError> {
+        let (name, descending) = match spec.strip_suffix('-') {
+            Some(name) => (name, Some(true)),
+            None => match spec.strip_suffix('+') {
+                Some(name) => (name, Some(false)),
+                None => (spec, None),
+            },
+        };
+        let column = Column::parse(name, udas).map_err(|_| TaskError::Validation {
+            message: format!("Invalid sort key: {spec}"),
+        })?;
+        let descending = descending.unwrap_or(column == Column::Urgency);
+        Ok(Self { column, descending })
+    }
+}
+
+/// Order tasks, each paired with its urgency, by the given keys; ties keep
+/// their original order
+pub fn sort_tasks(tasks: &mut [(Task, f64)], keys: &[SortKey]) {
+    tasks.sort_by(|(a, urgency_a), (b, urgency_b)| {
+        for key in keys {
+            let order = match (
+                key.column.sort_value(a, *urgency_a),
+                key.column.sort_value(b, *urgency_b),
+            ) {
+                (Some(x), Some(y)) if key.descending => y.compare(&x),
+                (Some(x), Some(y)) => x.compare(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        Ordering::Equal
+    });
+}
+
+/// Lay out a table of cells as lines of text
+///
+/// Columns that are empty for every row are left out. Column widths are
+/// capped by `max_widths`; when `width` is given the description column is
+/// narrowed to fit it and, if that is not enough, columns are dropped from
+/// the right. Cells that don't fit are cut short with `…`.
+pub fn layout(
+    columns: &[Column],
+    max_widths: &[Option<usize>],
+    rows: &[Vec<String>],
+    width: Option<usize>,
+) -> Vec<String> {
+    let chars = |s: &str| s.chars().count();
+    let mut visible: Vec<usize> = (0..columns.len())
+        .filter(|&i| columns[i] == Column::Description || rows.iter().any(|row| !row[i].is_empty()))
+        .collect();
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let natural = rows
+                .iter()
+                .map(|row| chars(&row[i]))
+                .chain([chars(&column.label())])
+                .max()
+                .unwrap_or(0);
+            max_widths[i].map_or(natural, |max| natural.min(max.max(1)))
+        })
+        .collect();
+
+    if let Some(width) = width {
+        let total = |visible: &[usize], widths: &[usize]| {
+            visible.iter().map(|&i| widths[i]).sum::<usize>() + visible.len().saturating_sub(1)
+        };
+        let description = visible
+            .iter()
+            .copied()
+            .find(|&i| columns[i] == Column::Description);
+        if let Some(d) = description {
+            let excess = total(&visible, &widths).saturating_sub(width);
+            widths[d] = widths[d]
+                .saturating_sub(excess)
+                .max(MIN_DESCRIPTION_WIDTH.min(widths[d]));
+        }
+        while visible.len() > 1 && total(&visible, &widths) > width {
+            let last = visible
+                .iter()
+                .rposition(|&i| Some(i) != description)
+                .unwrap_or(visible.len() - 1);
+            visible.remove(last);
+        }
+    }
+
+    let line = |cells: Vec<String>| {
+        let parts: Vec<String> = visible
+            .iter()
+            .zip(cells)
+            .map(|(&i, cell)| {
+                let cell = truncate(&cell, widths[i]);
+                if columns[i].is_numeric() {
+                    format!("{cell:>w$}", w = widths[i])
+                } else {
+                    format!("{cell:<w$}", w = widths[i])
+                }
+            })
+            .collect();
+        parts.join(" ").trim_end().to_string()
+    };
+
+    let mut lines = vec![line(visible.iter().map(|&i| columns[i].label()).collect())];
+    for row in rows {
+        lines.push(line(visible.iter().map(|&i| row[i].clone()).collect()));
+    }
+    lines
+}
+
+/// Cut text to at most `width` characters, marking the cut with `…`
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Format a date relative to now, e.g. `2d ago` or `in 3w`
+pub fn format_relative(date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now - date;
+    if elapsed.num_seconds().abs() < 60 {
+        return "now".to_string();
+    }
+    if elapsed > Duration::zero() {
+        format!("{} ago", format_age(elapsed))
+    } else {
+        format!("in {}", format_age(-elapsed))
+    }
+}
+
+/// Format a span in its largest whole unit: `45s`, `5min`, `3h`, `2d`, `3w`,
+/// `4mo` or `2y`
+pub fn format_age(span: Duration) -> String {
+    let seconds = span.num_seconds().max(0);
+    let (amount, unit) = match seconds {
+        s if s < 60 => (s, "s"),
+        s if s < 3600 => (s / 60, "min"),
+        s if s < 86_400 => (s / 3600, "h"),
+        s if s < 14 * 86_400 => (s / 86_400, "d"),
+        s if s < 90 * 86_400 => (s / (7 * 86_400), "w"),
+        s if s < 365 * 86_400 => (s / (30 * 86_400), "mo"),
+        s => (s / (365 * 86_400), "y"),
+    };
+    format!("{amount}{unit}")
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+/// Validation message without the error kind prefix
+fn error_message(error: TaskError) -> String {
+    match error {
+        TaskError::Validation { message } | TaskError::InvalidFilter { message } => message,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 11, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_reports_are_valid() {
+        for (name, report) in builtin_reports() {
+            assert!(report.validate(&name, &BTreeMap::new()).is_ok(), "{name}");
+        }
+        let report = ReportDefinition {
+            columns: strings(&["id", "colour"]),
+            ..ReportDefinition::default()
+        };
+        assert!(report.validate("bad", &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        assert_eq!(format_relative(now() - Duration::days(2), now()), "2d ago");
+        assert_eq!(format_relative(now() + Duration::days(21), now()), "in 3w");
+        assert_eq!(format_relative(now() - Duration::hours(5), now()), "5h ago");
+        assert_eq!(format_relative(now(), now()), "now");
+        assert_eq!(format_age(Duration::days(400)), "1y");
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let udas = BTreeMap::new();
+        let mut a = Task::new("Alpha".to_string());
+        a.id = Some(1);
+        let mut b = Task::new("Beta".to_string());
+        b.id = Some(2);
+        b.due_date = Some(now());
+        let mut tasks = vec![(a, 1.0), (b, 5.0)];
+
+        sort_tasks(&mut tasks, &[SortKey::parse("urgency", &udas).unwrap()]);
+        assert_eq!(tasks[0].0.description, "Beta");
+
+        // Tasks without a due date sort last either way
+        sort_tasks(&mut tasks, &[SortKey::parse("due-", &udas).unwrap()]);
+        assert_eq!(tasks[0].0.description, "Beta");
+
+        sort_tasks(&mut tasks, &[SortKey::parse("id+", &udas).unwrap()]);
+        assert_eq!(tasks[0].0.description, "Alpha");
+        assert!(SortKey::parse("colour-", &udas).is_err());
+    }
+
+    #[test]
+    fn test_layout_fits_width() {
+        let columns = vec![Column::Id, Column::Project, Column::Description];
+        let rows = vec![
+            vec![
+                "1".to_string(),
+                String::new(),
+                "Write the quarterly report".to_string(),
+            ],
+            vec!["12".to_string(), String::new(), "Call Bob".to_string()],
+        ];
+        let lines = layout(&columns, &[None, None, None], &rows, None);
+        // The empty project column is left out
+        assert_eq!(lines[0], "ID Description");
+        assert_eq!(lines[1], " 1 Write the quarterly report");
+
+        let lines = layout(&columns, &[None, None, None], &rows, Some(20));
+        assert_eq!(lines[1], " 1 Write the quarte…");
+        assert!(lines.iter().all(|line| line.chars().count() <= 20));
+
+        let lines = layout(&columns, &[None, None, Some(5)], &rows, None);
+        assert_eq!(lines[2], "12 Call…");
+    }
+}
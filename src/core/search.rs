@@ -0,0 +1,76 @@
+use crate::core::TaskStatus;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Kind of record a search hit points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchEntity {
+    Task,
+    Document,
+}
+
+impl SearchEntity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchEntity::Task => "task",
+            SearchEntity::Document => "document",
+        }
+    }
+}
+
+impl std::fmt::Display for SearchEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A task or document matching a full-text search, best matches first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub entity: SearchEntity,
+    pub id: i64,
+    pub uuid: Uuid,
+    /// Task description or document title
+    pub title: String,
+    /// Best-matching passage, with matched terms wrapped in `**`
+    pub snippet: String,
+    /// Status of a task hit; documents have none
+    pub status: Option<TaskStatus>,
+    /// Relevance, higher is better
+    pub score: f64,
+}
+
+/// Turn free-text search terms into an FTS5 query matching all of them
+///
+/// Every word is quoted, so punctuation such as `-` or `:` is searched for
+/// rather than read as query syntax; a trailing `*` keeps its meaning as a
+/// prefix match. Returns `None` when there is nothing to search for.
+pub fn fts_query(terms: &str) -> Option<String> {
+    let words: Vec<String> = terms
+        .split_whitespace()
+        .filter_map(|word| {
+            let (word, prefix) = match word.strip_suffix('*') {
+                Some(stem) => (stem, "*"),
+                None => (word, ""),
+            };
+            (!word.is_empty()).then(|| format!("\"{}\"{prefix}", word.replace('"', "\"\"")))
+        })
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("fix  login").as_deref(), Some(r#""fix" "login""#));
+        assert_eq!(
+            fts_query(r#"auth* x-ray "token""#).as_deref(),
+            Some(r#""auth"* "x-ray" """token""""#)
+        );
+        assert_eq!(fts_query("  * "), None);
+    }
+}
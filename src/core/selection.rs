@@ -0,0 +1,125 @@
+use crate::core::date::DateParser;
+use crate::core::uda::UdaDefinition;
+use crate::core::{FilterExpr, TaskError};
+use std::collections::BTreeMap;
+
+/// Shortest UUID prefix accepted as a task reference
+const MIN_UUID_PREFIX: usize = 8;
+
+/// Explicit reference to one or more tasks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskReference {
+    Id(i64),
+    /// Inclusive range of IDs such as `3-7`
+    Range(i64, i64),
+    /// Leading characters of a task UUID
+    UuidPrefix(String),
+}
+
+impl std::str::FromStr for TaskReference {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TaskError::Validation {
+            message: format!("Invalid task reference: {s}"),
+        };
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+        if is_number(s) {
+            return s.parse().map(TaskReference::Id).map_err(|_| invalid());
+        }
+        if let Some((start, end)) = s.split_once('-')
+            && is_number(start)
+            && is_number(end)
+        {
+            let start: i64 = start.parse().map_err(|_| invalid())?;
+            let end: i64 = end.parse().map_err(|_| invalid())?;
+            if start > end {
+                return Err(invalid());
+            }
+            return Ok(TaskReference::Range(start, end));
+        }
+        if s.len() >= MIN_UUID_PREFIX && s.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Ok(TaskReference::UuidPrefix(s.to_lowercase()));
+        }
+        Err(invalid())
+    }
+}
+
+/// Tasks a command applies to: explicit references such as `1,4 7-9 a1b2c3d4`
+/// or, when any word isn't a reference, a filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskSelection {
+    References(Vec<TaskReference>),
+    Filter(FilterExpr),
+}
+
+impl TaskSelection {
+    /// Parse command-line arguments, resolving dates in filters with the given
+    /// parser and accepting terms on the given user-defined attributes
+    pub fn from_args(
+        args: &[String],
+        dates: DateParser,
+        udas: &BTreeMap<String, UdaDefinition>,
+    ) -> Result<Self, TaskError> {
+        let words: Vec<&str> = args
+            .iter()
+            .flat_map(|arg| arg.split([',', ' ']))
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.is_empty() {
+            return Err(TaskError::Validation {
+                message: "No tasks selected".to_string(),
+            });
+        }
+
+        match words
+            .iter()
+            .map(|word| word.parse::<TaskReference>())
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(references) => Ok(TaskSelection::References(references)),
+            Err(_) => Ok(TaskSelection::Filter(FilterExpr::from_args_with(
+                args, dates, udas,
+            )?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Tz;
+
+    fn select(args: &[&str]) -> Result<TaskSelection, TaskError> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        TaskSelection::from_args(&args, DateParser::new(Tz::UTC), &BTreeMap::new())
+    }
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(
+            select(&["1,4", "7-9", "A1B2C3D4"]).unwrap(),
+            TaskSelection::References(vec![
+                TaskReference::Id(1),
+                TaskReference::Id(4),
+                TaskReference::Range(7, 9),
+                TaskReference::UuidPrefix("a1b2c3d4".to_string()),
+            ])
+        );
+        assert!("9-7".parse::<TaskReference>().is_err());
+        assert!("abc".parse::<TaskReference>().is_err());
+    }
+
+    #[test]
+    fn test_parse_filter_selection() {
+        let selection = select(&["project:web +bug"]).unwrap();
+        assert!(matches!(selection, TaskSelection::Filter(_)));
+
+        // A single non-reference word turns the whole selection into a filter
+        let selection = select(&["1", "+bug"]).unwrap();
+        assert!(matches!(selection, TaskSelection::Filter(_)));
+
+        assert!(select(&[]).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::core::Task;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// A task with its subtasks, each with their own subtasks in turn
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskTree {
+    pub task: Task,
+    pub children: Vec<TaskTree>,
+}
+
+impl TaskTree {
+    /// Arrange tasks under `root` by their parent UUIDs, subtasks ordered
+    /// by ID; tasks not connected to the root are left out
+    pub fn build(root: Task, descendants: Vec<Task>) -> Self {
+        let mut by_parent: HashMap<Uuid, Vec<Task>> = HashMap::new();
+        for task in descendants {
+            if let Some(parent) = task.parent_uuid {
+                by_parent.entry(parent).or_default().push(task);
+            }
+        }
+        Self::attach(root, &mut by_parent)
+    }
+
+    fn attach(task: Task, by_parent: &mut HashMap<Uuid, Vec<Task>>) -> Self {
+        // Taking the children out of the map keeps a corrupt cycle from
+        // recursing forever
+        let mut children = by_parent.remove(&task.uuid).unwrap_or_default();
+        children.sort_by_key(|child| child.id);
+        Self {
+            children: children
+                .into_iter()
+                .map(|child| Self::attach(child, by_parent))
+                .collect(),
+            task,
+        }
+    }
+
+    /// Estimated effort of the task and everything below it, in minutes
+    pub fn effort(&self) -> u32 {
+        self.roll_up(&|task| task.effort.unwrap_or(0))
+    }
+
+    /// Effort spent on the task and everything below it, in minutes
+    pub fn effort_spent(&self) -> u32 {
+        self.roll_up(&|task| task.effort_spent.unwrap_or(0))
+    }
+
+    /// Estimated minus spent effort across the open tasks of the tree, in
+    /// minutes
+    pub fn remaining(&self) -> u32 {
+        self.roll_up(&|task| {
+            if task.is_active() {
+                task.effort
+                    .unwrap_or(0)
+                    .saturating_sub(task.effort_spent.unwrap_or(0))
+            } else {
+                0
+            }
+        })
+    }
+
+    fn roll_up(&self, value: &impl Fn(&Task) -> u32) -> u32 {
+        self.children.iter().fold(value(&self.task), |sum, child| {
+            sum.saturating_add(child.roll_up(value))
+        })
+    }
+
+    /// Every node of the tree with its depth below the root, parents before
+    /// their subtasks
+    pub fn flatten(&self) -> Vec<(&TaskTree, usize)> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![(self, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            nodes.push((node, depth));
+            stack.extend(node.children.iter().rev().map(|child| (child, depth + 1)));
+        }
+        nodes
+    }
+}
+
+/// Order listed tasks so each subtask follows its parent, pairing every row
+/// with its nesting depth
+///
+/// Only parents that are themselves listed count; other tasks stay at the
+/// top level. Siblings keep their order from `rows`.
+pub fn nest_subtasks<T>(rows: Vec<(Task, T)>) -> Vec<(Task, T, usize)> {
+    let listed: HashSet<Uuid> = rows.iter().map(|(task, _)| task.uuid).collect();
+    let mut children: HashMap<Uuid, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (index, (task, _)) in rows.iter().enumerate() {
+        match task.parent_uuid {
+            Some(parent) if parent != task.uuid && listed.contains(&parent) => {
+                children.entry(parent).or_default().push(index)
+            }
+            _ => roots.push(index),
+        }
+    }
+
+    let mut order = Vec::with_capacity(rows.len());
+    let mut stack: Vec<(usize, usize)> = roots.into_iter().rev().map(|i| (i, 0)).collect();
+    while let Some((index, depth)) = stack.pop() {
+        order.push((index, depth));
+        if let Some(subtasks) = children.remove(&rows[index].0.uuid) {
+            stack.extend(subtasks.into_iter().rev().map(|i| (i, depth + 1)));
+        }
+    }
+    // Tasks in a parent cycle are never reached from a root
+    let placed: HashSet<usize> = order.iter().map(|&(index, _)| index).collect();
+    order.extend(
+        (0..rows.len())
+            .filter(|i| !placed.contains(i))
+            .map(|i| (i, 0)),
+    );
+
+    let mut rows: Vec<Option<(Task, T)>> = rows.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|(index, depth)| rows[index].take().map(|(task, value)| (task, value, depth)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::TaskStatus;
+
+    fn subtask(description: &str, id: i64, parent: &Task) -> Task {
+        let mut task = Task::new(description.to_string());
+        task.id = Some(id);
+        task.parent_uuid = Some(parent.uuid);
+        task
+    }
+
+    #[test]
+    fn test_tree_rolls_up_effort() {
+        let mut release = Task::new("Release".to_string());
+        release.id = Some(1);
+        release.effort = Some(30);
+        let mut docs = subtask("Docs", 3, &release);
+        docs.effort = Some(60);
+        docs.effort_spent = Some(20);
+        let mut build = subtask("Build", 2, &release);
+        build.status = TaskStatus::Completed;
+        build.effort = Some(45);
+        build.effort_spent = Some(50);
+        let mut changelog = subtask("Changelog", 4, &docs);
+        changelog.effort = Some(15);
+        let stray = Task::new("Unrelated".to_string());
+
+        let tree = TaskTree::build(release, vec![docs, changelog, build, stray]);
+        assert_eq!(tree.effort(), 150);
+        assert_eq!(tree.effort_spent(), 70);
+        assert_eq!(tree.remaining(), 30 + 40 + 15);
+
+        let flat: Vec<(&str, usize)> = tree
+            .flatten()
+            .into_iter()
+            .map(|(node, depth)| (node.task.description.as_str(), depth))
+            .collect();
+        assert_eq!(
+            flat,
+            [("Release", 0), ("Build", 1), ("Docs", 1), ("Changelog", 2)]
+        );
+    }
+
+    #[test]
+    fn test_nest_subtasks() {
+        let release = Task::new("Release".to_string());
+        let build = subtask("Build", 2, &release);
+        let tests = subtask("Tests", 3, &build);
+        let orphan = subtask("Orphan", 4, &Task::new("Unlisted".to_string()));
+        let rows = vec![(tests, 1.0), (orphan, 2.0), (build, 3.0), (release, 4.0)];
+
+        let nested: Vec<(String, usize)> = nest_subtasks(rows)
+            .into_iter()
+            .map(|(task, _, depth)| (task.description, depth))
+            .collect();
+        assert_eq!(
+            nested,
+            [
+                ("Orphan".to_string(), 0),
+                ("Release".to_string(), 0),
+                ("Build".to_string(), 1),
+                ("Tests".to_string(), 2),
+            ]
+        );
+    }
+}
@@ -1,7 +1,27 @@
-use crate::core::{EddaError, EddaResult, TaskError};
-use chrono::{DateTime, Utc};
+use crate::core::config::{SubtaskConfig, UrgencyConfig};
+use crate::core::date::DateParser;
+use crate::core::journal::{Operation, OperationKind};
+use crate::core::modification::TaskModification;
+use crate::core::project::{ProjectSummary, validate_project};
+use crate::core::recurrence::{
+    MASK_COMPLETED, MASK_DELETED, MASK_PENDING, MASK_WAITING, Recurrence,
+};
+use crate::core::search::{SearchHit, fts_query};
+use crate::core::selection::{TaskReference, TaskSelection};
+use crate::core::subtask::TaskTree;
+use crate::core::taskwarrior::ImportSummary;
+use crate::core::timesheet::{TimeInterval, Timesheet, TimesheetGroup, effort_minutes};
+use crate::core::uda::UdaDefinition;
+use crate::core::urgency::Urgency;
+use crate::core::virtual_tag::{TagClock, TaskRelations, VirtualTag, check_stored_tag};
+use crate::core::{
+    EddaError, EddaResult, FilterAttribute, FilterExpr, FilterModifier, FilterTerm, FilterValue,
+    TaskError,
+};
+use crate::storage::{IntervalFilter, OperationFilter};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
 /// Task status enum matching Taskwarrior statuses
@@ -13,6 +33,8 @@ pub enum TaskStatus {
     Completed,
     Deleted,
     Waiting,
+    /// Template of a recurring task; instances are generated from it
+    Recurring,
 }
 
 impl std::fmt::Display for TaskStatus {
@@ -23,6 +45,29 @@ impl std::fmt::Display for TaskStatus {
             TaskStatus::Completed => write!(f, "completed"),
             TaskStatus::Deleted => write!(f, "deleted"),
             TaskStatus::Waiting => write!(f, "waiting"),
+            TaskStatus::Recurring => write!(f, "recurring"),
+        }
+    }
+}
+
+impl TaskStatus {
+    /// Check if a task may move from this status to `to`
+    ///
+    /// Keeping a status unchanged is always allowed. Deleted is terminal.
+    pub fn can_transition_to(&self, to: &TaskStatus) -> bool {
+        use TaskStatus::*;
+
+        if self == to {
+            return true;
+        }
+
+        match self {
+            Pending => matches!(to, InProgress | Waiting | Completed | Deleted | Recurring),
+            InProgress => matches!(to, Pending | Completed | Deleted),
+            Waiting => matches!(to, Pending | InProgress | Completed | Deleted | Recurring),
+            Completed => matches!(to, Pending | Waiting | Deleted),
+            Recurring => matches!(to, Pending | Deleted),
+            Deleted => false,
         }
     }
 }
@@ -37,6 +82,7 @@ impl std::str::FromStr for TaskStatus {
             "completed" => Ok(TaskStatus::Completed),
             "deleted" => Ok(TaskStatus::Deleted),
             "waiting" => Ok(TaskStatus::Waiting),
+            "recurring" => Ok(TaskStatus::Recurring),
             _ => Err(crate::core::TaskError::Validation {
                 message: format!("Invalid task status: {s}"),
             }),
@@ -44,6 +90,17 @@ impl std::str::FromStr for TaskStatus {
     }
 }
 
+/// Tasks changed by [`TaskEngine::update_lifecycle`]
+#[derive(Debug, Clone, Default)]
+pub struct LifecycleChanges {
+    /// Waiting tasks whose wait date passed, now pending
+    pub unwaited: Vec<Task>,
+    /// Open tasks past their `until` date, now deleted
+    pub expired: Vec<Task>,
+    /// Claimed tasks whose lease ran out, back to pending
+    pub reclaimed: Vec<Task>,
+}
+
 /// Task priority enum matching Taskwarrior priorities
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
 pub enum Priority {
@@ -96,6 +153,9 @@ impl std::str::FromStr for Priority {
 pub struct Annotation {
     pub entry: DateTime<Utc>,
     pub description: String,
+    /// Person or agent that left the note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
 }
 
 /// Main Task struct with Taskwarrior-compatible fields
@@ -125,6 +185,9 @@ pub struct Task {
     /// Scheduled date (when task should be started)
     pub scheduled_date: Option<DateTime<Utc>>,
 
+    /// Wait date (task is hidden until then)
+    pub wait_date: Option<DateTime<Utc>>,
+
     /// Start date (when task was actually started)
     pub start_date: Option<DateTime<Utc>>,
 
@@ -152,11 +215,37 @@ pub struct Task {
     /// Recurrence pattern (if task repeats)
     pub recurrence: Option<String>,
 
+    /// Until date (when the task expires; ends recurrence for templates)
+    pub until_date: Option<DateTime<Utc>>,
+
+    /// Recurrence mask of a recurring template, one marker per generated instance
+    pub recurrence_mask: Option<String>,
+
+    /// Index of a recurring instance within its template's mask
+    pub recurrence_index: Option<u32>,
+
     /// Estimated effort (in minutes)
     pub effort: Option<u32>,
 
     /// Actual effort spent (in minutes)
     pub effort_spent: Option<u32>,
+
+    /// User-defined attribute values, keyed by the names declared in the config
+    #[serde(default)]
+    pub udas: BTreeMap<String, serde_json::Value>,
+
+    /// Identifier of the task's counterpart in a sync backend, such as the
+    /// URL of a GitHub issue
+    #[serde(default)]
+    pub sync_id: Option<String>,
+
+    /// Agent that claimed the task from the work queue
+    #[serde(default)]
+    pub claimed_by: Option<String>,
+
+    /// When the claim lapses unless renewed
+    #[serde(default)]
+    pub lease_expires: Option<DateTime<Utc>>,
 }
 
 impl Task {
@@ -172,6 +261,7 @@ impl Task {
             project: None,
             due_date: None,
             scheduled_date: None,
+            wait_date: None,
             start_date: None,
             end_date: None,
             entry_date: now,
@@ -181,9 +271,41 @@ impl Task {
             parent_uuid: None,
             depends: HashSet::new(),
             recurrence: None,
+            until_date: None,
+            recurrence_mask: None,
+            recurrence_index: None,
             effort: None,
             effort_spent: None,
+            udas: BTreeMap::new(),
+            sync_id: None,
+            claimed_by: None,
+            lease_expires: None,
+        }
+    }
+
+    /// Check if this task is a recurring template
+    pub fn is_recurring_template(&self) -> bool {
+        self.recurrence.is_some() && self.recurrence_index.is_none()
+    }
+
+    /// Build the recurring instance at `index` of this template, due on `due`
+    pub fn recurring_instance(&self, index: u32, due: DateTime<Utc>) -> Task {
+        let mut instance = Task::new(self.description.clone());
+        instance.priority = self.priority.clone();
+        instance.project = self.project.clone();
+        instance.tags = self.tags.clone();
+        instance.annotations = self.annotations.clone();
+        instance.effort = self.effort;
+        instance.recurrence = self.recurrence.clone();
+        instance.parent_uuid = Some(self.uuid);
+        instance.recurrence_index = Some(index);
+        instance.due_date = Some(due);
+        // The wait date keeps its distance from the due date
+        if let (Some(wait), Some(template_due)) = (self.wait_date, self.due_date) {
+            instance.wait_date = Some(due - (template_due - wait));
         }
+        instance.sync_wait(Utc::now());
+        instance
     }
 
     /// Add a tag to the task
@@ -203,60 +325,160 @@ impl Task {
 
     /// Add an annotation to the task
     pub fn add_annotation(&mut self, description: String) {
+        self.add_annotation_by(description, None);
+    }
+
+    /// Add an annotation to the task, recording who left it
+    pub fn add_annotation_by(&mut self, description: String, author: Option<String>) {
         let annotation = Annotation {
             entry: Utc::now(),
             description,
+            author,
         };
         self.annotations.push(annotation);
         self.modified_date = Utc::now();
     }
 
-    /// Mark task as started
-    pub fn start(&mut self) -> Result<(), crate::core::TaskError> {
-        if self.status != TaskStatus::Pending && self.status != TaskStatus::Waiting {
-            return Err(crate::core::TaskError::InvalidStatusTransition {
-                from: self.status.to_string(),
-                to: "started".to_string(),
+    /// Find an annotation by its position, counting from 1, or by its text:
+    /// an exact match, or else the only annotation containing the text
+    pub fn find_annotation(&self, selector: &str) -> Result<usize, crate::core::TaskError> {
+        let id = self.id.unwrap_or(0);
+        if let Ok(position) = selector.parse::<usize>() {
+            if (1..=self.annotations.len()).contains(&position) {
+                return Ok(position - 1);
+            }
+            return Err(crate::core::TaskError::Validation {
+                message: format!(
+                    "Task {id} has no annotation {position} (it has {})",
+                    self.annotations.len()
+                ),
             });
         }
 
-        self.start_date = Some(Utc::now());
-        self.modified_date = Utc::now();
-        Ok(())
+        if let Some(index) = self
+            .annotations
+            .iter()
+            .position(|a| a.description == selector)
+        {
+            return Ok(index);
+        }
+        let matches: Vec<usize> = self
+            .annotations
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.description.contains(selector))
+            .map(|(index, _)| index)
+            .collect();
+        match matches.as_slice() {
+            [index] => Ok(*index),
+            [] => Err(crate::core::TaskError::Validation {
+                message: format!("Task {id} has no annotation matching '{selector}'"),
+            }),
+            _ => Err(crate::core::TaskError::Validation {
+                message: format!(
+                    "'{selector}' matches {} annotations of task {id}; give its number instead",
+                    matches.len()
+                ),
+            }),
+        }
     }
 
-    /// Mark task as completed
-    pub fn complete(&mut self) -> Result<(), crate::core::TaskError> {
-        if self.status == TaskStatus::Completed {
+    /// Move the task to a new status, enforcing the status state machine
+    fn transition_to(&mut self, to: TaskStatus) -> Result<(), crate::core::TaskError> {
+        if self.status == to || !self.status.can_transition_to(&to) {
             return Err(crate::core::TaskError::InvalidStatusTransition {
                 from: self.status.to_string(),
-                to: "completed".to_string(),
+                to: to.to_string(),
             });
         }
 
-        self.status = TaskStatus::Completed;
-        self.end_date = Some(Utc::now());
+        self.status = to;
         self.modified_date = Utc::now();
+        self.release_claim();
         Ok(())
     }
 
-    /// Mark task as deleted
-    pub fn delete(&mut self) -> Result<(), crate::core::TaskError> {
-        if self.status == TaskStatus::Deleted {
+    /// Drop the task's claim unless it is still in progress; a claim only
+    /// lasts while its agent works on the task
+    fn release_claim(&mut self) {
+        if self.status != TaskStatus::InProgress {
+            self.claimed_by = None;
+            self.lease_expires = None;
+        }
+    }
+
+    /// Check if the task is claimed and the lease has run out at `now`
+    pub fn lease_expired(&self, now: DateTime<Utc>) -> bool {
+        self.claimed_by.is_some() && self.lease_expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Mark task as started (moves it to in progress)
+    pub fn start(&mut self) -> Result<(), crate::core::TaskError> {
+        self.transition_to(TaskStatus::InProgress)?;
+        self.start_date = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Mark task as stopped (moves it back to pending)
+    pub fn stop(&mut self) -> Result<(), crate::core::TaskError> {
+        if self.status != TaskStatus::InProgress {
             return Err(crate::core::TaskError::InvalidStatusTransition {
                 from: self.status.to_string(),
-                to: "deleted".to_string(),
+                to: "stopped".to_string(),
             });
         }
 
-        self.status = TaskStatus::Deleted;
-        self.modified_date = Utc::now();
+        self.transition_to(TaskStatus::Pending)?;
+        self.start_date = None;
+        Ok(())
+    }
+
+    /// Mark task as completed
+    pub fn complete(&mut self) -> Result<(), crate::core::TaskError> {
+        self.transition_to(TaskStatus::Completed)?;
+        self.end_date = Some(Utc::now());
         Ok(())
     }
 
-    /// Check if task is active (pending or waiting)
+    /// Mark task as deleted
+    pub fn delete(&mut self) -> Result<(), crate::core::TaskError> {
+        self.transition_to(TaskStatus::Deleted)
+    }
+
+    /// Check if task is active: pending, in progress or waiting, and not
+    /// past its `until` date
     pub fn is_active(&self) -> bool {
-        matches!(self.status, TaskStatus::Pending | TaskStatus::Waiting)
+        self.is_open() && !self.is_expired()
+    }
+
+    /// Check if task is pending, in progress or waiting
+    fn is_open(&self) -> bool {
+        matches!(
+            self.status,
+            TaskStatus::Pending | TaskStatus::InProgress | TaskStatus::Waiting
+        )
+    }
+
+    /// Check if an open task has passed its `until` date and is due to be
+    /// deleted
+    pub fn is_expired(&self) -> bool {
+        self.is_open() && self.until_date.is_some_and(|until| until <= Utc::now())
+    }
+
+    /// Match the status to the wait date at `now`: a pending task with a
+    /// future wait date becomes waiting, and a waiting task whose wait date
+    /// has passed (or was removed) becomes pending again
+    ///
+    /// Returns whether the status changed.
+    pub fn sync_wait(&mut self, now: DateTime<Utc>) -> bool {
+        let hidden = self.wait_date.is_some_and(|wait| wait > now);
+        let status = match self.status {
+            TaskStatus::Pending if hidden => TaskStatus::Waiting,
+            TaskStatus::Waiting if !hidden => TaskStatus::Pending,
+            _ => return false,
+        };
+        self.status = status;
+        true
     }
 
     /// Check if task is completed
@@ -269,10 +491,10 @@ impl Task {
         self.status == TaskStatus::Deleted
     }
 
-    /// Check if task is overdue
+    /// Check if task is overdue; waiting tasks are hidden and never overdue
     pub fn is_overdue(&self) -> bool {
         if let Some(due_date) = self.due_date {
-            self.is_active() && Utc::now() > due_date
+            self.is_active() && self.status != TaskStatus::Waiting && Utc::now() > due_date
         } else {
             false
         }
@@ -284,46 +506,12 @@ impl Task {
         (now - self.entry_date).num_days()
     }
 
-    /// Get task urgency score (simplified version)
+    /// Get task urgency with the default coefficients, ignoring dependencies
+    ///
+    /// Use [`TaskEngine::urgency`] for the configured model including the
+    /// blocked and blocking terms.
     pub fn urgency_score(&self) -> f64 {
-        let mut score = 0.0;
-
-        // Priority score
-        if let Some(priority) = &self.priority {
-            match priority {
-                Priority::High => score += 10.0,
-                Priority::Medium => score += 5.0,
-                Priority::Low => score += 1.0,
-                Priority::Number(n) => score += *n as f64,
-            }
-        }
-
-        // Due date score
-        if let Some(due_date) = self.due_date {
-            let now = Utc::now();
-            if now > due_date {
-                // Overdue tasks get high urgency
-                score += 15.0;
-            } else {
-                // Due soon tasks get moderate urgency
-                let days_until_due = (due_date - now).num_days();
-                if days_until_due <= 1 {
-                    score += 10.0;
-                } else if days_until_due <= 7 {
-                    score += 5.0;
-                }
-            }
-        }
-
-        // Age score (older tasks get slightly higher urgency)
-        let age_days = self.age_days();
-        if age_days > 30 {
-            score += 2.0;
-        } else if age_days > 7 {
-            score += 1.0;
-        }
-
-        score
+        Urgency::of(self, &UrgencyConfig::default(), false, false, Utc::now()).total()
     }
 }
 
@@ -376,6 +564,7 @@ mod tests {
     #[test]
     fn test_task_status_display() {
         assert_eq!(TaskStatus::Pending.to_string(), "pending");
+        assert_eq!(TaskStatus::InProgress.to_string(), "in_progress");
         assert_eq!(TaskStatus::Completed.to_string(), "completed");
         assert_eq!(TaskStatus::Deleted.to_string(), "deleted");
         assert_eq!(TaskStatus::Waiting.to_string(), "waiting");
@@ -458,18 +647,109 @@ mod tests {
         assert!(task.modified_date > original_modified);
     }
 
+    #[test]
+    fn test_task_find_annotation() {
+        let mut task = Task::new("Test task".to_string());
+        task.add_annotation("Seen on staging".to_string());
+        task.add_annotation_by("Seen on prod".to_string(), Some("agent-a".to_string()));
+        task.add_annotation("Seen".to_string());
+
+        assert_eq!(task.find_annotation("2").unwrap(), 1);
+        assert!(task.find_annotation("4").is_err());
+        assert!(task.find_annotation("0").is_err());
+        assert_eq!(task.find_annotation("Seen").unwrap(), 2);
+        assert_eq!(task.find_annotation("prod").unwrap(), 1);
+        assert!(task.find_annotation("Seen on").is_err());
+        assert!(task.find_annotation("dev").is_err());
+        assert_eq!(task.annotations[1].author.as_deref(), Some("agent-a"));
+    }
+
     #[test]
     fn test_task_start() {
         let mut task = Task::new("Test task".to_string());
 
         assert!(task.start().is_ok());
         assert!(task.start_date.is_some());
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert!(task.is_active());
+
+        // Can't start a task that is already in progress
+        assert!(task.start().is_err());
 
         // Can't start a completed task
         task.status = TaskStatus::Completed;
         assert!(task.start().is_err());
     }
 
+    #[test]
+    fn test_task_stop() {
+        let mut task = Task::new("Test task".to_string());
+
+        // Can't stop a task that was never started
+        assert!(task.stop().is_err());
+
+        task.start().unwrap();
+        assert!(task.stop().is_ok());
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(task.start_date.is_none());
+
+        // Waiting tasks aren't running either
+        task.status = TaskStatus::Waiting;
+        assert!(task.stop().is_err());
+    }
+
+    #[test]
+    fn test_task_complete_in_progress() {
+        let mut task = Task::new("Test task".to_string());
+        task.start().unwrap();
+
+        assert!(task.complete().is_ok());
+        assert_eq!(task.status, TaskStatus::Completed);
+
+        // Deleted tasks can't be completed
+        let mut task = Task::new("Test task".to_string());
+        task.delete().unwrap();
+        assert!(task.complete().is_err());
+    }
+
+    #[test]
+    fn test_task_status_transitions() {
+        use TaskStatus::*;
+
+        let all = [Pending, InProgress, Waiting, Completed, Deleted, Recurring];
+        let allowed = [
+            (Pending, InProgress),
+            (Pending, Waiting),
+            (Pending, Completed),
+            (Pending, Deleted),
+            (Pending, Recurring),
+            (InProgress, Pending),
+            (InProgress, Completed),
+            (InProgress, Deleted),
+            (Waiting, Pending),
+            (Waiting, InProgress),
+            (Waiting, Completed),
+            (Waiting, Deleted),
+            (Waiting, Recurring),
+            (Completed, Pending),
+            (Completed, Waiting),
+            (Completed, Deleted),
+            (Recurring, Pending),
+            (Recurring, Deleted),
+        ];
+
+        for from in &all {
+            for to in &all {
+                let expected = from == to || allowed.contains(&(from.clone(), to.clone()));
+                assert_eq!(
+                    TaskEngine::is_valid_status_transition(from, to),
+                    expected,
+                    "transition {from} -> {to}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_task_complete() {
         let mut task = Task::new("Test task".to_string());
@@ -504,11 +784,11 @@ mod tests {
 
         // High priority increases urgency
         task.priority = Some(Priority::High);
-        assert_eq!(task.urgency_score(), 10.0);
+        assert_eq!(task.urgency_score(), 6.0);
 
         // Due date increases urgency
         task.due_date = Some(Utc::now() + chrono::Duration::days(1));
-        assert!(task.urgency_score() > 10.0);
+        assert!(task.urgency_score() > 6.0);
     }
 
     #[test]
@@ -530,50 +810,83 @@ mod tests {
         task.status = TaskStatus::Completed;
         assert!(!task.is_overdue());
     }
+
+    #[test]
+    fn test_task_wait_and_until() {
+        let now = Utc::now();
+        let mut task = Task::new("Renew domain".to_string());
+        task.due_date = Some(now - chrono::Duration::days(1));
+
+        // A future wait date hides the task, which is then not overdue
+        task.wait_date = Some(now + chrono::Duration::days(2));
+        assert!(task.sync_wait(now));
+        assert_eq!(task.status, TaskStatus::Waiting);
+        assert!(!task.sync_wait(now));
+        assert!(task.is_active());
+        assert!(!task.is_overdue());
+
+        // Once the date passes it is pending again
+        assert!(task.sync_wait(now + chrono::Duration::days(3)));
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(task.is_overdue());
+
+        // Past its until date the task is no longer active
+        task.until_date = Some(now - chrono::Duration::hours(1));
+        assert!(task.is_expired());
+        assert!(!task.is_active());
+        assert!(!task.is_overdue());
+    }
 }
 
 /// Task engine for high-level task management operations
 pub struct TaskEngine {
     storage: Box<dyn crate::storage::TaskStorage + Send + Sync>,
+    udas: BTreeMap<String, UdaDefinition>,
+    urgency: UrgencyConfig,
+    subtasks: SubtaskConfig,
 }
 
 impl TaskEngine {
     /// Create a new task engine with the given storage backend
     pub fn new(storage: Box<dyn crate::storage::TaskStorage + Send + Sync>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            udas: BTreeMap::new(),
+            urgency: UrgencyConfig::default(),
+            subtasks: SubtaskConfig::default(),
+        }
     }
 
-    /// Create a new task with validation
-    pub async fn create_task(&self, description: String) -> EddaResult<Task> {
-        // Validate description
-        if description.trim().is_empty() {
-            return Err(EddaError::Task(TaskError::Validation {
-                message: "Task description cannot be empty".to_string(),
-            }));
-        }
+    /// Declare the user-defined attributes tasks may carry
+    pub fn with_udas(mut self, udas: BTreeMap<String, UdaDefinition>) -> Self {
+        self.udas = udas;
+        self
+    }
 
-        let task = Task::new(description);
-        self.storage.create_task(task).await
+    /// Set the coefficients of the urgency model
+    pub fn with_urgency(mut self, urgency: UrgencyConfig) -> Self {
+        self.urgency = urgency;
+        self
     }
 
-    /// Get a task by ID with validation
-    pub async fn get_task(&self, id: i64) -> EddaResult<Option<Task>> {
-        if id <= 0 {
-            return Err(EddaError::Task(TaskError::Validation {
-                message: "Task ID must be positive".to_string(),
-            }));
-        }
+    /// Set how completing and deleting tasks cascades through subtasks
+    pub fn with_subtasks(mut self, subtasks: SubtaskConfig) -> Self {
+        self.subtasks = subtasks;
+        self
+    }
 
-        self.storage.get_task_by_id(id).await
+    /// User-defined attributes declared for this engine
+    pub fn udas(&self) -> &BTreeMap<String, UdaDefinition> {
+        &self.udas
     }
 
-    /// Get a task by UUID
-    pub async fn get_task_by_uuid(&self, uuid: Uuid) -> EddaResult<Option<Task>> {
-        self.storage.get_task_by_uuid(uuid).await
+    /// Create a new task with validation
+    pub async fn create_task(&self, description: String) -> EddaResult<Task> {
+        self.add_task(Task::new(description)).await
     }
 
-    /// Update a task with validation
-    pub async fn update_task(&self, mut task: Task) -> EddaResult<Task> {
+    /// Create a task whose attributes have already been filled in
+    pub async fn add_task(&self, mut task: Task) -> EddaResult<Task> {
         // Validate description
         if task.description.trim().is_empty() {
             return Err(EddaError::Task(TaskError::Validation {
@@ -581,148 +894,1468 @@ impl TaskEngine {
             }));
         }
 
-        // Validate status transitions
-        if let Some(existing_task) = self.storage.get_task_by_id(task.id.unwrap_or(0)).await? {
-            if !self.is_valid_status_transition(&existing_task.status, &task.status) {
-                return Err(EddaError::Task(TaskError::Validation {
-                    message: format!(
-                        "Invalid status transition from {} to {}",
-                        existing_task.status, task.status
-                    ),
-                }));
-            }
+        Self::prepare_recurrence(&mut task)?;
+        task.sync_wait(Utc::now());
+        if let Some(project) = &task.project {
+            validate_project(project)?;
+        }
+        for tag in &task.tags {
+            check_stored_tag(tag)?;
+        }
+        self.validate_udas(&task, None)?;
+        if task.parent_uuid.is_some() {
+            self.validate_parent(&task).await?;
         }
+        if !task.depends.is_empty() {
+            let depends: Vec<Uuid> = task.depends.iter().copied().collect();
+            self.validate_dependencies(&task, &depends).await?;
+        }
+        self.journaled_create(task).await
+    }
 
-        // Update timestamps
-        task.modified_date = Utc::now();
+    /// Apply inline modifications to a task in memory, resolving
+    /// `depends:` references to UUIDs; the caller saves the task
+    pub async fn apply_modification(
+        &self,
+        task: &mut Task,
+        modification: &TaskModification,
+        dates: &DateParser,
+    ) -> EddaResult<()> {
+        modification.apply(task, dates, &self.udas)?;
+
+        for value in modification.dependencies() {
+            // `depends:` on its own clears every dependency
+            if value.is_empty() {
+                task.depends.clear();
+                continue;
+            }
+            for reference in value.split(',').filter(|r| !r.is_empty()) {
+                // A leading `-` removes the dependency, as in `depends:-12`
+                let (remove, reference) = match reference.strip_prefix('-') {
+                    Some(reference) => (true, reference),
+                    None => (false, reference),
+                };
+                let uuid = self.resolve_task_reference(reference).await?;
+                if remove {
+                    task.depends.remove(&uuid);
+                } else {
+                    task.depends.insert(uuid);
+                }
+            }
+        }
 
-        self.storage.update_task(task).await
+        // `parent:` on its own makes the task top-level again
+        if let Some(reference) = modification.parent() {
+            task.parent_uuid = match reference {
+                "" => None,
+                reference => Some(self.resolve_task_reference(reference).await?),
+            };
+        }
+        Ok(())
     }
 
-    /// Mark a task as completed
-    pub async fn complete_task(&self, id: i64) -> EddaResult<Task> {
+    /// Load a task, apply inline modifications and save it
+    pub async fn modify_task(
+        &self,
+        id: i64,
+        modification: &TaskModification,
+        dates: &DateParser,
+    ) -> EddaResult<Task> {
         let mut task = self
             .get_task(id)
             .await?
             .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
 
-        task.complete()?;
-        self.storage.update_task(task).await
+        self.apply_modification(&mut task, modification, dates)
+            .await?;
+        self.update_task(task).await
     }
 
-    /// Mark a task as deleted
-    pub async fn delete_task(&self, id: i64) -> EddaResult<Task> {
-        let mut task = self
-            .get_task(id)
-            .await?
-            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+    /// Find the tasks a selection refers to, in the order given or by ID
+    /// for filters
+    ///
+    /// Explicit IDs and UUID prefixes must exist and prefixes must be
+    /// unambiguous; IDs missing from a range are skipped.
+    pub async fn select_tasks(&self, selection: &TaskSelection) -> EddaResult<Vec<Task>> {
+        let references = match selection {
+            TaskSelection::Filter(expression) => {
+                let names_status = expression.references(FilterAttribute::Status);
+                let filter = crate::storage::TaskFilter {
+                    // An explicit status term may ask for deleted tasks or
+                    // recurring templates
+                    include_deleted: names_status,
+                    include_recurring: names_status,
+                    expression: Some(expression.clone()),
+                    ..Default::default()
+                };
+                let mut tasks = self.storage.list_tasks(Some(filter)).await?;
+                tasks.sort_by_key(|task| task.id);
+                return Ok(tasks);
+            }
+            TaskSelection::References(references) => references,
+        };
 
-        task.delete()?;
-        self.storage.update_task(task).await
+        let mut tasks: Vec<Task> = Vec::new();
+        for reference in references {
+            let found = match reference {
+                TaskReference::Id(id) => {
+                    vec![self.get_task(*id).await?.ok_or_else(|| {
+                        EddaError::Task(TaskError::NotFound { id: id.to_string() })
+                    })?]
+                }
+                TaskReference::Range(start, end) => {
+                    let filter = crate::storage::TaskFilter {
+                        include_deleted: true,
+                        include_recurring: true,
+                        id_range: Some((*start, *end)),
+                        ..Default::default()
+                    };
+                    let mut found = self.storage.list_tasks(Some(filter)).await?;
+                    found.sort_by_key(|task| task.id);
+                    found
+                }
+                TaskReference::UuidPrefix(prefix) => {
+                    let filter = crate::storage::TaskFilter {
+                        include_deleted: true,
+                        include_recurring: true,
+                        expression: Some(FilterExpr::Term(FilterTerm::Attribute {
+                            attribute: FilterAttribute::Uuid,
+                            modifier: FilterModifier::StartsWith,
+                            value: FilterValue::Text(prefix.clone()),
+                        })),
+                        ..Default::default()
+                    };
+                    let matches = self.storage.list_tasks(Some(filter)).await?;
+                    if matches.len() > 1 {
+                        return Err(EddaError::Task(TaskError::Validation {
+                            message: format!(
+                                "UUID prefix {prefix} matches {} tasks",
+                                matches.len()
+                            ),
+                        }));
+                    }
+                    if matches.is_empty() {
+                        return Err(EddaError::Task(TaskError::NotFound { id: prefix.clone() }));
+                    }
+                    matches
+                }
+            };
+            for task in found {
+                if !tasks.iter().any(|t| t.uuid == task.uuid) {
+                    tasks.push(task);
+                }
+            }
+        }
+        Ok(tasks)
     }
 
-    /// Start time tracking for a task
-    pub async fn start_task(&self, id: i64) -> EddaResult<Task> {
-        let mut task = self
-            .get_task(id)
-            .await?
-            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+    /// Run every following change in one transaction until
+    /// [`commit_batch`](Self::commit_batch) or [`rollback_batch`](Self::rollback_batch)
+    pub async fn begin_batch(&self) -> EddaResult<()> {
+        self.storage.begin_batch().await
+    }
 
-        task.start()?;
-        self.storage.update_task(task).await
+    /// Commit the changes made since `begin_batch`
+    pub async fn commit_batch(&self) -> EddaResult<()> {
+        self.storage.commit_batch().await
     }
 
-    /// Stop time tracking for a task
-    pub async fn stop_task(&self, id: i64) -> EddaResult<Task> {
-        let mut task = self
-            .get_task(id)
-            .await?
-            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+    /// Discard the changes made since `begin_batch`
+    pub async fn rollback_batch(&self) -> EddaResult<()> {
+        self.storage.rollback_batch().await
+    }
 
-        // Stop time tracking by clearing start date
-        task.start_date = None;
-        task.modified_date = Utc::now();
+    /// Run `change` in a batch of its own so it's saved whole or not at all;
+    /// inside an open batch it joins that one instead
+    async fn atomically<T>(&self, change: impl AsyncFnOnce() -> EddaResult<T>) -> EddaResult<T> {
+        if self.storage.in_batch().await {
+            return change().await;
+        }
+        self.begin_batch().await?;
+        match change().await {
+            Ok(value) => {
+                self.commit_batch().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.rollback_batch().await?;
+                Err(e)
+            }
+        }
+    }
 
-        self.storage.update_task(task).await
+    /// Resolve a task reference given as a numeric ID or a UUID
+    pub async fn resolve_task_reference(&self, reference: &str) -> EddaResult<Uuid> {
+        if let Ok(id) = reference.parse::<i64>() {
+            let task = self.get_task(id).await?.ok_or_else(|| {
+                EddaError::Task(TaskError::NotFound {
+                    id: reference.to_string(),
+                })
+            })?;
+            return Ok(task.uuid);
+        }
+        Uuid::parse_str(reference).map_err(|_| {
+            EddaError::Task(TaskError::Validation {
+                message: format!("Invalid task ID: {reference}"),
+            })
+        })
     }
 
-    /// Add an annotation to a task
-    pub async fn annotate_task(&self, id: i64, description: String) -> EddaResult<Task> {
-        if description.trim().is_empty() {
-            return Err(EddaError::Task(TaskError::Validation {
-                message: "Annotation description cannot be empty".to_string(),
-            }));
+    /// Validate a task's recurrence and turn it into a recurring template
+    /// when a pattern is set on a task that isn't an instance, or back into
+    /// a pending task when the pattern is cleared
+    fn prepare_recurrence(task: &mut Task) -> EddaResult<()> {
+        let Some(pattern) = &task.recurrence else {
+            if task.status == TaskStatus::Recurring {
+                task.status = TaskStatus::Pending;
+            }
+            return Ok(());
+        };
+        pattern.parse::<Recurrence>()?;
+
+        if task.is_recurring_template()
+            && matches!(task.status, TaskStatus::Pending | TaskStatus::Waiting)
+        {
+            if task.due_date.is_none() {
+                return Err(EddaError::Task(TaskError::Validation {
+                    message: "Recurring tasks require a due date".to_string(),
+                }));
+            }
+            task.status = TaskStatus::Recurring;
+            task.recurrence_mask.get_or_insert_with(String::new);
         }
 
-        let mut task = self
-            .get_task(id)
-            .await?
-            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+        Ok(())
+    }
 
-        task.add_annotation(description);
-        self.storage.update_task(task).await
+    /// Create a task and record it in the operations journal
+    async fn journaled_create(&self, task: Task) -> EddaResult<Task> {
+        let task = self.storage.create_task(task).await?;
+        self.storage
+            .append_operation(Operation::new(
+                OperationKind::Create,
+                None,
+                Some(task.clone()),
+            ))
+            .await?;
+        Ok(task)
     }
 
-    /// Add a tag to a task
-    pub async fn add_tag(&self, id: i64, tag: String) -> EddaResult<Task> {
-        if tag.trim().is_empty() {
+    /// Update a task and record its before/after snapshots in the operations journal
+    async fn journaled_update(&self, task: Task) -> EddaResult<Task> {
+        let before = self.storage.get_task_by_id(task.id.unwrap_or(0)).await?;
+        let task = self.storage.update_task(task).await?;
+        self.storage
+            .append_operation(Operation::new(
+                OperationKind::Update,
+                before,
+                Some(task.clone()),
+            ))
+            .await?;
+        Ok(task)
+    }
+
+    /// Revert the most recent `count` journaled changes, newest first
+    ///
+    /// Each revert is journaled as a new entry pointing at the one it undid,
+    /// so undone entries are skipped by later undos. Derived state (recurrence
+    /// masks and running intervals) is recomputed rather than restored from
+    /// the snapshot.
+    pub async fn undo(&self, count: usize) -> EddaResult<Vec<Operation>> {
+        if count == 0 {
             return Err(EddaError::Task(TaskError::Validation {
-                message: "Tag cannot be empty".to_string(),
+                message: "Undo count must be at least 1".to_string(),
             }));
         }
 
-        let mut task = self
-            .get_task(id)
-            .await?
-            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
-
-        task.add_tag(tag);
-        self.storage.update_task(task).await
+        let filter = OperationFilter {
+            undoable: true,
+            limit: Some(count as u32),
+            ..Default::default()
+        };
+        let operations = self.storage.list_operations(filter).await?;
+        self.atomically(async || {
+            for operation in &operations {
+                self.revert(operation).await?;
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(operations)
     }
 
-    /// Remove a tag from a task
-    pub async fn remove_tag(&self, id: i64, tag: &str) -> EddaResult<Task> {
-        let mut task = self
+    /// Journal entries for a task, oldest first
+    pub async fn history(&self, id: i64) -> EddaResult<Vec<Operation>> {
+        let task = self
             .get_task(id)
             .await?
             .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
 
-        task.remove_tag(tag);
-        self.storage.update_task(task).await
+        let filter = OperationFilter {
+            task_uuid: Some(task.uuid),
+            ..Default::default()
+        };
+        let mut operations = self.storage.list_operations(filter).await?;
+        operations.reverse();
+        Ok(operations)
     }
 
-    /// List tasks with filtering
-    pub async fn list_tasks(
-        &self,
-        filter: Option<crate::storage::TaskFilter>,
-    ) -> EddaResult<Vec<Task>> {
-        self.storage.list_tasks(filter).await
-    }
+    /// Apply the inverse of a journal entry and record it
+    async fn revert(&self, operation: &Operation) -> EddaResult<()> {
+        let current = self.storage.get_task_by_uuid(operation.task_uuid).await?;
+
+        let (kind, after) = match (&operation.before, &current) {
+            // Undo a create by removing the task again
+            (None, Some(current)) => {
+                self.storage.delete_task(current.id.unwrap_or(0)).await?;
+                (OperationKind::Delete, None)
+            }
+            // Undo an update by restoring the earlier snapshot
+            (Some(before), Some(current)) => {
+                let mut restored = before.clone();
+                restored.id = current.id;
+                if restored.status == TaskStatus::Recurring {
+                    // Keep instances generated since, so they aren't generated twice
+                    restored.recurrence_mask = current.recurrence_mask.clone();
+                }
+                self.restore_intervals(&restored).await?;
+                let restored = self.storage.update_task(restored).await?;
+                self.update_recurrence_mask(&restored).await?;
+                (OperationKind::Update, Some(restored))
+            }
+            // Undo a delete by recreating the task
+            (Some(before), None) => {
+                let restored = self.storage.create_task(before.clone()).await?;
+                (OperationKind::Create, Some(restored))
+            }
+            (None, None) => {
+                return Err(EddaError::Task(TaskError::NotFound {
+                    id: operation.task_uuid.to_string(),
+                }));
+            }
+        };
+
+        let mut entry = Operation::new(kind, current, after);
+        entry.undoes = operation.id;
+        self.storage.append_operation(entry).await?;
+        Ok(())
+    }
+
+    /// Bring a restored task's intervals in line with its status: drop the
+    /// interval of an undone `start`, reopen the one closed by an undone `stop`
+    ///
+    /// The snapshot's `effort_spent` already matches the intervals as they
+    /// were, along with any effort recorded by hand, so it is kept as is.
+    async fn restore_intervals(&self, task: &Task) -> EddaResult<()> {
+        let filter = IntervalFilter {
+            task_uuid: Some(task.uuid),
+            ..Default::default()
+        };
+        let intervals = self.storage.list_intervals(filter).await?;
+        let open = intervals.iter().find(|i| i.is_open());
+
+        match (task.status == TaskStatus::InProgress, open) {
+            (false, Some(open)) => {
+                self.storage.delete_interval(open.id.unwrap_or(0)).await?;
+            }
+            (true, None) => {
+                if let Some(mut last) = intervals.last().cloned() {
+                    last.end = None;
+                    self.storage.update_interval(last).await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Get a task by ID with validation
+    pub async fn get_task(&self, id: i64) -> EddaResult<Option<Task>> {
+        if id <= 0 {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Task ID must be positive".to_string(),
+            }));
+        }
+
+        self.storage.get_task_by_id(id).await
+    }
+
+    /// Get a task by UUID
+    pub async fn get_task_by_uuid(&self, uuid: Uuid) -> EddaResult<Option<Task>> {
+        self.storage.get_task_by_uuid(uuid).await
+    }
+
+    /// Update a task with validation
+    pub async fn update_task(&self, mut task: Task) -> EddaResult<Task> {
+        // Validate description
+        if task.description.trim().is_empty() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Task description cannot be empty".to_string(),
+            }));
+        }
+
+        Self::prepare_recurrence(&mut task)?;
+
+        // Validate status transitions
+        let existing = self.storage.get_task_by_id(task.id.unwrap_or(0)).await?;
+        if existing
+            .as_ref()
+            .is_none_or(|existing| existing.wait_date != task.wait_date)
+        {
+            task.sync_wait(Utc::now());
+        }
+        task.release_claim();
+        if let Some(existing_task) = &existing {
+            if !Self::is_valid_status_transition(&existing_task.status, &task.status) {
+                return Err(EddaError::Task(TaskError::InvalidStatusTransition {
+                    from: existing_task.status.to_string(),
+                    to: task.status.to_string(),
+                }));
+            }
+        }
+
+        if let Some(project) = &task.project
+            && existing.as_ref().is_none_or(|e| e.project != task.project)
+        {
+            validate_project(project)?;
+        }
+        for tag in &task.tags {
+            if existing.as_ref().is_none_or(|e| !e.tags.contains(tag)) {
+                check_stored_tag(tag)?;
+            }
+        }
+        self.validate_udas(&task, existing.as_ref())?;
+        if task.parent_uuid.is_some()
+            && existing
+                .as_ref()
+                .is_none_or(|e| e.parent_uuid != task.parent_uuid)
+        {
+            self.validate_parent(&task).await?;
+        }
+
+        // Validate dependencies added by this update
+        let added: Vec<Uuid> = task
+            .depends
+            .iter()
+            .filter(|uuid| existing.as_ref().is_none_or(|e| !e.depends.contains(uuid)))
+            .copied()
+            .collect();
+        self.validate_dependencies(&task, &added).await?;
+
+        // Update timestamps
+        task.modified_date = Utc::now();
+
+        let task = self.journaled_update(task).await?;
+        self.update_recurrence_mask(&task).await?;
+        Ok(task)
+    }
+
+    /// Insert or update tasks by UUID in one transaction, as when importing
+    /// a Taskwarrior export
+    ///
+    /// Statuses and dependencies are taken as given, since dependencies may
+    /// point at tasks later in the import. Effort fields, which Taskwarrior
+    /// doesn't carry, are kept from the existing task.
+    pub async fn import_tasks(&self, tasks: Vec<Task>) -> EddaResult<ImportSummary> {
+        self.begin_batch().await?;
+        match self.import_batch(tasks).await {
+            Ok(summary) => {
+                self.commit_batch().await?;
+                Ok(summary)
+            }
+            Err(e) => {
+                self.rollback_batch().await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn import_batch(&self, tasks: Vec<Task>) -> EddaResult<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        for mut task in tasks {
+            if task.description.trim().is_empty() {
+                return Err(EddaError::Task(TaskError::Validation {
+                    message: format!("Task {} has an empty description", task.uuid),
+                }));
+            }
+            if let Some(pattern) = &task.recurrence {
+                pattern.parse::<Recurrence>()?;
+            }
+
+            let existing = self.storage.get_task_by_uuid(task.uuid).await?;
+            self.validate_udas(&task, existing.as_ref())?;
+            match existing {
+                Some(existing) => {
+                    task.id = existing.id;
+                    task.effort = existing.effort;
+                    task.effort_spent = existing.effort_spent;
+                    task.claimed_by = existing.claimed_by.clone();
+                    task.lease_expires = existing.lease_expires;
+                    task.release_claim();
+                    let unchanged = Task {
+                        modified_date: existing.modified_date,
+                        ..task.clone()
+                    } == existing;
+                    if unchanged {
+                        summary.unchanged += 1;
+                    } else {
+                        self.journaled_update(task).await?;
+                        summary.updated += 1;
+                    }
+                }
+                None => {
+                    task.id = None;
+                    self.journaled_create(task).await?;
+                    summary.added += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Check UDA values against their declarations
+    ///
+    /// Only values that differ from `existing` are checked, so tasks keep
+    /// working after a declaration is narrowed; values of undeclared
+    /// attributes are kept as they are.
+    fn validate_udas(&self, task: &Task, existing: Option<&Task>) -> EddaResult<()> {
+        for (name, value) in &task.udas {
+            if existing.is_some_and(|e| e.udas.get(name) == Some(value)) {
+                continue;
+            }
+            if let Some(definition) = self.udas.get(name) {
+                definition.validate_value(name, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark a task as completed
+    ///
+    /// Refuses while any dependency is still open unless `force` is set, and
+    /// likewise while a subtask is open if `subtasks.block_parent` is on.
+    /// With `subtasks.complete_parent`, completing the last open subtask
+    /// completes the parent too, on up the tree.
+    pub async fn complete_task(&self, id: i64, force: bool) -> EddaResult<Task> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        if !force {
+            let blockers = self.open_dependencies(&task).await?;
+            if !blockers.is_empty() {
+                return Err(EddaError::Task(TaskError::Blocked {
+                    id: id.to_string(),
+                    blockers: blockers
+                        .iter()
+                        .map(|t| t.id.map_or_else(|| t.uuid.to_string(), |id| id.to_string()))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                }));
+            }
+            if self.subtasks.block_parent {
+                let open = self.open_subtasks(task.uuid).await?;
+                if !open.is_empty() {
+                    return Err(EddaError::Task(TaskError::OpenSubtasks {
+                        id: id.to_string(),
+                        subtasks: open
+                            .iter()
+                            .map(|t| t.id.map_or_else(|| t.uuid.to_string(), |id| id.to_string()))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    }));
+                }
+            }
+        }
+
+        task.complete()?;
+        self.atomically(async || {
+            self.close_open_interval(&mut task).await?;
+            let task = self.journaled_update(task).await?;
+            self.update_recurrence_mask(&task).await?;
+            if self.subtasks.complete_parent {
+                self.complete_finished_parents(&task).await?;
+            }
+            Ok(task)
+        })
+        .await
+    }
+
+    /// Complete the parents of a just-completed task whose subtasks are now
+    /// all done, walking up the tree; parents with open dependencies stay
+    /// open
+    async fn complete_finished_parents(&self, task: &Task) -> EddaResult<()> {
+        let mut child = task.clone();
+        while let Some(parent_uuid) = child.parent_uuid {
+            // Recurring instances point at their template, not a parent
+            if child.recurrence_index.is_some() {
+                break;
+            }
+            let Some(mut parent) = self.storage.get_task_by_uuid(parent_uuid).await? else {
+                break;
+            };
+            if !parent.is_active()
+                || !self.open_subtasks(parent.uuid).await?.is_empty()
+                || !self.open_dependencies(&parent).await?.is_empty()
+            {
+                break;
+            }
+            parent.complete()?;
+            self.close_open_interval(&mut parent).await?;
+            child = self.journaled_update(parent).await?;
+            self.update_recurrence_mask(&child).await?;
+        }
+        Ok(())
+    }
+
+    /// Mark a task as deleted, along with its subtasks if
+    /// `subtasks.delete_children` is on
+    pub async fn delete_task(&self, id: i64) -> EddaResult<Task> {
+        let task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        self.atomically(async || {
+            let deleted = self.delete_one(task).await?;
+            if self.subtasks.delete_children {
+                let mut parents = vec![deleted.uuid];
+                while let Some(parent) = parents.pop() {
+                    for child in self.subtasks_of(parent).await? {
+                        if !child.is_deleted() {
+                            parents.push(self.delete_one(child).await?.uuid);
+                        }
+                    }
+                }
+            }
+            Ok(deleted)
+        })
+        .await
+    }
+
+    async fn delete_one(&self, mut task: Task) -> EddaResult<Task> {
+        task.delete()?;
+        self.close_open_interval(&mut task).await?;
+        let task = self.journaled_update(task).await?;
+        self.update_recurrence_mask(&task).await?;
+        Ok(task)
+    }
+
+    /// Full-text search of task descriptions, annotations and documents for
+    /// all of `terms`, best matches first
+    pub async fn search(&self, terms: &str, limit: u32) -> EddaResult<Vec<SearchHit>> {
+        let query = fts_query(terms).ok_or_else(|| {
+            EddaError::Task(TaskError::Validation {
+                message: "Search terms cannot be empty".to_string(),
+            })
+        })?;
+        self.storage.search(&query, limit).await
+    }
+
+    /// The most urgent task ready to be worked on: pending, unblocked and
+    /// unclaimed, narrowed by `filter`
+    pub async fn next_task(
+        &self,
+        filter: Option<crate::storage::TaskFilter>,
+    ) -> EddaResult<Option<Task>> {
+        Ok(self.ready_tasks(filter).await?.into_iter().next())
+    }
+
+    /// Claim the most urgent ready task for `agent`, starting it and holding
+    /// it until the lease runs out
+    ///
+    /// Candidates are tried in order of urgency, each with a conditional
+    /// update, so when agents race for a task exactly one gets it and the
+    /// others move on to the next candidate.
+    pub async fn claim_next_task(
+        &self,
+        agent: &str,
+        lease: Duration,
+        filter: Option<crate::storage::TaskFilter>,
+    ) -> EddaResult<Option<Task>> {
+        Self::validate_claim(agent, lease)?;
+        for candidate in self.ready_tasks(filter).await? {
+            let now = Utc::now();
+            if !self
+                .storage
+                .claim_task(candidate.uuid, agent, now + lease, now)
+                .await?
+            {
+                continue;
+            }
+            let claimed = self
+                .storage
+                .get_task_by_uuid(candidate.uuid)
+                .await?
+                .ok_or_else(|| {
+                    EddaError::Task(TaskError::NotFound {
+                        id: candidate.uuid.to_string(),
+                    })
+                })?;
+            self.storage
+                .append_operation(Operation::new(
+                    OperationKind::Update,
+                    Some(candidate),
+                    Some(claimed.clone()),
+                ))
+                .await?;
+            self.storage
+                .create_interval(TimeInterval::new(claimed.uuid, now))
+                .await?;
+            return Ok(Some(claimed));
+        }
+        Ok(None)
+    }
+
+    /// Extend the lease on a task `agent` has claimed to `lease` from now
+    pub async fn renew_lease(&self, id: i64, agent: &str, lease: Duration) -> EddaResult<Task> {
+        Self::validate_claim(agent, lease)?;
+        let task = self.claimed_task(id, agent).await?;
+        if !self
+            .storage
+            .renew_lease(task.uuid, agent, Utc::now() + lease)
+            .await?
+        {
+            return Err(Self::not_claimed(id, agent));
+        }
+        self.storage
+            .get_task_by_uuid(task.uuid)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))
+    }
+
+    /// Give up `agent`'s claim on a task, stopping it so another agent can
+    /// claim it
+    pub async fn release_task(&self, id: i64, agent: &str) -> EddaResult<Task> {
+        let mut task = self.claimed_task(id, agent).await?;
+        task.stop()?;
+        self.close_open_interval(&mut task).await?;
+        self.journaled_update(task).await
+    }
+
+    /// Pending, unblocked and unclaimed tasks matching `filter`, most urgent
+    /// first
+    async fn ready_tasks(
+        &self,
+        filter: Option<crate::storage::TaskFilter>,
+    ) -> EddaResult<Vec<Task>> {
+        let filter = crate::storage::TaskFilter {
+            status: Some(TaskStatus::Pending),
+            dependency: Some(crate::storage::DependencyState::Unblocked),
+            ..filter.unwrap_or_default()
+        };
+        let tasks: Vec<Task> = self
+            .storage
+            .list_tasks(Some(filter))
+            .await?
+            .into_iter()
+            .filter(|task| task.claimed_by.is_none())
+            .collect();
+        let urgency = self.urgency(&tasks).await?;
+        let mut ranked: Vec<(Task, f64)> = tasks
+            .into_iter()
+            .zip(urgency.iter().map(Urgency::total))
+            .collect();
+        ranked.sort_by(|(a, x), (b, y)| y.total_cmp(x).then(a.id.cmp(&b.id)));
+        Ok(ranked.into_iter().map(|(task, _)| task).collect())
+    }
+
+    /// Load a task that `agent` holds the claim on
+    async fn claimed_task(&self, id: i64, agent: &str) -> EddaResult<Task> {
+        let task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+        if task.status != TaskStatus::InProgress || task.claimed_by.as_deref() != Some(agent) {
+            return Err(Self::not_claimed(id, agent));
+        }
+        Ok(task)
+    }
+
+    fn not_claimed(id: i64, agent: &str) -> EddaError {
+        EddaError::Task(TaskError::Validation {
+            message: format!("Task {id} is not claimed by {agent}"),
+        })
+    }
+
+    fn validate_claim(agent: &str, lease: Duration) -> EddaResult<()> {
+        if agent.trim().is_empty() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Agent name cannot be empty".to_string(),
+            }));
+        }
+        if lease <= Duration::zero() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Lease must be longer than zero".to_string(),
+            }));
+        }
+        if Utc::now().checked_add_signed(lease).is_none() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Lease is too long".to_string(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Start time tracking for a task
+    pub async fn start_task(&self, id: i64) -> EddaResult<Task> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        task.start()?;
+        let start = task.start_date.unwrap_or_else(Utc::now);
+        let task = self.journaled_update(task).await?;
+        self.storage
+            .create_interval(TimeInterval::new(task.uuid, start))
+            .await?;
+        Ok(task)
+    }
+
+    /// Stop time tracking for a task
+    pub async fn stop_task(&self, id: i64) -> EddaResult<Task> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        task.stop()?;
+        self.close_open_interval(&mut task).await?;
+        self.journaled_update(task).await
+    }
+
+    /// Close the task's running interval, if any, and refresh its effort
+    async fn close_open_interval(&self, task: &mut Task) -> EddaResult<()> {
+        let tracked_before = self.tracked_minutes(task.uuid).await?;
+        let filter = IntervalFilter {
+            task_uuid: Some(task.uuid),
+            open_only: true,
+            ..Default::default()
+        };
+        for mut interval in self.storage.list_intervals(filter).await? {
+            interval.end = Some(Utc::now().max(interval.start));
+            self.storage.update_interval(interval).await?;
+        }
+        self.refresh_effort_spent(task, tracked_before).await
+    }
+
+    /// Minutes tracked in the task's closed intervals, `None` if it has none
+    async fn tracked_minutes(&self, task_uuid: Uuid) -> EddaResult<Option<u32>> {
+        let filter = IntervalFilter {
+            task_uuid: Some(task_uuid),
+            ..Default::default()
+        };
+        let intervals = self.storage.list_intervals(filter).await?;
+        Ok(intervals
+            .iter()
+            .any(|i| !i.is_open())
+            .then(|| effort_minutes(&intervals)))
+    }
+
+    /// Add the change in tracked time since `tracked_before` to `effort_spent`
+    ///
+    /// Effort recorded by hand or imported is kept as a baseline that
+    /// tracked time adds to.
+    async fn refresh_effort_spent(
+        &self,
+        task: &mut Task,
+        tracked_before: Option<u32>,
+    ) -> EddaResult<()> {
+        let tracked = self.tracked_minutes(task.uuid).await?;
+        if tracked.is_some() || tracked_before.is_some() {
+            let baseline = task
+                .effort_spent
+                .unwrap_or(0)
+                .saturating_sub(tracked_before.unwrap_or(0));
+            task.effort_spent = Some(baseline + tracked.unwrap_or(0));
+        }
+        Ok(())
+    }
+
+    /// List recorded time intervals
+    pub async fn list_intervals(&self, filter: IntervalFilter) -> EddaResult<Vec<TimeInterval>> {
+        self.storage.list_intervals(filter).await
+    }
+
+    /// Record a finished interval for a task, e.g. when a timer was forgotten
+    pub async fn add_interval(
+        &self,
+        id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> EddaResult<TimeInterval> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        let interval = TimeInterval {
+            end: Some(end),
+            ..TimeInterval::new(task.uuid, start)
+        };
+        self.validate_interval(&interval).await?;
+
+        let tracked_before = self.tracked_minutes(task.uuid).await?;
+        let interval = self.storage.create_interval(interval).await?;
+        self.refresh_effort_spent(&mut task, tracked_before).await?;
+        self.storage.update_task(task).await?;
+        Ok(interval)
+    }
+
+    /// Change the start and/or end of a recorded interval
+    pub async fn modify_interval(
+        &self,
+        interval_id: i64,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> EddaResult<TimeInterval> {
+        let mut interval = self.get_interval(interval_id).await?;
+        if let Some(start) = start {
+            interval.start = start;
+        }
+        if let Some(end) = end {
+            interval.end = Some(end);
+        }
+        self.validate_interval(&interval).await?;
+
+        let tracked_before = self.tracked_minutes(interval.task_uuid).await?;
+        let interval = self.storage.update_interval(interval).await?;
+        self.refresh_interval_task(interval.task_uuid, tracked_before)
+            .await?;
+        Ok(interval)
+    }
+
+    /// Delete a recorded interval
+    pub async fn delete_interval(&self, interval_id: i64) -> EddaResult<TimeInterval> {
+        let interval = self.get_interval(interval_id).await?;
+        let tracked_before = self.tracked_minutes(interval.task_uuid).await?;
+        self.storage.delete_interval(interval_id).await?;
+        self.refresh_interval_task(interval.task_uuid, tracked_before)
+            .await?;
+        Ok(interval)
+    }
+
+    /// Aggregate tracked time into a timesheet over the `[from, to)` window
+    pub async fn timesheet(
+        &self,
+        group_by: TimesheetGroup,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> EddaResult<Timesheet> {
+        let filter = IntervalFilter {
+            from,
+            to,
+            ..Default::default()
+        };
+        let intervals = self.storage.list_intervals(filter).await?;
+
+        let mut tasks = HashMap::new();
+        for interval in &intervals {
+            if !tasks.contains_key(&interval.task_uuid)
+                && let Some(task) = self.storage.get_task_by_uuid(interval.task_uuid).await?
+            {
+                tasks.insert(task.uuid, task);
+            }
+        }
+
+        Ok(Timesheet::build(
+            group_by,
+            &intervals,
+            &tasks,
+            from,
+            to,
+            Utc::now(),
+        ))
+    }
+
+    async fn get_interval(&self, interval_id: i64) -> EddaResult<TimeInterval> {
+        self.storage
+            .get_interval(interval_id)
+            .await?
+            .ok_or_else(|| {
+                EddaError::Task(TaskError::NotFound {
+                    id: format!("interval {interval_id}"),
+                })
+            })
+    }
+
+    /// Reject intervals that are backwards or overlap another interval of the same task
+    async fn validate_interval(&self, interval: &TimeInterval) -> EddaResult<()> {
+        interval.validate()?;
+
+        let filter = IntervalFilter {
+            task_uuid: Some(interval.task_uuid),
+            ..Default::default()
+        };
+        let now = Utc::now();
+        let overlapping = self
+            .storage
+            .list_intervals(filter)
+            .await?
+            .into_iter()
+            .filter(|other| other.id != interval.id)
+            .find(|other| other.overlaps(interval, now));
+        if let Some(other) = overlapping {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: format!(
+                    "Interval overlaps interval {} of the same task",
+                    other.id.unwrap_or(0)
+                ),
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Refresh `effort_spent` on the task an interval belongs to
+    async fn refresh_interval_task(
+        &self,
+        task_uuid: Uuid,
+        tracked_before: Option<u32>,
+    ) -> EddaResult<()> {
+        if let Some(mut task) = self.storage.get_task_by_uuid(task_uuid).await? {
+            self.refresh_effort_spent(&mut task, tracked_before).await?;
+            self.storage.update_task(task).await?;
+        }
+        Ok(())
+    }
+
+    /// Make a task depend on the task with the given UUID
+    pub async fn add_dependency(&self, id: i64, depends_on: Uuid) -> EddaResult<Task> {
+        self.add_dependencies(id, &[depends_on]).await
+    }
+
+    /// Make a task depend on each of the tasks with the given UUIDs in a
+    /// single update, so nothing is saved if any of them is invalid
+    pub async fn add_dependencies(&self, id: i64, depends_on: &[Uuid]) -> EddaResult<Task> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        task.depends.extend(depends_on.iter().copied());
+        self.update_task(task).await
+    }
+
+    /// Remove a dependency from a task
+    pub async fn remove_dependency(&self, id: i64, depends_on: Uuid) -> EddaResult<Task> {
+        self.remove_dependencies(id, &[depends_on]).await
+    }
+
+    /// Remove several dependencies from a task in a single update
+    pub async fn remove_dependencies(&self, id: i64, depends_on: &[Uuid]) -> EddaResult<Task> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        for uuid in depends_on {
+            if !task.depends.remove(uuid) {
+                return Err(EddaError::Task(TaskError::Validation {
+                    message: format!("Task {id} does not depend on {uuid}"),
+                }));
+            }
+        }
+        self.update_task(task).await
+    }
+
+    /// Get the dependencies of a task that are still open
+    pub async fn open_dependencies(&self, task: &Task) -> EddaResult<Vec<Task>> {
+        let mut open = Vec::new();
+        for uuid in &task.depends {
+            if let Some(dependency) = self.storage.get_task_by_uuid(*uuid).await?
+                && dependency.is_active()
+            {
+                open.push(dependency);
+            }
+        }
+        open.sort_by_key(|t| t.id);
+        Ok(open)
+    }
+
+    /// Check that newly added dependencies exist and don't close a cycle
+    async fn validate_dependencies(&self, task: &Task, added: &[Uuid]) -> EddaResult<()> {
+        for uuid in added {
+            if *uuid == task.uuid {
+                return Err(EddaError::Task(TaskError::DependencyCycle {
+                    message: format!("task {} cannot depend on itself", task.uuid),
+                }));
+            }
+            if self.storage.get_task_by_uuid(*uuid).await?.is_none() {
+                return Err(EddaError::Task(TaskError::Validation {
+                    message: format!("Dependency not found: {uuid}"),
+                }));
+            }
+        }
+
+        // Walk everything reachable from the new edges; reaching the task
+        // itself means the update would close a cycle
+        let mut visited = HashSet::new();
+        let mut stack: Vec<Uuid> = added.to_vec();
+        while let Some(uuid) = stack.pop() {
+            if !visited.insert(uuid) {
+                continue;
+            }
+            let Some(dependency) = self.storage.get_task_by_uuid(uuid).await? else {
+                continue;
+            };
+            if dependency.depends.contains(&task.uuid) {
+                return Err(EddaError::Task(TaskError::DependencyCycle {
+                    message: format!(
+                        "task {} would depend on itself through task {}",
+                        task.id.unwrap_or(0),
+                        dependency.id.unwrap_or(0)
+                    ),
+                }));
+            }
+            stack.extend(dependency.depends.iter().copied());
+        }
+
+        Ok(())
+    }
+
+    /// Add an annotation to a task, recording its author if known
+    pub async fn annotate_task(
+        &self,
+        id: i64,
+        description: String,
+        author: Option<String>,
+    ) -> EddaResult<Task> {
+        if description.trim().is_empty() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Annotation description cannot be empty".to_string(),
+            }));
+        }
+
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        task.add_annotation_by(description, author);
+        self.journaled_update(task).await
+    }
+
+    /// Remove an annotation, chosen by position or text as in
+    /// [`Task::find_annotation`], returning the task and what was removed
+    pub async fn denotate_task(&self, id: i64, selector: &str) -> EddaResult<(Task, Annotation)> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        let index = task.find_annotation(selector)?;
+        let removed = task.annotations.remove(index);
+        task.modified_date = Utc::now();
+        Ok((self.journaled_update(task).await?, removed))
+    }
+
+    /// Replace the text of an annotation, keeping its entry date and author
+    pub async fn edit_annotation(
+        &self,
+        id: i64,
+        selector: &str,
+        description: String,
+    ) -> EddaResult<Task> {
+        if description.trim().is_empty() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Annotation description cannot be empty".to_string(),
+            }));
+        }
+
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        let index = task.find_annotation(selector)?;
+        task.annotations[index].description = description;
+        task.modified_date = Utc::now();
+        self.journaled_update(task).await
+    }
+
+    /// Add a tag to a task
+    pub async fn add_tag(&self, id: i64, tag: String) -> EddaResult<Task> {
+        if tag.trim().is_empty() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "Tag cannot be empty".to_string(),
+            }));
+        }
+
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        task.add_tag(tag);
+        self.journaled_update(task).await
+    }
+
+    /// Remove a tag from a task
+    pub async fn remove_tag(&self, id: i64, tag: &str) -> EddaResult<Task> {
+        let mut task = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        task.remove_tag(tag);
+        self.journaled_update(task).await
+    }
+
+    /// List tasks with filtering
+    pub async fn list_tasks(
+        &self,
+        filter: Option<crate::storage::TaskFilter>,
+    ) -> EddaResult<Vec<Task>> {
+        self.storage.list_tasks(filter).await
+    }
 
     /// Count tasks with filtering
     pub async fn count_tasks(&self, filter: Option<crate::storage::TaskFilter>) -> EddaResult<u64> {
         self.storage.count_tasks(filter).await
     }
 
+    /// Summarise the matching tasks by project, rolled up through the
+    /// project hierarchy
+    pub async fn project_summaries(
+        &self,
+        filter: Option<crate::storage::TaskFilter>,
+    ) -> EddaResult<Vec<ProjectSummary>> {
+        let tasks = self.storage.list_tasks(filter).await?;
+        Ok(ProjectSummary::build(&tasks))
+    }
+
+    /// Compute the urgency of each task, in the same order
+    pub async fn urgency(&self, tasks: &[Task]) -> EddaResult<Vec<Urgency>> {
+        let blocked = self
+            .dependency_uuids(crate::storage::DependencyState::Blocked)
+            .await?;
+        let blocking = self
+            .dependency_uuids(crate::storage::DependencyState::Blocking)
+            .await?;
+
+        let now = Utc::now();
+        Ok(tasks
+            .iter()
+            .map(|task| {
+                Urgency::of(
+                    task,
+                    &self.urgency,
+                    blocked.contains(&task.uuid),
+                    blocking.contains(&task.uuid),
+                    now,
+                )
+            })
+            .collect())
+    }
+
+    /// Compute the virtual tags of each task, in the same order
+    pub async fn virtual_tags(
+        &self,
+        tasks: &[Task],
+        clock: TagClock,
+    ) -> EddaResult<Vec<Vec<VirtualTag>>> {
+        let blocked = self
+            .dependency_uuids(crate::storage::DependencyState::Blocked)
+            .await?;
+        let blocking = self
+            .dependency_uuids(crate::storage::DependencyState::Blocking)
+            .await?;
+        let children = crate::storage::TaskFilter {
+            expression: Some(FilterExpr::Term(FilterTerm::VirtualTag {
+                tag: VirtualTag::Child,
+                include: true,
+                clock,
+            })),
+            ..Default::default()
+        };
+        let parents: HashSet<Uuid> = self
+            .storage
+            .list_tasks(Some(children))
+            .await?
+            .into_iter()
+            .filter_map(|child| child.parent_uuid)
+            .collect();
+
+        Ok(tasks
+            .iter()
+            .map(|task| {
+                let relations = TaskRelations {
+                    blocked: blocked.contains(&task.uuid),
+                    blocking: blocking.contains(&task.uuid),
+                    parent: parents.contains(&task.uuid),
+                };
+                VirtualTag::of(task, relations, clock)
+            })
+            .collect())
+    }
+
+    /// UUIDs of the tasks in the given dependency state
+    async fn dependency_uuids(
+        &self,
+        state: crate::storage::DependencyState,
+    ) -> EddaResult<HashSet<Uuid>> {
+        let filter = crate::storage::TaskFilter {
+            dependency: Some(state),
+            ..Default::default()
+        };
+        let tasks = self.storage.list_tasks(Some(filter)).await?;
+        Ok(tasks.into_iter().map(|task| task.uuid).collect())
+    }
+
     /// Check if a status transition is valid
-    fn is_valid_status_transition(&self, from: &TaskStatus, to: &TaskStatus) -> bool {
-        match (from, to) {
-            // Any status can transition to deleted
-            (_, TaskStatus::Deleted) => true,
-            // Deleted tasks cannot transition to other statuses
-            (TaskStatus::Deleted, _) => false,
-            // Pending can transition to any non-deleted status
-            (TaskStatus::Pending, _) => true,
-            // Completed can transition back to pending or waiting
-            (TaskStatus::Completed, TaskStatus::Pending | TaskStatus::Waiting) => true,
-            // Waiting can transition to pending or completed
-            (TaskStatus::Waiting, TaskStatus::Pending | TaskStatus::Completed) => true,
-            // Other transitions are invalid
-            _ => false,
-        }
-    }
-
-    /// Get child tasks of a parent task
+    ///
+    /// ```text
+    /// pending     -> in_progress | waiting | completed | deleted | recurring
+    /// in_progress -> pending | completed | deleted
+    /// waiting     -> pending | in_progress | completed | deleted | recurring
+    /// completed   -> pending | waiting | deleted
+    /// recurring   -> pending | deleted
+    /// deleted     -> (terminal)
+    /// ```
+    pub fn is_valid_status_transition(from: &TaskStatus, to: &TaskStatus) -> bool {
+        from.can_transition_to(to)
+    }
+
+    /// Generate pending instances of every recurring template whose next
+    /// occurrences fall within `horizon` from now
+    ///
+    /// Instances are numbered by their position in the template's recurrence
+    /// mask, so completing or deleting one never affects later generation.
+    /// New instances and mask updates go through the journal like any other
+    /// change, so they show in history and can be undone.
+    pub async fn generate_recurring_instances(&self, horizon: Duration) -> EddaResult<Vec<Task>> {
+        let filter = crate::storage::TaskFilter {
+            status: Some(TaskStatus::Recurring),
+            ..Default::default()
+        };
+        let templates = self.storage.list_tasks(Some(filter)).await?;
+        let horizon_end = Utc::now() + horizon;
+        let mut created = Vec::new();
+
+        for mut template in templates {
+            let (Some(pattern), Some(anchor)) = (&template.recurrence, template.due_date) else {
+                continue;
+            };
+            let recurrence = match pattern.parse::<Recurrence>() {
+                Ok(recurrence) => recurrence,
+                Err(e) => {
+                    tracing::warn!("Skipping recurring task {}: {e}", template.uuid);
+                    continue;
+                }
+            };
+
+            let mut mask = template.recurrence_mask.clone().unwrap_or_default();
+            let generated = mask.chars().count();
+
+            for (index, due) in recurrence.occurrences(anchor).enumerate().skip(generated) {
+                if due > horizon_end || template.until_date.is_some_and(|until| due > until) {
+                    break;
+                }
+                let instance = template.recurring_instance(index as u32, due);
+                created.push(self.journaled_create(instance).await?);
+                mask.push(MASK_PENDING);
+            }
+
+            if mask.chars().count() != generated {
+                template.recurrence_mask = Some(mask);
+                self.journaled_update(template).await?;
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Bring waiting tasks whose wait date has passed back to pending and
+    /// delete open tasks past their `until` date
+    ///
+    /// Both changes go through the journal, so they show in a task's history
+    /// and can be undone.
+    pub async fn update_lifecycle(&self, now: DateTime<Utc>) -> EddaResult<LifecycleChanges> {
+        let mut changes = LifecycleChanges::default();
+
+        let filter = crate::storage::TaskFilter {
+            status: Some(TaskStatus::Waiting),
+            ..Default::default()
+        };
+        for mut task in self.storage.list_tasks(Some(filter)).await? {
+            if task.sync_wait(now) {
+                changes.unwaited.push(self.update_task(task).await?);
+            }
+        }
+
+        let filter = crate::storage::TaskFilter {
+            expression: Some(FilterExpr::Term(FilterTerm::Attribute {
+                attribute: FilterAttribute::Until,
+                modifier: FilterModifier::Before,
+                value: FilterValue::Date(now),
+            })),
+            ..Default::default()
+        };
+        for mut task in self.storage.list_tasks(Some(filter)).await? {
+            if !task.is_open() {
+                continue;
+            }
+            task.delete()?;
+            self.close_open_interval(&mut task).await?;
+            changes.expired.push(self.update_task(task).await?);
+        }
+
+        let filter = crate::storage::TaskFilter {
+            status: Some(TaskStatus::InProgress),
+            ..Default::default()
+        };
+        for mut task in self.storage.list_tasks(Some(filter)).await? {
+            if !task.lease_expired(now) {
+                continue;
+            }
+            task.stop()?;
+            self.close_open_interval(&mut task).await?;
+            changes.reclaimed.push(self.update_task(task).await?);
+        }
+
+        Ok(changes)
+    }
+
+    /// Record a recurring instance's status in its template's mask
+    async fn update_recurrence_mask(&self, instance: &Task) -> EddaResult<()> {
+        let (Some(parent_uuid), Some(index)) = (instance.parent_uuid, instance.recurrence_index)
+        else {
+            return Ok(());
+        };
+        let Some(mut template) = self.storage.get_task_by_uuid(parent_uuid).await? else {
+            return Ok(());
+        };
+
+        let marker = match instance.status {
+            TaskStatus::Completed => MASK_COMPLETED,
+            TaskStatus::Deleted => MASK_DELETED,
+            TaskStatus::Waiting => MASK_WAITING,
+            _ => MASK_PENDING,
+        };
+
+        let index = index as usize;
+        let mut mask: Vec<char> = template
+            .recurrence_mask
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .collect();
+        if mask.len() <= index {
+            mask.resize(index + 1, MASK_PENDING);
+        }
+        if mask[index] != marker {
+            mask[index] = marker;
+            template.recurrence_mask = Some(mask.into_iter().collect());
+            self.storage.update_task(template).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the subtasks of a task, ordered by ID
     pub async fn get_child_tasks(&self, parent_id: i64) -> EddaResult<Vec<Task>> {
         let parent_task = self.get_task(parent_id).await?.ok_or_else(|| {
             EddaError::Task(TaskError::NotFound {
@@ -730,16 +2363,113 @@ impl TaskEngine {
             })
         })?;
 
-        let filter = crate::storage::TaskFilter::default();
-        // Note: This would need to be implemented in the storage layer
-        // For now, we'll get all tasks and filter in memory
-        let all_tasks = self.storage.list_tasks(None).await?;
-        let child_tasks: Vec<Task> = all_tasks
+        self.subtasks_of(parent_task.uuid).await
+    }
+
+    /// Load a task with all of its subtasks, deleted ones left out
+    pub async fn task_tree(&self, id: i64) -> EddaResult<TaskTree> {
+        let root = self
+            .get_task(id)
+            .await?
+            .ok_or_else(|| EddaError::Task(TaskError::NotFound { id: id.to_string() }))?;
+
+        let mut descendants = Vec::new();
+        let mut visited = HashSet::from([root.uuid]);
+        let mut parents = vec![root.uuid];
+        while let Some(parent) = parents.pop() {
+            for child in self.subtasks_of(parent).await? {
+                if child.is_deleted() || !visited.insert(child.uuid) {
+                    continue;
+                }
+                parents.push(child.uuid);
+                descendants.push(child);
+            }
+        }
+        Ok(TaskTree::build(root, descendants))
+    }
+
+    /// Tasks whose parent is the given task, excluding the instances of a
+    /// recurring template
+    async fn subtasks_of(&self, parent: Uuid) -> EddaResult<Vec<Task>> {
+        let filter = crate::storage::TaskFilter {
+            parent: Some(parent),
+            include_deleted: true,
+            include_recurring: true,
+            ..Default::default()
+        };
+        let mut subtasks: Vec<Task> = self
+            .storage
+            .list_tasks(Some(filter))
+            .await?
             .into_iter()
-            .filter(|task| task.parent_uuid == Some(parent_task.uuid))
+            .filter(|task| task.recurrence_index.is_none())
             .collect();
+        subtasks.sort_by_key(|task| task.id);
+        Ok(subtasks)
+    }
+
+    /// Subtasks of a task that are still open
+    async fn open_subtasks(&self, parent: Uuid) -> EddaResult<Vec<Task>> {
+        let subtasks = self.subtasks_of(parent).await?;
+        Ok(subtasks.into_iter().filter(Task::is_active).collect())
+    }
 
-        Ok(child_tasks)
+    /// Check that a task's parent exists, can have subtasks and isn't the
+    /// task itself or one of its subtasks
+    async fn validate_parent(&self, task: &Task) -> EddaResult<()> {
+        let Some(parent_uuid) = task.parent_uuid else {
+            return Ok(());
+        };
+        if task.recurrence_index.is_some() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: "A recurring instance can't be moved under another task".to_string(),
+            }));
+        }
+        let parent = self
+            .storage
+            .get_task_by_uuid(parent_uuid)
+            .await?
+            .ok_or_else(|| {
+                EddaError::Task(TaskError::Validation {
+                    message: format!("Parent task not found: {parent_uuid}"),
+                })
+            })?;
+        if parent.is_deleted() || parent.is_recurring_template() {
+            return Err(EddaError::Task(TaskError::Validation {
+                message: format!(
+                    "Task {} can't have subtasks: it is {}",
+                    parent.id.unwrap_or(0),
+                    if parent.is_deleted() {
+                        "deleted"
+                    } else {
+                        "a recurring template"
+                    }
+                ),
+            }));
+        }
+
+        // Walk up from the new parent; meeting the task means it would
+        // become its own ancestor
+        let mut ancestor = Some(parent);
+        let mut visited = HashSet::new();
+        while let Some(current) = ancestor {
+            if current.uuid == task.uuid {
+                return Err(EddaError::Task(TaskError::Validation {
+                    message: format!(
+                        "Task {} can't be a subtask of itself or of its own subtasks",
+                        task.id.unwrap_or(0)
+                    ),
+                }));
+            }
+            if !visited.insert(current.uuid) {
+                break;
+            }
+            ancestor = match current.parent_uuid {
+                Some(uuid) => self.storage.get_task_by_uuid(uuid).await?,
+                None => None,
+            };
+        }
+        Ok(())
     }
 
     /// Get tasks that depend on a given task
@@ -750,13 +2480,11 @@ impl TaskEngine {
             })
         })?;
 
-        let all_tasks = self.storage.list_tasks(None).await?;
-        let dependent_tasks: Vec<Task> = all_tasks
-            .into_iter()
-            .filter(|t| t.depends.contains(&task.uuid))
-            .collect();
-
-        Ok(dependent_tasks)
+        let filter = crate::storage::TaskFilter {
+            depends_on: Some(task.uuid),
+            ..Default::default()
+        };
+        self.storage.list_tasks(Some(filter)).await
     }
 }
 
@@ -810,7 +2538,7 @@ mod task_engine_tests {
         let task_id = task.id.unwrap();
 
         // Complete the task
-        let completed_task = engine.complete_task(task_id).await.unwrap();
+        let completed_task = engine.complete_task(task_id, false).await.unwrap();
         assert_eq!(completed_task.status, TaskStatus::Completed);
         assert!(completed_task.end_date.is_some());
     }
@@ -827,10 +2555,22 @@ mod task_engine_tests {
         // Start the task
         let started_task = engine.start_task(task_id).await.unwrap();
         assert!(started_task.start_date.is_some());
+        assert_eq!(started_task.status, TaskStatus::InProgress);
+
+        // The in-progress status survives a round trip through storage
+        let stored_task = engine.get_task(task_id).await.unwrap().unwrap();
+        assert_eq!(stored_task.status, TaskStatus::InProgress);
 
         // Stop the task
         let stopped_task = engine.stop_task(task_id).await.unwrap();
         assert!(stopped_task.start_date.is_none());
+        assert_eq!(stopped_task.status, TaskStatus::Pending);
+
+        // Stopping again is rejected
+        assert!(matches!(
+            engine.stop_task(task_id).await.unwrap_err(),
+            EddaError::Task(TaskError::InvalidStatusTransition { .. })
+        ));
     }
 
     #[tokio::test]
@@ -865,11 +2605,31 @@ mod task_engine_tests {
 
         // Add an annotation
         let annotated_task = engine
-            .annotate_task(task_id, "This is a note".to_string())
+            .annotate_task(task_id, "This is a note".to_string(), None)
             .await
             .unwrap();
         assert_eq!(annotated_task.annotations.len(), 1);
         assert_eq!(annotated_task.annotations[0].description, "This is a note");
+
+        engine
+            .annotate_task(
+                task_id,
+                "Second note".to_string(),
+                Some("agent-a".to_string()),
+            )
+            .await
+            .unwrap();
+        let edited = engine
+            .edit_annotation(task_id, "Second", "Revised note".to_string())
+            .await
+            .unwrap();
+        assert_eq!(edited.annotations[1].description, "Revised note");
+        assert_eq!(edited.annotations[1].author.as_deref(), Some("agent-a"));
+
+        let (task, removed) = engine.denotate_task(task_id, "1").await.unwrap();
+        assert_eq!(removed.description, "This is a note");
+        assert_eq!(task.annotations.len(), 1);
+        assert!(engine.denotate_task(task_id, "missing").await.is_err());
     }
 
     #[tokio::test]
@@ -888,7 +2648,669 @@ mod task_engine_tests {
 
         // Test invalid transition (completed -> deleted should be valid)
         task.status = TaskStatus::Deleted;
-        let updated_task = engine.update_task(task).await.unwrap();
+        let updated_task = engine.update_task(task.clone()).await.unwrap();
         assert_eq!(updated_task.status, TaskStatus::Deleted);
+
+        // Deleted is terminal
+        task.status = TaskStatus::InProgress;
+        assert!(matches!(
+            engine.update_task(task).await.unwrap_err(),
+            EddaError::Task(TaskError::InvalidStatusTransition { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_recurring_task_generates_instances() {
+        let engine = create_test_engine().await;
+
+        let task = engine
+            .create_task("Weekly review".to_string())
+            .await
+            .unwrap();
+        let mut task = engine.get_task(task.id.unwrap()).await.unwrap().unwrap();
+
+        // A recurrence without a due date is rejected
+        task.recurrence = Some("weekly".to_string());
+        assert!(engine.update_task(task.clone()).await.is_err());
+
+        let anchor = Utc::now() - Duration::days(15);
+        task.due_date = Some(anchor);
+        let template = engine.update_task(task).await.unwrap();
+        assert_eq!(template.status, TaskStatus::Recurring);
+        assert_eq!(template.recurrence_mask.as_deref(), Some(""));
+
+        // Occurrences at -15d, -8d, -1d and +6d fall within a 7 day horizon
+        let created = engine
+            .generate_recurring_instances(Duration::days(7))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 4);
+        assert!(created.iter().all(|t| t.parent_uuid == Some(template.uuid)));
+        assert_eq!(created[0].due_date, Some(anchor));
+        assert_eq!(created[3].recurrence_index, Some(3));
+        let history = engine.history(created[0].id.unwrap()).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].kind, OperationKind::Create);
+        let history = engine.history(template.id.unwrap()).await.unwrap();
+        assert_eq!(
+            history
+                .last()
+                .unwrap()
+                .after
+                .as_ref()
+                .unwrap()
+                .recurrence_mask,
+            Some("----".to_string())
+        );
+
+        // Running again doesn't duplicate instances
+        let created_again = engine
+            .generate_recurring_instances(Duration::days(7))
+            .await
+            .unwrap();
+        assert!(created_again.is_empty());
+
+        // Completing and deleting instances updates the mask without breaking the chain
+        engine
+            .complete_task(created[0].id.unwrap(), false)
+            .await
+            .unwrap();
+        engine.delete_task(created[1].id.unwrap()).await.unwrap();
+        let template = engine
+            .get_task(template.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(template.recurrence_mask.as_deref(), Some("+X--"));
+
+        let created = engine
+            .generate_recurring_instances(Duration::days(14))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].recurrence_index, Some(4));
+
+        // Clearing the recurrence turns the template back into a plain task
+        let mut template = engine
+            .get_task(template.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        template.recurrence = None;
+        let task = engine.update_task(template).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_recurring_task_respects_until() {
+        let engine = create_test_engine().await;
+
+        let task = engine
+            .create_task("Daily standup".to_string())
+            .await
+            .unwrap();
+        let mut task = engine.get_task(task.id.unwrap()).await.unwrap().unwrap();
+        let anchor = Utc::now() - Duration::days(10);
+        task.due_date = Some(anchor);
+        task.until_date = Some(anchor + Duration::days(2));
+        task.recurrence = Some("daily".to_string());
+        engine.update_task(task).await.unwrap();
+
+        let created = engine
+            .generate_recurring_instances(Duration::days(7))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 3);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_dependency_validation() {
+        let engine = create_test_engine().await;
+        let a = engine.create_task("A".to_string()).await.unwrap();
+        let b = engine.create_task("B".to_string()).await.unwrap();
+        let c = engine.create_task("C".to_string()).await.unwrap();
+
+        engine.add_dependency(a.id.unwrap(), b.uuid).await.unwrap();
+        engine.add_dependency(b.id.unwrap(), c.uuid).await.unwrap();
+
+        // c -> a would close a -> b -> c -> a
+        let result = engine.add_dependency(c.id.unwrap(), a.uuid).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            EddaError::Task(TaskError::DependencyCycle { .. })
+        ));
+
+        let result = engine.add_dependency(a.id.unwrap(), a.uuid).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            EddaError::Task(TaskError::DependencyCycle { .. })
+        ));
+
+        let result = engine.add_dependency(a.id.unwrap(), Uuid::new_v4()).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            EddaError::Task(TaskError::Validation { .. })
+        ));
+
+        let dependents = engine.get_dependent_tasks(b.id.unwrap()).await.unwrap();
+        assert_eq!(dependents.len(), 1);
+        assert_eq!(dependents[0].uuid, a.uuid);
+
+        let task = engine
+            .remove_dependency(a.id.unwrap(), b.uuid)
+            .await
+            .unwrap();
+        assert!(task.depends.is_empty());
+        assert!(
+            engine
+                .remove_dependency(a.id.unwrap(), b.uuid)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_complete_blocked_task() {
+        let engine = create_test_engine().await;
+        let task = engine.create_task("Deploy".to_string()).await.unwrap();
+        let blocker = engine.create_task("Review".to_string()).await.unwrap();
+        engine
+            .add_dependency(task.id.unwrap(), blocker.uuid)
+            .await
+            .unwrap();
+
+        let result = engine.complete_task(task.id.unwrap(), false).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            EddaError::Task(TaskError::Blocked { .. })
+        ));
+
+        engine
+            .complete_task(blocker.id.unwrap(), false)
+            .await
+            .unwrap();
+        let completed = engine.complete_task(task.id.unwrap(), false).await.unwrap();
+        assert_eq!(completed.status, TaskStatus::Completed);
+
+        let forced = engine.create_task("Forced".to_string()).await.unwrap();
+        let open = engine.create_task("Still open".to_string()).await.unwrap();
+        engine
+            .add_dependency(forced.id.unwrap(), open.uuid)
+            .await
+            .unwrap();
+        let completed = engine
+            .complete_task(forced.id.unwrap(), true)
+            .await
+            .unwrap();
+        assert_eq!(completed.status, TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_subtask_cascades() {
+        let engine = create_test_engine().await.with_subtasks(SubtaskConfig {
+            complete_parent: true,
+            ..Default::default()
+        });
+        let release = engine.create_task("Release".to_string()).await.unwrap();
+        let mut subtasks = Vec::new();
+        for description in ["Build", "Docs"] {
+            let mut task = Task::new(description.to_string());
+            task.parent_uuid = Some(release.uuid);
+            subtasks.push(engine.add_task(task).await.unwrap());
+        }
+        let children = engine.get_child_tasks(release.id.unwrap()).await.unwrap();
+        assert_eq!(children.len(), 2);
+
+        // A task can't move under its own subtask
+        let mut cycle = release.clone();
+        cycle.parent_uuid = Some(subtasks[0].uuid);
+        assert!(engine.update_task(cycle).await.is_err());
+
+        let result = engine.complete_task(release.id.unwrap(), false).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            EddaError::Task(TaskError::OpenSubtasks { .. })
+        ));
+
+        engine
+            .complete_task(subtasks[0].id.unwrap(), false)
+            .await
+            .unwrap();
+        let parent = engine
+            .get_task_by_uuid(release.uuid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(parent.status, TaskStatus::Pending);
+        engine
+            .complete_task(subtasks[1].id.unwrap(), false)
+            .await
+            .unwrap();
+        let parent = engine
+            .get_task_by_uuid(release.uuid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(parent.status, TaskStatus::Completed);
+
+        // Deleting a parent takes its open subtasks with it
+        let cleanup = engine.create_task("Cleanup".to_string()).await.unwrap();
+        let mut step = Task::new("Step".to_string());
+        step.parent_uuid = Some(cleanup.uuid);
+        let step = engine.add_task(step).await.unwrap();
+        engine.delete_task(cleanup.id.unwrap()).await.unwrap();
+        let step = engine.get_task_by_uuid(step.uuid).await.unwrap().unwrap();
+        assert_eq!(step.status, TaskStatus::Deleted);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_claim_next_task() {
+        let engine = create_test_engine().await;
+        let low = engine.create_task("Low".to_string()).await.unwrap();
+        let mut high = Task::new("High".to_string());
+        high.priority = Some(Priority::High);
+        let high = engine.add_task(high).await.unwrap();
+        let mut blocked = Task::new("Blocked".to_string());
+        blocked.priority = Some(Priority::High);
+        blocked.depends.insert(high.uuid);
+        engine.add_task(blocked).await.unwrap();
+
+        let next = engine.next_task(None).await.unwrap().unwrap();
+        assert_eq!(next.uuid, high.uuid);
+
+        let lease = Duration::minutes(15);
+        let claimed = engine
+            .claim_next_task("agent-a", lease, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(claimed.uuid, high.uuid);
+        assert_eq!(claimed.status, TaskStatus::InProgress);
+        assert_eq!(claimed.claimed_by.as_deref(), Some("agent-a"));
+        assert!(claimed.start_date.is_some());
+
+        // A second agent gets the next task, and then nothing is left
+        let second = engine
+            .claim_next_task("agent-b", lease, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.uuid, low.uuid);
+        assert!(
+            engine
+                .claim_next_task("agent-c", lease, None)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let id = claimed.id.unwrap();
+        assert!(engine.renew_lease(id, "agent-b", lease).await.is_err());
+        assert!(
+            engine
+                .renew_lease(id, "agent-a", Duration::MAX)
+                .await
+                .is_err()
+        );
+        let renewed = engine
+            .renew_lease(id, "agent-a", Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(renewed.lease_expires > claimed.lease_expires);
+
+        assert!(engine.release_task(id, "agent-b").await.is_err());
+        let released = engine.release_task(id, "agent-a").await.unwrap();
+        assert_eq!(released.status, TaskStatus::Pending);
+        assert_eq!(released.claimed_by, None);
+        assert_eq!(released.lease_expires, None);
+
+        // An expired lease puts the task back in the queue
+        let changes = engine
+            .update_lifecycle(Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(changes.reclaimed.len(), 1);
+        assert_eq!(changes.reclaimed[0].uuid, low.uuid);
+        assert_eq!(changes.reclaimed[0].status, TaskStatus::Pending);
+        assert_eq!(changes.reclaimed[0].claimed_by, None);
+        let intervals = engine
+            .list_intervals(IntervalFilter {
+                task_uuid: Some(low.uuid),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(intervals.iter().all(|interval| !interval.is_open()));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_start_stop_records_intervals() {
+        let engine = create_test_engine().await;
+        let task = engine.create_task("Tracked".to_string()).await.unwrap();
+        let id = task.id.unwrap();
+
+        engine.start_task(id).await.unwrap();
+        let filter = IntervalFilter {
+            task_uuid: Some(task.uuid),
+            ..Default::default()
+        };
+        let intervals = engine.list_intervals(filter.clone()).await.unwrap();
+        assert_eq!(intervals.len(), 1);
+        assert!(intervals[0].is_open());
+
+        let stopped = engine.stop_task(id).await.unwrap();
+        assert_eq!(stopped.effort_spent, Some(0));
+        let intervals = engine.list_intervals(filter.clone()).await.unwrap();
+        assert!(!intervals[0].is_open());
+
+        // A forgotten timer is recorded by hand
+        let start = Utc::now() - Duration::hours(3);
+        let added = engine
+            .add_interval(id, start, start + Duration::minutes(90))
+            .await
+            .unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.effort_spent, Some(90));
+
+        // Overlapping and backwards intervals are rejected
+        assert!(
+            engine
+                .add_interval(
+                    id,
+                    start + Duration::minutes(30),
+                    start + Duration::hours(2)
+                )
+                .await
+                .is_err()
+        );
+        assert!(
+            engine
+                .modify_interval(added.id.unwrap(), None, Some(start - Duration::minutes(1)))
+                .await
+                .is_err()
+        );
+
+        engine
+            .modify_interval(added.id.unwrap(), None, Some(start + Duration::minutes(45)))
+            .await
+            .unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.effort_spent, Some(45));
+
+        engine.delete_interval(added.id.unwrap()).await.unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.effort_spent, Some(0));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_tracked_time_adds_to_recorded_effort() {
+        let engine = create_test_engine().await;
+        let mut task = engine.create_task("Imported".to_string()).await.unwrap();
+        task.effort_spent = Some(120);
+        let task = engine.update_task(task).await.unwrap();
+        let id = task.id.unwrap();
+
+        let started = engine.start_task(id).await.unwrap();
+        assert_eq!(started.effort_spent, Some(120));
+        let stopped = engine.stop_task(id).await.unwrap();
+        assert_eq!(stopped.effort_spent, Some(120));
+
+        let start = Utc::now() - Duration::hours(3);
+        let added = engine
+            .add_interval(id, start, start + Duration::minutes(30))
+            .await
+            .unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.effort_spent, Some(150));
+
+        engine
+            .modify_interval(added.id.unwrap(), None, Some(start + Duration::minutes(45)))
+            .await
+            .unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.effort_spent, Some(165));
+
+        engine.delete_interval(added.id.unwrap()).await.unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.effort_spent, Some(120));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_undo_reverts_journaled_changes() {
+        let engine = create_test_engine().await;
+        let task = engine.create_task("Journaled".to_string()).await.unwrap();
+        let id = task.id.unwrap();
+        engine.add_tag(id, "ops".to_string()).await.unwrap();
+        engine.start_task(id).await.unwrap();
+        engine.complete_task(id, false).await.unwrap();
+
+        let history = engine.history(id).await.unwrap();
+        let kinds: Vec<OperationKind> = history.iter().map(|op| op.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                OperationKind::Create,
+                OperationKind::Update,
+                OperationKind::Update,
+                OperationKind::Update
+            ]
+        );
+
+        // Undoing the completion reopens the interval closed by it
+        let undone = engine.undo(1).await.unwrap();
+        assert_eq!(undone.len(), 1);
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::InProgress);
+        let open = IntervalFilter {
+            task_uuid: Some(task.uuid),
+            open_only: true,
+            ..Default::default()
+        };
+        assert_eq!(engine.list_intervals(open.clone()).await.unwrap().len(), 1);
+
+        // Undoing the start drops its interval
+        engine.undo(1).await.unwrap();
+        let task = engine.get_task(id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(engine.list_intervals(open).await.unwrap().is_empty());
+
+        // Undo entries are skipped, so the next undos walk further back
+        let undone = engine.undo(5).await.unwrap();
+        assert_eq!(undone.len(), 2);
+        assert!(engine.get_task(id).await.unwrap().is_none());
+        assert!(engine.undo(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_undo_is_all_or_nothing() {
+        let engine = create_test_engine().await;
+        let first = engine.create_task("First".to_string()).await.unwrap();
+        let second = engine.create_task("Second".to_string()).await.unwrap();
+
+        // Removing a task behind the journal's back makes its entry
+        // impossible to revert
+        engine.storage.delete_task(first.id.unwrap()).await.unwrap();
+
+        assert!(engine.undo(2).await.is_err());
+        assert!(engine.get_task(second.id.unwrap()).await.unwrap().is_some());
+        assert_eq!(engine.undo(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_select_tasks_and_batch_rollback() {
+        let engine = create_test_engine().await;
+        let mut uuids = Vec::new();
+        for name in ["one", "two", "three", "four"] {
+            let task = engine.create_task(name.to_string()).await.unwrap();
+            uuids.push(task.uuid);
+        }
+        engine.add_tag(4, "ops".to_string()).await.unwrap();
+
+        let prefix = uuids[1].to_string()[..8].to_string();
+        let selection = TaskSelection::References(vec![
+            TaskReference::Range(3, 9),
+            TaskReference::UuidPrefix(prefix),
+            TaskReference::Id(3),
+        ]);
+        let ids: Vec<i64> = engine
+            .select_tasks(&selection)
+            .await
+            .unwrap()
+            .iter()
+            .filter_map(|t| t.id)
+            .collect();
+        assert_eq!(ids, [3, 4, 2]);
+
+        // A range is resolved in one query, however wide it is
+        let wide = TaskSelection::References(vec![TaskReference::Range(2, i64::MAX)]);
+        assert_eq!(engine.select_tasks(&wide).await.unwrap().len(), 3);
+
+        let selection = TaskSelection::Filter("+ops".parse().unwrap());
+        assert_eq!(engine.select_tasks(&selection).await.unwrap().len(), 1);
+        let missing = TaskSelection::References(vec![TaskReference::Id(42)]);
+        assert!(engine.select_tasks(&missing).await.is_err());
+
+        engine.begin_batch().await.unwrap();
+        engine.complete_task(1, false).await.unwrap();
+        engine.delete_task(2).await.unwrap();
+        engine.rollback_batch().await.unwrap();
+        assert_eq!(
+            engine.get_task(1).await.unwrap().unwrap().status,
+            TaskStatus::Pending
+        );
+        assert_eq!(
+            engine.get_task(2).await.unwrap().unwrap().status,
+            TaskStatus::Pending
+        );
+
+        engine.begin_batch().await.unwrap();
+        engine.complete_task(1, false).await.unwrap();
+        engine.commit_batch().await.unwrap();
+        assert_eq!(
+            engine.get_task(1).await.unwrap().unwrap().status,
+            TaskStatus::Completed
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_atomically_rolls_back_on_error() {
+        let engine = create_test_engine().await;
+
+        let result: EddaResult<()> = engine
+            .atomically(async || {
+                engine.create_task("Half done".to_string()).await?;
+                Err(EddaError::Task(TaskError::Validation {
+                    message: "fails midway".to_string(),
+                }))
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(engine.list_tasks(None).await.unwrap().is_empty());
+
+        // Without an error the change is saved, and the batch is closed again
+        engine
+            .atomically(async || engine.create_task("Done".to_string()).await)
+            .await
+            .unwrap();
+        assert_eq!(engine.list_tasks(None).await.unwrap().len(), 1);
+        engine.begin_batch().await.unwrap();
+        engine.rollback_batch().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_update_lifecycle_wakes_and_expires() {
+        let engine = create_test_engine().await;
+        let now = Utc::now();
+
+        let mut waiting = Task::new("Follow up".to_string());
+        waiting.wait_date = Some(now + Duration::hours(1));
+        let waiting = engine.add_task(waiting).await.unwrap();
+        assert_eq!(waiting.status, TaskStatus::Waiting);
+
+        let mut expiring = Task::new("Claim voucher".to_string());
+        expiring.until_date = Some(now + Duration::hours(2));
+        let expiring = engine.add_task(expiring).await.unwrap();
+
+        // Nothing is due yet
+        let changes = engine.update_lifecycle(now).await.unwrap();
+        assert!(changes.unwaited.is_empty() && changes.expired.is_empty());
+
+        let changes = engine
+            .update_lifecycle(now + Duration::hours(3))
+            .await
+            .unwrap();
+        assert_eq!(changes.unwaited.len(), 1);
+        assert_eq!(changes.expired.len(), 1);
+        let woken = engine.get_task(waiting.id.unwrap()).await.unwrap().unwrap();
+        assert_eq!(woken.status, TaskStatus::Pending);
+        let expired = engine
+            .get_task(expiring.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(expired.status, TaskStatus::Deleted);
+
+        // Both transitions are journaled
+        let history = engine.history(expiring.id.unwrap()).await.unwrap();
+        assert_eq!(
+            history.last().unwrap().after.as_ref().unwrap().status,
+            TaskStatus::Deleted
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_import_tasks_upserts_by_uuid() {
+        let engine = create_test_engine().await;
+        let existing = engine.create_task("Old title".to_string()).await.unwrap();
+
+        let blocker = Task::new("Blocker".to_string());
+        let mut dependent = Task::new("Dependent".to_string());
+        dependent.depends.insert(blocker.uuid);
+        let mut renamed = existing.clone();
+        renamed.description = "New title".to_string();
+        renamed.id = None;
+
+        // The dependency points at a task later in the same import
+        let tasks = vec![dependent, blocker, renamed];
+        let summary = engine.import_tasks(tasks.clone()).await.unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 2,
+                updated: 1,
+                unchanged: 0
+            }
+        );
+        assert_eq!(
+            engine
+                .get_task(existing.id.unwrap())
+                .await
+                .unwrap()
+                .unwrap()
+                .description,
+            "New title"
+        );
+
+        let summary = engine.import_tasks(tasks).await.unwrap();
+        assert_eq!(summary.unchanged, 3);
+
+        // A bad task rolls back the whole import
+        let bad = vec![Task::new("Fine".to_string()), Task::new("  ".to_string())];
+        assert!(engine.import_tasks(bad).await.is_err());
+        assert_eq!(engine.list_tasks(None).await.unwrap().len(), 3);
     }
 }
@@ -0,0 +1,397 @@
+use crate::core::uda::{UdaDefinition, UdaType, format_iso_duration, parse_duration};
+use crate::core::{Annotation, Priority, Task, TaskError, TaskStatus};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Timestamp format used by Taskwarrior, e.g. `20260311T153000Z`
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Attributes Taskwarrior computes on export; they are not imported
+const COMPUTED_ATTRIBUTES: [&str; 2] = ["id", "urgency"];
+
+/// Counts of what an import did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+/// Format a timestamp the way Taskwarrior exports it
+pub fn format_date(date: DateTime<Utc>) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// Parse a Taskwarrior timestamp, also accepting RFC 3339
+pub fn parse_date(value: &str) -> Result<DateTime<Utc>, TaskError> {
+    NaiveDateTime::parse_from_str(value, DATE_FORMAT)
+        .map(|date| date.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|d| d.with_timezone(&Utc)))
+        .map_err(|_| TaskError::Validation {
+            message: format!("Invalid Taskwarrior date: {value}"),
+        })
+}
+
+/// Convert a task to a Taskwarrior `task export` object
+///
+/// In-progress tasks are exported as pending with a `start` date, which is
+/// how Taskwarrior marks active tasks. UDA values are written as top-level
+/// attributes, with dates and durations in Taskwarrior's formats.
+pub fn to_taskwarrior(task: &Task, udas: &BTreeMap<String, UdaDefinition>) -> Value {
+    let mut object = Map::new();
+    let is_open = matches!(
+        task.status,
+        TaskStatus::Pending | TaskStatus::InProgress | TaskStatus::Waiting
+    );
+    object.insert(
+        "id".to_string(),
+        Value::from(if is_open { task.id.unwrap_or(0) } else { 0 }),
+    );
+    object.insert("description".into(), Value::from(task.description.clone()));
+    object.insert("entry".into(), Value::from(format_date(task.entry_date)));
+    object.insert(
+        "modified".into(),
+        Value::from(format_date(task.modified_date)),
+    );
+    let status = match task.status {
+        TaskStatus::Pending | TaskStatus::InProgress => "pending",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Deleted => "deleted",
+        TaskStatus::Waiting => "waiting",
+        TaskStatus::Recurring => "recurring",
+    };
+    object.insert("status".into(), Value::from(status));
+    object.insert("uuid".into(), Value::from(task.uuid.to_string()));
+
+    if let Some(project) = &task.project {
+        object.insert("project".into(), Value::from(project.clone()));
+    }
+    if let Some(priority) = &task.priority {
+        object.insert("priority".into(), Value::from(priority.to_string()));
+    }
+    for (name, date) in [
+        ("due", task.due_date),
+        ("scheduled", task.scheduled_date),
+        ("wait", task.wait_date),
+        ("until", task.until_date),
+        ("start", task.start_date),
+        ("end", task.end_date),
+    ] {
+        if let Some(date) = date {
+            object.insert(name.into(), Value::from(format_date(date)));
+        }
+    }
+
+    if !task.tags.is_empty() {
+        let mut tags: Vec<&String> = task.tags.iter().collect();
+        tags.sort();
+        object.insert("tags".into(), Value::from_iter(tags.into_iter().cloned()));
+    }
+    if !task.annotations.is_empty() {
+        let annotations = task.annotations.iter().map(|annotation| {
+            let mut json = serde_json::json!({
+                "entry": format_date(annotation.entry),
+                "description": annotation.description,
+            });
+            // Not a Taskwarrior field, but kept so a round trip through
+            // export and import doesn't lose it
+            if let Some(author) = &annotation.author {
+                json["author"] = Value::from(author.as_str());
+            }
+            json
+        });
+        object.insert("annotations".into(), Value::from_iter(annotations));
+    }
+    if !task.depends.is_empty() {
+        let mut depends: Vec<String> = task.depends.iter().map(|u| u.to_string()).collect();
+        depends.sort();
+        object.insert("depends".into(), Value::from(depends));
+    }
+
+    if let Some(recurrence) = &task.recurrence {
+        object.insert("recur".into(), Value::from(recurrence.clone()));
+    }
+    if let Some(mask) = &task.recurrence_mask {
+        object.insert("mask".into(), Value::from(mask.clone()));
+    }
+    if let Some(index) = task.recurrence_index {
+        object.insert("imask".into(), Value::from(index));
+    }
+    if let Some(parent) = task.parent_uuid {
+        object.insert("parent".into(), Value::from(parent.to_string()));
+    }
+
+    for (name, value) in &task.udas {
+        let kind = udas.get(name).map(|definition| definition.kind);
+        let value = match (kind, value) {
+            (Some(UdaType::Date), Value::String(text)) => parse_date(text)
+                .map(|date| Value::from(format_date(date)))
+                .unwrap_or_else(|_| value.clone()),
+            (Some(UdaType::Duration), Value::Number(seconds)) => seconds
+                .as_u64()
+                .map(|seconds| Value::from(format_iso_duration(seconds)))
+                .unwrap_or_else(|| value.clone()),
+            _ => value.clone(),
+        };
+        object.insert(name.clone(), value);
+    }
+
+    Value::Object(object)
+}
+
+/// Convert a Taskwarrior `task export` object to a task
+///
+/// Attributes Edda doesn't know are kept as UDA values so they survive a
+/// later export; values of declared UDAs are converted to their stored form.
+pub fn from_taskwarrior(
+    value: &Value,
+    udas: &BTreeMap<String, UdaDefinition>,
+) -> Result<Task, TaskError> {
+    let object = value.as_object().ok_or_else(|| TaskError::Validation {
+        message: format!("Expected a task object, got: {value}"),
+    })?;
+    let text = |name: &str| -> Result<Option<&str>, TaskError> {
+        match object.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(text)) => Ok(Some(text.as_str())),
+            Some(other) => Err(TaskError::Validation {
+                message: format!("Invalid {name} attribute: {other}"),
+            }),
+        }
+    };
+    let date = |name: &str| text(name)?.map(parse_date).transpose();
+    let uuid = |value: &str| {
+        Uuid::parse_str(value.trim()).map_err(|_| TaskError::Validation {
+            message: format!("Invalid UUID: {value}"),
+        })
+    };
+
+    let description = text("description")?.ok_or_else(|| TaskError::Validation {
+        message: "Task is missing a description".to_string(),
+    })?;
+    let mut task = Task::new(description.to_string());
+    task.uuid = uuid(text("uuid")?.ok_or_else(|| TaskError::Validation {
+        message: format!("Task is missing a UUID: {description}"),
+    })?)?;
+
+    task.entry_date = date("entry")?.unwrap_or(task.entry_date);
+    task.due_date = date("due")?;
+    task.scheduled_date = date("scheduled")?;
+    task.wait_date = date("wait")?;
+    task.until_date = date("until")?;
+    task.start_date = date("start")?;
+    task.end_date = date("end")?;
+    task.project = text("project")?.map(String::from);
+    task.priority = text("priority")?.map(str::parse::<Priority>).transpose()?;
+    task.recurrence = text("recur")?.map(String::from);
+    task.recurrence_mask = text("mask")?.map(String::from);
+    task.parent_uuid = text("parent")?.map(uuid).transpose()?;
+
+    task.status = match text("status")?.unwrap_or("pending") {
+        "pending" if task.start_date.is_some() => TaskStatus::InProgress,
+        status => status.parse::<TaskStatus>()?,
+    };
+
+    task.recurrence_index = match object.get("imask") {
+        None | Some(Value::Null) => None,
+        Some(Value::Number(index)) => index.as_u64().map(|i| i as u32),
+        Some(Value::String(index)) => index.parse().ok(),
+        Some(other) => {
+            return Err(TaskError::Validation {
+                message: format!("Invalid imask attribute: {other}"),
+            });
+        }
+    };
+
+    if let Some(tags) = object.get("tags").and_then(Value::as_array) {
+        for tag in tags.iter().filter_map(Value::as_str) {
+            task.add_tag(tag.to_string());
+        }
+    }
+
+    if let Some(annotations) = object.get("annotations").and_then(Value::as_array) {
+        for annotation in annotations {
+            let description = annotation["description"].as_str().unwrap_or_default();
+            let entry = match annotation["entry"].as_str() {
+                Some(entry) => parse_date(entry)?,
+                None => task.entry_date,
+            };
+            task.annotations.push(Annotation {
+                entry,
+                description: description.to_string(),
+                author: annotation["author"].as_str().map(str::to_string),
+            });
+        }
+    }
+
+    // Taskwarrior 2.6+ writes an array, older versions a comma-separated string
+    match object.get("depends") {
+        Some(Value::Array(depends)) => {
+            for value in depends.iter().filter_map(Value::as_str) {
+                task.depends.insert(uuid(value)?);
+            }
+        }
+        Some(Value::String(depends)) => {
+            for value in depends.split(',').filter(|v| !v.trim().is_empty()) {
+                task.depends.insert(uuid(value)?);
+            }
+        }
+        _ => {}
+    }
+
+    const KNOWN: [&str; 20] = [
+        "description",
+        "uuid",
+        "status",
+        "entry",
+        "modified",
+        "due",
+        "scheduled",
+        "wait",
+        "until",
+        "start",
+        "end",
+        "project",
+        "priority",
+        "recur",
+        "mask",
+        "imask",
+        "parent",
+        "tags",
+        "annotations",
+        "depends",
+    ];
+    for (name, value) in object {
+        if KNOWN.contains(&name.as_str())
+            || COMPUTED_ATTRIBUTES.contains(&name.as_str())
+            || value.is_null()
+        {
+            continue;
+        }
+        let value = match (udas.get(name).map(|d| d.kind), value) {
+            (Some(UdaType::Date), Value::String(text)) => {
+                Value::from(parse_date(text)?.to_rfc3339())
+            }
+            (Some(UdaType::Duration), Value::String(text)) => {
+                Value::from(parse_duration(text).ok_or_else(|| TaskError::Validation {
+                    message: format!("Invalid duration value for {name}: {text}"),
+                })?)
+            }
+            (Some(UdaType::Number), Value::String(text)) => {
+                serde_json::from_str::<serde_json::Number>(text)
+                    .map(Value::Number)
+                    .map_err(|_| TaskError::Validation {
+                        message: format!("Invalid number value for {name}: {text}"),
+                    })?
+            }
+            _ => value.clone(),
+        };
+        task.udas.insert(name.clone(), value);
+    }
+
+    // Set last, since adding tags touches the modified date
+    task.modified_date = date("modified")?.unwrap_or(task.entry_date);
+    Ok(task)
+}
+
+/// Parse `task export` output: a JSON array, or one JSON object per line as
+/// written by older Taskwarrior versions
+pub fn parse_export(
+    input: &str,
+    udas: &BTreeMap<String, UdaDefinition>,
+) -> Result<Vec<Task>, TaskError> {
+    let invalid = |e: serde_json::Error| TaskError::Validation {
+        message: format!("Invalid Taskwarrior JSON: {e}"),
+    };
+    let values: Vec<Value> = if input.trim_start().starts_with('[') {
+        serde_json::from_str(input).map_err(invalid)?
+    } else {
+        input
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_str(line).map_err(invalid))
+            .collect::<Result<_, _>>()?
+    };
+    values
+        .iter()
+        .map(|value| from_taskwarrior(value, udas))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const EXPORT: &str = r#"[
+{"id":1,"description":"Fix login","entry":"20260301T090000Z","modified":"20260302T100000Z","status":"pending","uuid":"5f0c6b1e-6e4a-4c8a-9d3e-2f1b7c9a0d11","project":"web","priority":"H","due":"20260310T170000Z","start":"20260302T100000Z","tags":["bug","auth"],"annotations":[{"entry":"20260301T091500Z","description":"Reported by support"}],"depends":"0b6d7c7e-1f7e-4a55-8c59-3b8b3a2b4c10","estimate":"PT2H","customer":"acme","urgency":12.3}
+]"#;
+
+    fn udas() -> BTreeMap<String, UdaDefinition> {
+        BTreeMap::from([(
+            "estimate".to_string(),
+            UdaDefinition {
+                kind: UdaType::Duration,
+                values: Vec::new(),
+            },
+        )])
+    }
+
+    #[test]
+    fn test_import_taskwarrior_task() {
+        let tasks = parse_export(EXPORT, &udas()).unwrap();
+        assert_eq!(tasks.len(), 1);
+        let task = &tasks[0];
+        assert_eq!(task.description, "Fix login");
+        assert_eq!(
+            task.uuid.to_string(),
+            "5f0c6b1e-6e4a-4c8a-9d3e-2f1b7c9a0d11"
+        );
+        // Pending with a start date is an active task
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert_eq!(
+            task.entry_date,
+            Utc.with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap()
+        );
+        assert_eq!(task.priority, Some(Priority::High));
+        assert!(task.tags.contains("bug") && task.tags.contains("auth"));
+        assert_eq!(task.annotations[0].description, "Reported by support");
+        assert_eq!(task.depends.len(), 1);
+        assert_eq!(task.udas["estimate"], Value::from(7200));
+        assert_eq!(task.udas["customer"], Value::from("acme"));
+        assert!(!task.udas.contains_key("urgency"));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let task = parse_export(EXPORT, &udas()).unwrap().remove(0);
+        let exported = to_taskwarrior(&task, &udas());
+        assert_eq!(exported["status"], "pending");
+        assert_eq!(exported["due"], "20260310T170000Z");
+        assert_eq!(exported["estimate"], "PT2H");
+        assert_eq!(exported["customer"], "acme");
+        assert_eq!(
+            exported["depends"],
+            serde_json::json!(["0b6d7c7e-1f7e-4a55-8c59-3b8b3a2b4c10"])
+        );
+        assert_eq!(from_taskwarrior(&exported, &udas()).unwrap(), task);
+    }
+
+    #[test]
+    fn test_parse_export_lines_and_errors() {
+        let lines = concat!(
+            r#"{"description":"One","uuid":"5f0c6b1e-6e4a-4c8a-9d3e-2f1b7c9a0d11"}"#,
+            "\n",
+            r#"{"description":"Two","uuid":"0b6d7c7e-1f7e-4a55-8c59-3b8b3a2b4c10","status":"completed"}"#,
+        );
+        let tasks = parse_export(lines, &BTreeMap::new()).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].status, TaskStatus::Completed);
+
+        assert!(parse_export(r#"[{"description":"No UUID"}]"#, &BTreeMap::new()).is_err());
+        assert!(parse_export("not json", &BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use crate::core::{Task, TaskError};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Label used for intervals whose task has no project or no tags
+const UNASSIGNED: &str = "(none)";
+
+/// A span of work recorded against a task
+///
+/// Intervals are opened by `task start` and closed by `task stop`; an open
+/// interval has no `end` yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeInterval {
+    pub id: Option<i64>,
+    pub task_uuid: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl TimeInterval {
+    /// Create a new open interval
+    pub fn new(task_uuid: Uuid, start: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            task_uuid,
+            start,
+            end: None,
+        }
+    }
+
+    /// Check if the interval is still running
+    pub fn is_open(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Length of the interval, measuring an open interval up to `now`
+    pub fn duration(&self, now: DateTime<Utc>) -> Duration {
+        (self.end.unwrap_or(now) - self.start).max(Duration::zero())
+    }
+
+    /// Check that the interval doesn't end before it starts
+    pub fn validate(&self) -> Result<(), TaskError> {
+        match self.end {
+            Some(end) if end < self.start => Err(TaskError::Validation {
+                message: format!(
+                    "Interval ends ({}) before it starts ({})",
+                    end.to_rfc3339(),
+                    self.start.to_rfc3339()
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if two intervals share any time, treating open intervals as running until `now`
+    pub fn overlaps(&self, other: &TimeInterval, now: DateTime<Utc>) -> bool {
+        self.start < other.end.unwrap_or(now) && other.start < self.end.unwrap_or(now)
+    }
+}
+
+/// Total whole minutes across the closed intervals, used for `effort_spent`
+pub fn effort_minutes(intervals: &[TimeInterval]) -> u32 {
+    let seconds: i64 = intervals
+        .iter()
+        .filter_map(|interval| interval.end.map(|end| (end - interval.start).num_seconds()))
+        .sum();
+    (seconds.max(0) / 60) as u32
+}
+
+/// How a timesheet groups tracked time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimesheetGroup {
+    Day,
+    Week,
+    Project,
+    Tag,
+}
+
+impl std::fmt::Display for TimesheetGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimesheetGroup::Day => write!(f, "day"),
+            TimesheetGroup::Week => write!(f, "week"),
+            TimesheetGroup::Project => write!(f, "project"),
+            TimesheetGroup::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+impl std::str::FromStr for TimesheetGroup {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" | "daily" => Ok(TimesheetGroup::Day),
+            "week" | "weekly" => Ok(TimesheetGroup::Week),
+            "project" => Ok(TimesheetGroup::Project),
+            "tag" => Ok(TimesheetGroup::Tag),
+            _ => Err(TaskError::Validation {
+                message: format!(
+                    "Invalid timesheet grouping: {s} (expected day, week, project or tag)"
+                ),
+            }),
+        }
+    }
+}
+
+/// Time tracked for one group of a timesheet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimesheetRow {
+    pub key: String,
+    pub minutes: u64,
+    /// Number of distinct tasks that contributed time
+    pub tasks: usize,
+}
+
+/// Tracked time aggregated by day, week, project or tag
+///
+/// Intervals are clipped to the `[from, to)` window, and day and week groups
+/// split intervals at UTC midnight. With tag grouping a task's time counts
+/// towards each of its tags, so rows can add up to more than `total_minutes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Timesheet {
+    pub group_by: TimesheetGroup,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub rows: Vec<TimesheetRow>,
+    pub total_minutes: u64,
+}
+
+impl Timesheet {
+    /// Aggregate intervals, looking up each interval's task in `tasks`
+    pub fn build(
+        group_by: TimesheetGroup,
+        intervals: &[TimeInterval],
+        tasks: &HashMap<Uuid, Task>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut groups: BTreeMap<String, (i64, HashSet<Uuid>)> = BTreeMap::new();
+        let mut total_seconds = 0;
+
+        for interval in intervals {
+            let start = from.map_or(interval.start, |from| interval.start.max(from));
+            let end = to.map_or(interval.end.unwrap_or(now), |to| {
+                interval.end.unwrap_or(now).min(to)
+            });
+            if end <= start {
+                continue;
+            }
+            total_seconds += (end - start).num_seconds();
+
+            let task = tasks.get(&interval.task_uuid);
+            for (key, seconds) in group_pieces(group_by, task, start, end) {
+                let entry = groups.entry(key).or_default();
+                entry.0 += seconds;
+                entry.1.insert(interval.task_uuid);
+            }
+        }
+
+        let rows = groups
+            .into_iter()
+            .map(|(key, (seconds, tasks))| TimesheetRow {
+                key,
+                minutes: (seconds / 60) as u64,
+                tasks: tasks.len(),
+            })
+            .collect();
+
+        Self {
+            group_by,
+            from,
+            to,
+            rows,
+            total_minutes: (total_seconds / 60) as u64,
+        }
+    }
+}
+
+/// Split a clipped interval into `(group key, seconds)` pieces
+fn group_pieces(
+    group_by: TimesheetGroup,
+    task: Option<&Task>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<(String, i64)> {
+    let seconds = (end - start).num_seconds();
+    match group_by {
+        TimesheetGroup::Project => {
+            let project = task
+                .and_then(|t| t.project.clone())
+                .unwrap_or_else(|| UNASSIGNED.to_string());
+            vec![(project, seconds)]
+        }
+        TimesheetGroup::Tag => {
+            let tags: Vec<String> = task
+                .map(|t| {
+                    let mut tags: Vec<String> = t.tags.iter().cloned().collect();
+                    tags.sort();
+                    tags
+                })
+                .unwrap_or_default();
+            if tags.is_empty() {
+                vec![(UNASSIGNED.to_string(), seconds)]
+            } else {
+                tags.into_iter().map(|tag| (tag, seconds)).collect()
+            }
+        }
+        TimesheetGroup::Day | TimesheetGroup::Week => {
+            let format = if group_by == TimesheetGroup::Day {
+                "%Y-%m-%d"
+            } else {
+                "%G-W%V"
+            };
+            let mut pieces = Vec::new();
+            let mut cursor = start;
+            while cursor < end {
+                let next_midnight = (cursor.date_naive() + Duration::days(1))
+                    .and_time(NaiveTime::MIN)
+                    .and_utc();
+                let piece_end = next_midnight.min(end);
+                pieces.push((
+                    cursor.format(format).to_string(),
+                    (piece_end - cursor).num_seconds(),
+                ));
+                cursor = piece_end;
+            }
+            pieces
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn closed(task: &Task, start: DateTime<Utc>, end: DateTime<Utc>) -> TimeInterval {
+        TimeInterval {
+            end: Some(end),
+            ..TimeInterval::new(task.uuid, start)
+        }
+    }
+
+    #[test]
+    fn test_effort_minutes_ignores_open_intervals() {
+        let task = Task::new("Write report".to_string());
+        let intervals = vec![
+            closed(&task, at(2, 9), at(2, 10)),
+            closed(&task, at(2, 11), at(2, 11) + Duration::minutes(30)),
+            TimeInterval::new(task.uuid, at(2, 13)),
+        ];
+        assert_eq!(effort_minutes(&intervals), 90);
+    }
+
+    #[test]
+    fn test_interval_validation_and_overlap() {
+        let task = Task::new("Write report".to_string());
+        assert!(closed(&task, at(2, 10), at(2, 9)).validate().is_err());
+
+        let morning = closed(&task, at(2, 9), at(2, 12));
+        let noon = closed(&task, at(2, 11), at(2, 13));
+        let afternoon = closed(&task, at(2, 12), at(2, 14));
+        assert!(morning.overlaps(&noon, at(3, 0)));
+        assert!(!morning.overlaps(&afternoon, at(3, 0)));
+    }
+
+    #[test]
+    fn test_timesheet_by_day_splits_at_midnight() {
+        let task = Task::new("Deploy".to_string());
+        let intervals = vec![closed(&task, at(2, 22), at(3, 1))];
+        let tasks = HashMap::from([(task.uuid, task)]);
+
+        let sheet = Timesheet::build(
+            TimesheetGroup::Day,
+            &intervals,
+            &tasks,
+            None,
+            None,
+            at(4, 0),
+        );
+        assert_eq!(sheet.total_minutes, 180);
+        assert_eq!(sheet.rows.len(), 2);
+        assert_eq!(sheet.rows[0].key, "2026-03-02");
+        assert_eq!(sheet.rows[0].minutes, 120);
+        assert_eq!(sheet.rows[1].key, "2026-03-03");
+        assert_eq!(sheet.rows[1].minutes, 60);
+    }
+
+    #[test]
+    fn test_timesheet_by_project_and_tag_with_window() {
+        let mut web = Task::new("Landing page".to_string());
+        web.project = Some("web".to_string());
+        web.add_tag("frontend".to_string());
+        web.add_tag("urgent".to_string());
+        let chores = Task::new("Chores".to_string());
+        let intervals = vec![
+            closed(&web, at(2, 9), at(2, 11)),
+            closed(&chores, at(2, 12), at(2, 13)),
+            // Open interval, measured up to `now`
+            TimeInterval::new(web.uuid, at(2, 14)),
+        ];
+        let tasks = HashMap::from([(web.uuid, web), (chores.uuid, chores)]);
+
+        let sheet = Timesheet::build(
+            TimesheetGroup::Project,
+            &intervals,
+            &tasks,
+            Some(at(2, 10)),
+            None,
+            at(2, 15),
+        );
+        assert_eq!(sheet.total_minutes, 180);
+        assert_eq!(
+            sheet.rows,
+            vec![
+                TimesheetRow {
+                    key: "(none)".to_string(),
+                    minutes: 60,
+                    tasks: 1
+                },
+                TimesheetRow {
+                    key: "web".to_string(),
+                    minutes: 120,
+                    tasks: 1
+                },
+            ]
+        );
+
+        let sheet = Timesheet::build(
+            TimesheetGroup::Tag,
+            &intervals,
+            &tasks,
+            None,
+            None,
+            at(2, 15),
+        );
+        let keys: Vec<&str> = sheet.rows.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, ["(none)", "frontend", "urgent"]);
+        assert_eq!(sheet.rows[1].minutes, 180);
+        assert_eq!(sheet.total_minutes, 240);
+    }
+}
@@ -0,0 +1,341 @@
+use crate::core::TaskError;
+use crate::core::date::DateParser;
+use crate::core::filter::FilterAttribute;
+use crate::core::modification::ModifyAttribute;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Value type of a user-defined attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UdaType {
+    String,
+    Number,
+    /// Stored as an RFC 3339 timestamp
+    Date,
+    /// Stored as whole seconds
+    Duration,
+    /// A string restricted to the declared values
+    Enum,
+}
+
+impl UdaType {
+    /// Check if values of this type are ordered, so `.before` and `.after` apply
+    pub fn is_ordered(&self) -> bool {
+        matches!(self, UdaType::Number | UdaType::Date | UdaType::Duration)
+    }
+}
+
+impl std::fmt::Display for UdaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UdaType::String => write!(f, "string"),
+            UdaType::Number => write!(f, "number"),
+            UdaType::Date => write!(f, "date"),
+            UdaType::Duration => write!(f, "duration"),
+            UdaType::Enum => write!(f, "enum"),
+        }
+    }
+}
+
+impl std::str::FromStr for UdaType {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "string" => Ok(UdaType::String),
+            "number" | "numeric" => Ok(UdaType::Number),
+            "date" => Ok(UdaType::Date),
+            "duration" => Ok(UdaType::Duration),
+            "enum" => Ok(UdaType::Enum),
+            _ => Err(TaskError::Validation {
+                message: format!(
+                    "Invalid UDA type: {s} (expected string, number, date, duration or enum)"
+                ),
+            }),
+        }
+    }
+}
+
+/// A user-defined attribute declared under `[uda.<name>]` in the config
+///
+/// ```toml
+/// [uda.agent_model]
+/// type = "enum"
+/// values = ["opus", "sonnet", "haiku"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UdaDefinition {
+    #[serde(rename = "type")]
+    pub kind: UdaType,
+    /// Allowed values; required for `enum`, optional for `string`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+impl UdaDefinition {
+    /// Check that the declaration is usable under the given name
+    pub fn validate(&self, name: &str) -> Result<(), TaskError> {
+        let mut chars = name.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(TaskError::Validation {
+                message: format!("Invalid UDA name: {name} (use letters, digits and underscores)"),
+            });
+        }
+        if FilterAttribute::from_name(name).is_some() || ModifyAttribute::from_name(name).is_some()
+        {
+            return Err(TaskError::Validation {
+                message: format!("UDA {name} clashes with a built-in attribute"),
+            });
+        }
+        if self.kind == UdaType::Enum && self.values.is_empty() {
+            return Err(TaskError::Validation {
+                message: format!("Enum UDA {name} needs a list of values"),
+            });
+        }
+        if !self.values.is_empty() && !matches!(self.kind, UdaType::String | UdaType::Enum) {
+            return Err(TaskError::Validation {
+                message: format!("UDA {name}: allowed values need a string or enum type"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Parse a command-line value into the JSON stored on the task
+    pub fn parse_value(
+        &self,
+        name: &str,
+        raw: &str,
+        dates: &DateParser,
+    ) -> Result<Value, TaskError> {
+        let invalid = || TaskError::Validation {
+            message: format!("Invalid {} value for {name}: {raw}", self.kind),
+        };
+        let value = match self.kind {
+            UdaType::String | UdaType::Enum => Value::String(raw.to_string()),
+            UdaType::Number => match raw.parse::<i64>() {
+                Ok(number) => Value::from(number),
+                Err(_) => raw
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or_else(invalid)?,
+            },
+            UdaType::Date => Value::String(dates.parse(raw).map_err(|_| invalid())?.to_rfc3339()),
+            UdaType::Duration => Value::from(parse_duration(raw).ok_or_else(invalid)?),
+        };
+        self.validate_value(name, &value)?;
+        Ok(value)
+    }
+
+    /// Check a stored value against the declared type and allowed values
+    pub fn validate_value(&self, name: &str, value: &Value) -> Result<(), TaskError> {
+        let valid = match (self.kind, value) {
+            (UdaType::String | UdaType::Enum, Value::String(text)) => {
+                if !self.values.is_empty() && !self.values.contains(text) {
+                    return Err(TaskError::Validation {
+                        message: format!(
+                            "Invalid value for {name}: {text} (expected one of {})",
+                            self.values.join(", ")
+                        ),
+                    });
+                }
+                true
+            }
+            (UdaType::Number, Value::Number(_)) => true,
+            (UdaType::Date, Value::String(text)) => DateTime::parse_from_rfc3339(text).is_ok(),
+            (UdaType::Duration, Value::Number(seconds)) => seconds.as_u64().is_some(),
+            _ => false,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(TaskError::Validation {
+                message: format!("Invalid {} value for {name}: {value}", self.kind),
+            })
+        }
+    }
+}
+
+/// Parse a duration such as `90min` (or `90m`), `2h`, `1h30min` or `3d` into seconds;
+/// a bare number is taken as seconds, and ISO 8601 forms such as `PT1H30M`
+/// (as written by Taskwarrior) are accepted too
+pub fn parse_duration(input: &str) -> Option<u64> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return None;
+    }
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Some(seconds);
+    }
+    if let Some(iso) = input.strip_prefix('p') {
+        return parse_iso_duration(iso);
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = input.as_str();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit_end = rest[digits..]
+            .find(|c: char| c.is_ascii_digit())
+            .map_or(rest.len(), |i| i + digits);
+        let amount: u64 = rest[..digits].parse().ok()?;
+        let unit: u64 = match &rest[digits..unit_end] {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+            "d" | "day" | "days" => 86_400,
+            "w" | "wk" | "wks" | "week" | "weeks" => 604_800,
+            _ => return None,
+        };
+        total = total.checked_add(amount.checked_mul(unit)?)?;
+        rest = &rest[unit_end..];
+    }
+    Some(total)
+}
+
+/// Parse the part of an ISO 8601 duration after the `P`
+fn parse_iso_duration(input: &str) -> Option<u64> {
+    let (date, time) = input.split_once('t').unwrap_or((input, ""));
+    let mut total: u64 = 0;
+    for (part, units) in [
+        (date, [('w', 604_800), ('d', 86_400)].as_slice()),
+        (time, [('h', 3600), ('m', 60), ('s', 1)].as_slice()),
+    ] {
+        let mut amount = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                amount.push(c);
+                continue;
+            }
+            let (_, unit) = units.iter().find(|(u, _)| *u == c)?;
+            let value: u64 = std::mem::take(&mut amount).parse().ok()?;
+            total = total.checked_add(value.checked_mul(*unit)?)?;
+        }
+        if !amount.is_empty() {
+            return None;
+        }
+    }
+    (!input.is_empty()).then_some(total)
+}
+
+/// Format seconds as an ISO 8601 duration such as `PT1H30M`
+pub fn format_iso_duration(seconds: u64) -> String {
+    let (days, rest) = (seconds / 86_400, seconds % 86_400);
+    let (hours, minutes, secs) = (rest / 3600, rest % 3600 / 60, rest % 60);
+    let mut output = String::from("P");
+    if days > 0 {
+        output.push_str(&format!("{days}D"));
+    }
+    if rest > 0 || days == 0 {
+        output.push('T');
+        if hours > 0 {
+            output.push_str(&format!("{hours}H"));
+        }
+        if minutes > 0 {
+            output.push_str(&format!("{minutes}M"));
+        }
+        if secs > 0 || rest == 0 {
+            output.push_str(&format!("{secs}S"));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Tz;
+
+    fn definition(kind: UdaType, values: &[&str]) -> UdaDefinition {
+        UdaDefinition {
+            kind,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_definition() {
+        assert!(
+            definition(UdaType::Number, &[])
+                .validate("estimate_points")
+                .is_ok()
+        );
+        assert!(
+            definition(UdaType::Enum, &[])
+                .validate("agent_model")
+                .is_err()
+        );
+        assert!(definition(UdaType::String, &[]).validate("due").is_err());
+        assert!(
+            definition(UdaType::String, &[])
+                .validate("bad-name")
+                .is_err()
+        );
+        assert!(
+            definition(UdaType::Number, &["1"])
+                .validate("points")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let dates = DateParser::new(Tz::UTC);
+        let number = definition(UdaType::Number, &[]);
+        assert_eq!(
+            number.parse_value("n", "5", &dates).unwrap(),
+            Value::from(5)
+        );
+        assert_eq!(
+            number.parse_value("n", "2.5", &dates).unwrap(),
+            Value::from(2.5)
+        );
+        assert!(number.parse_value("n", "lots", &dates).is_err());
+
+        let model = definition(UdaType::Enum, &["opus", "sonnet"]);
+        assert!(model.parse_value("model", "opus", &dates).is_ok());
+        assert!(model.parse_value("model", "gpt", &dates).is_err());
+
+        let date = definition(UdaType::Date, &[]);
+        assert_eq!(
+            date.parse_value("d", "2026-11-01", &dates).unwrap(),
+            Value::from("2026-11-01T00:00:00+00:00")
+        );
+
+        let duration = definition(UdaType::Duration, &[]);
+        assert_eq!(
+            duration.parse_value("d", "1h30min", &dates).unwrap(),
+            Value::from(5400)
+        );
+        assert!(duration.validate_value("d", &Value::from(-1)).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("90min"), Some(5400));
+        assert_eq!(parse_duration("15m"), Some(900));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("1d12h"), Some(129_600));
+        assert_eq!(parse_duration("PT1H30M"), Some(5400));
+        assert_eq!(parse_duration("P1DT12H"), Some(129_600));
+        assert_eq!(parse_duration("P2W"), Some(1_209_600));
+        assert_eq!(parse_duration("P"), None);
+        assert_eq!(parse_duration("PT5X"), None);
+        assert_eq!(format_iso_duration(5400), "PT1H30M");
+        assert_eq!(format_iso_duration(129_600), "P1DT12H");
+        assert_eq!(format_iso_duration(0), "PT0S");
+        assert_eq!(parse_duration("1mo"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::core::config::UrgencyConfig;
+use crate::core::project::is_within;
+use crate::core::{Task, TaskStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// One term of a task's urgency: a factor, usually between 0 and 1, scaled
+/// by its configured coefficient
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UrgencyTerm {
+    pub name: String,
+    pub factor: f64,
+    pub coefficient: f64,
+}
+
+impl UrgencyTerm {
+    /// Amount this term adds to the urgency
+    pub fn contribution(&self) -> f64 {
+        self.factor * self.coefficient
+    }
+}
+
+/// Urgency of a task, broken down into the terms that contribute to it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Urgency {
+    pub terms: Vec<UrgencyTerm>,
+}
+
+impl Urgency {
+    /// Compute a task's urgency at `now`
+    ///
+    /// `blocked` and `blocking` say whether the task waits on open tasks and
+    /// whether open tasks wait on it; they need storage, so callers supply
+    /// them. Closed tasks and recurring templates have no urgency.
+    pub fn of(
+        task: &Task,
+        config: &UrgencyConfig,
+        blocked: bool,
+        blocking: bool,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut urgency = Urgency::default();
+        if !task.is_active() {
+            return urgency;
+        }
+
+        if let Some(priority) = &task.priority
+            && let Some(coefficient) = config.priority.get(&priority.to_string())
+        {
+            urgency.add("priority", 1.0, *coefficient);
+        }
+        if let Some(due) = task.due_date {
+            urgency.add("due", due_factor(due, now), config.due);
+        }
+        if task
+            .scheduled_date
+            .is_some_and(|scheduled| scheduled <= now)
+        {
+            urgency.add("scheduled", 1.0, config.scheduled);
+        }
+        if task.status == TaskStatus::InProgress {
+            urgency.add("active", 1.0, config.active);
+        }
+        let waiting = task.status == TaskStatus::Waiting || task.wait_date.is_some_and(|w| w > now);
+        if waiting {
+            urgency.add("waiting", 1.0, config.waiting);
+        }
+        if blocked {
+            urgency.add("blocked", 1.0, config.blocked);
+        }
+        if blocking {
+            urgency.add("blocking", 1.0, config.blocking);
+        }
+
+        let age_days = (now - task.entry_date).num_seconds().max(0) as f64 / 86_400.0;
+        let age = if config.age_max_days == 0 {
+            1.0
+        } else {
+            (age_days / config.age_max_days as f64).min(1.0)
+        };
+        urgency.add("age", age, config.age);
+
+        urgency.add(
+            "annotations",
+            count_factor(task.annotations.len()),
+            config.annotations,
+        );
+        urgency.add("tags", count_factor(task.tags.len()), config.tags);
+        if let Some(project) = &task.project {
+            urgency.add("project", 1.0, config.project);
+            for (name, coefficient) in &config.user_project {
+                if is_within(project, name) {
+                    urgency.add(&format!("project {name}"), 1.0, *coefficient);
+                }
+            }
+        }
+
+        let mut tags: Vec<&String> = task.tags.iter().collect();
+        tags.sort();
+        for tag in tags {
+            if let Some(coefficient) = config.user_tag.get(tag) {
+                urgency.add(&format!("tag +{tag}"), 1.0, *coefficient);
+            }
+        }
+
+        urgency
+    }
+
+    /// Sum of every term's contribution
+    pub fn total(&self) -> f64 {
+        self.terms.iter().map(UrgencyTerm::contribution).sum()
+    }
+
+    /// Record a term, skipping ones that contribute nothing
+    fn add(&mut self, name: &str, factor: f64, coefficient: f64) {
+        if factor != 0.0 && coefficient != 0.0 {
+            self.terms.push(UrgencyTerm {
+                name: name.to_string(),
+                factor,
+                coefficient,
+            });
+        }
+    }
+}
+
+/// Due factor as in Taskwarrior: 0.2 until two weeks before the due date,
+/// rising linearly to 1.0 a week after it
+fn due_factor(due: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let days_overdue = (now - due).num_seconds() as f64 / 86_400.0;
+    if days_overdue >= 7.0 {
+        1.0
+    } else if days_overdue >= -14.0 {
+        (days_overdue + 14.0) * 0.8 / 21.0 + 0.2
+    } else {
+        0.2
+    }
+}
+
+/// Factor for counted attributes such as tags: 0.8, 0.9, then 1.0
+fn count_factor(count: usize) -> f64 {
+    match count {
+        0 => 0.0,
+        1 => 0.8,
+        2 => 0.9,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Priority;
+    use chrono::{Duration, TimeZone};
+
+    fn term<'a>(urgency: &'a Urgency, name: &str) -> Option<&'a UrgencyTerm> {
+        urgency.terms.iter().find(|term| term.name == name)
+    }
+
+    #[test]
+    fn test_urgency_terms() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 11, 12, 0, 0).unwrap();
+        let config = UrgencyConfig::default();
+        let mut task = Task::new("Fix login".to_string());
+        task.entry_date = now;
+
+        assert_eq!(Urgency::of(&task, &config, false, false, now).total(), 0.0);
+
+        task.priority = Some(Priority::High);
+        task.project = Some("web.frontend".to_string());
+        task.add_tag("next".to_string());
+        task.due_date = Some(now);
+        let urgency = Urgency::of(&task, &config, false, true, now);
+        assert_eq!(term(&urgency, "priority").unwrap().contribution(), 6.0);
+        assert_eq!(term(&urgency, "blocking").unwrap().contribution(), 8.0);
+        assert_eq!(term(&urgency, "tag +next").unwrap().contribution(), 15.0);
+        let due = term(&urgency, "due").unwrap();
+        assert!((due.factor - (14.0 * 0.8 / 21.0 + 0.2)).abs() < 1e-9);
+        assert!(term(&urgency, "age").is_none());
+
+        let config = UrgencyConfig {
+            user_project: [("web".to_string(), 2.5)].into(),
+            ..UrgencyConfig::default()
+        };
+        let urgency = Urgency::of(&task, &config, true, false, now);
+        assert_eq!(term(&urgency, "project web").unwrap().contribution(), 2.5);
+        assert_eq!(term(&urgency, "blocked").unwrap().contribution(), -5.0);
+
+        task.status = TaskStatus::Completed;
+        assert!(
+            Urgency::of(&task, &config, true, false, now)
+                .terms
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_due_and_age_scaling() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 11, 12, 0, 0).unwrap();
+        assert_eq!(due_factor(now - Duration::days(10), now), 1.0);
+        assert_eq!(due_factor(now + Duration::days(30), now), 0.2);
+        assert!((due_factor(now - Duration::days(7), now) - 1.0).abs() < 1e-9);
+
+        let config = UrgencyConfig::default();
+        let mut task = Task::new("Old task".to_string());
+        task.entry_date = now - Duration::days(730);
+        let urgency = Urgency::of(&task, &config, false, false, now);
+        assert_eq!(term(&urgency, "age").unwrap().factor, 1.0);
+        assert_eq!(urgency.total(), config.age);
+    }
+}
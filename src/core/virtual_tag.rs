@@ -0,0 +1,212 @@
+use crate::core::date::DateParser;
+use crate::core::{Task, TaskError, TaskStatus};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// How far ahead a due date makes a task `+DUE`
+const DUE_SOON_DAYS: i64 = 7;
+
+/// Tag computed from a task's state rather than stored, such as `+OVERDUE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum VirtualTag {
+    /// Started and not yet stopped
+    Active,
+    Annotated,
+    /// Depends on an open task
+    Blocked,
+    /// An open task depends on it
+    Blocking,
+    /// Has a parent task
+    Child,
+    /// Due within the next week
+    Due,
+    Overdue,
+    /// Has subtasks
+    Parent,
+    /// Linked to a task in a sync backend
+    Synced,
+    /// Due today
+    Today,
+    Untagged,
+    Waiting,
+}
+
+impl VirtualTag {
+    /// Every virtual tag, in display order
+    pub const ALL: [VirtualTag; 12] = [
+        VirtualTag::Active,
+        VirtualTag::Annotated,
+        VirtualTag::Blocked,
+        VirtualTag::Blocking,
+        VirtualTag::Child,
+        VirtualTag::Due,
+        VirtualTag::Overdue,
+        VirtualTag::Parent,
+        VirtualTag::Synced,
+        VirtualTag::Today,
+        VirtualTag::Untagged,
+        VirtualTag::Waiting,
+    ];
+
+    /// Look up a virtual tag by its name; names are upper case, as in
+    /// Taskwarrior, so `+due` stays an ordinary tag
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tag| tag.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VirtualTag::Active => "ACTIVE",
+            VirtualTag::Annotated => "ANNOTATED",
+            VirtualTag::Blocked => "BLOCKED",
+            VirtualTag::Blocking => "BLOCKING",
+            VirtualTag::Child => "CHILD",
+            VirtualTag::Due => "DUE",
+            VirtualTag::Overdue => "OVERDUE",
+            VirtualTag::Parent => "PARENT",
+            VirtualTag::Synced => "SYNCED",
+            VirtualTag::Today => "TODAY",
+            VirtualTag::Untagged => "UNTAGGED",
+            VirtualTag::Waiting => "WAITING",
+        }
+    }
+
+    /// Virtual tags that apply to a task
+    ///
+    /// Whether the task is blocked, blocking or a parent depends on other
+    /// tasks, so callers look that up in storage and pass it in.
+    pub fn of(task: &Task, relations: TaskRelations, clock: TagClock) -> Vec<VirtualTag> {
+        let scheduled = matches!(task.status, TaskStatus::Pending | TaskStatus::InProgress);
+        let due_between = |from: DateTime<Utc>, to: DateTime<Utc>| {
+            scheduled && task.due_date.is_some_and(|due| from <= due && due < to)
+        };
+
+        Self::ALL
+            .into_iter()
+            .filter(|tag| match tag {
+                VirtualTag::Active => task.status == TaskStatus::InProgress,
+                VirtualTag::Annotated => !task.annotations.is_empty(),
+                VirtualTag::Blocked => relations.blocked,
+                VirtualTag::Blocking => relations.blocking,
+                VirtualTag::Child => task.parent_uuid.is_some(),
+                VirtualTag::Due => due_between(clock.today, clock.due_soon()),
+                VirtualTag::Overdue => {
+                    scheduled && task.due_date.is_some_and(|due| due < clock.now)
+                }
+                VirtualTag::Parent => relations.parent,
+                VirtualTag::Synced => task.sync_id.is_some(),
+                VirtualTag::Today => due_between(clock.today, clock.tomorrow),
+                VirtualTag::Untagged => task.tags.is_empty(),
+                VirtualTag::Waiting => task.status == TaskStatus::Waiting,
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for VirtualTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Reject adding or removing a virtual tag as if it were stored
+pub fn check_stored_tag(tag: &str) -> Result<(), TaskError> {
+    match VirtualTag::from_name(tag) {
+        Some(tag) => Err(TaskError::Validation {
+            message: format!("+{tag} is a virtual tag and can't be set or removed"),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Facts about a task that depend on other tasks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskRelations {
+    pub blocked: bool,
+    pub blocking: bool,
+    pub parent: bool,
+}
+
+/// Times the date-based virtual tags are measured against, fixed when a
+/// filter is parsed so SQL and in-memory checks agree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagClock {
+    pub now: DateTime<Utc>,
+    /// Start of the current day in the configured timezone
+    pub today: DateTime<Utc>,
+    pub tomorrow: DateTime<Utc>,
+}
+
+impl TagClock {
+    pub fn new(dates: &DateParser) -> Self {
+        let now = dates.now();
+        Self {
+            now,
+            today: dates.parse("today").unwrap_or(now),
+            tomorrow: dates.parse("tomorrow").unwrap_or(now),
+        }
+    }
+
+    /// End of the window in which a due date makes a task `+DUE`
+    pub fn due_soon(&self) -> DateTime<Utc> {
+        self.now + Duration::days(DUE_SOON_DAYS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    #[test]
+    fn test_virtual_tags_of_task() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 11, 15, 0, 0).unwrap();
+        let clock = TagClock::new(&DateParser::with_now(now, Tz::UTC));
+        assert_eq!(
+            clock.today,
+            Utc.with_ymd_and_hms(2026, 3, 11, 0, 0, 0).unwrap()
+        );
+
+        let mut task = Task::new("Fix login".to_string());
+        assert_eq!(
+            VirtualTag::of(&task, TaskRelations::default(), clock),
+            [VirtualTag::Untagged]
+        );
+
+        task.add_tag("web".to_string());
+        task.add_annotation("Seen on staging".to_string());
+        task.due_date = Some(now - Duration::hours(1));
+        task.status = TaskStatus::InProgress;
+        task.sync_id = Some("https://github.com/acme/web/issues/7".to_string());
+        let relations = TaskRelations {
+            blocked: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            VirtualTag::of(&task, relations, clock),
+            [
+                VirtualTag::Active,
+                VirtualTag::Annotated,
+                VirtualTag::Blocked,
+                VirtualTag::Due,
+                VirtualTag::Overdue,
+                VirtualTag::Synced,
+                VirtualTag::Today,
+            ]
+        );
+
+        task.status = TaskStatus::Completed;
+        task.due_date = Some(now + Duration::days(3));
+        assert!(!VirtualTag::of(&task, relations, clock).contains(&VirtualTag::Due));
+    }
+
+    #[test]
+    fn test_virtual_tag_names() {
+        assert_eq!(VirtualTag::from_name("OVERDUE"), Some(VirtualTag::Overdue));
+        assert_eq!(VirtualTag::from_name("overdue"), None);
+        assert!(check_stored_tag("BLOCKED").is_err());
+        assert!(check_stored_tag("blocked").is_ok());
+    }
+}
@@ -632,6 +632,7 @@ impl GitHubClient {
 
         // Add GitHub URL as annotation
         task.add_annotation(format!("GitHub Issue: {}", issue.html_url));
+        task.sync_id = Some(issue.html_url.clone());
 
         task
     }
@@ -651,6 +652,7 @@ impl GitHubClient {
 
         // Add project card info as annotation
         task.add_annotation(format!("GitHub Project Card: {}", card.id));
+        task.sync_id = Some(format!("github-card:{}", card.id));
 
         task
     }
@@ -697,6 +699,7 @@ impl GitHubClient {
             crate::core::task::TaskStatus::Completed => "done",
             crate::core::task::TaskStatus::Deleted => "deleted",
             crate::core::task::TaskStatus::Waiting => "waiting",
+            crate::core::task::TaskStatus::Recurring => "recurring",
         };
 
         for (column_name, mapped_status) in column_mapping {
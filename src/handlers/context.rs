@@ -0,0 +1,99 @@
+use crate::cli::ContextCommands;
+use crate::core::config::{config_file_path, load_stored_config};
+use crate::core::error::ConfigError;
+use crate::core::{EddaConfig, EddaError, EddaResult, TaskError, save_config, validate_config};
+use std::path::PathBuf;
+
+pub async fn handle_context_commands(
+    subcommand: ContextCommands,
+    config_path: Option<PathBuf>,
+    config: &EddaConfig,
+) -> EddaResult<()> {
+    match subcommand {
+        ContextCommands::Define { name, filter } => {
+            let filter = filter.join(" ");
+            update_stored_config(config_path, |stored| stored.define_context(&name, &filter))?;
+            println!("Defined context '{name}': {filter}");
+            Ok(())
+        }
+        ContextCommands::Delete { name } => {
+            update_stored_config(config_path, |stored| {
+                if stored.contexts.remove(&name).is_none() {
+                    return Err(unknown_context(&name));
+                }
+                if stored.context.as_deref() == Some(name.as_str()) {
+                    stored.context = None;
+                }
+                Ok(())
+            })?;
+            println!("Deleted context '{name}'");
+            Ok(())
+        }
+        ContextCommands::List => {
+            if config.contexts.is_empty() {
+                println!("No contexts defined");
+            }
+            for (name, filter) in &config.contexts {
+                let marker = if config.context.as_deref() == Some(name.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{marker} {name:<12} {filter}");
+            }
+            Ok(())
+        }
+        ContextCommands::Show => {
+            match (&config.context, config.context_filter()) {
+                (Some(name), Some(filter)) => println!("Context '{name}': {filter}"),
+                _ => println!("No context is active"),
+            }
+            Ok(())
+        }
+        ContextCommands::None => {
+            update_stored_config(config_path, |stored| {
+                stored.context = None;
+                Ok(())
+            })?;
+            println!("Context cleared");
+            Ok(())
+        }
+        ContextCommands::Use(args) => {
+            let [name] = args.as_slice() else {
+                return Err(TaskError::Validation {
+                    message: format!("Expected a single context name, got: {}", args.join(" ")),
+                }
+                .into());
+            };
+            update_stored_config(config_path, |stored| {
+                if !stored.contexts.contains_key(name) {
+                    return Err(unknown_context(name));
+                }
+                stored.context = Some(name.clone());
+                Ok(())
+            })?;
+            println!("Context '{name}' is now active");
+            Ok(())
+        }
+    }
+}
+
+/// Apply a change to the configuration file as written, leaving out
+/// environment and command-line overrides, then save it
+fn update_stored_config(
+    config_path: Option<PathBuf>,
+    change: impl FnOnce(&mut EddaConfig) -> EddaResult<()>,
+) -> EddaResult<()> {
+    let path = config_file_path(config_path);
+    let mut stored = load_stored_config(&path)?;
+    change(&mut stored)?;
+    validate_config(&stored)?;
+    save_config(&stored, Some(path))
+}
+
+fn unknown_context(name: &str) -> EddaError {
+    ConfigError::Validation {
+        message: format!("Unknown context: {name}"),
+    }
+    .into()
+}
@@ -1,12 +1,18 @@
+mod context;
 mod doc;
+mod project;
 mod query;
+mod search;
 mod state;
 mod sync;
 mod system;
 mod task;
 
+pub use context::handle_context_commands;
 pub use doc::handle_doc_commands;
+pub use project::handle_project_commands;
 pub use query::handle_query_command;
+pub use search::handle_search_command;
 pub use state::handle_state_commands;
 pub use sync::{handle_github_sync_commands, handle_sync_commands};
 pub use system::handle_system_commands;
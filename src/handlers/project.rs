@@ -0,0 +1,64 @@
+use crate::cli::ProjectCommands;
+use crate::core::{EddaConfig, EddaResult, ProjectSummary};
+use crate::handlers::task::{create_task_engine, format_minutes, listing_filter, refresh_tasks};
+
+pub async fn handle_project_commands(
+    subcommand: ProjectCommands,
+    config: &EddaConfig,
+    format: &str,
+    quiet: bool,
+) -> EddaResult<()> {
+    let task_engine = create_task_engine(config).await?;
+    refresh_tasks(&task_engine, config).await?;
+    let dates = config.date_parser();
+
+    let (query, detailed) = match subcommand {
+        ProjectCommands::List { query } => (query, false),
+        ProjectCommands::Summary { query } => (query, true),
+    };
+    let filter = listing_filter(&query, &dates, config)?;
+    let summaries = task_engine.project_summaries(Some(filter)).await?;
+
+    if format == "json" {
+        let json = serde_json::json!({ "projects": summaries });
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return Ok(());
+    }
+    if summaries.is_empty() {
+        if !quiet {
+            println!("No projects found.");
+        }
+        return Ok(());
+    }
+
+    if !detailed {
+        for summary in &summaries {
+            println!("{:<30} {:>7}", tree_label(summary), summary.pending);
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{:<30} {:>7} {:>9} {:>8} {:>9} {:>7}",
+        "Project", "Pending", "Completed", "Complete", "Remaining", "Overdue"
+    );
+    for summary in &summaries {
+        println!(
+            "{:<30} {:>7} {:>9} {:>7.0}% {:>9} {:>7}",
+            tree_label(summary),
+            summary.pending,
+            summary.completed,
+            summary.completion,
+            format_minutes(summary.remaining_minutes),
+            summary.overdue
+        );
+    }
+    Ok(())
+}
+
+/// Last segment of the project name, indented by its depth
+fn tree_label(summary: &ProjectSummary) -> String {
+    let leaf = summary.name.rsplit('.').next().unwrap_or(&summary.name);
+    let label = format!("{}{leaf}", "  ".repeat(summary.depth));
+    label.chars().take(30).collect()
+}
@@ -0,0 +1,36 @@
+use crate::core::{EddaConfig, EddaResult};
+use crate::handlers::task::create_task_engine;
+
+pub async fn handle_search_command(
+    terms: &str,
+    limit: u32,
+    config: &EddaConfig,
+    format: &str,
+    quiet: bool,
+) -> EddaResult<()> {
+    let task_engine = create_task_engine(config).await?;
+    let hits = task_engine.search(terms, limit).await?;
+
+    if format == "json" {
+        let json = serde_json::json!({ "results": hits });
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return Ok(());
+    }
+    if hits.is_empty() {
+        if !quiet {
+            println!("No matches found.");
+        }
+        return Ok(());
+    }
+
+    for hit in &hits {
+        let status = hit
+            .status
+            .as_ref()
+            .map(|status| format!(" [{status}]"))
+            .unwrap_or_default();
+        println!("{} {}: {}{status}", hit.entity, hit.id, hit.title);
+        println!("    {}", hit.snippet.replace('\n', " "));
+    }
+    Ok(())
+}
@@ -1,6 +1,10 @@
-use crate::cli::{ConfigCommands, SystemCommands};
+use crate::cli::{BackupCommands, ConfigCommands, MigrateCommands, SystemCommands};
 use crate::core::{EddaConfig, EddaResult};
-use std::path::PathBuf;
+use crate::storage::backup::{self, BackupFormat};
+use crate::storage::database;
+use crate::storage::encryption::{self, BackupKey};
+use crate::storage::migration::{self, MigrationDirection};
+use std::path::{Path, PathBuf};
 
 pub async fn handle_system_commands(
     subcommand: SystemCommands,
@@ -33,11 +37,7 @@ pub async fn handle_system_commands(
             }
 
             // Initialize database
-            let db_path = if config.database.url.starts_with("sqlite:") {
-                PathBuf::from(config.database.url.trim_start_matches("sqlite:"))
-            } else {
-                config.data_dir.join("edda.db")
-            };
+            let db_path = crate::handlers::task::database_path(config);
             println!("[DEBUG] Using database path: {db_path:?}");
 
             // Create database directory if needed
@@ -52,19 +52,42 @@ pub async fn handle_system_commands(
             }
 
             // Initialize database with migrations
-            crate::storage::init_database(db_path).await?;
+            let key = backup_key(config, false)?;
+            crate::storage::init_database(db_path, &backup_dir(config), key.as_ref()).await?;
             println!("Database initialized successfully");
 
             Ok(())
         }
-        SystemCommands::Backup => {
-            println!("Creating backup...");
-            // TODO: Implement backup
-            Ok(())
-        }
+        SystemCommands::Backup {
+            subcommand,
+            compress,
+            encrypt,
+        } => handle_backup_commands(subcommand, compress, encrypt, config).await,
         SystemCommands::Restore { backup } => {
-            println!("Restoring from backup: {backup:?}");
-            // TODO: Implement restore
+            let db_path = crate::handlers::task::database_path(config);
+            let backup_dir = backup_dir(config);
+            // A bare name refers to a backup in the backups directory
+            let backup_path = if !backup.exists() && backup_dir.join(&backup).exists() {
+                backup_dir.join(&backup)
+            } else {
+                backup
+            };
+
+            // Encrypted backups need a key even when new ones aren't encrypted
+            let key = match backup_key(config, false)? {
+                Some(key) => Some(key),
+                None if backup_path.exists() && encryption::is_encrypted(&backup_path)? => {
+                    BackupKey::load(config.backup.key_file.as_deref())?
+                }
+                None => None,
+            };
+
+            let safety =
+                database::restore_backup(&backup_path, &db_path, &backup_dir, key.as_ref()).await?;
+            if let Some(safety) = safety {
+                println!("Saved current database to {}", safety.display());
+            }
+            println!("Restored database from {}", backup_path.display());
             Ok(())
         }
         SystemCommands::Config { subcommand } => handle_config_commands(subcommand, config).await,
@@ -73,10 +96,225 @@ pub async fn handle_system_commands(
             Ok(())
         }
         SystemCommands::Cleanup => {
-            println!("Cleanup not yet implemented");
+            let task_engine = crate::handlers::task::create_task_engine(config).await?;
+            let horizon = chrono::Duration::days(config.recurrence.horizon_days as i64);
+            let generated = task_engine.generate_recurring_instances(horizon).await?;
+            let changes = task_engine.update_lifecycle(chrono::Utc::now()).await?;
+            println!("Generated {} recurring task instances", generated.len());
+            println!("Woke {} waiting tasks", changes.unwaited.len());
+            println!(
+                "Expired {} tasks past their until date",
+                changes.expired.len()
+            );
+            println!(
+                "Reclaimed {} tasks with expired leases",
+                changes.reclaimed.len()
+            );
             Ok(())
         }
+        SystemCommands::Migrate { subcommand } => handle_migrate_commands(subcommand, config).await,
+    }
+}
+
+/// Directory holding database backups
+fn backup_dir(config: &EddaConfig) -> PathBuf {
+    config.data_dir.join("backups")
+}
+
+/// Key for new backups, or `None` when they aren't encrypted
+fn backup_key(config: &EddaConfig, encrypt: bool) -> EddaResult<Option<BackupKey>> {
+    if !(encrypt || config.backup.encrypt) {
+        return Ok(None);
+    }
+    match BackupKey::load(config.backup.key_file.as_deref())? {
+        Some(key) => Ok(Some(key)),
+        None => Err(crate::core::EddaError::Storage(
+            crate::core::StorageError::Backup {
+                message: format!(
+                    "Backup encryption needs a key: set {} or {}, or backup.key_file",
+                    encryption::PASSPHRASE_ENV,
+                    encryption::KEY_FILE_ENV
+                ),
+            },
+        )),
+    }
+}
+
+/// File stem backups of the database are named after
+fn database_stem(db_path: &Path) -> String {
+    db_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "edda".to_string())
+}
+
+async fn handle_backup_commands(
+    subcommand: Option<BackupCommands>,
+    compress: bool,
+    encrypt: bool,
+    config: &EddaConfig,
+) -> EddaResult<()> {
+    let db_path = crate::handlers::task::database_path(config);
+    let dir = backup_dir(config);
+    let stem = database_stem(&db_path);
+    let settings = &config.backup;
+
+    match subcommand {
+        None => {
+            let key = backup_key(config, encrypt)?;
+            let format = match &key {
+                Some(_) => BackupFormat::Encrypted,
+                None if compress || settings.compress => BackupFormat::Compressed,
+                None => BackupFormat::Plain,
+            };
+            let backup_path = dir.join(backup::backup_file_name(
+                &stem,
+                None,
+                chrono::Utc::now(),
+                format,
+            ));
+            match (&key, format) {
+                (Some(key), _) => {
+                    database::create_encrypted_backup(&db_path, &backup_path, key).await?
+                }
+                (None, BackupFormat::Compressed) => {
+                    database::create_compressed_backup(&db_path, &backup_path).await?
+                }
+                (None, _) => database::create_backup(&db_path, &backup_path).await?,
+            }
+            println!("Backed up database to {}", backup_path.display());
+
+            let pruned =
+                backup::prune_backups(&dir, &stem, settings.keep_daily, settings.keep_weekly)?;
+            if !pruned.is_empty() {
+                println!("Removed {} expired backups", pruned.len());
+            }
+        }
+        Some(BackupCommands::List) => {
+            let backups = backup::list_backups(&dir, &stem)?;
+            if backups.is_empty() {
+                println!("No backups found in {}", dir.display());
+            }
+            for backup in backups {
+                println!(
+                    "{}  {:<10}  {:<18}  {:>10}  {}",
+                    backup.created.format("%Y-%m-%d %H:%M:%S"),
+                    backup.format,
+                    backup.label.as_deref().unwrap_or("regular"),
+                    format!("{} KiB", backup.size.div_ceil(1024)),
+                    backup.file_name()
+                );
+            }
+        }
+        Some(BackupCommands::Prune { dry_run }) => {
+            let backups = backup::list_backups(&dir, &stem)?;
+            let expired =
+                backup::expired_backups(&backups, settings.keep_daily, settings.keep_weekly);
+            if expired.is_empty() {
+                println!("No expired backups");
+            } else if dry_run {
+                for backup in expired {
+                    println!("Would remove {}", backup.file_name());
+                }
+            } else {
+                let pruned =
+                    backup::prune_backups(&dir, &stem, settings.keep_daily, settings.keep_weekly)?;
+                for backup in pruned {
+                    println!("Removed {}", backup.file_name());
+                }
+            }
+        }
     }
+
+    Ok(())
+}
+
+async fn handle_migrate_commands(
+    subcommand: MigrateCommands,
+    config: &EddaConfig,
+) -> EddaResult<()> {
+    let db_path = crate::handlers::task::database_path(config);
+    if !db_path.exists() {
+        return Err(crate::core::EddaError::Storage(
+            crate::core::StorageError::Connection {
+                message: format!(
+                    "Database not found at {}; run `edda system init` first",
+                    db_path.display()
+                ),
+            },
+        ));
+    }
+    let pool = database::connect_pool(&db_path).await?;
+    let current = migration::current_version(&pool).await?;
+
+    match subcommand {
+        MigrateCommands::Status => {
+            println!(
+                "Schema version: {current} (latest: {})",
+                migration::latest_version()
+            );
+            for status in migration::migration_status(&pool).await? {
+                let applied_at = status.applied_at.unwrap_or_default();
+                println!(
+                    "{:>4}  {:<8}  {:<25}  {}",
+                    status.version, status.state, applied_at, status.description
+                );
+            }
+        }
+        MigrateCommands::Up { to, dry_run } => {
+            let plan = migration::plan_up(&pool, to).await?;
+            if plan.is_empty() {
+                println!("Database is up to date (schema version {current})");
+            } else if dry_run {
+                for step in &plan {
+                    println!("Would apply {}: {}", step.version, step.description);
+                }
+            } else {
+                if current > 0 {
+                    let key = backup_key(config, false)?;
+                    let backup = database::backup_before_migration(
+                        &pool,
+                        &db_path,
+                        &backup_dir(config),
+                        key.as_ref(),
+                    )
+                    .await?;
+                    println!("Backed up database to {}", backup.display());
+                }
+                for step in plan {
+                    migration::apply_migrations(&pool, &[step], MigrationDirection::Up).await?;
+                    println!("Applied {}: {}", step.version, step.description);
+                }
+            }
+        }
+        MigrateCommands::Down { to, dry_run } => {
+            let target = to.unwrap_or((current - 1).max(0));
+            let plan = migration::plan_down(&pool, target).await?;
+            if plan.is_empty() {
+                println!("Nothing to revert (schema version {current})");
+            } else if dry_run {
+                for step in &plan {
+                    println!("Would revert {}: {}", step.version, step.description);
+                }
+            } else {
+                let key = backup_key(config, false)?;
+                let backup = database::backup_before_migration(
+                    &pool,
+                    &db_path,
+                    &backup_dir(config),
+                    key.as_ref(),
+                )
+                .await?;
+                println!("Backed up database to {}", backup.display());
+                for step in plan {
+                    migration::apply_migrations(&pool, &[step], MigrationDirection::Down).await?;
+                    println!("Reverted {}: {}", step.version, step.description);
+                }
+            }
+        }
+    }
+
+    Ok(())
 }
 
 async fn handle_config_commands(subcommand: ConfigCommands, config: &EddaConfig) -> EddaResult<()> {
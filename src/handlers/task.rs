@@ -1,20 +1,44 @@
-use crate::cli::TaskCommands;
-use crate::core::{EddaConfig, EddaResult, Priority, TaskEngine, TaskStatus};
-use crate::storage::SqliteTaskStorage;
+use crate::cli::{BulkArgs, IntervalCommands, TaskCommands};
+use crate::core::date::DateParser;
+use crate::core::report;
+use crate::core::subtask::nest_subtasks;
+use crate::core::taskwarrior;
+use crate::core::uda::parse_duration;
+use crate::core::{
+    EddaConfig, EddaResult, FilterAttribute, FilterExpr, ModifyAttribute, ReportDefinition,
+    TagClock, Task, TaskEngine, TaskModification, TaskSelection, TaskStatus, TaskTree,
+    TimeInterval, TimesheetGroup, UdaDefinition, UdaType, Urgency,
+};
+use crate::storage::{DependencyState, IntervalFilter, SqliteTaskStorage, TaskFilter};
 use std::path::PathBuf;
-use std::str::FromStr;
+use uuid::Uuid;
 
-/// Create a task engine instance for the given configuration
-pub async fn create_task_engine(config: &EddaConfig) -> EddaResult<TaskEngine> {
-    let db_path = if config.database.url.starts_with("sqlite:") {
+/// Path of the SQLite database for the given configuration
+pub(crate) fn database_path(config: &EddaConfig) -> PathBuf {
+    if config.database.url.starts_with("sqlite:") {
         PathBuf::from(config.database.url.trim_start_matches("sqlite:"))
     } else {
         config.data_dir.join("edda.db")
-    };
+    }
+}
 
-    let pool = crate::storage::get_pool(db_path).await?;
+/// Create a task engine instance for the given configuration
+pub async fn create_task_engine(config: &EddaConfig) -> EddaResult<TaskEngine> {
+    let pool = crate::storage::get_pool(database_path(config)).await?;
     let storage = SqliteTaskStorage::new(pool);
-    Ok(TaskEngine::new(Box::new(storage)))
+    Ok(TaskEngine::new(Box::new(storage))
+        .with_udas(config.uda.clone())
+        .with_urgency(config.urgency.clone())
+        .with_subtasks(config.subtasks.clone()))
+}
+
+/// Bring recurring, waiting and expiring tasks up to date before running a
+/// command
+pub(crate) async fn refresh_tasks(task_engine: &TaskEngine, config: &EddaConfig) -> EddaResult<()> {
+    let horizon = chrono::Duration::days(config.recurrence.horizon_days as i64);
+    task_engine.generate_recurring_instances(horizon).await?;
+    task_engine.update_lifecycle(chrono::Utc::now()).await?;
+    Ok(())
 }
 
 pub async fn handle_task_commands(
@@ -24,19 +48,38 @@ pub async fn handle_task_commands(
     quiet: bool,
 ) -> EddaResult<()> {
     // Initialize storage and task engine
-    let db_path = if config.database.url.starts_with("sqlite:") {
-        PathBuf::from(config.database.url.trim_start_matches("sqlite:"))
-    } else {
-        config.data_dir.join("edda.db")
-    };
-    println!("[DEBUG] Using database path: {db_path:?}");
-    let pool = crate::storage::get_pool(db_path).await?;
+    let pool = crate::storage::get_pool(database_path(config)).await?;
     let storage = SqliteTaskStorage::new(pool);
-    let task_engine = TaskEngine::new(Box::new(storage));
+    let task_engine = TaskEngine::new(Box::new(storage))
+        .with_udas(config.uda.clone())
+        .with_urgency(config.urgency.clone())
+        .with_subtasks(config.subtasks.clone());
+    refresh_tasks(&task_engine, config).await?;
+
+    let dates = config.date_parser();
 
     match subcommand {
-        TaskCommands::Add { description } => {
-            let task = task_engine.create_task(description).await?;
+        TaskCommands::Add {
+            description,
+            due,
+            scheduled,
+            wait,
+            until,
+            parent,
+        } => {
+            let modification = TaskModification::from_args(&description, &config.uda);
+            let mut task = Task::new(modification.description.clone().unwrap_or_default());
+            if let Some(parent) = parent {
+                task.parent_uuid = Some(task_engine.resolve_task_reference(&parent).await?);
+            }
+            task.due_date = due.map(|d| dates.parse(&d)).transpose()?;
+            task.scheduled_date = scheduled.map(|d| dates.parse(&d)).transpose()?;
+            task.wait_date = wait.map(|d| dates.parse(&d)).transpose()?;
+            task.until_date = until.map(|d| dates.parse(&d)).transpose()?;
+            task_engine
+                .apply_modification(&mut task, &modification, &dates)
+                .await?;
+            let task = task_engine.add_task(task).await?;
             println!(
                 "Created task {}: {}",
                 task.id.unwrap_or(0),
@@ -44,232 +87,1170 @@ pub async fn handle_task_commands(
             );
             Ok(())
         }
-        TaskCommands::List { query: _ } => {
-            let tasks = task_engine.list_tasks(None).await?;
+        TaskCommands::List { sort, query } => {
+            let report = named_report(config, "list", sort.as_deref(), None)?;
+            let tasks = task_engine
+                .list_tasks(Some(report_filter(&report, &query, &dates, config)?))
+                .await?;
+            print_report(&task_engine, &report, tasks, &dates, config, format, quiet).await
+        }
+        TaskCommands::Report {
+            name,
+            sort,
+            limit,
+            query,
+        } => {
+            let report = named_report(config, &name, sort.as_deref(), limit)?;
+            let tasks = task_engine
+                .list_tasks(Some(report_filter(&report, &query, &dates, config)?))
+                .await?;
+            print_report(&task_engine, &report, tasks, &dates, config, format, quiet).await
+        }
+        TaskCommands::Reports => {
+            let reports = config.reports();
+            match format {
+                "json" => println!("{}", serde_json::to_string_pretty(&reports).unwrap()),
+                _ => {
+                    for (name, report) in reports {
+                        println!("{:<12} {}", name, report.description);
+                    }
+                }
+            }
+            Ok(())
+        }
+        TaskCommands::Next {
+            claim,
+            agent,
+            lease,
+            query,
+        } => {
+            let filter = listing_filter(&query, &dates, config)?;
+            let task = if claim {
+                let agent = claim_agent(agent, config)?;
+                task_engine
+                    .claim_next_task(&agent, parse_lease(&lease)?, Some(filter))
+                    .await?
+            } else {
+                task_engine.next_task(Some(filter)).await?
+            };
 
-            if tasks.is_empty() {
+            if claim || format == "json" {
+                let json = match &task {
+                    Some(task) => {
+                        let urgency = task_engine.urgency(std::slice::from_ref(task)).await?;
+                        let mut json = serde_json::to_value(task).unwrap();
+                        json["urgency"] = serde_json::json!(round_urgency(urgency[0].total()));
+                        json
+                    }
+                    None => serde_json::Value::Null,
+                };
+                println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                return Ok(());
+            }
+            match task {
+                Some(task) => println!("Task {}: {}", task.id.unwrap_or(0), task.description),
+                None if !quiet => println!("No tasks ready."),
+                None => {}
+            }
+            Ok(())
+        }
+        TaskCommands::Heartbeat { id, agent, lease } => {
+            let task_id = id.parse::<i64>().map_err(|_| {
+                crate::core::EddaError::Task(crate::core::TaskError::Validation {
+                    message: format!("Invalid task ID: {id}"),
+                })
+            })?;
+            let agent = claim_agent(agent, config)?;
+            let task = task_engine
+                .renew_lease(task_id, &agent, parse_lease(&lease)?)
+                .await?;
+            if let Some(expires) = task.lease_expires {
+                println!(
+                    "Task {task_id} held by {agent} until {}",
+                    dates.format(expires)
+                );
+            }
+            Ok(())
+        }
+        TaskCommands::Release { id, agent } => {
+            let task_id = id.parse::<i64>().map_err(|_| {
+                crate::core::EddaError::Task(crate::core::TaskError::Validation {
+                    message: format!("Invalid task ID: {id}"),
+                })
+            })?;
+            let agent = claim_agent(agent, config)?;
+            let task = task_engine.release_task(task_id, &agent).await?;
+            println!("Released task {task_id}: {}", task.description);
+            Ok(())
+        }
+        TaskCommands::Blocked { query } => {
+            let filter = dependency_filter(&query, DependencyState::Blocked, &dates, config)?;
+            let tasks = task_engine.list_tasks(Some(filter)).await?;
+            let report = named_report(config, "list", None, None)?;
+            print_report(&task_engine, &report, tasks, &dates, config, format, quiet).await
+        }
+        TaskCommands::Blocking { query } => {
+            let filter = dependency_filter(&query, DependencyState::Blocking, &dates, config)?;
+            let tasks = task_engine.list_tasks(Some(filter)).await?;
+            let report = named_report(config, "list", None, None)?;
+            print_report(&task_engine, &report, tasks, &dates, config, format, quiet).await
+        }
+        TaskCommands::Unblocked { query } => {
+            let filter = dependency_filter(&query, DependencyState::Unblocked, &dates, config)?;
+            let tasks = task_engine.list_tasks(Some(filter)).await?;
+            let report = named_report(config, "list", None, None)?;
+            print_report(&task_engine, &report, tasks, &dates, config, format, quiet).await
+        }
+        TaskCommands::Get { id } => {
+            let task_id = id.parse::<i64>().map_err(|_| {
+                crate::core::EddaError::Task(crate::core::TaskError::Validation {
+                    message: format!("Invalid task ID: {id}"),
+                })
+            })?;
+            let task = task_engine.get_task(task_id).await?;
+
+            let Some(task) = task else {
                 if !quiet {
-                    println!("No tasks found.");
+                    println!("Task {task_id} not found.");
                 }
                 return Ok(());
-            }
+            };
+            let virtual_tags = task_engine
+                .virtual_tags(std::slice::from_ref(&task), TagClock::new(&dates))
+                .await?
+                .remove(0);
 
             match format {
                 "json" => {
-                    let json = serde_json::json!({
-                        "tasks": tasks,
-                        "meta": {
-                            "total": tasks.len(),
-                            "pending": tasks.iter().filter(|t| t.status == TaskStatus::Pending).count(),
-                            "completed": tasks.iter().filter(|t| t.status == TaskStatus::Completed).count(),
-                        }
-                    });
+                    let mut json = serde_json::to_value(&task).unwrap();
+                    json["virtual_tags"] = serde_json::json!(virtual_tags);
                     println!("{}", serde_json::to_string_pretty(&json).unwrap());
                 }
                 _ => {
-                    // Text table output
+                    println!("Task {}: {}", task.id.unwrap_or(0), task.description);
+                    println!("  Status: {}", task.status);
+                    println!(
+                        "  Priority: {}",
+                        task.priority
+                            .as_ref()
+                            .map(|p| p.to_string())
+                            .unwrap_or_else(|| "None".to_string())
+                    );
+                    println!("  Project: {}", task.project.as_deref().unwrap_or("None"));
+                    println!(
+                        "  Tags: {}",
+                        if task.tags.is_empty() {
+                            "None".to_string()
+                        } else {
+                            task.tags
+                                .iter()
+                                .map(|t| format!("+{t}"))
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        }
+                    );
                     println!(
-                        "{:<4} {:<30} {:<10} {:<20} {:<20}",
-                        "ID", "Description", "Status", "Created", "Modified"
+                        "  Virtual tags: {}",
+                        virtual_tags
+                            .iter()
+                            .map(|t| t.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
                     );
-                    for task in tasks {
+                    if !task.depends.is_empty() {
                         println!(
-                            "{:<4} {:<30} {:<10} {:<20} {:<20}",
-                            task.id.unwrap_or(0),
-                            task.description.chars().take(30).collect::<String>(),
-                            task.status,
-                            task.entry_date.format("%Y-%m-%d %H:%M"),
-                            task.modified_date.format("%Y-%m-%d %H:%M")
+                            "  Depends: {}",
+                            task.depends
+                                .iter()
+                                .map(|u| u.to_string())
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        );
+                    }
+                    if let Some(minutes) = task.effort_spent {
+                        println!("  Time spent: {}", format_minutes(minutes as u64));
+                    }
+                    if let (Some(agent), Some(expires)) = (&task.claimed_by, task.lease_expires) {
+                        println!("  Claimed by: {agent} until {}", dates.format(expires));
+                    }
+                    if !task.annotations.is_empty() {
+                        println!("  Annotations:");
+                        for (number, annotation) in task.annotations.iter().enumerate() {
+                            let author = annotation
+                                .author
+                                .as_ref()
+                                .map(|author| format!(" [{author}]"))
+                                .unwrap_or_default();
+                            println!(
+                                "    {}. {}{author} {}",
+                                number + 1,
+                                dates.format(annotation.entry),
+                                annotation.description
+                            );
+                        }
+                    }
+                    for (label, date) in [
+                        ("Due", task.due_date),
+                        ("Scheduled", task.scheduled_date),
+                        ("Wait", task.wait_date),
+                        ("Until", task.until_date),
+                    ] {
+                        if let Some(date) = date {
+                            println!("  {label}: {}", dates.format(date));
+                        }
+                    }
+                    if let Some(recurrence) = &task.recurrence {
+                        println!("  Recurrence: {recurrence}");
+                    }
+                    for (name, value) in &task.udas {
+                        println!(
+                            "  {name}: {}",
+                            format_uda(config.uda.get(name), value, &dates)
                         );
                     }
+                    println!("  Created: {}", task.entry_date);
+                    println!("  Modified: {}", task.modified_date);
                 }
             }
             Ok(())
         }
-        TaskCommands::Get { id } => {
+        TaskCommands::Tree { id } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.get_task(task_id).await?;
-
-            match task {
-                Some(task) => match format {
-                    "json" => {
-                        println!("{}", serde_json::to_string_pretty(&task).unwrap());
-                    }
-                    _ => {
-                        println!("Task {}: {}", task.id.unwrap_or(0), task.description);
-                        println!("  Status: {}", task.status);
-                        println!(
-                            "  Priority: {}",
-                            task.priority
-                                .as_ref()
-                                .map(|p| p.to_string())
-                                .unwrap_or_else(|| "None".to_string())
-                        );
-                        println!("  Project: {}", task.project.as_deref().unwrap_or("None"));
-                        println!(
-                            "  Tags: {}",
-                            if task.tags.is_empty() {
-                                "None".to_string()
-                            } else {
-                                task.tags
-                                    .iter()
-                                    .map(|t| format!("+{t}"))
-                                    .collect::<Vec<_>>()
-                                    .join(" ")
-                            }
+            let tree = task_engine.task_tree(task_id).await?;
+            match format {
+                "json" => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&tree_json(&tree)).unwrap()
+                ),
+                _ => {
+                    for (node, depth) in tree.flatten() {
+                        let task = &node.task;
+                        let mut line = format!(
+                            "{}{} {} [{}]",
+                            "  ".repeat(depth),
+                            task.id.unwrap_or(0),
+                            task.description,
+                            task.status
                         );
-                        println!("  Created: {}", task.entry_date);
-                        println!("  Modified: {}", task.modified_date);
-                    }
-                },
-                None => {
-                    if !quiet {
-                        println!("Task {task_id} not found.");
+                        if node.effort() > 0 || node.effort_spent() > 0 {
+                            line.push_str(&format!(
+                                " effort {}, spent {}, remaining {}",
+                                format_minutes(u64::from(node.effort())),
+                                format_minutes(u64::from(node.effort_spent())),
+                                format_minutes(u64::from(node.remaining()))
+                            ));
+                        }
+                        println!("{line}");
                     }
                 }
             }
             Ok(())
         }
-        TaskCommands::Modify { id, field, value } => {
+        TaskCommands::Urgency { id, explain } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let mut task = task_engine.get_task(task_id).await?.ok_or_else(|| {
+            let task = task_engine.get_task(task_id).await?.ok_or_else(|| {
                 crate::core::EddaError::Task(crate::core::TaskError::NotFound { id: id.clone() })
             })?;
-
-            match field.to_lowercase().as_str() {
-                "description" => task.description = value,
-                "status" => {
-                    task.status = TaskStatus::from_str(&value).map_err(|e| {
-                        crate::core::EddaError::Task(crate::core::TaskError::Validation {
-                            message: format!("Invalid status: {e}"),
-                        })
-                    })?
-                }
-                "priority" => {
-                    task.priority = Some(Priority::from_str(&value).map_err(|e| {
-                        crate::core::EddaError::Task(crate::core::TaskError::Validation {
-                            message: format!("Invalid priority: {e}"),
-                        })
-                    })?)
-                }
-                "project" => task.project = Some(value),
-                _ => {
-                    return Err(crate::core::EddaError::Task(
-                        crate::core::TaskError::Validation {
-                            message: format!("Unknown field: {field}"),
-                        },
-                    ));
-                }
-            }
-
-            let updated_task = task_engine.update_task(task).await?;
-            println!(
-                "Updated task {}: {}",
-                updated_task.id.unwrap_or(0),
-                updated_task.description
-            );
+            let urgency = task_engine
+                .urgency(std::slice::from_ref(&task))
+                .await?
+                .remove(0);
+            print_urgency(task_id, &urgency, explain, format);
             Ok(())
         }
-        TaskCommands::Done { id } => {
+        TaskCommands::Modify {
+            filter,
+            modifications,
+            bulk,
+        } => {
+            let modification =
+                TaskModification::from_args(&legacy_modification(modifications), &config.uda);
+            if modification.is_empty() {
+                return Err(crate::core::EddaError::Task(
+                    crate::core::TaskError::Validation {
+                        message: "No modifications given".to_string(),
+                    },
+                ));
+            }
+            let selection = TaskSelection::from_args(&[filter], dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("modify", "Updated"),
+                async |id| task_engine.modify_task(id, &modification, &dates).await,
+            )
+            .await
+        }
+        TaskCommands::Done {
+            filter,
+            force,
+            bulk,
+        } => {
+            let selection = TaskSelection::from_args(&filter, dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("complete", "Completed"),
+                async |id| task_engine.complete_task(id, force).await,
+            )
+            .await
+        }
+        TaskCommands::Delete { filter, bulk } => {
+            let selection = TaskSelection::from_args(&filter, dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("delete", "Deleted"),
+                async |id| task_engine.delete_task(id).await,
+            )
+            .await
+        }
+        TaskCommands::Start { filter, bulk } => {
+            let selection = TaskSelection::from_args(&filter, dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("start", "Started"),
+                async |id| task_engine.start_task(id).await,
+            )
+            .await
+        }
+        TaskCommands::Stop { filter, bulk } => {
+            let selection = TaskSelection::from_args(&filter, dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("stop", "Stopped"),
+                async |id| task_engine.stop_task(id).await,
+            )
+            .await
+        }
+        TaskCommands::Annotate {
+            filter,
+            note,
+            author,
+            bulk,
+        } => {
+            let author = author.or_else(|| config.author.clone());
+            let selection = TaskSelection::from_args(&[filter], dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("annotate", "Annotated"),
+                async |id| {
+                    task_engine
+                        .annotate_task(id, note.clone(), author.clone())
+                        .await
+                },
+            )
+            .await
+        }
+        TaskCommands::Denotate { id, annotation } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.complete_task(task_id).await?;
+            let (_, removed) = task_engine.denotate_task(task_id, &annotation).await?;
             println!(
-                "Completed task {}: {}",
-                task.id.unwrap_or(0),
-                task.description
+                "Removed annotation from task {task_id}: {}",
+                removed.description
             );
             Ok(())
         }
-        TaskCommands::Delete { id } => {
+        TaskCommands::Reannotate {
+            id,
+            annotation,
+            note,
+        } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.delete_task(task_id).await?;
-            println!(
-                "Deleted task {}: {}",
-                task.id.unwrap_or(0),
-                task.description
-            );
+            task_engine
+                .edit_annotation(task_id, &annotation, note)
+                .await?;
+            println!("Updated annotation of task {task_id}");
             Ok(())
         }
-        TaskCommands::Start { id } => {
+        TaskCommands::Tag { filter, tag, bulk } => {
+            let selection = TaskSelection::from_args(&[filter], dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("tag", "Added tag to"),
+                async |id| task_engine.add_tag(id, tag.clone()).await,
+            )
+            .await
+        }
+        TaskCommands::Untag { filter, tag, bulk } => {
+            let selection = TaskSelection::from_args(&[filter], dates, &config.uda)?;
+            run_bulk(
+                &task_engine,
+                &selection,
+                bulk,
+                config,
+                ("untag", "Removed tag from"),
+                async |id| task_engine.remove_tag(id, &tag).await,
+            )
+            .await
+        }
+        TaskCommands::Depend { id, depends_on } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.start_task(task_id).await?;
-            println!(
-                "Started task {}: {}",
-                task.id.unwrap_or(0),
-                task.description
-            );
+            let uuids = resolve_references(&task_engine, &depends_on).await?;
+            task_engine.add_dependencies(task_id, &uuids).await?;
+            println!("Task {task_id} now depends on {}", depends_on.join(", "));
             Ok(())
         }
-        TaskCommands::Stop { id } => {
+        TaskCommands::Undepend { id, depends_on } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.stop_task(task_id).await?;
+            let uuids = resolve_references(&task_engine, &depends_on).await?;
+            task_engine.remove_dependencies(task_id, &uuids).await?;
             println!(
-                "Stopped task {}: {}",
-                task.id.unwrap_or(0),
-                task.description
+                "Task {task_id} no longer depends on {}",
+                depends_on.join(", ")
             );
             Ok(())
         }
-        TaskCommands::Annotate { id, note } => {
+        TaskCommands::Timesheet { by, from, to } => {
+            let group_by = by.parse::<TimesheetGroup>()?;
+            let from = from.map(|d| dates.parse(&d)).transpose()?;
+            let to = to.map(|d| dates.parse(&d)).transpose()?;
+            let timesheet = task_engine.timesheet(group_by, from, to).await?;
+
+            match format {
+                "json" => {
+                    println!("{}", serde_json::to_string_pretty(&timesheet).unwrap());
+                }
+                _ => {
+                    if timesheet.rows.is_empty() {
+                        if !quiet {
+                            println!("No time tracked.");
+                        }
+                        return Ok(());
+                    }
+                    println!("{:<30} {:>8} {:>6}", group_by.to_string(), "Time", "Tasks");
+                    for row in &timesheet.rows {
+                        println!(
+                            "{:<30} {:>8} {:>6}",
+                            row.key.chars().take(30).collect::<String>(),
+                            format_minutes(row.minutes),
+                            row.tasks
+                        );
+                    }
+                    println!(
+                        "{:<30} {:>8}",
+                        "Total",
+                        format_minutes(timesheet.total_minutes)
+                    );
+                }
+            }
+            Ok(())
+        }
+        TaskCommands::Interval { subcommand } => {
+            handle_interval_commands(subcommand, &task_engine, &dates, format, quiet).await
+        }
+        TaskCommands::Undo { count } => {
+            let operations = task_engine.undo(count).await?;
+            if operations.is_empty() {
+                if !quiet {
+                    println!("Nothing to undo.");
+                }
+                return Ok(());
+            }
+            for operation in operations {
+                let task = operation.after.as_ref().or(operation.before.as_ref());
+                println!(
+                    "Undid {} of task {}: {}",
+                    operation.kind,
+                    task.and_then(|t| t.id).unwrap_or(0),
+                    task.map(|t| t.description.as_str()).unwrap_or_default()
+                );
+            }
+            Ok(())
+        }
+        TaskCommands::History { id } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.annotate_task(task_id, note).await?;
-            println!(
-                "Annotated task {}: {}",
-                task.id.unwrap_or(0),
-                task.description
-            );
+            let operations = task_engine.history(task_id).await?;
+
+            match format {
+                "json" => {
+                    let entries: Vec<_> = operations
+                        .iter()
+                        .map(|operation| {
+                            serde_json::json!({
+                                "id": operation.id,
+                                "operation": operation.kind,
+                                "timestamp": operation.timestamp,
+                                "undoes": operation.undoes,
+                                "changes": operation.changes(),
+                            })
+                        })
+                        .collect();
+                    let json = serde_json::json!({ "history": entries });
+                    println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                }
+                _ => {
+                    for operation in &operations {
+                        let undo = operation
+                            .undoes
+                            .map(|id| format!(" (undo of #{id})"))
+                            .unwrap_or_default();
+                        println!(
+                            "#{} {} {}{undo}",
+                            operation.id.unwrap_or(0),
+                            operation.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                            operation.kind
+                        );
+                        for change in operation.changes() {
+                            println!(
+                                "    {}: {} -> {}",
+                                change.field, change.before, change.after
+                            );
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        TaskCommands::Export { query } => {
+            // Like `task export`, include completed and deleted tasks
+            let filter = TaskFilter {
+                include_deleted: true,
+                include_recurring: true,
+                ..query_filter(&query, &dates, config)?.unwrap_or_default()
+            };
+            let mut tasks = task_engine.list_tasks(Some(filter)).await?;
+            tasks.sort_by_key(|task| task.id);
+
+            // One task per line, as Taskwarrior writes it
+            let lines: Vec<String> = tasks
+                .iter()
+                .map(|task| taskwarrior::to_taskwarrior(task, &config.uda).to_string())
+                .collect();
+            if lines.is_empty() {
+                println!("[]");
+            } else {
+                println!("[\n{}\n]", lines.join(",\n"));
+            }
             Ok(())
         }
-        TaskCommands::Tag { id, tag } => {
+        TaskCommands::Import { file } => {
+            let input = match file.filter(|path| path.as_os_str() != "-") {
+                Some(path) => std::fs::read_to_string(&path)?,
+                None => {
+                    use std::io::Read;
+
+                    let mut input = String::new();
+                    std::io::stdin().read_to_string(&mut input)?;
+                    input
+                }
+            };
+            let tasks = taskwarrior::parse_export(&input, &config.uda)?;
+            let total = tasks.len();
+            let summary = task_engine.import_tasks(tasks).await?;
+            if !quiet {
+                println!(
+                    "Imported {total} tasks: {} added, {} updated, {} unchanged",
+                    summary.added, summary.updated, summary.unchanged
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Handle `task interval` subcommands
+async fn handle_interval_commands(
+    subcommand: IntervalCommands,
+    task_engine: &TaskEngine,
+    dates: &DateParser,
+    format: &str,
+    quiet: bool,
+) -> EddaResult<()> {
+    match subcommand {
+        IntervalCommands::List { id } => {
+            let task_uuid = match id {
+                Some(id) => Some(task_engine.resolve_task_reference(&id).await?),
+                None => None,
+            };
+            let intervals = task_engine
+                .list_intervals(IntervalFilter {
+                    task_uuid,
+                    ..Default::default()
+                })
+                .await?;
+
+            if intervals.is_empty() {
+                if !quiet {
+                    println!("No intervals found.");
+                }
+                return Ok(());
+            }
+
+            match format {
+                "json" => {
+                    let json = serde_json::json!({ "intervals": intervals });
+                    println!("{}", serde_json::to_string_pretty(&json).unwrap());
+                }
+                _ => {
+                    println!(
+                        "{:<4} {:<36} {:<20} {:<20} {:>8}",
+                        "ID", "Task", "Start", "End", "Time"
+                    );
+                    let now = chrono::Utc::now();
+                    for interval in intervals {
+                        println!(
+                            "{:<4} {:<36} {:<20} {:<20} {:>8}",
+                            interval.id.unwrap_or(0),
+                            interval.task_uuid,
+                            interval.start.format("%Y-%m-%d %H:%M"),
+                            interval
+                                .end
+                                .map(|end| end.format("%Y-%m-%d %H:%M").to_string())
+                                .unwrap_or_else(|| "running".to_string()),
+                            format_minutes(interval.duration(now).num_minutes() as u64)
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+        IntervalCommands::Add { id, start, end } => {
             let task_id = id.parse::<i64>().map_err(|_| {
                 crate::core::EddaError::Task(crate::core::TaskError::Validation {
                     message: format!("Invalid task ID: {id}"),
                 })
             })?;
-            let task = task_engine.add_tag(task_id, tag).await?;
+            let interval = task_engine
+                .add_interval(task_id, dates.parse(&start)?, dates.parse(&end)?)
+                .await?;
+            print_interval_change("Added", &interval);
+            Ok(())
+        }
+        IntervalCommands::Modify {
+            interval_id,
+            start,
+            end,
+        } => {
+            let interval_id = parse_interval_id(&interval_id)?;
+            let start = start.map(|d| dates.parse(&d)).transpose()?;
+            let end = end.map(|d| dates.parse(&d)).transpose()?;
+            let interval = task_engine.modify_interval(interval_id, start, end).await?;
+            print_interval_change("Updated", &interval);
+            Ok(())
+        }
+        IntervalCommands::Delete { interval_id } => {
+            let interval_id = parse_interval_id(&interval_id)?;
+            let interval = task_engine.delete_interval(interval_id).await?;
+            print_interval_change("Deleted", &interval);
+            Ok(())
+        }
+    }
+}
+
+fn parse_interval_id(id: &str) -> EddaResult<i64> {
+    id.parse::<i64>().map_err(|_| {
+        crate::core::EddaError::Task(crate::core::TaskError::Validation {
+            message: format!("Invalid interval ID: {id}"),
+        })
+    })
+}
+
+fn print_interval_change(action: &str, interval: &TimeInterval) {
+    println!(
+        "{action} interval {}: {} - {}",
+        interval.id.unwrap_or(0),
+        interval.start.to_rfc3339(),
+        interval
+            .end
+            .map(|end| end.to_rfc3339())
+            .unwrap_or_else(|| "running".to_string())
+    );
+}
+
+/// Resolve every task reference before anything is changed, so one bad
+/// reference leaves the task as it was
+async fn resolve_references(
+    task_engine: &TaskEngine,
+    references: &[String],
+) -> EddaResult<Vec<Uuid>> {
+    let mut uuids = Vec::with_capacity(references.len());
+    for reference in references {
+        uuids.push(task_engine.resolve_task_reference(reference).await?);
+    }
+    Ok(uuids)
+}
+
+/// Format a UDA value for display according to its declared type
+fn format_uda(
+    definition: Option<&UdaDefinition>,
+    value: &serde_json::Value,
+    dates: &DateParser,
+) -> String {
+    let kind = definition.map(|d| d.kind);
+    match (kind, value) {
+        (Some(UdaType::Date), serde_json::Value::String(text)) => {
+            match chrono::DateTime::parse_from_rfc3339(text) {
+                Ok(date) => dates.format(date.with_timezone(&chrono::Utc)),
+                Err(_) => text.clone(),
+            }
+        }
+        (Some(UdaType::Duration), serde_json::Value::Number(seconds)) => match seconds.as_u64() {
+            Some(seconds) if seconds % 60 == 0 => format_minutes(seconds / 60),
+            _ => format!("{seconds}s"),
+        },
+        (_, serde_json::Value::String(text)) => text.clone(),
+        (_, other) => other.to_string(),
+    }
+}
+
+/// Format minutes as `H:MM`
+pub(crate) fn format_minutes(minutes: u64) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Build a task filter from a Taskwarrior-style query, if one was given
+fn query_filter(
+    query: &[String],
+    dates: &DateParser,
+    config: &EddaConfig,
+) -> EddaResult<Option<TaskFilter>> {
+    Ok(query_expression(query, dates, config)?
+        .map(|expression| expression_filter(Some(expression))))
+}
+
+/// Build a filter for one of the dependency views, narrowed by the active
+/// context and an optional query
+fn dependency_filter(
+    query: &[String],
+    state: DependencyState,
+    dates: &DateParser,
+    config: &EddaConfig,
+) -> EddaResult<TaskFilter> {
+    Ok(TaskFilter {
+        dependency: Some(state),
+        ..listing_filter(query, dates, config)?
+    })
+}
+
+/// Build the filter of a listing: the active context narrowed by an
+/// optional query
+pub(crate) fn listing_filter(
+    query: &[String],
+    dates: &DateParser,
+    config: &EddaConfig,
+) -> EddaResult<TaskFilter> {
+    Ok(expression_filter(all_of([
+        context_expression(dates, config)?,
+        query_expression(query, dates, config)?,
+    ])))
+}
+
+fn query_expression(
+    query: &[String],
+    dates: &DateParser,
+    config: &EddaConfig,
+) -> EddaResult<Option<FilterExpr>> {
+    if query.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(FilterExpr::from_args_with(
+        query,
+        *dates,
+        &config.uda,
+    )?))
+}
+
+/// Filter of the active context, which narrows every listing
+fn context_expression(dates: &DateParser, config: &EddaConfig) -> EddaResult<Option<FilterExpr>> {
+    config
+        .context_filter()
+        .map(|filter| FilterExpr::from_args_with(&[filter.to_string()], *dates, &config.uda))
+        .transpose()
+        .map_err(Into::into)
+}
+
+/// Conjunction of the given expressions, skipping absent ones
+fn all_of(expressions: impl IntoIterator<Item = Option<FilterExpr>>) -> Option<FilterExpr> {
+    expressions
+        .into_iter()
+        .flatten()
+        .reduce(|left, right| FilterExpr::And(Box::new(left), Box::new(right)))
+}
+
+fn expression_filter(expression: Option<FilterExpr>) -> TaskFilter {
+    // An explicit status term may ask for deleted tasks or recurring templates
+    let names_status = expression
+        .as_ref()
+        .is_some_and(|e| e.references(FilterAttribute::Status));
+    TaskFilter {
+        include_deleted: names_status,
+        include_recurring: names_status,
+        expression,
+        ..Default::default()
+    }
+}
+
+/// Look up a report, applying command-line overrides of its sort keys
+/// (comma-separated) and limit
+fn named_report(
+    config: &EddaConfig,
+    name: &str,
+    sort: Option<&str>,
+    limit: Option<usize>,
+) -> EddaResult<ReportDefinition> {
+    let mut report = config.reports().remove(name).ok_or_else(|| {
+        crate::core::EddaError::Task(crate::core::TaskError::Validation {
+            message: format!("Unknown report: {name} (see `task reports`)"),
+        })
+    })?;
+    if let Some(sort) = sort {
+        report.sort = sort.split(',').map(|key| key.trim().to_string()).collect();
+    }
+    if limit.is_some() {
+        report.limit = limit;
+    }
+    Ok(report)
+}
+
+/// Build the filter of a report, narrowed by the active context and a
+/// command-line query
+fn report_filter(
+    report: &ReportDefinition,
+    query: &[String],
+    dates: &DateParser,
+    config: &EddaConfig,
+) -> EddaResult<TaskFilter> {
+    Ok(expression_filter(all_of([
+        context_expression(dates, config)?,
+        report.filter(*dates, &config.uda)?,
+        query_expression(query, dates, config)?,
+    ])))
+}
+
+/// Print tasks as a report table, or as JSON with each task's urgency
+async fn print_report(
+    task_engine: &TaskEngine,
+    report: &ReportDefinition,
+    tasks: Vec<Task>,
+    dates: &DateParser,
+    config: &EddaConfig,
+    format: &str,
+    quiet: bool,
+) -> EddaResult<()> {
+    let columns = report.columns(&config.uda)?;
+    let sort_keys = report.sort_keys(&config.uda)?;
+    let urgency = task_engine.urgency(&tasks).await?;
+    let mut rows: Vec<(Task, f64)> = tasks
+        .into_iter()
+        .zip(urgency.iter().map(Urgency::total))
+        .collect();
+    report::sort_tasks(&mut rows, &sort_keys);
+    let mut rows = nest_subtasks(rows);
+    let matched = rows.len();
+    if let Some(limit) = report.limit {
+        rows.truncate(limit);
+    }
+
+    if rows.is_empty() {
+        if !quiet {
+            println!("No tasks found.");
+        }
+        return Ok(());
+    }
+
+    match format {
+        "json" => {
+            let entries: Vec<serde_json::Value> = rows
+                .iter()
+                .map(|(task, urgency, _)| {
+                    let mut entry = serde_json::to_value(task).unwrap();
+                    entry["urgency"] = serde_json::json!(round_urgency(*urgency));
+                    entry
+                })
+                .collect();
+            let count =
+                |status: TaskStatus| rows.iter().filter(|(t, _, _)| t.status == status).count();
+            let json = serde_json::json!({
+                "tasks": entries,
+                "meta": {
+                    "total": rows.len(),
+                    "matched": matched,
+                    "pending": count(TaskStatus::Pending),
+                    "completed": count(TaskStatus::Completed),
+                }
+            });
+            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        }
+        _ => {
+            let cells: Vec<Vec<String>> = rows
+                .iter()
+                .map(|(task, urgency, depth)| {
+                    columns
+                        .iter()
+                        .map(|column| match column {
+                            // Subtasks are indented under their parent
+                            report::Column::Description => format!(
+                                "{}{}",
+                                "  ".repeat(*depth),
+                                column.render(task, *urgency, dates)
+                            ),
+                            _ => column.render(task, *urgency, dates),
+                        })
+                        .collect()
+                })
+                .collect();
+            let max_widths: Vec<Option<usize>> = report
+                .columns
+                .iter()
+                .map(|spec| report.widths.get(spec).copied())
+                .collect();
+            for line in report::layout(&columns, &max_widths, &cells, terminal_width()) {
+                println!("{line}");
+            }
+            if !quiet && matched > rows.len() {
+                println!();
+                println!("{} of {} tasks shown", rows.len(), matched);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A task tree as JSON, each task with its rolled-up effort and subtasks
+fn tree_json(tree: &TaskTree) -> serde_json::Value {
+    let mut json = serde_json::to_value(&tree.task).unwrap();
+    json["rolled_up"] = serde_json::json!({
+        "effort": tree.effort(),
+        "effort_spent": tree.effort_spent(),
+        "remaining": tree.remaining(),
+    });
+    json["subtasks"] = tree.children.iter().map(tree_json).collect();
+    json
+}
+
+/// Width to fit report tables to: `COLUMNS` if set, otherwise the size of
+/// the terminal; output to a pipe or file is not limited
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .filter(|&c| c > 0)
+    {
+        return Some(columns);
+    }
+    if !atty::is(atty::Stream::Stdout) {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+/// Print a task's urgency, optionally with the contribution of each term
+fn print_urgency(id: i64, urgency: &Urgency, explain: bool, format: &str) {
+    match format {
+        "json" => {
+            let terms: Vec<serde_json::Value> = urgency
+                .terms
+                .iter()
+                .map(|term| {
+                    serde_json::json!({
+                        "name": term.name,
+                        "value": term.factor,
+                        "coefficient": term.coefficient,
+                        "urgency": round_urgency(term.contribution()),
+                    })
+                })
+                .collect();
+            let json = serde_json::json!({
+                "id": id,
+                "urgency": round_urgency(urgency.total()),
+                "terms": terms,
+            });
+            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        }
+        _ if explain => {
             println!(
-                "Added tag to task {}: {}",
+                "{:<20} {:>8} {:>12} {:>8}",
+                "Term", "Value", "Coefficient", "Urgency"
+            );
+            for term in &urgency.terms {
+                println!(
+                    "{:<20} {:>8.3} {:>12.2} {:>8.2}",
+                    term.name,
+                    term.factor,
+                    term.coefficient,
+                    term.contribution()
+                );
+            }
+            println!("{:<20} {:>30.2}", "Total", urgency.total());
+        }
+        _ => println!("Task {id} urgency: {:.2}", urgency.total()),
+    }
+}
+
+/// Agent named by `--agent`, falling back to the configured author
+fn claim_agent(agent: Option<String>, config: &EddaConfig) -> EddaResult<String> {
+    agent.or_else(|| config.author.clone()).ok_or_else(|| {
+        crate::core::EddaError::Task(crate::core::TaskError::Validation {
+            message: "No agent given: pass --agent or set author".to_string(),
+        })
+    })
+}
+
+/// Parse a lease length such as `15m` or `1h`
+fn parse_lease(lease: &str) -> EddaResult<chrono::Duration> {
+    parse_duration(lease)
+        .and_then(|seconds| i64::try_from(seconds).ok())
+        .and_then(chrono::Duration::try_seconds)
+        .ok_or_else(|| {
+            crate::core::EddaError::Task(crate::core::TaskError::Validation {
+                message: format!("Invalid lease: {lease} (expected a duration such as 15m)"),
+            })
+        })
+}
+
+/// Round an urgency to two decimals for machine-readable output
+fn round_urgency(urgency: f64) -> f64 {
+    (urgency * 100.0).round() / 100.0
+}
+
+/// Apply `action` to every selected task in one transaction
+///
+/// Nothing is saved if any task fails. Selections larger than
+/// `bulk.confirm_threshold` need confirmation unless `--yes` is given, and
+/// `--dry-run` runs every change and then rolls it back, so the preview also
+/// reports errors such as blocked tasks.
+async fn run_bulk(
+    task_engine: &TaskEngine,
+    selection: &TaskSelection,
+    bulk: BulkArgs,
+    config: &EddaConfig,
+    (verb, past): (&str, &str),
+    action: impl AsyncFn(i64) -> EddaResult<Task>,
+) -> EddaResult<()> {
+    let tasks = task_engine.select_tasks(selection).await?;
+    if tasks.is_empty() {
+        println!("No tasks found.");
+        return Ok(());
+    }
+
+    let threshold = config.bulk.confirm_threshold;
+    if !bulk.dry_run && !bulk.yes && threshold > 0 && tasks.len() > threshold {
+        for task in &tasks {
+            println!("  {} {}", task.id.unwrap_or(0), task.description);
+        }
+        if !confirm(&format!(
+            "This will {verb} {} tasks. Continue?",
+            tasks.len()
+        )) {
+            println!("No changes made.");
+            return Ok(());
+        }
+    }
+
+    // Subtasks go before their parents, so a parent is completed after its
+    // subtasks; a task an earlier change already cascaded to is skipped
+    let mut nested = nest_subtasks(tasks.into_iter().map(|task| (task, ())).collect());
+    nested.sort_by_key(|(_, _, depth)| std::cmp::Reverse(*depth));
+
+    task_engine.begin_batch().await?;
+    let mut changed = Vec::with_capacity(nested.len());
+    for (task, _, _) in nested {
+        let result = match task_engine.get_task_by_uuid(task.uuid).await {
+            Ok(Some(current)) if current.status != task.status => Ok(current),
+            Ok(_) => action(task.id.unwrap_or(0)).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(task) => changed.push(task),
+            Err(e) => {
+                task_engine.rollback_batch().await?;
+                return Err(e);
+            }
+        }
+    }
+    if bulk.dry_run {
+        task_engine.rollback_batch().await?;
+    } else {
+        task_engine.commit_batch().await?;
+    }
+
+    for task in &changed {
+        if bulk.dry_run {
+            println!(
+                "Would {verb} task {}: {}",
                 task.id.unwrap_or(0),
                 task.description
             );
-            Ok(())
+        } else {
+            println!("{past} task {}: {}", task.id.unwrap_or(0), task.description);
         }
-        TaskCommands::Untag { id, tag } => {
-            let task_id = id.parse::<i64>().map_err(|_| {
-                crate::core::EddaError::Task(crate::core::TaskError::Validation {
-                    message: format!("Invalid task ID: {id}"),
-                })
-            })?;
-            let task = task_engine.remove_tag(task_id, &tag).await?;
-            Ok(())
+    }
+    if bulk.dry_run {
+        println!("Dry run: no changes saved.");
+    }
+    Ok(())
+}
+
+/// Ask a yes/no question on stdin, treating anything but `y` or `yes` as no
+fn confirm(question: &str) -> bool {
+    use std::io::Write;
+
+    print!("{question} [y/N] ");
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Rewrite the older `modify <id> <field> <value>` form as `field:value`
+fn legacy_modification(modifications: Vec<String>) -> Vec<String> {
+    match modifications.as_slice() {
+        [field, value]
+            if !field.contains(':')
+                && !field.starts_with(['+', '-'])
+                && ModifyAttribute::from_name(field).is_some() =>
+        {
+            vec![format!("{field}:{value}")]
         }
+        _ => modifications,
     }
 }
@@ -9,7 +9,8 @@ use edda::cli::{
 };
 use edda::core::{EddaConfig, EddaResult};
 use edda::handlers::{
-    handle_doc_commands, handle_github_sync_commands, handle_query_command, handle_state_commands,
+    handle_context_commands, handle_doc_commands, handle_github_sync_commands,
+    handle_project_commands, handle_query_command, handle_search_command, handle_state_commands,
     handle_sync_commands, handle_system_commands, handle_task_commands,
 };
 
@@ -37,6 +38,28 @@ async fn main() {
         }
         Some(Commands::Doc { subcommand }) => handle_doc_commands(subcommand).await,
         Some(Commands::State { subcommand }) => handle_state_commands(subcommand).await,
+        Some(Commands::Project { subcommand }) => {
+            handle_project_commands(
+                subcommand,
+                &config,
+                cli.format.as_deref().unwrap_or("text"),
+                cli.quiet,
+            )
+            .await
+        }
+        Some(Commands::Context { subcommand }) => {
+            handle_context_commands(subcommand, cli.config, &config).await
+        }
+        Some(Commands::Search { terms, limit }) => {
+            handle_search_command(
+                &terms.join(" "),
+                limit,
+                &config,
+                cli.format.as_deref().unwrap_or("text"),
+                cli.quiet,
+            )
+            .await
+        }
         Some(Commands::Query { query }) => handle_query_command(query).await,
         Some(Commands::System { subcommand }) => handle_system_commands(subcommand, &config).await,
         Some(Commands::Sync { subcommand }) => {
@@ -0,0 +1,294 @@
+use crate::core::{EddaError, EddaResult, StorageError};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Timestamp in backup file names; millisecond precision keeps backups
+/// taken in quick succession apart
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+const TIMESTAMP_LEN: usize = "20260101-000000-000".len();
+
+/// How a backup file is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupFormat {
+    /// A plain SQLite database
+    Plain,
+    /// Gzip-compressed
+    Compressed,
+    /// Gzip-compressed, then encrypted
+    Encrypted,
+}
+
+impl BackupFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BackupFormat::Plain => "db",
+            BackupFormat::Compressed => "db.gz",
+            BackupFormat::Encrypted => "db.gz.enc",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackupFormat::Plain => "plain",
+            BackupFormat::Compressed => "compressed",
+            BackupFormat::Encrypted => "encrypted",
+        }
+    }
+}
+
+impl std::fmt::Display for BackupFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// A database backup in the backups directory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub created: DateTime<Utc>,
+    /// Why the backup was taken, such as `pre-restore`; `None` for regular
+    /// backups, which are the only ones rotated
+    pub label: Option<String>,
+    pub format: BackupFormat,
+    pub size: u64,
+}
+
+impl BackupInfo {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+fn backup_error(message: String) -> EddaError {
+    EddaError::Storage(StorageError::Backup { message })
+}
+
+/// File name for a backup of the database named `stem`, e.g.
+/// `edda-pre-restore-20261017-093000-250.db.gz`
+pub fn backup_file_name(
+    stem: &str,
+    label: Option<&str>,
+    created: DateTime<Utc>,
+    format: BackupFormat,
+) -> String {
+    let label = label.map(|label| format!("-{label}")).unwrap_or_default();
+    format!(
+        "{stem}{label}-{}.{}",
+        created.format(TIMESTAMP_FORMAT),
+        format.extension()
+    )
+}
+
+/// Read a backup file name made by [`backup_file_name`] for the database
+/// named `stem`
+fn parse_backup_file_name(
+    stem: &str,
+    file_name: &str,
+) -> Option<(Option<String>, DateTime<Utc>, BackupFormat)> {
+    let (name, format) = [
+        BackupFormat::Encrypted,
+        BackupFormat::Compressed,
+        BackupFormat::Plain,
+    ]
+    .into_iter()
+    .find_map(|format| {
+        let name = file_name.strip_suffix(format.extension())?;
+        Some((name.strip_suffix('.')?, format))
+    })?;
+    let split = name.len().checked_sub(TIMESTAMP_LEN)?;
+    let (prefix, timestamp) = (name.get(..split)?, name.get(split..)?);
+    let created = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+    let prefix = prefix.strip_suffix('-')?;
+    let label = if prefix == stem {
+        None
+    } else {
+        Some(prefix.strip_prefix(stem)?.strip_prefix('-')?.to_string())
+    };
+    Some((label, created, format))
+}
+
+/// Backups of the database named `stem` in `dir`, newest first
+pub fn list_backups(dir: &Path, stem: &str) -> EddaResult<Vec<BackupInfo>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(dir)
+        .map_err(|e| backup_error(format!("Failed to read backup directory: {e}")))?;
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| backup_error(format!("Failed to read backup directory: {e}")))?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some((label, created, format)) = parse_backup_file_name(stem, &file_name) else {
+            continue;
+        };
+        let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        backups.push(BackupInfo {
+            path: entry.path(),
+            created,
+            label,
+            format,
+            size,
+        });
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created));
+    Ok(backups)
+}
+
+/// Regular backups that fall outside the retention policy
+///
+/// The newest backup of each of the last `keep_daily` days and of each of
+/// the last `keep_weekly` ISO weeks is kept, and so is the newest backup
+/// overall whatever the policy; labelled backups are never expired.
+pub fn expired_backups(
+    backups: &[BackupInfo],
+    keep_daily: usize,
+    keep_weekly: usize,
+) -> Vec<&BackupInfo> {
+    let mut regular: Vec<&BackupInfo> = backups
+        .iter()
+        .filter(|backup| backup.label.is_none())
+        .collect();
+    regular.sort_by_key(|backup| std::cmp::Reverse(backup.created));
+
+    let mut days = Vec::new();
+    let mut weeks = Vec::new();
+    regular
+        .into_iter()
+        .enumerate()
+        .filter(|(index, backup)| {
+            let mut keep = *index == 0;
+            let day = backup.created.date_naive();
+            if days.len() < keep_daily && !days.contains(&day) {
+                days.push(day);
+                keep = true;
+            }
+            let week = chrono::Datelike::iso_week(&backup.created);
+            if weeks.len() < keep_weekly && !weeks.contains(&week) {
+                weeks.push(week);
+                keep = true;
+            }
+            !keep
+        })
+        .map(|(_, backup)| backup)
+        .collect()
+}
+
+/// Delete regular backups in `dir` that fall outside the retention policy,
+/// returning them
+pub fn prune_backups(
+    dir: &Path,
+    stem: &str,
+    keep_daily: usize,
+    keep_weekly: usize,
+) -> EddaResult<Vec<BackupInfo>> {
+    let backups = list_backups(dir, stem)?;
+    let expired: Vec<BackupInfo> = expired_backups(&backups, keep_daily, keep_weekly)
+        .into_iter()
+        .cloned()
+        .collect();
+    for backup in &expired {
+        fs::remove_file(&backup.path).map_err(|e| {
+            backup_error(format!(
+                "Failed to remove expired backup {}: {e}",
+                backup.path.display()
+            ))
+        })?;
+    }
+    Ok(expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn backup(created: DateTime<Utc>, label: Option<&str>) -> BackupInfo {
+        BackupInfo {
+            path: PathBuf::from(backup_file_name(
+                "edda",
+                label,
+                created,
+                BackupFormat::Plain,
+            )),
+            created,
+            label: label.map(str::to_string),
+            format: BackupFormat::Plain,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_backup_file_names_round_trip() {
+        let created = Utc.with_ymd_and_hms(2026, 10, 17, 9, 30, 0).unwrap();
+        let name = backup_file_name("edda", None, created, BackupFormat::Compressed);
+        assert_eq!(name, "edda-20261017-093000-000.db.gz");
+        assert_eq!(
+            parse_backup_file_name("edda", &name),
+            Some((None, created, BackupFormat::Compressed))
+        );
+
+        let name = backup_file_name(
+            "edda",
+            Some("pre-migration-v9"),
+            created,
+            BackupFormat::Encrypted,
+        );
+        assert_eq!(
+            parse_backup_file_name("edda", &name),
+            Some((
+                Some("pre-migration-v9".to_string()),
+                created,
+                BackupFormat::Encrypted
+            ))
+        );
+        assert_eq!(parse_backup_file_name("other", &name), None);
+        assert_eq!(parse_backup_file_name("edda", "edda.db"), None);
+    }
+
+    #[test]
+    fn test_expired_backups() {
+        // Two backups a day, at 06:00 and 18:00, over three weeks ending on
+        // Saturday 2026-10-17
+        let end = Utc.with_ymd_and_hms(2026, 10, 17, 18, 0, 0).unwrap();
+        let mut backups: Vec<BackupInfo> = (0..42)
+            .map(|half_days| backup(end - chrono::Duration::hours(12 * half_days), None))
+            .collect();
+        backups.push(backup(
+            end - chrono::Duration::days(30),
+            Some("pre-restore"),
+        ));
+
+        let expired = expired_backups(&backups, 3, 2);
+        let kept: Vec<DateTime<Utc>> = backups
+            .iter()
+            .filter(|backup| !expired.contains(backup))
+            .map(|backup| backup.created)
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                end,
+                end - chrono::Duration::days(1),
+                end - chrono::Duration::days(2),
+                // Newest of the previous ISO week (Sunday 2026-10-11)
+                end - chrono::Duration::days(6),
+                end - chrono::Duration::days(30),
+            ]
+        );
+
+        // The newest backup survives even a policy keeping nothing
+        let expired = expired_backups(&backups, 0, 0);
+        assert_eq!(expired.len(), 41);
+    }
+}
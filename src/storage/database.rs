@@ -1,3 +1,6 @@
+use super::backup::{self, BackupFormat};
+use super::encryption::{self, BackupKey};
+use super::migration::{self, MigrationDirection};
 use crate::core::EddaResult;
 use chrono::Utc;
 use flate2::Compression;
@@ -5,13 +8,21 @@ use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use sqlx::{Row, SqlitePool, sqlite::SqlitePoolOptions};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 #[cfg(test)]
 use sqlx::Row as _;
 
 /// Initialize the SQLite database
-pub async fn init_database(db_path: PathBuf) -> EddaResult<()> {
+///
+/// An existing database is backed up to `backup_dir` before pending
+/// migrations run, encrypted with `backup_key` if one is given.
+pub async fn init_database(
+    db_path: PathBuf,
+    backup_dir: &Path,
+    backup_key: Option<&BackupKey>,
+) -> EddaResult<()> {
     // Create database directory if it doesn't exist
     if let Some(parent) = db_path.parent() {
         std::fs::create_dir_all(parent).map_err(|e| {
@@ -45,8 +56,12 @@ pub async fn init_database(db_path: PathBuf) -> EddaResult<()> {
             })
         })?;
 
-    // Run migrations
-    run_migrations(&pool).await?;
+    // Run migrations, backing up an existing database first
+    let plan = migration::plan_up(&pool, None).await?;
+    if !plan.is_empty() && migration::current_version(&pool).await? > 0 {
+        backup_before_migration(&pool, &db_path, backup_dir, backup_key).await?;
+    }
+    migration::apply_migrations(&pool, &plan, MigrationDirection::Up).await?;
 
     // Validate database integrity
     validate_database_integrity(&pool).await?;
@@ -54,30 +69,27 @@ pub async fn init_database(db_path: PathBuf) -> EddaResult<()> {
     Ok(())
 }
 
-/// Create a backup of the database
-pub async fn create_backup(db_path: &PathBuf, backup_path: &PathBuf) -> EddaResult<()> {
-    // Ensure backup directory exists
-    if let Some(parent) = backup_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| {
+/// Write a consistent snapshot of the database to `backup_path`
+///
+/// `VACUUM INTO` reads through SQLite, so the copy is consistent even while
+/// other connections are writing, unlike copying the file.
+async fn snapshot_database(pool: &SqlitePool, backup_path: &Path) -> EddaResult<()> {
+    create_backup_dir(backup_path)?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(backup_path.to_string_lossy().into_owned())
+        .execute(pool)
+        .await
+        .map_err(|e| {
             crate::core::EddaError::Storage(crate::core::StorageError::Backup {
-                message: format!("Failed to create backup directory: {e}"),
+                message: format!("Failed to create backup: {e}"),
             })
         })?;
-    }
-
-    // Simple file copy for backup
-    fs::copy(db_path, backup_path).map_err(|e| {
-        crate::core::EddaError::Storage(crate::core::StorageError::Backup {
-            message: format!("Failed to create backup: {e}"),
-        })
-    })?;
 
     Ok(())
 }
 
-/// Create a compressed backup of the database
-pub async fn create_compressed_backup(db_path: &PathBuf, backup_path: &PathBuf) -> EddaResult<()> {
-    // Ensure backup directory exists
+/// Create the directory a backup is written to
+fn create_backup_dir(backup_path: &Path) -> EddaResult<()> {
     if let Some(parent) = backup_path.parent() {
         fs::create_dir_all(parent).map_err(|e| {
             crate::core::EddaError::Storage(crate::core::StorageError::Backup {
@@ -85,9 +97,113 @@ pub async fn create_compressed_backup(db_path: &PathBuf, backup_path: &PathBuf)
             })
         })?;
     }
+    Ok(())
+}
+
+/// Create a backup of the database
+pub async fn create_backup(db_path: &Path, backup_path: &Path) -> EddaResult<()> {
+    let pool = connect_pool(db_path).await?;
+    let result = snapshot_database(&pool, backup_path).await;
+    pool.close().await;
+    result
+}
+
+/// Create a compressed backup of the database
+pub async fn create_compressed_backup(db_path: &Path, backup_path: &Path) -> EddaResult<()> {
+    // Snapshot privately, then compress the snapshot into place
+    let staging = staging_dir(db_path)?;
+    let snapshot_path = staging.path().join("snapshot.db");
+    create_backup(db_path, &snapshot_path).await?;
+    create_backup_dir(backup_path)?;
+    compress_file(&snapshot_path, backup_path)
+}
 
-    // Read the database file
-    let mut input = fs::File::open(db_path).map_err(|e| {
+/// Create a compressed backup of the database, encrypted with `key`
+pub async fn create_encrypted_backup(
+    db_path: &Path,
+    backup_path: &Path,
+    key: &BackupKey,
+) -> EddaResult<()> {
+    let pool = connect_pool(db_path).await?;
+    let result = write_backup(&pool, db_path, backup_path, Some(key)).await;
+    pool.close().await;
+    result
+}
+
+/// Snapshot the database at `db_path` to `backup_path`, compressed and
+/// encrypted if a `key` is given
+///
+/// Backups may sit on shared storage, so the plaintext stages of an
+/// encrypted backup are kept out of the backups directory.
+async fn write_backup(
+    pool: &SqlitePool,
+    db_path: &Path,
+    backup_path: &Path,
+    key: Option<&BackupKey>,
+) -> EddaResult<()> {
+    let Some(key) = key else {
+        return snapshot_database(pool, backup_path).await;
+    };
+
+    let staging = staging_dir(db_path)?;
+    let snapshot_path = staging.path().join("snapshot.db");
+    let compressed_path = staging.path().join("snapshot.db.gz");
+    snapshot_database(pool, &snapshot_path).await?;
+    compress_file(&snapshot_path, &compressed_path)?;
+    create_backup_dir(backup_path)?;
+    encryption::encrypt_file(&compressed_path, backup_path, key)
+}
+
+/// A private directory next to the database for the intermediate files of
+/// a backup, removed with everything in it when dropped
+fn staging_dir(db_path: &Path) -> EddaResult<tempfile::TempDir> {
+    let parent = db_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    tempfile::Builder::new()
+        .prefix(".edda-backup-")
+        .tempdir_in(parent)
+        .map_err(|e| {
+            crate::core::EddaError::Storage(crate::core::StorageError::Backup {
+                message: format!("Failed to create staging directory: {e}"),
+            })
+        })
+}
+
+/// Path for a labelled backup of the database at `db_path` in `dir`
+fn labelled_backup_path(
+    dir: &Path,
+    db_path: &Path,
+    label: &str,
+    key: Option<&BackupKey>,
+) -> PathBuf {
+    let stem = db_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "edda".to_string());
+    let format = if key.is_some() {
+        BackupFormat::Encrypted
+    } else {
+        BackupFormat::Plain
+    };
+    dir.join(backup::backup_file_name(
+        &stem,
+        Some(label),
+        Utc::now(),
+        format,
+    ))
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn compress_file(source: &Path, backup_path: &Path) -> EddaResult<()> {
+    let mut input = fs::File::open(source).map_err(|e| {
         crate::core::EddaError::Storage(crate::core::StorageError::Backup {
             message: format!("Failed to open database for backup: {e}"),
         })
@@ -117,7 +233,19 @@ pub async fn create_compressed_backup(db_path: &PathBuf, backup_path: &PathBuf)
 }
 
 /// Restore database from backup
-pub async fn restore_backup(backup_path: &PathBuf, db_path: &PathBuf) -> EddaResult<()> {
+///
+/// Compressed and encrypted backups are recognised by their contents;
+/// encrypted ones are opened with `key`. The backup is checked and migrated
+/// to the current schema in a staging copy first, so a bad backup leaves
+/// the database untouched. The current database is then saved to
+/// `safety_dir` as a `pre-restore` backup, encrypted with `key` if one is
+/// given, and its path returned.
+pub async fn restore_backup(
+    backup_path: &Path,
+    db_path: &Path,
+    safety_dir: &Path,
+    key: Option<&BackupKey>,
+) -> EddaResult<Option<PathBuf>> {
     // Check if backup file exists
     if !backup_path.exists() {
         return Err(crate::core::EddaError::Storage(
@@ -127,43 +255,102 @@ pub async fn restore_backup(backup_path: &PathBuf, db_path: &PathBuf) -> EddaRes
         ));
     }
 
-    // Determine if backup is compressed
-    let is_compressed = backup_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext == "gz")
-        .unwrap_or(false);
+    let staged_path = with_suffix(db_path, ".restore");
+    let staged = stage_restore(backup_path, &staged_path, key).await;
+    if let Err(e) = staged {
+        let _ = fs::remove_file(&staged_path);
+        return Err(e);
+    }
 
-    if is_compressed {
-        restore_compressed_backup(backup_path, db_path).await?;
+    let safety_path = if db_path.exists() {
+        let path = labelled_backup_path(safety_dir, db_path, "pre-restore", key);
+        let pool = connect_pool(db_path).await?;
+        let result = write_backup(&pool, db_path, &path, key).await;
+        pool.close().await;
+        result?;
+        Some(path)
     } else {
-        // Simple file copy for uncompressed backup
-        fs::copy(backup_path, db_path).map_err(|e| {
-            crate::core::EddaError::Storage(crate::core::StorageError::Backup {
-                message: format!("Failed to restore backup: {e}"),
+        None
+    };
+
+    // A leftover journal belongs to the database being replaced
+    for suffix in ["-journal", "-wal", "-shm"] {
+        let _ = fs::remove_file(with_suffix(db_path, suffix));
+    }
+    fs::rename(&staged_path, db_path).map_err(|e| {
+        crate::core::EddaError::Storage(crate::core::StorageError::Backup {
+            message: format!("Failed to restore backup: {e}"),
+        })
+    })?;
+
+    Ok(safety_path)
+}
+
+/// Whether the file at `path` starts with the gzip magic bytes
+fn is_gzip(path: &Path) -> EddaResult<bool> {
+    let mut magic = [0u8; 2];
+    let read = fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic));
+    match read {
+        Ok(()) => Ok(magic == [0x1f, 0x8b]),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(crate::core::EddaError::Storage(
+            crate::core::StorageError::Backup {
+                message: format!("Failed to read backup: {e}"),
+            },
+        )),
+    }
+}
+
+/// Copy a backup to `staged_path`, check it and bring its schema up to date
+async fn stage_restore(
+    backup_path: &Path,
+    staged_path: &Path,
+    key: Option<&BackupKey>,
+) -> EddaResult<()> {
+    let decrypted_path = with_suffix(staged_path, ".decrypted");
+    let unpacked = async {
+        let source = if encryption::is_encrypted(backup_path)? {
+            encryption::decrypt_file(backup_path, &decrypted_path, key)?;
+            decrypted_path.as_path()
+        } else {
+            backup_path
+        };
+
+        if is_gzip(source)? {
+            restore_compressed_backup(source, staged_path).await
+        } else {
+            fs::copy(source, staged_path).map(|_| ()).map_err(|e| {
+                crate::core::EddaError::Storage(crate::core::StorageError::Backup {
+                    message: format!("Failed to restore backup: {e}"),
+                })
             })
-        })?;
+        }
     }
+    .await;
+    let _ = fs::remove_file(&decrypted_path);
+    unpacked?;
 
-    // Validate restored database
-    let database_url = format!("sqlite:{}", db_path.to_string_lossy());
     let pool = SqlitePoolOptions::new()
         .max_connections(1)
-        .connect(&database_url)
+        .connect(&format!("sqlite:{}", staged_path.to_string_lossy()))
         .await
         .map_err(|e| {
             crate::core::EddaError::Storage(crate::core::StorageError::Connection {
-                message: format!("Failed to connect to restored database: {e}"),
+                message: format!("Failed to open backup: {e}"),
             })
         })?;
-
-    validate_database_integrity(&pool).await?;
-
-    Ok(())
+    let result = async {
+        migration::check_schema_version(&pool).await?;
+        run_migrations(&pool).await?;
+        validate_database_integrity(&pool).await
+    }
+    .await;
+    pool.close().await;
+    result
 }
 
 /// Restore from compressed backup
-async fn restore_compressed_backup(backup_path: &PathBuf, db_path: &PathBuf) -> EddaResult<()> {
+async fn restore_compressed_backup(backup_path: &Path, db_path: &Path) -> EddaResult<()> {
     let input = fs::File::open(backup_path).map_err(|e| {
         crate::core::EddaError::Storage(crate::core::StorageError::Backup {
             message: format!("Failed to open compressed backup: {e}"),
@@ -218,7 +405,18 @@ async fn validate_database_integrity(pool: &SqlitePool) -> EddaResult<()> {
             })
         })?;
 
-    let required_tables = vec!["tasks", "documents", "state", "schema_version"];
+    let required_tables = vec![
+        "tasks",
+        "documents",
+        "state",
+        "schema_version",
+        "time_intervals",
+        "operations",
+        "task_tags",
+        "task_annotations",
+        "task_dependencies",
+        "search_index",
+    ];
     let existing_tables: Vec<String> = tables.iter().map(|row| row.get("name")).collect();
 
     for required_table in required_tables {
@@ -298,191 +496,38 @@ pub fn validate_task_data(
     Ok(())
 }
 
-/// Run database migrations
+/// Bring the database schema up to the latest version
 pub async fn run_migrations(pool: &SqlitePool) -> EddaResult<()> {
-    // Create schema version table to track migrations
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS schema_version (
-            version INTEGER PRIMARY KEY,
-            applied_at TEXT NOT NULL CHECK (datetime(applied_at) IS NOT NULL),
-            description TEXT NOT NULL
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-            message: format!("Failed to create schema_version table: {e}"),
-        })
-    })?;
-
-    // Get current schema version
-    let current_version = sqlx::query("SELECT MAX(version) as version FROM schema_version")
-        .fetch_optional(pool)
-        .await
-        .map_err(|e| {
-            crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-                message: format!("Failed to get current schema version: {e}"),
-            })
-        })?
-        .map(|row| row.get::<i32, _>("version"))
-        .unwrap_or(0);
-
-    // Apply migrations in order
-    let migrations = vec![(
-        1,
-        "Initial schema with tasks, documents, state tables, constraints, and indexes",
-    )];
-
-    for (version, description) in migrations {
-        if version > current_version {
-            apply_migration(pool, version, description).await?;
-        }
-    }
-
-    Ok(())
-}
-
-/// Apply a specific migration
-async fn apply_migration(pool: &SqlitePool, version: i32, description: &str) -> EddaResult<()> {
-    match version {
-        1 => apply_migration_1(pool).await?,
-        _ => {
-            return Err(crate::core::EddaError::Storage(
-                crate::core::StorageError::Migration {
-                    message: format!("Unknown migration version: {}", version),
-                },
-            ));
-        }
-    }
-
-    // Record the migration
-    sqlx::query("INSERT INTO schema_version (version, applied_at, description) VALUES (?, ?, ?)")
-        .bind(version)
-        .bind(Utc::now().to_rfc3339())
-        .bind(description)
-        .execute(pool)
-        .await
-        .map_err(|e| {
-            crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-                message: format!("Failed to record migration {}: {}", version, e),
-            })
-        })?;
-
-    Ok(())
+    let plan = migration::plan_up(pool, None).await?;
+    migration::apply_migrations(pool, &plan, MigrationDirection::Up).await
 }
 
-/// Migration 1: Complete schema with constraints and indexes
-async fn apply_migration_1(pool: &SqlitePool) -> EddaResult<()> {
-    // Create tasks table with Taskwarrior-compatible fields and constraints
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tasks (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            uuid TEXT UNIQUE NOT NULL,
-            description TEXT NOT NULL CHECK (length(trim(description)) > 0),
-            status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'completed', 'deleted', 'waiting')),
-            priority TEXT CHECK (priority IN ('H', 'M', 'L') OR (priority GLOB '[0-9]' AND CAST(priority AS INTEGER) BETWEEN 0 AND 9)),
-            project TEXT,
-            due_date TEXT CHECK (due_date IS NULL OR datetime(due_date) IS NOT NULL),
-            scheduled_date TEXT CHECK (scheduled_date IS NULL OR datetime(scheduled_date) IS NOT NULL),
-            start_date TEXT CHECK (start_date IS NULL OR datetime(start_date) IS NOT NULL),
-            end_date TEXT CHECK (end_date IS NULL OR datetime(end_date) IS NOT NULL),
-            entry_date TEXT NOT NULL CHECK (datetime(entry_date) IS NOT NULL),
-            modified_date TEXT NOT NULL CHECK (datetime(modified_date) IS NOT NULL),
-            tags TEXT CHECK (tags IS NULL OR json_valid(tags)),
-            annotations TEXT CHECK (annotations IS NULL OR json_valid(annotations)),
-            parent_uuid TEXT CHECK (parent_uuid IS NULL OR length(parent_uuid) = 36),
-            depends TEXT CHECK (depends IS NULL OR json_valid(depends)),
-            recurrence TEXT,
-            effort INTEGER CHECK (effort IS NULL OR effort >= 0),
-            effort_spent INTEGER CHECK (effort_spent IS NULL OR effort_spent >= 0),
-            created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
-            updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-        message: format!("Failed to create tasks table: {}", e),
-    }))?;
-
-    // Create documents table with constraints
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS documents (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            uuid TEXT UNIQUE NOT NULL,
-            title TEXT NOT NULL CHECK (length(trim(title)) > 0),
-            content TEXT,
-            content_type TEXT,
-            file_path TEXT,
-            metadata TEXT CHECK (metadata IS NULL OR json_valid(metadata)),
-            created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
-            updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-            message: format!("Failed to create documents table: {}", e),
-        })
-    })?;
-
-    // Create state table with constraints
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS state (
-            key TEXT PRIMARY KEY CHECK (length(trim(key)) > 0),
-            value TEXT NOT NULL,
-            created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
-            updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-            message: format!("Failed to create state table: {}", e),
-        })
-    })?;
-
-    // Create all indexes for optimal performance
-    let indexes = vec![
-        "CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_project ON tasks(project)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_uuid ON tasks(uuid)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_parent_uuid ON tasks(parent_uuid)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_entry_date ON tasks(entry_date)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_modified_date ON tasks(modified_date)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_status_priority ON tasks(status, priority)",
-        "CREATE INDEX IF NOT EXISTS idx_tasks_project_status ON tasks(project, status)",
-        "CREATE INDEX IF NOT EXISTS idx_documents_uuid ON documents(uuid)",
-        "CREATE INDEX IF NOT EXISTS idx_documents_content_type ON documents(content_type)",
-        "CREATE INDEX IF NOT EXISTS idx_state_key ON state(key)",
-    ];
-
-    for index_sql in indexes {
-        sqlx::query(index_sql).execute(pool).await.map_err(|e| {
-            crate::core::EddaError::Storage(crate::core::StorageError::Migration {
-                message: format!("Failed to create index: {}", e),
-            })
-        })?;
-    }
-
-    Ok(())
+/// Copy the database to `backup_dir` before migrating it, encrypted with
+/// `key` if one is given, returning the backup path
+pub async fn backup_before_migration(
+    pool: &SqlitePool,
+    db_path: &Path,
+    backup_dir: &Path,
+    key: Option<&BackupKey>,
+) -> EddaResult<PathBuf> {
+    let version = migration::current_version(pool).await?;
+    let backup_path = labelled_backup_path(
+        backup_dir,
+        db_path,
+        &format!("pre-migration-v{version}"),
+        key,
+    );
+    write_backup(pool, db_path, &backup_path, key).await?;
+
+    tracing::info!(
+        "Backed up schema version {version} database to {}",
+        backup_path.display()
+    );
+    Ok(backup_path)
 }
 
-/// Get a database connection pool
-pub async fn get_pool(db_path: PathBuf) -> EddaResult<SqlitePool> {
+/// Connect to the database without checking its schema version
+pub async fn connect_pool(db_path: &Path) -> EddaResult<SqlitePool> {
     let database_url = format!("sqlite:{}", db_path.to_string_lossy());
 
     Ok(SqlitePoolOptions::new()
@@ -494,11 +539,27 @@ pub async fn get_pool(db_path: PathBuf) -> EddaResult<SqlitePool> {
         })?)
 }
 
+/// Get a database connection pool, refusing a database created by a newer
+/// version of edda
+pub async fn get_pool(db_path: PathBuf) -> EddaResult<SqlitePool> {
+    let pool = connect_pool(&db_path).await?;
+    migration::check_schema_version(&pool).await?;
+    Ok(pool)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serial_test::serial;
 
+    /// Apply migrations up to and including `version`
+    async fn migrate_to(pool: &SqlitePool, version: i32) {
+        let plan = migration::plan_up(pool, Some(version)).await.unwrap();
+        migration::apply_migrations(pool, &plan, MigrationDirection::Up)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_init_database() {
@@ -600,4 +661,166 @@ mod tests {
         assert!(index_names.contains(&"idx_documents_content_type".to_string()));
         assert!(index_names.contains(&"idx_state_key".to_string()));
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_migration_2_preserves_tasks_and_allows_in_progress() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        // Bring the database to schema version 1 with an existing task
+        migrate_to(&pool, 1).await;
+
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO tasks (uuid, description, status, entry_date, modified_date, tags, annotations, depends, created_at, updated_at)
+             VALUES ('00000000-0000-0000-0000-000000000001', 'Existing task', 'pending', ?, ?, '[]', '[]', '[]', ?, ?)",
+        )
+        .bind(&now)
+        .bind(&now)
+        .bind(&now)
+        .bind(&now)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Version 1 rejects in_progress
+        let result = sqlx::query("UPDATE tasks SET status = 'in_progress'")
+            .execute(&pool)
+            .await;
+        assert!(result.is_err());
+
+        run_migrations(&pool).await.unwrap();
+
+        let version: i32 = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(version >= 2);
+
+        let description: String = sqlx::query_scalar("SELECT description FROM tasks WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(description, "Existing task");
+
+        sqlx::query("UPDATE tasks SET status = 'in_progress'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let result = sqlx::query("UPDATE tasks SET status = 'bogus'")
+            .execute(&pool)
+            .await;
+        assert!(result.is_err());
+
+        // Indexes are recreated on the rebuilt table
+        let index_count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'idx_tasks_status'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(index_count, 1);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_migration_10_moves_json_columns_into_tables() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        migrate_to(&pool, 9).await;
+
+        let now = Utc::now().to_rfc3339();
+        let dependency = "00000000-0000-0000-0000-000000000002";
+        sqlx::query(
+            "INSERT INTO tasks (uuid, description, status, entry_date, modified_date, tags, annotations, depends, created_at, updated_at)
+             VALUES ('00000000-0000-0000-0000-000000000001', 'Existing task', 'pending', ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&now)
+        .bind(&now)
+        .bind(r#"["web","bug"]"#)
+        .bind(format!(
+            r#"[{{"entry":"{now}","description":"First"}},{{"entry":"{now}","description":"Second","author":"agent-a"}}]"#
+        ))
+        .bind(format!(r#"["{dependency}"]"#))
+        .bind(&now)
+        .bind(&now)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run_migrations(&pool).await.unwrap();
+
+        let tags: Vec<String> = sqlx::query_scalar("SELECT tag FROM task_tags ORDER BY tag")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, ["bug", "web"]);
+        let notes: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+            "SELECT position, description, author FROM task_annotations ORDER BY position",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            notes,
+            [
+                (0, "First".to_string(), None),
+                (1, "Second".to_string(), Some("agent-a".to_string()))
+            ]
+        );
+        let depends: String = sqlx::query_scalar("SELECT depends_on FROM task_dependencies")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(depends, dependency);
+
+        // The JSON columns are gone
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info('tasks')")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert!(!columns.iter().any(|c| c == "tags" || c == "depends"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_operations_journal_is_append_only() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        run_migrations(&pool).await.unwrap();
+
+        sqlx::query(
+            "INSERT INTO operations (task_uuid, operation, created_at) VALUES (?, 'create', ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(
+            sqlx::query("UPDATE operations SET operation = 'update'")
+                .execute(&pool)
+                .await
+                .is_err()
+        );
+        assert!(
+            sqlx::query("DELETE FROM operations")
+                .execute(&pool)
+                .await
+                .is_err()
+        );
+    }
 }
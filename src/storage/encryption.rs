@@ -0,0 +1,381 @@
+use crate::core::{EddaError, EddaResult, StorageError};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, pbkdf2};
+use std::fs;
+use std::io::{Read, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding a backup passphrase
+pub const PASSPHRASE_ENV: &str = "EDDA_BACKUP_PASSPHRASE";
+/// Environment variable naming a backup key file
+pub const KEY_FILE_ENV: &str = "EDDA_BACKUP_KEY_FILE";
+
+const MAGIC: &[u8; 7] = b"EDDAENC";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 1 + 4 + SALT_LEN + NONCE_PREFIX_LEN + 4;
+const SALT_LEN: usize = 16;
+const NONCE_PREFIX_LEN: usize = NONCE_LEN - 5;
+const TAG_LEN: usize = 16;
+const CHUNK_SIZE: u32 = 64 * 1024;
+/// OWASP's recommendation for PBKDF2-HMAC-SHA256
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// Key files shorter than this are refused
+const MIN_KEY_FILE_LEN: usize = 32;
+const HKDF_INFO: &[u8] = b"edda backup key v1";
+
+/// How the archive key is derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kdf {
+    /// PBKDF2-HMAC-SHA256 over a passphrase
+    Passphrase = 1,
+    /// HKDF-SHA256 over the contents of a key file
+    KeyFile = 2,
+}
+
+/// Key material for encrypting and decrypting backups
+pub enum BackupKey {
+    Passphrase(String),
+    KeyFile(Vec<u8>),
+}
+
+impl std::fmt::Debug for BackupKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupKey::Passphrase(_) => write!(f, "BackupKey::Passphrase(..)"),
+            BackupKey::KeyFile(_) => write!(f, "BackupKey::KeyFile(..)"),
+        }
+    }
+}
+
+fn encryption_error(message: String) -> EddaError {
+    EddaError::Storage(StorageError::Backup { message })
+}
+
+impl BackupKey {
+    /// Load key material: the key file named by `EDDA_BACKUP_KEY_FILE`, else
+    /// `key_file`, else the passphrase in `EDDA_BACKUP_PASSPHRASE`
+    pub fn load(key_file: Option<&Path>) -> EddaResult<Option<BackupKey>> {
+        let key_file = std::env::var_os(KEY_FILE_ENV)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| key_file.map(Path::to_path_buf));
+        if let Some(path) = key_file {
+            return Self::from_key_file(&path).map(Some);
+        }
+        Ok(std::env::var(PASSPHRASE_ENV)
+            .ok()
+            .filter(|passphrase| !passphrase.is_empty())
+            .map(BackupKey::Passphrase))
+    }
+
+    pub fn from_key_file(path: &Path) -> EddaResult<BackupKey> {
+        let bytes = fs::read(path).map_err(|e| {
+            encryption_error(format!(
+                "Failed to read backup key file {}: {e}",
+                path.display()
+            ))
+        })?;
+        if bytes.len() < MIN_KEY_FILE_LEN {
+            return Err(encryption_error(format!(
+                "Backup key file {} is too short (at least {MIN_KEY_FILE_LEN} bytes needed)",
+                path.display()
+            )));
+        }
+        Ok(BackupKey::KeyFile(bytes))
+    }
+
+    fn kdf(&self) -> Kdf {
+        match self {
+            BackupKey::Passphrase(_) => Kdf::Passphrase,
+            BackupKey::KeyFile(_) => Kdf::KeyFile,
+        }
+    }
+
+    fn derive(&self, salt: &[u8], iterations: u32) -> EddaResult<LessSafeKey> {
+        let mut key = [0u8; 32];
+        match self {
+            BackupKey::Passphrase(passphrase) => {
+                let iterations = NonZeroU32::new(iterations)
+                    .ok_or_else(|| encryption_error("Invalid backup header".to_string()))?;
+                pbkdf2::derive(
+                    pbkdf2::PBKDF2_HMAC_SHA256,
+                    iterations,
+                    salt,
+                    passphrase.as_bytes(),
+                    &mut key,
+                );
+            }
+            BackupKey::KeyFile(bytes) => {
+                hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+                    .extract(bytes)
+                    .expand(&[HKDF_INFO], &AES_256_GCM)
+                    .and_then(|okm| okm.fill(&mut key))
+                    .map_err(|_| encryption_error("Failed to derive backup key".to_string()))?;
+            }
+        }
+        let key = UnboundKey::new(&AES_256_GCM, &key)
+            .map_err(|_| encryption_error("Failed to derive backup key".to_string()))?;
+        Ok(LessSafeKey::new(key))
+    }
+}
+
+/// Whether the file at `path` is an encrypted backup
+pub fn is_encrypted(path: &Path) -> EddaResult<bool> {
+    let mut magic = [0u8; MAGIC.len()];
+    let mut file = fs::File::open(path)
+        .map_err(|e| encryption_error(format!("Failed to open backup: {e}")))?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(encryption_error(format!("Failed to read backup: {e}"))),
+    }
+}
+
+fn chunk_nonce(prefix: &[u8], index: u32, last: bool) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[NONCE_LEN - 1] = u8::from(last);
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Read until `buffer` holds `limit` bytes or the input ends
+fn read_chunk(reader: &mut impl Read, buffer: &mut Vec<u8>, limit: usize) -> std::io::Result<()> {
+    buffer.clear();
+    reader.take(limit as u64).read_to_end(buffer)?;
+    Ok(())
+}
+
+/// Encrypt `source` into a new archive at `dest`
+///
+/// The archive is a header followed by `source` in AES-256-GCM sealed
+/// chunks:
+///
+/// ```text
+/// magic "EDDAENC" | format version u8 | kdf u8 | iterations u32 BE |
+/// salt [16] | nonce prefix [7] | chunk size u32 BE | chunks...
+/// ```
+///
+/// Each chunk's nonce is the prefix, its index and a flag set on the last
+/// chunk, and the header is authenticated with every chunk, so reordered,
+/// truncated or edited archives fail to open.
+pub fn encrypt_file(source: &Path, dest: &Path, key: &BackupKey) -> EddaResult<()> {
+    encrypt_with_iterations(source, dest, key, PBKDF2_ITERATIONS)
+}
+
+fn encrypt_with_iterations(
+    source: &Path,
+    dest: &Path,
+    key: &BackupKey,
+    iterations: u32,
+) -> EddaResult<()> {
+    let io_error = |e: std::io::Error| encryption_error(format!("Failed to encrypt backup: {e}"));
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    rng.fill(&mut salt)
+        .and_then(|()| rng.fill(&mut nonce_prefix))
+        .map_err(|_| encryption_error("Failed to generate backup salt".to_string()))?;
+
+    let kdf = key.kdf();
+    let iterations = if kdf == Kdf::Passphrase {
+        iterations
+    } else {
+        0
+    };
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    header.push(kdf as u8);
+    header.extend_from_slice(&iterations.to_be_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce_prefix);
+    header.extend_from_slice(&CHUNK_SIZE.to_be_bytes());
+    let sealing_key = key.derive(&salt, iterations)?;
+
+    let mut input = std::io::BufReader::new(fs::File::open(source).map_err(io_error)?);
+    let mut output = std::io::BufWriter::new(fs::File::create(dest).map_err(io_error)?);
+    output.write_all(&header).map_err(io_error)?;
+
+    // A chunk shorter than CHUNK_SIZE is the last one, so input that fills
+    // its chunks exactly ends with an empty chunk
+    let mut buffer = Vec::with_capacity(CHUNK_SIZE as usize + TAG_LEN);
+    let mut index: u32 = 0;
+    loop {
+        read_chunk(&mut input, &mut buffer, CHUNK_SIZE as usize).map_err(io_error)?;
+        let last = buffer.len() < CHUNK_SIZE as usize;
+        sealing_key
+            .seal_in_place_append_tag(
+                chunk_nonce(&nonce_prefix, index, last),
+                Aad::from(&header),
+                &mut buffer,
+            )
+            .map_err(|_| encryption_error("Failed to encrypt backup".to_string()))?;
+        output.write_all(&buffer).map_err(io_error)?;
+        if last {
+            break;
+        }
+        index = index
+            .checked_add(1)
+            .ok_or_else(|| encryption_error("Backup is too large to encrypt".to_string()))?;
+    }
+    output.flush().map_err(io_error)?;
+
+    Ok(())
+}
+
+/// Decrypt the archive at `source` into `dest`
+pub fn decrypt_file(source: &Path, dest: &Path, key: Option<&BackupKey>) -> EddaResult<()> {
+    let io_error = |e: std::io::Error| encryption_error(format!("Failed to decrypt backup: {e}"));
+    let mut input = std::io::BufReader::new(fs::File::open(source).map_err(io_error)?);
+
+    let mut header = [0u8; HEADER_LEN];
+    input
+        .read_exact(&mut header)
+        .map_err(|_| encryption_error("Backup is not an encrypted edda backup".to_string()))?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(encryption_error(
+            "Backup is not an encrypted edda backup".to_string(),
+        ));
+    }
+    let mut fields = &header[MAGIC.len()..];
+    let mut take = |len: usize| {
+        let (field, rest) = fields.split_at(len);
+        fields = rest;
+        field
+    };
+    let version = take(1)[0];
+    if version != FORMAT_VERSION {
+        return Err(encryption_error(format!(
+            "Unsupported encrypted backup format version {version}; upgrade edda to restore it"
+        )));
+    }
+    let kdf = match take(1)[0] {
+        1 => Kdf::Passphrase,
+        2 => Kdf::KeyFile,
+        other => {
+            return Err(encryption_error(format!(
+                "Unsupported backup key derivation {other}"
+            )));
+        }
+    };
+    let iterations = u32::from_be_bytes(take(4).try_into().expect("4-byte field"));
+    if iterations > 100 * PBKDF2_ITERATIONS {
+        return Err(encryption_error(
+            "Invalid encrypted backup header".to_string(),
+        ));
+    }
+    let salt = take(SALT_LEN).to_vec();
+    let nonce_prefix = take(NONCE_PREFIX_LEN).to_vec();
+    let chunk_size = u32::from_be_bytes(take(4).try_into().expect("4-byte field")) as usize;
+    if chunk_size == 0 || chunk_size > 16 * 1024 * 1024 {
+        return Err(encryption_error(
+            "Invalid encrypted backup header".to_string(),
+        ));
+    }
+
+    let key = match (kdf, key) {
+        (_, Some(key)) if key.kdf() == kdf => key,
+        (Kdf::Passphrase, _) => {
+            return Err(encryption_error(format!(
+                "Backup is encrypted with a passphrase; set {PASSPHRASE_ENV} to restore it"
+            )));
+        }
+        (Kdf::KeyFile, _) => {
+            return Err(encryption_error(format!(
+                "Backup is encrypted with a key file; set {KEY_FILE_ENV} or backup.key_file \
+                 to restore it"
+            )));
+        }
+    };
+    let opening_key = key.derive(&salt, iterations)?;
+
+    let mut output = std::io::BufWriter::new(fs::File::create(dest).map_err(io_error)?);
+    let mut buffer = Vec::with_capacity(chunk_size + TAG_LEN);
+    let mut index: u32 = 0;
+    loop {
+        read_chunk(&mut input, &mut buffer, chunk_size + TAG_LEN).map_err(io_error)?;
+        let last = buffer.len() < chunk_size + TAG_LEN;
+        let plaintext = opening_key
+            .open_in_place(
+                chunk_nonce(&nonce_prefix, index, last),
+                Aad::from(&header),
+                &mut buffer,
+            )
+            .map_err(|_| {
+                encryption_error(
+                    "Failed to decrypt backup: wrong key, or the file is damaged".to_string(),
+                )
+            })?;
+        output.write_all(plaintext).map_err(io_error)?;
+        if last {
+            break;
+        }
+        index = index
+            .checked_add(1)
+            .ok_or_else(|| encryption_error("Invalid encrypted backup".to_string()))?;
+    }
+    output.flush().map_err(io_error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_encrypt_and_decrypt_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let plain = temp.path().join("plain");
+        let sealed = temp.path().join("sealed");
+        let opened = temp.path().join("opened");
+        // Exactly two chunks, so the archive ends with an empty chunk
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2).map(|i| (i % 251) as u8).collect();
+        fs::write(&plain, &data).unwrap();
+
+        let key = BackupKey::KeyFile(vec![7; 32]);
+        encrypt_file(&plain, &sealed, &key).unwrap();
+        assert!(is_encrypted(&sealed).unwrap());
+        assert!(!is_encrypted(&plain).unwrap());
+        assert_eq!(
+            fs::metadata(&sealed).unwrap().len() as usize,
+            HEADER_LEN + data.len() + 3 * TAG_LEN
+        );
+        decrypt_file(&sealed, &opened, Some(&key)).unwrap();
+        assert_eq!(fs::read(&opened).unwrap(), data);
+
+        // Wrong key, missing key and truncation are all refused
+        let wrong = BackupKey::KeyFile(vec![8; 32]);
+        assert!(decrypt_file(&sealed, &opened, Some(&wrong)).is_err());
+        let error = decrypt_file(&sealed, &opened, None).unwrap_err();
+        assert!(error.to_string().contains(KEY_FILE_ENV));
+        let mut bytes = fs::read(&sealed).unwrap();
+        bytes.truncate(bytes.len() - TAG_LEN);
+        fs::write(&sealed, &bytes).unwrap();
+        assert!(decrypt_file(&sealed, &opened, Some(&key)).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_encryption() {
+        let temp = TempDir::new().unwrap();
+        let plain = temp.path().join("plain");
+        let sealed = temp.path().join("sealed");
+        let opened = temp.path().join("opened");
+        fs::write(&plain, b"customer details").unwrap();
+
+        // Few iterations keep the test fast; the count is read from the header
+        let key = BackupKey::Passphrase("correct horse".to_string());
+        encrypt_with_iterations(&plain, &sealed, &key, 1_000).unwrap();
+        let bytes = fs::read(&sealed).unwrap();
+        assert!(!bytes.windows(8).any(|window| window == b"customer"));
+
+        decrypt_file(&sealed, &opened, Some(&key)).unwrap();
+        assert_eq!(fs::read(&opened).unwrap(), b"customer details");
+        let wrong = BackupKey::Passphrase("battery staple".to_string());
+        assert!(decrypt_file(&sealed, &opened, Some(&wrong)).is_err());
+    }
+}
@@ -0,0 +1,1041 @@
+use crate::core::{EddaError, EddaResult, StorageError};
+use chrono::Utc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::collections::BTreeMap;
+
+/// One schema change, applied or reverted in its own transaction
+#[derive(Debug)]
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    /// Statements applying the change, in order
+    pub up: &'static [&'static str],
+    /// Statements reverting it, in order; empty if it can't be reverted
+    pub down: &'static [&'static str],
+}
+
+impl Migration {
+    /// SHA-256 of the `up` statements, recorded when the migration is applied
+    /// so later edits to an applied migration are caught
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha256::new();
+        for statement in self.up {
+            hasher.update(statement.as_bytes());
+            hasher.update(b"\n;\n");
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn reversible(&self) -> bool {
+        !self.down.is_empty()
+    }
+}
+
+/// Where a migration stands in a database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but its statements have changed since
+    Modified,
+    /// Applied by a newer build that this one doesn't know
+    Unknown,
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+            MigrationState::Unknown => "unknown",
+        };
+        f.pad(name)
+    }
+}
+
+/// A migration and its state in a database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationStatus {
+    pub version: i32,
+    pub description: String,
+    pub state: MigrationState,
+    pub applied_at: Option<String>,
+}
+
+/// Direction to move the schema in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationDirection {
+    Up,
+    Down,
+}
+
+/// A migration as recorded in `schema_version`
+struct AppliedMigration {
+    applied_at: String,
+    description: String,
+    checksum: Option<String>,
+}
+
+/// Newest schema version this build knows
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+fn migration_error(message: String) -> EddaError {
+    EddaError::Storage(StorageError::Migration { message })
+}
+
+/// Create the `schema_version` table, add the checksum column to one made
+/// before checksums were tracked, and record checksums missing from it
+async fn ensure_schema_table(pool: &SqlitePool) -> EddaResult<()> {
+    let error = |e: sqlx::Error| migration_error(format!("Failed to prepare schema_version: {e}"));
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            applied_at TEXT NOT NULL CHECK (datetime(applied_at) IS NOT NULL),
+            description TEXT NOT NULL,
+            checksum TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .map_err(error)?;
+
+    let has_checksum: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('schema_version') WHERE name = 'checksum'",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(error)?;
+    if !has_checksum {
+        sqlx::query("ALTER TABLE schema_version ADD COLUMN checksum TEXT")
+            .execute(pool)
+            .await
+            .map_err(error)?;
+    }
+
+    // Migrations applied before checksums were tracked are trusted as they are
+    for migration in MIGRATIONS {
+        sqlx::query(
+            "UPDATE schema_version SET checksum = ? WHERE version = ? AND checksum IS NULL",
+        )
+        .bind(migration.checksum())
+        .bind(migration.version)
+        .execute(pool)
+        .await
+        .map_err(error)?;
+    }
+    Ok(())
+}
+
+/// Migrations recorded in `schema_version`, read without changing the
+/// table; checksums are `None` where they were never recorded
+async fn applied_migrations(pool: &SqlitePool) -> EddaResult<BTreeMap<i32, AppliedMigration>> {
+    let error = |e: sqlx::Error| migration_error(format!("Failed to read schema_version: {e}"));
+    let exists: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(error)?;
+    if !exists {
+        return Ok(BTreeMap::new());
+    }
+    let has_checksum: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('schema_version') WHERE name = 'checksum'",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(error)?;
+    let checksum = if has_checksum {
+        "checksum"
+    } else {
+        "NULL AS checksum"
+    };
+    let rows = sqlx::query(&format!(
+        "SELECT version, applied_at, description, {checksum} FROM schema_version"
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(error)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.get("version"),
+                AppliedMigration {
+                    applied_at: row.get("applied_at"),
+                    description: row.get("description"),
+                    checksum: row.get("checksum"),
+                },
+            )
+        })
+        .collect())
+}
+
+/// Every known migration and every migration recorded in the database,
+/// oldest first
+pub async fn migration_status(pool: &SqlitePool) -> EddaResult<Vec<MigrationStatus>> {
+    let mut applied = applied_migrations(pool).await?;
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let record = applied.remove(&migration.version);
+            // Migrations applied before checksums were tracked are trusted
+            // as they are
+            let state = match &record {
+                None => MigrationState::Pending,
+                Some(AppliedMigration {
+                    checksum: Some(checksum),
+                    ..
+                }) if *checksum != migration.checksum() => MigrationState::Modified,
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+                applied_at: record.map(|record| record.applied_at),
+            }
+        })
+        .collect();
+    statuses.extend(
+        applied
+            .into_iter()
+            .map(|(version, record)| MigrationStatus {
+                version,
+                description: record.description,
+                state: MigrationState::Unknown,
+                applied_at: Some(record.applied_at),
+            }),
+    );
+    Ok(statuses)
+}
+
+/// Highest schema version applied to the database, 0 for a new one
+pub async fn current_version(pool: &SqlitePool) -> EddaResult<i32> {
+    Ok(applied_migrations(pool)
+        .await?
+        .keys()
+        .next_back()
+        .copied()
+        .unwrap_or(0))
+}
+
+/// Refuse a database whose schema is newer than this build knows, without
+/// writing to it
+pub async fn check_schema_version(pool: &SqlitePool) -> EddaResult<()> {
+    let error = |e: sqlx::Error| migration_error(format!("Failed to read schema version: {e}"));
+    let exists: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(error)?;
+    if !exists {
+        return Ok(());
+    }
+    let version: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await
+        .map_err(error)?;
+    check_not_newer(version.unwrap_or(0))
+}
+
+fn check_not_newer(version: i32) -> EddaResult<()> {
+    let latest = latest_version();
+    if version > latest {
+        return Err(migration_error(format!(
+            "Database schema version {version} is newer than this build of edda supports \
+             ({latest}); upgrade edda to use it"
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the database can be migrated at all: not newer than this
+/// build and with no applied migration changed since
+async fn validated_status(pool: &SqlitePool) -> EddaResult<Vec<MigrationStatus>> {
+    let statuses = migration_status(pool).await?;
+    for status in &statuses {
+        match status.state {
+            MigrationState::Unknown => check_not_newer(status.version)?,
+            MigrationState::Modified => {
+                return Err(migration_error(format!(
+                    "Migration {} was changed after it was applied (checksum mismatch)",
+                    status.version
+                )));
+            }
+            MigrationState::Applied | MigrationState::Pending => {}
+        }
+    }
+    Ok(statuses)
+}
+
+/// Migrations to apply, oldest first, to bring the database up to `target`
+/// (the latest version if `None`)
+pub async fn plan_up(
+    pool: &SqlitePool,
+    target: Option<i32>,
+) -> EddaResult<Vec<&'static Migration>> {
+    let target = target.unwrap_or_else(latest_version);
+    check_not_newer(target)?;
+    let statuses = validated_status(pool).await?;
+    Ok(MIGRATIONS
+        .iter()
+        .zip(&statuses)
+        .filter(|(migration, status)| {
+            migration.version <= target && status.state == MigrationState::Pending
+        })
+        .map(|(migration, _)| migration)
+        .collect())
+}
+
+/// Migrations to revert, newest first, to take the database back to
+/// `target`
+pub async fn plan_down(pool: &SqlitePool, target: i32) -> EddaResult<Vec<&'static Migration>> {
+    if target < 0 {
+        return Err(migration_error(format!(
+            "Invalid target schema version: {target}"
+        )));
+    }
+    let statuses = validated_status(pool).await?;
+    let plan: Vec<&'static Migration> = MIGRATIONS
+        .iter()
+        .zip(&statuses)
+        .rev()
+        .filter(|(migration, status)| {
+            migration.version > target && status.state == MigrationState::Applied
+        })
+        .map(|(migration, _)| migration)
+        .collect();
+    if let Some(migration) = plan.iter().find(|migration| !migration.reversible()) {
+        return Err(migration_error(format!(
+            "Migration {} ({}) can't be reverted",
+            migration.version, migration.description
+        )));
+    }
+    Ok(plan)
+}
+
+/// Apply or revert planned migrations, each in its own transaction together
+/// with its `schema_version` record
+pub async fn apply_migrations(
+    pool: &SqlitePool,
+    plan: &[&Migration],
+    direction: MigrationDirection,
+) -> EddaResult<()> {
+    ensure_schema_table(pool).await?;
+    for migration in plan {
+        let error = |e: sqlx::Error| {
+            let action = match direction {
+                MigrationDirection::Up => "apply",
+                MigrationDirection::Down => "revert",
+            };
+            migration_error(format!(
+                "Failed to {action} migration {} ({}): {e}",
+                migration.version, migration.description
+            ))
+        };
+
+        let mut tx = pool.begin().await.map_err(error)?;
+        let statements = match direction {
+            MigrationDirection::Up => migration.up,
+            MigrationDirection::Down => migration.down,
+        };
+        for statement in statements {
+            sqlx::query(statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(error)?;
+        }
+
+        let record = match direction {
+            MigrationDirection::Up => sqlx::query(
+                "INSERT INTO schema_version (version, applied_at, description, checksum) \
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(migration.version)
+            .bind(Utc::now().to_rfc3339())
+            .bind(migration.description)
+            .bind(migration.checksum()),
+            MigrationDirection::Down => {
+                sqlx::query("DELETE FROM schema_version WHERE version = ?").bind(migration.version)
+            }
+        };
+        record.execute(&mut *tx).await.map_err(error)?;
+
+        tx.commit().await.map_err(error)?;
+    }
+    Ok(())
+}
+
+/// Every schema migration, oldest first
+///
+/// Applied migrations must never be edited: their checksums are recorded,
+/// and a changed migration stops further migrations. Add a new one instead.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial schema with tasks, documents, state tables, constraints, and indexes",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                uuid TEXT UNIQUE NOT NULL,
+                description TEXT NOT NULL CHECK (length(trim(description)) > 0),
+                status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'completed', 'deleted', 'waiting')),
+                priority TEXT CHECK (priority IN ('H', 'M', 'L') OR (priority GLOB '[0-9]' AND CAST(priority AS INTEGER) BETWEEN 0 AND 9)),
+                project TEXT,
+                due_date TEXT CHECK (due_date IS NULL OR datetime(due_date) IS NOT NULL),
+                scheduled_date TEXT CHECK (scheduled_date IS NULL OR datetime(scheduled_date) IS NOT NULL),
+                start_date TEXT CHECK (start_date IS NULL OR datetime(start_date) IS NOT NULL),
+                end_date TEXT CHECK (end_date IS NULL OR datetime(end_date) IS NOT NULL),
+                entry_date TEXT NOT NULL CHECK (datetime(entry_date) IS NOT NULL),
+                modified_date TEXT NOT NULL CHECK (datetime(modified_date) IS NOT NULL),
+                tags TEXT CHECK (tags IS NULL OR json_valid(tags)),
+                annotations TEXT CHECK (annotations IS NULL OR json_valid(annotations)),
+                parent_uuid TEXT CHECK (parent_uuid IS NULL OR length(parent_uuid) = 36),
+                depends TEXT CHECK (depends IS NULL OR json_valid(depends)),
+                recurrence TEXT,
+                effort INTEGER CHECK (effort IS NULL OR effort >= 0),
+                effort_spent INTEGER CHECK (effort_spent IS NULL OR effort_spent >= 0),
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
+                updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS documents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                uuid TEXT UNIQUE NOT NULL,
+                title TEXT NOT NULL CHECK (length(trim(title)) > 0),
+                content TEXT,
+                content_type TEXT,
+                file_path TEXT,
+                metadata TEXT CHECK (metadata IS NULL OR json_valid(metadata)),
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
+                updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS state (
+                key TEXT PRIMARY KEY CHECK (length(trim(key)) > 0),
+                value TEXT NOT NULL,
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
+                updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_project ON tasks(project)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_uuid ON tasks(uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_parent_uuid ON tasks(parent_uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_entry_date ON tasks(entry_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_modified_date ON tasks(modified_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_status_priority ON tasks(status, priority)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_project_status ON tasks(project, status)",
+            "CREATE INDEX IF NOT EXISTS idx_documents_uuid ON documents(uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_documents_content_type ON documents(content_type)",
+            "CREATE INDEX IF NOT EXISTS idx_state_key ON state(key)",
+        ],
+        down: &[],
+    },
+    // SQLite can't alter a CHECK constraint in place, so the table is copied
+    // into a new one with the updated constraint
+    Migration {
+        version: 2,
+        description: "Allow in_progress in the tasks status constraint",
+        up: &[
+            r#"
+            CREATE TABLE tasks_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                uuid TEXT UNIQUE NOT NULL,
+                description TEXT NOT NULL CHECK (length(trim(description)) > 0),
+                status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'in_progress', 'completed', 'deleted', 'waiting')),
+                priority TEXT CHECK (priority IN ('H', 'M', 'L') OR (priority GLOB '[0-9]' AND CAST(priority AS INTEGER) BETWEEN 0 AND 9)),
+                project TEXT,
+                due_date TEXT CHECK (due_date IS NULL OR datetime(due_date) IS NOT NULL),
+                scheduled_date TEXT CHECK (scheduled_date IS NULL OR datetime(scheduled_date) IS NOT NULL),
+                start_date TEXT CHECK (start_date IS NULL OR datetime(start_date) IS NOT NULL),
+                end_date TEXT CHECK (end_date IS NULL OR datetime(end_date) IS NOT NULL),
+                entry_date TEXT NOT NULL CHECK (datetime(entry_date) IS NOT NULL),
+                modified_date TEXT NOT NULL CHECK (datetime(modified_date) IS NOT NULL),
+                tags TEXT CHECK (tags IS NULL OR json_valid(tags)),
+                annotations TEXT CHECK (annotations IS NULL OR json_valid(annotations)),
+                parent_uuid TEXT CHECK (parent_uuid IS NULL OR length(parent_uuid) = 36),
+                depends TEXT CHECK (depends IS NULL OR json_valid(depends)),
+                recurrence TEXT,
+                effort INTEGER CHECK (effort IS NULL OR effort >= 0),
+                effort_spent INTEGER CHECK (effort_spent IS NULL OR effort_spent >= 0),
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
+                updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
+            )
+            "#,
+            "INSERT INTO tasks_new SELECT * FROM tasks",
+            "DROP TABLE tasks",
+            "ALTER TABLE tasks_new RENAME TO tasks",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_project ON tasks(project)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_uuid ON tasks(uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_parent_uuid ON tasks(parent_uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_entry_date ON tasks(entry_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_modified_date ON tasks(modified_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_status_priority ON tasks(status, priority)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_project_status ON tasks(project, status)",
+        ],
+        down: &[],
+    },
+    // Rebuilds the tasks table again for the `recurring` status
+    Migration {
+        version: 3,
+        description: "Recurring task templates with until date and recurrence mask tracking",
+        up: &[
+            r#"
+            CREATE TABLE tasks_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                uuid TEXT UNIQUE NOT NULL,
+                description TEXT NOT NULL CHECK (length(trim(description)) > 0),
+                status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'in_progress', 'completed', 'deleted', 'waiting', 'recurring')),
+                priority TEXT CHECK (priority IN ('H', 'M', 'L') OR (priority GLOB '[0-9]' AND CAST(priority AS INTEGER) BETWEEN 0 AND 9)),
+                project TEXT,
+                due_date TEXT CHECK (due_date IS NULL OR datetime(due_date) IS NOT NULL),
+                scheduled_date TEXT CHECK (scheduled_date IS NULL OR datetime(scheduled_date) IS NOT NULL),
+                start_date TEXT CHECK (start_date IS NULL OR datetime(start_date) IS NOT NULL),
+                end_date TEXT CHECK (end_date IS NULL OR datetime(end_date) IS NOT NULL),
+                entry_date TEXT NOT NULL CHECK (datetime(entry_date) IS NOT NULL),
+                modified_date TEXT NOT NULL CHECK (datetime(modified_date) IS NOT NULL),
+                tags TEXT CHECK (tags IS NULL OR json_valid(tags)),
+                annotations TEXT CHECK (annotations IS NULL OR json_valid(annotations)),
+                parent_uuid TEXT CHECK (parent_uuid IS NULL OR length(parent_uuid) = 36),
+                depends TEXT CHECK (depends IS NULL OR json_valid(depends)),
+                recurrence TEXT,
+                effort INTEGER CHECK (effort IS NULL OR effort >= 0),
+                effort_spent INTEGER CHECK (effort_spent IS NULL OR effort_spent >= 0),
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
+                updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL),
+                until_date TEXT CHECK (until_date IS NULL OR datetime(until_date) IS NOT NULL),
+                recurrence_mask TEXT CHECK (recurrence_mask IS NULL OR recurrence_mask NOT GLOB '*[^-+XW]*'),
+                recurrence_index INTEGER CHECK (recurrence_index IS NULL OR recurrence_index >= 0)
+            )
+            "#,
+            r#"
+            INSERT INTO tasks_new (
+                id, uuid, description, status, priority, project, due_date, scheduled_date,
+                start_date, end_date, entry_date, modified_date, tags, annotations, parent_uuid,
+                depends, recurrence, effort, effort_spent, created_at, updated_at
+            )
+            SELECT
+                id, uuid, description, status, priority, project, due_date, scheduled_date,
+                start_date, end_date, entry_date, modified_date, tags, annotations, parent_uuid,
+                depends, recurrence, effort, effort_spent, created_at, updated_at
+            FROM tasks
+            "#,
+            "DROP TABLE tasks",
+            "ALTER TABLE tasks_new RENAME TO tasks",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_until_date ON tasks(until_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_project ON tasks(project)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_uuid ON tasks(uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_parent_uuid ON tasks(parent_uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks(priority)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_entry_date ON tasks(entry_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_modified_date ON tasks(modified_date)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_status_priority ON tasks(status, priority)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_project_status ON tasks(project, status)",
+        ],
+        down: &[],
+    },
+    // Intervals reference tasks by UUID without a foreign key so the tasks
+    // table can still be rebuilt; tasks already in progress get an open
+    // interval from their start date
+    Migration {
+        version: 4,
+        description: "Time intervals for start/stop time tracking",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS time_intervals (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_uuid TEXT NOT NULL CHECK (length(task_uuid) = 36),
+                start_time TEXT NOT NULL CHECK (datetime(start_time) IS NOT NULL),
+                end_time TEXT CHECK (end_time IS NULL OR (datetime(end_time) IS NOT NULL AND datetime(end_time) >= datetime(start_time))),
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL),
+                updated_at TEXT NOT NULL CHECK (datetime(updated_at) IS NOT NULL)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_time_intervals_task_uuid ON time_intervals(task_uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_time_intervals_start_time ON time_intervals(start_time)",
+            // At most one running interval per task
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_time_intervals_open ON time_intervals(task_uuid) WHERE end_time IS NULL",
+            r#"
+            INSERT INTO time_intervals (task_uuid, start_time, end_time, created_at, updated_at)
+            SELECT uuid, start_date, NULL, datetime('now'), datetime('now')
+            FROM tasks
+            WHERE status = 'in_progress' AND start_date IS NOT NULL
+            "#,
+        ],
+        down: &["DROP TABLE time_intervals"],
+    },
+    // Triggers reject updates and deletes so history can only grow; undo is
+    // itself recorded as a new entry pointing at the one it reverted
+    Migration {
+        version: 5,
+        description: "Append-only operations journal for undo and history",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS operations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_uuid TEXT NOT NULL CHECK (length(task_uuid) = 36),
+                operation TEXT NOT NULL CHECK (operation IN ('create', 'update', 'delete')),
+                before_json TEXT CHECK (before_json IS NULL OR json_valid(before_json)),
+                after_json TEXT CHECK (after_json IS NULL OR json_valid(after_json)),
+                undoes INTEGER REFERENCES operations(id),
+                created_at TEXT NOT NULL CHECK (datetime(created_at) IS NOT NULL)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_operations_task_uuid ON operations(task_uuid)",
+            "CREATE INDEX IF NOT EXISTS idx_operations_undoes ON operations(undoes)",
+            r#"
+            CREATE TRIGGER IF NOT EXISTS operations_no_update BEFORE UPDATE ON operations
+            BEGIN
+                SELECT RAISE(ABORT, 'operations journal is append-only');
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS operations_no_delete BEFORE DELETE ON operations
+            BEGIN
+                SELECT RAISE(ABORT, 'operations journal is append-only');
+            END
+            "#,
+        ],
+        down: &[
+            "DROP TRIGGER operations_no_update",
+            "DROP TRIGGER operations_no_delete",
+            "DROP TABLE operations",
+        ],
+    },
+    Migration {
+        version: 6,
+        description: "Wait date column for hiding tasks until a date",
+        up: &[
+            "ALTER TABLE tasks ADD COLUMN wait_date TEXT CHECK (wait_date IS NULL OR datetime(wait_date) IS NOT NULL)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_wait_date ON tasks(wait_date)",
+        ],
+        down: &[
+            "DROP INDEX idx_tasks_wait_date",
+            "ALTER TABLE tasks DROP COLUMN wait_date",
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "User-defined attribute values column",
+        up: &[
+            "ALTER TABLE tasks ADD COLUMN udas TEXT NOT NULL DEFAULT '{}' CHECK (json_valid(udas))",
+        ],
+        down: &["ALTER TABLE tasks DROP COLUMN udas"],
+    },
+    Migration {
+        version: 8,
+        description: "Sync backend identifier column",
+        up: &[
+            "ALTER TABLE tasks ADD COLUMN sync_id TEXT",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_tasks_sync_id ON tasks(sync_id)",
+        ],
+        down: &[
+            "DROP INDEX idx_tasks_sync_id",
+            "ALTER TABLE tasks DROP COLUMN sync_id",
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "Claim owner and lease expiry columns for work queues",
+        up: &[
+            "ALTER TABLE tasks ADD COLUMN claimed_by TEXT",
+            "ALTER TABLE tasks ADD COLUMN lease_expires TEXT CHECK (lease_expires IS NULL OR datetime(lease_expires) IS NOT NULL)",
+            "CREATE INDEX IF NOT EXISTS idx_tasks_claimed_by ON tasks(claimed_by)",
+        ],
+        down: &[
+            "DROP INDEX idx_tasks_claimed_by",
+            "ALTER TABLE tasks DROP COLUMN claimed_by",
+            "ALTER TABLE tasks DROP COLUMN lease_expires",
+        ],
+    },
+    // Each table is keyed by task UUID and indexed the other way round as
+    // well, so "tasks with tag X" and "tasks depending on U" are index
+    // lookups. As with intervals there is no foreign key. Existing JSON is
+    // copied over before the columns are dropped.
+    Migration {
+        version: 10,
+        description: "Relational tag, annotation and dependency tables",
+        up: &[
+            r#"
+            CREATE TABLE IF NOT EXISTS task_tags (
+                task_uuid TEXT NOT NULL CHECK (length(task_uuid) = 36),
+                tag TEXT NOT NULL CHECK (length(tag) > 0),
+                PRIMARY KEY (task_uuid, tag)
+            ) WITHOUT ROWID
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_task_tags_tag ON task_tags(tag, task_uuid)",
+            r#"
+            CREATE TABLE IF NOT EXISTS task_annotations (
+                task_uuid TEXT NOT NULL CHECK (length(task_uuid) = 36),
+                position INTEGER NOT NULL CHECK (position >= 0),
+                entry TEXT NOT NULL CHECK (datetime(entry) IS NOT NULL),
+                description TEXT NOT NULL,
+                author TEXT,
+                PRIMARY KEY (task_uuid, position)
+            ) WITHOUT ROWID
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS task_dependencies (
+                task_uuid TEXT NOT NULL CHECK (length(task_uuid) = 36),
+                depends_on TEXT NOT NULL CHECK (length(depends_on) = 36),
+                PRIMARY KEY (task_uuid, depends_on)
+            ) WITHOUT ROWID
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_task_dependencies_depends_on ON task_dependencies(depends_on, task_uuid)",
+            r#"
+            INSERT OR IGNORE INTO task_tags (task_uuid, tag)
+            SELECT tasks.uuid, tag.value
+            FROM tasks, json_each(COALESCE(tasks.tags, '[]')) AS tag
+            WHERE tag.type = 'text' AND length(tag.value) > 0
+            "#,
+            r#"
+            INSERT INTO task_annotations (task_uuid, position, entry, description, author)
+            SELECT tasks.uuid, note.key, json_extract(note.value, '$.entry'),
+                json_extract(note.value, '$.description'), json_extract(note.value, '$.author')
+            FROM tasks, json_each(COALESCE(tasks.annotations, '[]')) AS note
+            WHERE note.type = 'object'
+            "#,
+            r#"
+            INSERT OR IGNORE INTO task_dependencies (task_uuid, depends_on)
+            SELECT tasks.uuid, dep.value
+            FROM tasks, json_each(COALESCE(tasks.depends, '[]')) AS dep
+            WHERE dep.type = 'text'
+            "#,
+            "ALTER TABLE tasks DROP COLUMN tags",
+            "ALTER TABLE tasks DROP COLUMN annotations",
+            "ALTER TABLE tasks DROP COLUMN depends",
+        ],
+        down: &[
+            "ALTER TABLE tasks ADD COLUMN tags TEXT CHECK (tags IS NULL OR json_valid(tags))",
+            "ALTER TABLE tasks ADD COLUMN annotations TEXT CHECK (annotations IS NULL OR json_valid(annotations))",
+            "ALTER TABLE tasks ADD COLUMN depends TEXT CHECK (depends IS NULL OR json_valid(depends))",
+            r#"
+            UPDATE tasks SET
+                tags = (SELECT json_group_array(tag) FROM task_tags WHERE task_uuid = tasks.uuid),
+                annotations = (
+                    SELECT json_group_array(json_patch(
+                        json_object('entry', entry, 'description', description),
+                        CASE WHEN author IS NULL THEN '{}' ELSE json_object('author', author) END
+                    ) ORDER BY position)
+                    FROM task_annotations WHERE task_uuid = tasks.uuid
+                ),
+                depends = (SELECT json_group_array(depends_on) FROM task_dependencies WHERE task_uuid = tasks.uuid)
+            "#,
+            "DROP TABLE task_tags",
+            "DROP TABLE task_annotations",
+            "DROP TABLE task_dependencies",
+        ],
+    },
+    // Tasks keep their ID as rowid and documents take the negated ID, so the
+    // triggers find a row without scanning the index. The triggers go with
+    // the tasks table, so a migration rebuilding it must recreate them.
+    Migration {
+        version: 11,
+        description: "Full-text search index over tasks and documents",
+        up: &[
+            "CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(entity UNINDEXED, title, body, tokenize = 'porter unicode61 remove_diacritics 2')",
+            r#"
+            INSERT INTO search_index (rowid, entity, title, body)
+            SELECT id, 'task', description, COALESCE((
+                SELECT group_concat(description, char(10) ORDER BY position)
+                FROM task_annotations WHERE task_uuid = tasks.uuid
+            ), '')
+            FROM tasks
+            "#,
+            r#"
+            INSERT INTO search_index (rowid, entity, title, body)
+            SELECT -id, 'document', title, COALESCE(content, '') FROM documents
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_tasks_insert AFTER INSERT ON tasks BEGIN
+                INSERT INTO search_index (rowid, entity, title, body)
+                VALUES (NEW.id, 'task', NEW.description, COALESCE((
+                    SELECT group_concat(description, char(10) ORDER BY position)
+                    FROM task_annotations WHERE task_uuid = NEW.uuid
+                ), ''));
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_tasks_update AFTER UPDATE OF description ON tasks BEGIN
+                UPDATE search_index SET title = NEW.description WHERE rowid = NEW.id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_tasks_delete AFTER DELETE ON tasks BEGIN
+                DELETE FROM search_index WHERE rowid = OLD.id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_annotations_insert AFTER INSERT ON task_annotations BEGIN
+                UPDATE search_index SET body = COALESCE((
+                    SELECT group_concat(description, char(10) ORDER BY position)
+                    FROM task_annotations WHERE task_uuid = NEW.task_uuid
+                ), '')
+                WHERE rowid = (SELECT id FROM tasks WHERE uuid = NEW.task_uuid);
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_annotations_update AFTER UPDATE ON task_annotations BEGIN
+                UPDATE search_index SET body = COALESCE((
+                    SELECT group_concat(description, char(10) ORDER BY position)
+                    FROM task_annotations WHERE task_uuid = NEW.task_uuid
+                ), '')
+                WHERE rowid = (SELECT id FROM tasks WHERE uuid = NEW.task_uuid);
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_annotations_delete AFTER DELETE ON task_annotations BEGIN
+                UPDATE search_index SET body = COALESCE((
+                    SELECT group_concat(description, char(10) ORDER BY position)
+                    FROM task_annotations WHERE task_uuid = OLD.task_uuid
+                ), '')
+                WHERE rowid = (SELECT id FROM tasks WHERE uuid = OLD.task_uuid);
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_documents_insert AFTER INSERT ON documents BEGIN
+                INSERT INTO search_index (rowid, entity, title, body)
+                VALUES (-NEW.id, 'document', NEW.title, COALESCE(NEW.content, ''));
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_documents_update AFTER UPDATE OF title, content ON documents BEGIN
+                UPDATE search_index SET title = NEW.title, body = COALESCE(NEW.content, '')
+                WHERE rowid = -NEW.id;
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS search_documents_delete AFTER DELETE ON documents BEGIN
+                DELETE FROM search_index WHERE rowid = -OLD.id;
+            END
+            "#,
+        ],
+        down: &[
+            "DROP TRIGGER search_tasks_insert",
+            "DROP TRIGGER search_tasks_update",
+            "DROP TRIGGER search_tasks_delete",
+            "DROP TRIGGER search_annotations_insert",
+            "DROP TRIGGER search_annotations_update",
+            "DROP TRIGGER search_annotations_delete",
+            "DROP TRIGGER search_documents_insert",
+            "DROP TRIGGER search_documents_update",
+            "DROP TRIGGER search_documents_delete",
+            "DROP TABLE search_index",
+        ],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn migrate(pool: &SqlitePool, target: Option<i32>) {
+        let plan = plan_up(pool, target).await.unwrap();
+        apply_migrations(pool, &plan, MigrationDirection::Up)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_registry_is_ordered() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i32 + 1);
+            assert!(!migration.up.is_empty());
+        }
+        assert_eq!(latest_version(), MIGRATIONS.len() as i32);
+        assert_eq!(MIGRATIONS[0].checksum(), MIGRATIONS[0].checksum());
+        assert_ne!(MIGRATIONS[0].checksum(), MIGRATIONS[1].checksum());
+    }
+
+    #[tokio::test]
+    async fn test_status_and_modified_migrations() {
+        let pool = memory_pool().await;
+        migrate(&pool, Some(3)).await;
+
+        let statuses = migration_status(&pool).await.unwrap();
+        assert_eq!(statuses.len(), MIGRATIONS.len());
+        assert!(
+            statuses[..3]
+                .iter()
+                .all(|status| status.state == MigrationState::Applied)
+        );
+        assert!(
+            statuses[3..]
+                .iter()
+                .all(|status| status.state == MigrationState::Pending)
+        );
+        assert_eq!(current_version(&pool).await.unwrap(), 3);
+        assert_eq!(
+            plan_up(&pool, None).await.unwrap().len(),
+            MIGRATIONS.len() - 3
+        );
+
+        sqlx::query("UPDATE schema_version SET checksum = 'edited' WHERE version = 2")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let statuses = migration_status(&pool).await.unwrap();
+        assert_eq!(statuses[1].state, MigrationState::Modified);
+        let error = plan_up(&pool, None).await.unwrap_err();
+        assert!(error.to_string().contains("Migration 2 was changed"));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_schema_version_gets_checksums() {
+        let pool = memory_pool().await;
+        migrate(&pool, Some(2)).await;
+        // Databases migrated before checksums were tracked
+        sqlx::query("ALTER TABLE schema_version DROP COLUMN checksum")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let statuses = migration_status(&pool).await.unwrap();
+        assert_eq!(statuses[0].state, MigrationState::Applied);
+        assert_eq!(statuses[1].state, MigrationState::Applied);
+        // Reading the status or planning leaves the table as it was
+        plan_up(&pool, None).await.unwrap();
+        let has_checksum: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('schema_version') WHERE name = 'checksum'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(!has_checksum);
+
+        migrate(&pool, None).await;
+        let checksum: String =
+            sqlx::query_scalar("SELECT checksum FROM schema_version WHERE version = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(checksum, MIGRATIONS[0].checksum());
+    }
+
+    #[tokio::test]
+    async fn test_down_and_up_round_trip() {
+        let pool = memory_pool().await;
+        migrate(&pool, None).await;
+
+        let now = Utc::now().to_rfc3339();
+        let uuid = "00000000-0000-0000-0000-000000000001";
+        sqlx::query(
+            "INSERT INTO tasks (uuid, description, status, entry_date, modified_date, created_at, updated_at)
+             VALUES (?, 'Write report', 'pending', ?, ?, ?, ?)",
+        )
+        .bind(uuid)
+        .bind(&now)
+        .bind(&now)
+        .bind(&now)
+        .bind(&now)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO task_tags (task_uuid, tag) VALUES (?, 'work')")
+            .bind(uuid)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO task_annotations (task_uuid, position, entry, description, author)
+             VALUES (?, 0, ?, 'Draft done', 'agent-a')",
+        )
+        .bind(uuid)
+        .bind(&now)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let plan = plan_down(&pool, 9).await.unwrap();
+        assert_eq!(plan.iter().map(|m| m.version).collect::<Vec<_>>(), [11, 10]);
+        apply_migrations(&pool, &plan, MigrationDirection::Down)
+            .await
+            .unwrap();
+        assert_eq!(current_version(&pool).await.unwrap(), 9);
+
+        let (tags, annotations): (String, String) =
+            sqlx::query_as("SELECT tags, annotations FROM tasks")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(tags, r#"["work"]"#);
+        let annotations: serde_json::Value = serde_json::from_str(&annotations).unwrap();
+        assert_eq!(annotations[0]["description"], "Draft done");
+        assert_eq!(annotations[0]["author"], "agent-a");
+
+        migrate(&pool, None).await;
+        let tag: String = sqlx::query_scalar("SELECT tag FROM task_tags")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tag, "work");
+        let found: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM search_index WHERE search_index MATCH 'draft'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(found, 1);
+
+        let error = plan_down(&pool, 0).await.unwrap_err();
+        assert!(error.to_string().contains("Migration 3"));
+    }
+
+    #[tokio::test]
+    async fn test_refuses_newer_database() {
+        let pool = memory_pool().await;
+        migrate(&pool, None).await;
+        check_schema_version(&pool).await.unwrap();
+
+        let newer = latest_version() + 1;
+        sqlx::query(
+            "INSERT INTO schema_version (version, applied_at, description, checksum)
+             VALUES (?, ?, 'From the future', 'abc')",
+        )
+        .bind(newer)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let error = check_schema_version(&pool).await.unwrap_err();
+        assert!(error.to_string().contains("newer than this build"));
+        assert!(plan_up(&pool, None).await.is_err());
+        let statuses = migration_status(&pool).await.unwrap();
+        assert_eq!(statuses.last().unwrap().state, MigrationState::Unknown);
+    }
+}
@@ -1,5 +1,10 @@
+pub mod backup;
 pub mod database;
+pub mod encryption;
+pub mod migration;
 pub mod task_storage;
 
 pub use database::{get_pool, init_database};
-pub use task_storage::{SqliteTaskStorage, TaskFilter, TaskStorage};
+pub use task_storage::{
+    DependencyState, IntervalFilter, OperationFilter, SqliteTaskStorage, TaskFilter, TaskStorage,
+};
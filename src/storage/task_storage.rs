@@ -1,7 +1,14 @@
-use crate::core::{Annotation, EddaError, EddaResult, Priority, Task, TaskError, TaskStatus};
+use crate::core::search::fts_query;
+use crate::core::{
+    Annotation, EddaError, EddaResult, FilterAttribute, FilterExpr, FilterModifier, FilterTerm,
+    FilterValue, Operation, OperationKind, Priority, SearchEntity, SearchHit, TagClock, Task,
+    TaskError, TaskStatus, TimeInterval, VirtualTag,
+};
 use chrono::{DateTime, Utc};
 use serde_json;
-use sqlx::{Row, SqlitePool};
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Row, Sqlite, SqliteConnection, SqlitePool, Transaction};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
 use uuid::Uuid;
 
 /// Trait for task storage operations
@@ -27,6 +34,66 @@ pub trait TaskStorage {
 
     /// Get task count
     async fn count_tasks(&self, filter: Option<TaskFilter>) -> EddaResult<u64>;
+
+    /// Full-text search over tasks and documents with an FTS5 `query`,
+    /// best matches first; deleted tasks are left out
+    async fn search(&self, query: &str, limit: u32) -> EddaResult<Vec<SearchHit>>;
+
+    /// Start a pending, unclaimed task on behalf of `agent` and hold it until
+    /// `lease_expires`, in a single conditional update
+    ///
+    /// Returns false if the task was no longer available, as when another
+    /// agent claimed it first.
+    async fn claim_task(
+        &self,
+        uuid: Uuid,
+        agent: &str,
+        lease_expires: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> EddaResult<bool>;
+
+    /// Move the lease of a task `agent` has claimed to `lease_expires`;
+    /// returns false if the agent no longer holds the claim
+    async fn renew_lease(
+        &self,
+        uuid: Uuid,
+        agent: &str,
+        lease_expires: DateTime<Utc>,
+    ) -> EddaResult<bool>;
+
+    /// Record a new time interval
+    async fn create_interval(&self, interval: TimeInterval) -> EddaResult<TimeInterval>;
+
+    /// Get a time interval by ID
+    async fn get_interval(&self, id: i64) -> EddaResult<Option<TimeInterval>>;
+
+    /// Update an existing time interval
+    async fn update_interval(&self, interval: TimeInterval) -> EddaResult<TimeInterval>;
+
+    /// Delete a time interval by ID
+    async fn delete_interval(&self, id: i64) -> EddaResult<bool>;
+
+    /// List time intervals, oldest first
+    async fn list_intervals(&self, filter: IntervalFilter) -> EddaResult<Vec<TimeInterval>>;
+
+    /// Append an entry to the operations journal
+    async fn append_operation(&self, operation: Operation) -> EddaResult<Operation>;
+
+    /// List journal entries, newest first
+    async fn list_operations(&self, filter: OperationFilter) -> EddaResult<Vec<Operation>>;
+
+    /// Start a batch: every call until `commit_batch` or `rollback_batch`
+    /// runs in one transaction
+    async fn begin_batch(&self) -> EddaResult<()>;
+
+    /// Commit the open batch
+    async fn commit_batch(&self) -> EddaResult<()>;
+
+    /// Discard every change made since `begin_batch`
+    async fn rollback_batch(&self) -> EddaResult<()>;
+
+    /// Whether a batch is open
+    async fn in_batch(&self) -> bool;
 }
 
 /// Task filter for querying tasks
@@ -37,57 +104,146 @@ pub struct TaskFilter {
     pub tags: Option<Vec<String>>,
     pub priority: Option<Priority>,
     pub include_deleted: bool,
+    /// Include recurring templates, which are hidden by default like in
+    /// Taskwarrior; a `status` restriction always applies as given
+    pub include_recurring: bool,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// Parsed Taskwarrior-style filter expression
+    pub expression: Option<FilterExpr>,
+    /// Restrict to tasks in the given dependency state
+    pub dependency: Option<DependencyState>,
+    /// Restrict to tasks that depend on the task with this UUID
+    pub depends_on: Option<Uuid>,
+    /// Restrict to subtasks of the task with this UUID
+    pub parent: Option<Uuid>,
+    /// Restrict to IDs within this inclusive range
+    pub id_range: Option<(i64, i64)>,
+}
+
+/// Filter for querying time intervals
+#[derive(Debug, Clone, Default)]
+pub struct IntervalFilter {
+    pub task_uuid: Option<Uuid>,
+    /// Only intervals still running at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only intervals that started before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Only intervals that are still running
+    pub open_only: bool,
+}
+
+/// Filter for querying the operations journal
+#[derive(Debug, Clone, Default)]
+pub struct OperationFilter {
+    pub task_uuid: Option<Uuid>,
+    /// Only entries that can still be undone: not undo entries themselves
+    /// and not already reverted
+    pub undoable: bool,
+    pub limit: Option<u32>,
+}
+
+/// Position of a task in the dependency graph
+///
+/// A dependency is open while its task is pending, in progress or waiting;
+/// dependencies on completed, deleted or missing tasks never block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyState {
+    /// Open tasks with at least one open dependency
+    Blocked,
+    /// Open tasks that at least one open task depends on
+    Blocking,
+    /// Pending or in-progress tasks with no open dependencies (ready to work)
+    Unblocked,
 }
 
 /// SQLite implementation of task storage
 pub struct SqliteTaskStorage {
     pool: SqlitePool,
+    /// Transaction shared by every call while a batch is open
+    batch: Mutex<Option<Transaction<'static, Sqlite>>>,
+}
+
+/// Connection used for one storage call
+enum StorageConnection<'a> {
+    Pool(PoolConnection<Sqlite>),
+    Batch(MappedMutexGuard<'a, Transaction<'static, Sqlite>>),
+}
+
+impl std::ops::Deref for StorageConnection<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        match self {
+            StorageConnection::Pool(conn) => conn,
+            StorageConnection::Batch(tx) => tx,
+        }
+    }
+}
+
+impl std::ops::DerefMut for StorageConnection<'_> {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        match self {
+            StorageConnection::Pool(conn) => conn,
+            StorageConnection::Batch(tx) => tx,
+        }
+    }
 }
 
 impl SqliteTaskStorage {
     /// Create a new SQLite task storage
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            batch: Mutex::new(None),
+        }
+    }
+
+    /// The open batch transaction, or a fresh connection from the pool
+    async fn connection(&self) -> EddaResult<StorageConnection<'_>> {
+        if let Ok(tx) = MutexGuard::try_map(self.batch.lock().await, |batch| batch.as_mut()) {
+            return Ok(StorageConnection::Batch(tx));
+        }
+        let conn = self.pool.acquire().await.map_err(|e| TaskError::Storage {
+            message: format!("Failed to acquire connection: {e}"),
+        })?;
+        Ok(StorageConnection::Pool(conn))
     }
 }
 
 #[async_trait::async_trait]
 impl TaskStorage for SqliteTaskStorage {
     async fn create_task(&self, mut task: Task) -> EddaResult<Task> {
+        let mut conn = self.connection().await?;
         // Ensure task has a UUID
         if task.uuid == Uuid::nil() {
             task.uuid = Uuid::new_v4();
         }
 
-        // Update timestamps
+        // Entry and modified dates are kept as given (Task::new sets them to
+        // now), so imported and restored tasks keep their history
         let now = Utc::now();
-        task.entry_date = now;
-        task.modified_date = now;
 
         // Serialize complex fields
-        let tags_json = serde_json::to_string(&task.tags).map_err(|e| TaskError::Validation {
-            message: format!("Failed to serialize tags: {e}"),
+        let udas_json = serde_json::to_string(&task.udas).map_err(|e| TaskError::Validation {
+            message: format!("Failed to serialize udas: {e}"),
         })?;
 
-        let annotations_json =
-            serde_json::to_string(&task.annotations).map_err(|e| TaskError::Validation {
-                message: format!("Failed to serialize annotations: {e}"),
-            })?;
-
-        let depends_json =
-            serde_json::to_string(&task.depends).map_err(|e| TaskError::Validation {
-                message: format!("Failed to serialize depends: {e}"),
-            })?;
+        let storage_error = |e: sqlx::Error| {
+            EddaError::Task(TaskError::Storage {
+                message: format!("Failed to create task: {e}"),
+            })
+        };
+        let mut tx = conn.begin().await.map_err(storage_error)?;
 
         let result = sqlx::query(
             r#"
             INSERT INTO tasks (
                 uuid, description, status, priority, project, due_date, scheduled_date,
-                start_date, end_date, entry_date, modified_date, tags, annotations,
-                parent_uuid, depends, recurrence, effort, effort_spent, created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                start_date, end_date, entry_date, modified_date, parent_uuid, recurrence,
+                until_date, recurrence_mask, recurrence_index, effort, effort_spent, created_at,
+                updated_at, wait_date, udas, sync_id, claimed_by, lease_expires
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(task.uuid.to_string())
@@ -101,33 +257,40 @@ impl TaskStorage for SqliteTaskStorage {
         .bind(task.end_date.map(|d| d.to_rfc3339()))
         .bind(task.entry_date.to_rfc3339())
         .bind(task.modified_date.to_rfc3339())
-        .bind(&tags_json)
-        .bind(&annotations_json)
         .bind(task.parent_uuid.map(|u| u.to_string()))
-        .bind(&depends_json)
         .bind(&task.recurrence)
+        .bind(task.until_date.map(|d| d.to_rfc3339()))
+        .bind(&task.recurrence_mask)
+        .bind(task.recurrence_index)
         .bind(task.effort)
         .bind(task.effort_spent)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&self.pool)
+        .bind(task.wait_date.map(|d| d.to_rfc3339()))
+        .bind(&udas_json)
+        .bind(&task.sync_id)
+        .bind(&task.claimed_by)
+        .bind(task.lease_expires.map(|d| d.to_rfc3339()))
+        .execute(&mut *tx)
         .await
-        .map_err(|e| {
-            EddaError::Task(TaskError::Storage {
-                message: format!("Failed to create task: {e}"),
-            })
-        })?;
+        .map_err(storage_error)?;
 
         // Set the ID from the insert result
         task.id = Some(result.last_insert_rowid());
 
+        save_task_relations(&mut tx, &task)
+            .await
+            .map_err(storage_error)?;
+        tx.commit().await.map_err(storage_error)?;
+
         Ok(task)
     }
 
     async fn get_task_by_id(&self, id: i64) -> EddaResult<Option<Task>> {
-        let row = sqlx::query("SELECT * FROM tasks WHERE id = ?")
+        let mut conn = self.connection().await?;
+        let row = sqlx::query(&format!("SELECT {TASK_COLUMNS} FROM tasks WHERE id = ?"))
             .bind(id)
-            .fetch_optional(&self.pool)
+            .fetch_optional(&mut *conn)
             .await
             .map_err(|e| {
                 EddaError::Task(TaskError::Storage {
@@ -143,9 +306,10 @@ impl TaskStorage for SqliteTaskStorage {
     }
 
     async fn get_task_by_uuid(&self, uuid: Uuid) -> EddaResult<Option<Task>> {
-        let row = sqlx::query("SELECT * FROM tasks WHERE uuid = ?")
+        let mut conn = self.connection().await?;
+        let row = sqlx::query(&format!("SELECT {TASK_COLUMNS} FROM tasks WHERE uuid = ?"))
             .bind(uuid.to_string())
-            .fetch_optional(&self.pool)
+            .fetch_optional(&mut *conn)
             .await
             .map_err(|e| TaskError::Storage {
                 message: format!("Failed to get task: {e}"),
@@ -159,6 +323,7 @@ impl TaskStorage for SqliteTaskStorage {
     }
 
     async fn update_task(&self, mut task: Task) -> EddaResult<Task> {
+        let mut conn = self.connection().await?;
         if task.id.is_none() {
             return Err(EddaError::Task(TaskError::Validation {
                 message: "Task must have an ID to update".to_string(),
@@ -169,27 +334,24 @@ impl TaskStorage for SqliteTaskStorage {
         task.modified_date = Utc::now();
 
         // Serialize complex fields
-        let tags_json = serde_json::to_string(&task.tags).map_err(|e| TaskError::Validation {
-            message: format!("Failed to serialize tags: {e}"),
+        let udas_json = serde_json::to_string(&task.udas).map_err(|e| TaskError::Validation {
+            message: format!("Failed to serialize udas: {e}"),
         })?;
 
-        let annotations_json =
-            serde_json::to_string(&task.annotations).map_err(|e| TaskError::Validation {
-                message: format!("Failed to serialize annotations: {e}"),
-            })?;
-
-        let depends_json =
-            serde_json::to_string(&task.depends).map_err(|e| TaskError::Validation {
-                message: format!("Failed to serialize depends: {e}"),
-            })?;
+        let storage_error = |e: sqlx::Error| TaskError::Storage {
+            message: format!("Failed to update task: {e}"),
+        };
+        let mut tx = conn.begin().await.map_err(storage_error)?;
 
         sqlx::query(
             r#"
             UPDATE tasks SET
                 description = ?, status = ?, priority = ?, project = ?, due_date = ?,
                 scheduled_date = ?, start_date = ?, end_date = ?, modified_date = ?,
-                tags = ?, annotations = ?, parent_uuid = ?, depends = ?, recurrence = ?,
-                effort = ?, effort_spent = ?, updated_at = ?
+                parent_uuid = ?, recurrence = ?, until_date = ?, recurrence_mask = ?,
+                recurrence_index = ?,
+                effort = ?, effort_spent = ?, updated_at = ?, wait_date = ?, udas = ?,
+                sync_id = ?, claimed_by = ?, lease_expires = ?
             WHERE id = ?
             "#,
         )
@@ -202,62 +364,71 @@ impl TaskStorage for SqliteTaskStorage {
         .bind(task.start_date.map(|d| d.to_rfc3339()))
         .bind(task.end_date.map(|d| d.to_rfc3339()))
         .bind(task.modified_date.to_rfc3339())
-        .bind(&tags_json)
-        .bind(&annotations_json)
         .bind(task.parent_uuid.map(|u| u.to_string()))
-        .bind(&depends_json)
         .bind(&task.recurrence)
+        .bind(task.until_date.map(|d| d.to_rfc3339()))
+        .bind(&task.recurrence_mask)
+        .bind(task.recurrence_index)
         .bind(task.effort)
         .bind(task.effort_spent)
         .bind(task.modified_date.to_rfc3339())
+        .bind(task.wait_date.map(|d| d.to_rfc3339()))
+        .bind(&udas_json)
+        .bind(&task.sync_id)
+        .bind(&task.claimed_by)
+        .bind(task.lease_expires.map(|d| d.to_rfc3339()))
         .bind(task.id.unwrap())
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await
-        .map_err(|e| TaskError::Storage {
-            message: format!("Failed to update task: {e}"),
-        })?;
+        .map_err(storage_error)?;
+
+        save_task_relations(&mut tx, &task)
+            .await
+            .map_err(storage_error)?;
+        tx.commit().await.map_err(storage_error)?;
 
         Ok(task)
     }
 
     async fn delete_task(&self, id: i64) -> EddaResult<bool> {
+        let mut conn = self.connection().await?;
+        let storage_error = |e: sqlx::Error| TaskError::Storage {
+            message: format!("Failed to delete task: {e}"),
+        };
+        let mut tx = conn.begin().await.map_err(storage_error)?;
+
+        for table in [
+            "time_intervals",
+            "task_tags",
+            "task_annotations",
+            "task_dependencies",
+        ] {
+            sqlx::query(&format!(
+                "DELETE FROM {table} WHERE task_uuid = (SELECT uuid FROM tasks WHERE id = ?)"
+            ))
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(storage_error)?;
+        }
+
         let result = sqlx::query("DELETE FROM tasks WHERE id = ?")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
-            .map_err(|e| TaskError::Storage {
-                message: format!("Failed to delete task: {e}"),
-            })?;
+            .map_err(storage_error)?;
+
+        tx.commit().await.map_err(storage_error)?;
 
         Ok(result.rows_affected() > 0)
     }
 
     async fn list_tasks(&self, filter: Option<TaskFilter>) -> EddaResult<Vec<Task>> {
+        let mut conn = self.connection().await?;
         let filter = filter.unwrap_or_default();
+        let (where_clause, binds) = build_where_clause(&filter);
 
-        let mut query = String::from("SELECT * FROM tasks WHERE 1=1");
-        let mut conditions = Vec::new();
-
-        if !filter.include_deleted {
-            conditions.push("status != 'deleted'");
-        }
-
-        if let Some(_status) = &filter.status {
-            conditions.push("status = ?");
-        }
-
-        if let Some(_project) = &filter.project {
-            conditions.push("project = ?");
-        }
-
-        if let Some(_priority) = &filter.priority {
-            conditions.push("priority = ?");
-        }
-
-        // Add conditions to query
-        for condition in conditions {
-            query.push_str(&format!(" AND {condition}"));
-        }
+        let mut query = format!("SELECT {TASK_COLUMNS} FROM tasks WHERE 1=1{where_clause}");
 
         // Add ordering
         query.push_str(" ORDER BY modified_date DESC");
@@ -273,21 +444,12 @@ impl TaskStorage for SqliteTaskStorage {
 
         // Execute query with parameters
         let mut query_builder = sqlx::query(&query);
-
-        if let Some(status) = &filter.status {
-            query_builder = query_builder.bind(status.to_string());
-        }
-
-        if let Some(project) = &filter.project {
-            query_builder = query_builder.bind(project);
-        }
-
-        if let Some(priority) = &filter.priority {
-            query_builder = query_builder.bind(priority.to_string());
+        for bind in &binds {
+            query_builder = query_builder.bind(bind);
         }
 
         let rows = query_builder
-            .fetch_all(&self.pool)
+            .fetch_all(&mut *conn)
             .await
             .map_err(|e| TaskError::Storage {
                 message: format!("Failed to list tasks: {e}"),
@@ -302,57 +464,691 @@ impl TaskStorage for SqliteTaskStorage {
     }
 
     async fn count_tasks(&self, filter: Option<TaskFilter>) -> EddaResult<u64> {
+        let mut conn = self.connection().await?;
         let filter = filter.unwrap_or_default();
+        let (where_clause, binds) = build_where_clause(&filter);
 
-        let mut query = String::from("SELECT COUNT(*) FROM tasks WHERE 1=1");
-        let mut conditions = Vec::new();
+        let query = format!("SELECT COUNT(*) FROM tasks WHERE 1=1{where_clause}");
 
-        if !filter.include_deleted {
-            conditions.push("status != 'deleted'");
+        // Execute query with parameters
+        let mut query_builder = sqlx::query_scalar(&query);
+        for bind in &binds {
+            query_builder = query_builder.bind(bind);
         }
 
-        if let Some(_status) = &filter.status {
-            conditions.push("status = ?");
+        let count: i64 =
+            query_builder
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|e| TaskError::Storage {
+                    message: format!("Failed to count tasks: {e}"),
+                })?;
+
+        Ok(count as u64)
+    }
+
+    async fn search(&self, query: &str, limit: u32) -> EddaResult<Vec<SearchHit>> {
+        let mut conn = self.connection().await?;
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT search_index.entity, search_index.rowid AS rowid, search_index.title,
+                snippet(search_index, -1, '**', '**', '…', {SNIPPET_TOKENS}) AS snippet,
+                bm25(search_index, {SEARCH_WEIGHTS}) AS rank,
+                COALESCE(tasks.uuid, documents.uuid) AS uuid, tasks.status
+            FROM search_index
+            LEFT JOIN tasks ON search_index.entity = 'task' AND tasks.id = search_index.rowid
+            LEFT JOIN documents
+                ON search_index.entity = 'document' AND documents.id = -search_index.rowid
+            WHERE search_index MATCH ? AND COALESCE(tasks.status, '') != 'deleted'
+            ORDER BY rank
+            LIMIT ?
+            "#
+        ))
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| TaskError::Storage {
+            message: format!("Failed to search: {e}"),
+        })?;
+
+        rows.into_iter().map(row_to_search_hit).collect()
+    }
+
+    async fn claim_task(
+        &self,
+        uuid: Uuid,
+        agent: &str,
+        lease_expires: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> EddaResult<bool> {
+        let mut conn = self.connection().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE tasks SET
+                status = 'in_progress', start_date = ?, claimed_by = ?, lease_expires = ?,
+                modified_date = ?, updated_at = ?
+            WHERE uuid = ? AND status = 'pending' AND claimed_by IS NULL
+            "#,
+        )
+        .bind(now.to_rfc3339())
+        .bind(agent)
+        .bind(lease_expires.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .bind(uuid.to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| TaskError::Storage {
+            message: format!("Failed to claim task: {e}"),
+        })?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn renew_lease(
+        &self,
+        uuid: Uuid,
+        agent: &str,
+        lease_expires: DateTime<Utc>,
+    ) -> EddaResult<bool> {
+        let mut conn = self.connection().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE tasks SET lease_expires = ?
+            WHERE uuid = ? AND status = 'in_progress' AND claimed_by = ?
+            "#,
+        )
+        .bind(lease_expires.to_rfc3339())
+        .bind(uuid.to_string())
+        .bind(agent)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| TaskError::Storage {
+            message: format!("Failed to renew lease: {e}"),
+        })?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_interval(&self, mut interval: TimeInterval) -> EddaResult<TimeInterval> {
+        let mut conn = self.connection().await?;
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO time_intervals (task_uuid, start_time, end_time, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(interval.task_uuid.to_string())
+        .bind(interval.start.to_rfc3339())
+        .bind(interval.end.map(|d| d.to_rfc3339()))
+        .bind(&now)
+        .bind(&now)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| TaskError::Storage {
+            message: format!("Failed to create time interval: {e}"),
+        })?;
+
+        interval.id = Some(result.last_insert_rowid());
+        Ok(interval)
+    }
+
+    async fn get_interval(&self, id: i64) -> EddaResult<Option<TimeInterval>> {
+        let mut conn = self.connection().await?;
+        let row = sqlx::query("SELECT * FROM time_intervals WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| TaskError::Storage {
+                message: format!("Failed to get time interval: {e}"),
+            })?;
+
+        row.map(row_to_interval).transpose()
+    }
+
+    async fn update_interval(&self, interval: TimeInterval) -> EddaResult<TimeInterval> {
+        let mut conn = self.connection().await?;
+        let id = interval.id.ok_or_else(|| TaskError::Validation {
+            message: "Time interval ID is required for update".to_string(),
+        })?;
+
+        sqlx::query(
+            "UPDATE time_intervals SET start_time = ?, end_time = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(interval.start.to_rfc3339())
+        .bind(interval.end.map(|d| d.to_rfc3339()))
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| TaskError::Storage {
+            message: format!("Failed to update time interval: {e}"),
+        })?;
+
+        Ok(interval)
+    }
+
+    async fn delete_interval(&self, id: i64) -> EddaResult<bool> {
+        let mut conn = self.connection().await?;
+        let result = sqlx::query("DELETE FROM time_intervals WHERE id = ?")
+            .bind(id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| TaskError::Storage {
+                message: format!("Failed to delete time interval: {e}"),
+            })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_intervals(&self, filter: IntervalFilter) -> EddaResult<Vec<TimeInterval>> {
+        let mut conn = self.connection().await?;
+        let mut query = "SELECT * FROM time_intervals WHERE 1=1".to_string();
+        let mut binds = Vec::new();
+
+        if let Some(task_uuid) = filter.task_uuid {
+            query.push_str(" AND task_uuid = ?");
+            binds.push(task_uuid.to_string());
+        }
+        if let Some(from) = filter.from {
+            query.push_str(" AND (end_time IS NULL OR datetime(end_time) > datetime(?))");
+            binds.push(from.to_rfc3339());
+        }
+        if let Some(to) = filter.to {
+            query.push_str(" AND datetime(start_time) < datetime(?)");
+            binds.push(to.to_rfc3339());
         }
+        if filter.open_only {
+            query.push_str(" AND end_time IS NULL");
+        }
+        query.push_str(" ORDER BY datetime(start_time), id");
 
-        if let Some(_project) = &filter.project {
-            conditions.push("project = ?");
+        let mut query_builder = sqlx::query(&query);
+        for bind in &binds {
+            query_builder = query_builder.bind(bind);
         }
 
-        if let Some(_priority) = &filter.priority {
-            conditions.push("priority = ?");
+        let rows = query_builder
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| TaskError::Storage {
+                message: format!("Failed to list time intervals: {e}"),
+            })?;
+
+        rows.into_iter().map(row_to_interval).collect()
+    }
+
+    async fn append_operation(&self, mut operation: Operation) -> EddaResult<Operation> {
+        let mut conn = self.connection().await?;
+        let to_json = |task: &Option<Task>| {
+            task.as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(|e| TaskError::Validation {
+                    message: format!("Failed to serialize task snapshot: {e}"),
+                })
+        };
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO operations (task_uuid, operation, before_json, after_json, undoes, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(operation.task_uuid.to_string())
+        .bind(operation.kind.to_string())
+        .bind(to_json(&operation.before)?)
+        .bind(to_json(&operation.after)?)
+        .bind(operation.undoes)
+        .bind(operation.timestamp.to_rfc3339())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| TaskError::Storage {
+            message: format!("Failed to record operation: {e}"),
+        })?;
+
+        operation.id = Some(result.last_insert_rowid());
+        Ok(operation)
+    }
+
+    async fn list_operations(&self, filter: OperationFilter) -> EddaResult<Vec<Operation>> {
+        let mut conn = self.connection().await?;
+        let mut query = "SELECT * FROM operations WHERE 1=1".to_string();
+        let mut binds = Vec::new();
+
+        if let Some(task_uuid) = filter.task_uuid {
+            query.push_str(" AND task_uuid = ?");
+            binds.push(task_uuid.to_string());
+        }
+        if filter.undoable {
+            query.push_str(
+                " AND undoes IS NULL AND NOT EXISTS \
+                 (SELECT 1 FROM operations AS undo WHERE undo.undoes = operations.id)",
+            );
+        }
+        query.push_str(" ORDER BY id DESC");
+        if let Some(limit) = filter.limit {
+            query.push_str(&format!(" LIMIT {limit}"));
         }
 
-        // Add conditions to query
-        for condition in conditions {
-            query.push_str(&format!(" AND {condition}"));
+        let mut query_builder = sqlx::query(&query);
+        for bind in &binds {
+            query_builder = query_builder.bind(bind);
         }
 
-        // Execute query with parameters
-        let mut query_builder = sqlx::query_scalar(&query);
+        let rows = query_builder
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| TaskError::Storage {
+                message: format!("Failed to list operations: {e}"),
+            })?;
+
+        rows.into_iter().map(row_to_operation).collect()
+    }
 
-        if let Some(status) = &filter.status {
-            query_builder = query_builder.bind(status.to_string());
+    async fn begin_batch(&self) -> EddaResult<()> {
+        let mut batch = self.batch.lock().await;
+        if batch.is_some() {
+            return Err(EddaError::Task(TaskError::Storage {
+                message: "A batch is already open".to_string(),
+            }));
         }
+        let tx = self.pool.begin().await.map_err(|e| TaskError::Storage {
+            message: format!("Failed to begin batch: {e}"),
+        })?;
+        *batch = Some(tx);
+        Ok(())
+    }
+
+    async fn commit_batch(&self) -> EddaResult<()> {
+        let tx = self
+            .batch
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| TaskError::Storage {
+                message: "No batch is open".to_string(),
+            })?;
+        tx.commit().await.map_err(|e| TaskError::Storage {
+            message: format!("Failed to commit batch: {e}"),
+        })?;
+        Ok(())
+    }
 
-        if let Some(project) = &filter.project {
-            query_builder = query_builder.bind(project);
+    async fn rollback_batch(&self) -> EddaResult<()> {
+        if let Some(tx) = self.batch.lock().await.take() {
+            tx.rollback().await.map_err(|e| TaskError::Storage {
+                message: format!("Failed to roll back batch: {e}"),
+            })?;
         }
+        Ok(())
+    }
+
+    async fn in_batch(&self) -> bool {
+        self.batch.lock().await.is_some()
+    }
+}
+
+/// Columns selected for a task row: the `tasks` table plus its tags,
+/// annotations and dependencies gathered back into JSON arrays
+const TASK_COLUMNS: &str = "tasks.*, \
+    (SELECT json_group_array(t.tag ORDER BY t.tag) FROM task_tags AS t \
+     WHERE t.task_uuid = tasks.uuid) AS tags, \
+    (SELECT json_group_array(json_object('entry', a.entry, 'description', a.description, \
+     'author', a.author) ORDER BY a.position) FROM task_annotations AS a \
+     WHERE a.task_uuid = tasks.uuid) AS annotations, \
+    (SELECT json_group_array(d.depends_on ORDER BY d.depends_on) FROM task_dependencies AS d \
+     WHERE d.task_uuid = tasks.uuid) AS depends";
+
+/// Replace the stored tags, annotations and dependencies of a task with
+/// those it carries now
+async fn save_task_relations(conn: &mut SqliteConnection, task: &Task) -> sqlx::Result<()> {
+    let uuid = task.uuid.to_string();
+    for table in ["task_tags", "task_annotations", "task_dependencies"] {
+        sqlx::query(&format!("DELETE FROM {table} WHERE task_uuid = ?"))
+            .bind(&uuid)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    for tag in &task.tags {
+        sqlx::query("INSERT INTO task_tags (task_uuid, tag) VALUES (?, ?)")
+            .bind(&uuid)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+    }
+    for (position, annotation) in task.annotations.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO task_annotations (task_uuid, position, entry, description, author) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&uuid)
+        .bind(position as i64)
+        .bind(annotation.entry.to_rfc3339())
+        .bind(&annotation.description)
+        .bind(&annotation.author)
+        .execute(&mut *conn)
+        .await?;
+    }
+    for dependency in &task.depends {
+        sqlx::query("INSERT INTO task_dependencies (task_uuid, depends_on) VALUES (?, ?)")
+            .bind(&uuid)
+            .bind(dependency.to_string())
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Build the `AND ...` conditions and bind values for a task filter
+fn build_where_clause(filter: &TaskFilter) -> (String, Vec<String>) {
+    let mut conditions = Vec::new();
+    let mut binds = Vec::new();
+
+    if !filter.include_deleted {
+        conditions.push("status != 'deleted'".to_string());
+    }
+
+    if !filter.include_recurring && filter.status.is_none() {
+        conditions.push("status != 'recurring'".to_string());
+    }
+
+    if let Some(status) = &filter.status {
+        conditions.push("status = ?".to_string());
+        binds.push(status.to_string());
+    }
+
+    if let Some(project) = &filter.project {
+        conditions.push(project_condition(project, &mut binds));
+    }
+
+    if let Some(priority) = &filter.priority {
+        conditions.push("priority = ?".to_string());
+        binds.push(priority.to_string());
+    }
 
-        if let Some(priority) = &filter.priority {
-            query_builder = query_builder.bind(priority.to_string());
+    if let Some(tags) = &filter.tags {
+        for tag in tags {
+            conditions.push(tag_condition(true));
+            binds.push(tag.clone());
         }
+    }
 
-        let count: i64 =
-            query_builder
-                .fetch_one(&self.pool)
-                .await
-                .map_err(|e| TaskError::Storage {
-                    message: format!("Failed to count tasks: {e}"),
-                })?;
+    if let Some(expression) = &filter.expression {
+        conditions.push(format!("({})", filter_expr_to_sql(expression, &mut binds)));
+    }
 
-        Ok(count as u64)
+    if let Some(state) = filter.dependency {
+        conditions.push(dependency_condition(state));
+    }
+
+    if let Some(uuid) = filter.depends_on {
+        conditions.push(
+            "tasks.uuid IN (SELECT task_uuid FROM task_dependencies WHERE depends_on = ?)"
+                .to_string(),
+        );
+        binds.push(uuid.to_string());
     }
+
+    if let Some(uuid) = filter.parent {
+        conditions.push("parent_uuid = ?".to_string());
+        binds.push(uuid.to_string());
+    }
+
+    if let Some((start, end)) = filter.id_range {
+        conditions.push("id BETWEEN ? AND ?".to_string());
+        binds.push(start.to_string());
+        binds.push(end.to_string());
+    }
+
+    let clause = conditions
+        .iter()
+        .map(|condition| format!(" AND {condition}"))
+        .collect();
+
+    (clause, binds)
+}
+
+/// Compile a filter expression into a SQL condition, appending bind values in order
+fn filter_expr_to_sql(expr: &FilterExpr, binds: &mut Vec<String>) -> String {
+    match expr {
+        FilterExpr::Term(term) => filter_term_to_sql(term, binds),
+        FilterExpr::Not(inner) => format!("NOT ({})", filter_expr_to_sql(inner, binds)),
+        FilterExpr::And(left, right) => format!(
+            "({} AND {})",
+            filter_expr_to_sql(left, binds),
+            filter_expr_to_sql(right, binds)
+        ),
+        FilterExpr::Or(left, right) => format!(
+            "({} OR {})",
+            filter_expr_to_sql(left, binds),
+            filter_expr_to_sql(right, binds)
+        ),
+    }
+}
+
+/// Compile a single filter term into a SQL condition
+fn filter_term_to_sql(term: &FilterTerm, binds: &mut Vec<String>) -> String {
+    match term {
+        FilterTerm::Tag { tag, include } => {
+            binds.push(tag.clone());
+            tag_condition(*include)
+        }
+        FilterTerm::VirtualTag {
+            tag,
+            include,
+            clock,
+        } => {
+            let condition = virtual_tag_condition(*tag, clock, binds);
+            if *include {
+                condition
+            } else {
+                format!("NOT ({condition})")
+            }
+        }
+        FilterTerm::Word(word) => {
+            binds.push(format!("%{}%", escape_like(word)));
+            "description LIKE ? ESCAPE '\\'".to_string()
+        }
+        FilterTerm::Attribute {
+            attribute: FilterAttribute::Description,
+            modifier: FilterModifier::Matches,
+            value: FilterValue::Text(terms),
+        } => match fts_query(terms) {
+            Some(query) => {
+                binds.push(format!("title : ({query})"));
+                "tasks.id IN (SELECT rowid FROM search_index \
+                 WHERE search_index MATCH ? AND entity = 'task')"
+                    .to_string()
+            }
+            None => "0".to_string(),
+        },
+        // A project matches its subprojects too: `project:web` finds `web.auth`
+        FilterTerm::Attribute {
+            attribute: FilterAttribute::Project,
+            modifier: FilterModifier::Is,
+            value: FilterValue::Text(project),
+        } => project_condition(project, binds),
+        FilterTerm::Attribute {
+            attribute: FilterAttribute::Project,
+            modifier: FilterModifier::Not,
+            value: FilterValue::Text(project),
+        } => format!(
+            "(project IS NULL OR NOT {})",
+            project_condition(project, binds)
+        ),
+        FilterTerm::Attribute {
+            attribute,
+            modifier,
+            value,
+        } => attribute_condition(attribute.column(), *modifier, value, binds),
+        FilterTerm::Uda {
+            name,
+            modifier,
+            value,
+        } => {
+            // UDA names are validated identifiers, so they are safe to inline
+            let column = format!("json_extract(tasks.udas, '$.{name}')");
+            attribute_condition(&column, *modifier, value, binds)
+        }
+    }
+}
+
+/// SQL condition comparing a column (or expression) against a typed value
+fn attribute_condition(
+    column: &str,
+    modifier: FilterModifier,
+    value: &FilterValue,
+    binds: &mut Vec<String>,
+) -> String {
+    match (modifier, value) {
+        (FilterModifier::None, _) => format!("{column} IS NULL"),
+        (FilterModifier::Any, _) => format!("{column} IS NOT NULL"),
+        (modifier, FilterValue::Date(date)) => {
+            binds.push(date.to_rfc3339());
+            match modifier {
+                FilterModifier::Before => format!("datetime({column}) < datetime(?)"),
+                FilterModifier::After => format!("datetime({column}) > datetime(?)"),
+                FilterModifier::Not => {
+                    format!("({column} IS NULL OR date({column}) != date(?))")
+                }
+                _ => format!("date({column}) = date(?)"),
+            }
+        }
+        (modifier, FilterValue::Number(number)) => {
+            binds.push(number.clone());
+            match modifier {
+                FilterModifier::Before => format!("{column} < CAST(? AS REAL)"),
+                FilterModifier::After => format!("{column} > CAST(? AS REAL)"),
+                FilterModifier::Not => {
+                    format!("({column} IS NULL OR {column} != CAST(? AS REAL))")
+                }
+                _ => format!("{column} = CAST(? AS REAL)"),
+            }
+        }
+        (modifier, FilterValue::Text(text)) => match modifier {
+            FilterModifier::Not => {
+                binds.push(text.clone());
+                format!("({column} IS NULL OR {column} != ?)")
+            }
+            FilterModifier::Contains => {
+                binds.push(format!("%{}%", escape_like(text)));
+                format!("{column} LIKE ? ESCAPE '\\'")
+            }
+            FilterModifier::Hasnt => {
+                binds.push(format!("%{}%", escape_like(text)));
+                format!("({column} IS NULL OR {column} NOT LIKE ? ESCAPE '\\')")
+            }
+            FilterModifier::StartsWith => {
+                binds.push(format!("{}%", escape_like(text)));
+                format!("{column} LIKE ? ESCAPE '\\'")
+            }
+            FilterModifier::EndsWith => {
+                binds.push(format!("%{}", escape_like(text)));
+                format!("{column} LIKE ? ESCAPE '\\'")
+            }
+            _ => {
+                binds.push(text.clone());
+                format!("{column} = ?")
+            }
+        },
+        (_, FilterValue::Empty) => format!("{column} IS NULL"),
+    }
+}
+
+/// SQL condition matching tasks in a project or any of its subprojects
+fn project_condition(project: &str, binds: &mut Vec<String>) -> String {
+    // Subprojects sort between `web.` and `web/`, as '/' follows '.'; unlike
+    // LIKE, the range is case-sensitive and can use the project index
+    binds.push(project.to_string());
+    binds.push(format!("{project}."));
+    binds.push(format!("{project}/"));
+    "(project = ? OR (project >= ? AND project < ?))".to_string()
+}
+
+/// SQL condition matching tasks that have (or lack) the bound tag, looked
+/// up through the tag index
+fn tag_condition(include: bool) -> String {
+    let tagged = "tasks.uuid IN (SELECT task_uuid FROM task_tags WHERE tag = ?)";
+    if include {
+        tagged.to_string()
+    } else {
+        format!("NOT {tagged}")
+    }
+}
+
+/// SQL condition matching tasks that carry a virtual tag; it never
+/// evaluates to NULL, so it can be negated safely
+fn virtual_tag_condition(tag: VirtualTag, clock: &TagClock, binds: &mut Vec<String>) -> String {
+    let mut due_between = |from: DateTime<Utc>, to: DateTime<Utc>| {
+        binds.push(from.to_rfc3339());
+        binds.push(to.to_rfc3339());
+        "(tasks.status IN ('pending', 'in_progress') AND tasks.due_date IS NOT NULL \
+         AND datetime(tasks.due_date) >= datetime(?) AND datetime(tasks.due_date) < datetime(?))"
+            .to_string()
+    };
+    match tag {
+        VirtualTag::Active => "tasks.status = 'in_progress'".to_string(),
+        VirtualTag::Annotated => "EXISTS (SELECT 1 FROM task_annotations \
+             WHERE task_annotations.task_uuid = tasks.uuid)"
+            .to_string(),
+        VirtualTag::Blocked => format!("({})", dependency_condition(DependencyState::Blocked)),
+        VirtualTag::Blocking => format!("({})", dependency_condition(DependencyState::Blocking)),
+        VirtualTag::Child => "tasks.parent_uuid IS NOT NULL".to_string(),
+        VirtualTag::Due => due_between(clock.today, clock.due_soon()),
+        VirtualTag::Overdue => {
+            binds.push(clock.now.to_rfc3339());
+            "(tasks.status IN ('pending', 'in_progress') AND tasks.due_date IS NOT NULL \
+             AND datetime(tasks.due_date) < datetime(?))"
+                .to_string()
+        }
+        VirtualTag::Parent => "EXISTS (SELECT 1 FROM tasks AS child \
+             WHERE child.parent_uuid = tasks.uuid AND child.status != 'deleted')"
+            .to_string(),
+        VirtualTag::Synced => "tasks.sync_id IS NOT NULL".to_string(),
+        VirtualTag::Today => due_between(clock.today, clock.tomorrow),
+        VirtualTag::Untagged => {
+            "NOT EXISTS (SELECT 1 FROM task_tags WHERE task_tags.task_uuid = tasks.uuid)"
+                .to_string()
+        }
+        VirtualTag::Waiting => "tasks.status = 'waiting'".to_string(),
+    }
+}
+
+/// Statuses whose tasks still block the tasks that depend on them
+const OPEN_STATUSES: &str = "('pending', 'in_progress', 'waiting')";
+
+/// SQL condition selecting tasks in the given dependency state
+fn dependency_condition(state: DependencyState) -> String {
+    let has_open_dependency = format!(
+        "EXISTS (SELECT 1 FROM task_dependencies AS dep \
+         JOIN tasks AS blocker ON blocker.uuid = dep.depends_on \
+         WHERE dep.task_uuid = tasks.uuid AND blocker.status IN {OPEN_STATUSES})"
+    );
+    match state {
+        DependencyState::Blocked => {
+            format!("tasks.status IN {OPEN_STATUSES} AND {has_open_dependency}")
+        }
+        DependencyState::Blocking => format!(
+            "tasks.status IN {OPEN_STATUSES} AND EXISTS (SELECT 1 FROM task_dependencies AS dep \
+             JOIN tasks AS dependent ON dependent.uuid = dep.task_uuid \
+             WHERE dep.depends_on = tasks.uuid AND dependent.status IN {OPEN_STATUSES})"
+        ),
+        DependencyState::Unblocked => {
+            format!("tasks.status IN ('pending', 'in_progress') AND NOT {has_open_dependency}")
+        }
+    }
+}
+
+/// Escape LIKE wildcards so user input is matched literally
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 /// Convert a database row to a Task
@@ -365,6 +1161,7 @@ fn row_to_task(row: sqlx::sqlite::SqliteRow) -> EddaResult<Task> {
     let project: Option<String> = row.get("project");
     let due_date_str: Option<String> = row.get("due_date");
     let scheduled_date_str: Option<String> = row.get("scheduled_date");
+    let wait_date_str: Option<String> = row.get("wait_date");
     let start_date_str: Option<String> = row.get("start_date");
     let end_date_str: Option<String> = row.get("end_date");
     let entry_date_str: String = row.get("entry_date");
@@ -374,8 +1171,15 @@ fn row_to_task(row: sqlx::sqlite::SqliteRow) -> EddaResult<Task> {
     let parent_uuid_str: Option<String> = row.get("parent_uuid");
     let depends_json: String = row.get("depends");
     let recurrence: Option<String> = row.get("recurrence");
+    let until_date_str: Option<String> = row.get("until_date");
+    let recurrence_mask: Option<String> = row.get("recurrence_mask");
+    let recurrence_index: Option<i64> = row.get("recurrence_index");
     let effort: Option<i64> = row.get("effort");
     let effort_spent: Option<i64> = row.get("effort_spent");
+    let udas_json: String = row.get("udas");
+    let sync_id: Option<String> = row.get("sync_id");
+    let claimed_by: Option<String> = row.get("claimed_by");
+    let lease_expires_str: Option<String> = row.get("lease_expires");
 
     // Parse UUID
     let uuid = Uuid::parse_str(&uuid_str).map_err(|e| TaskError::Validation {
@@ -451,6 +1255,42 @@ fn row_to_task(row: sqlx::sqlite::SqliteRow) -> EddaResult<Task> {
         None
     };
 
+    let wait_date = if let Some(date_str) = wait_date_str {
+        Some(
+            DateTime::parse_from_rfc3339(&date_str)
+                .map_err(|e| TaskError::Validation {
+                    message: format!("Invalid wait date: {e}"),
+                })?
+                .with_timezone(&Utc),
+        )
+    } else {
+        None
+    };
+
+    let lease_expires = if let Some(date_str) = lease_expires_str {
+        Some(
+            DateTime::parse_from_rfc3339(&date_str)
+                .map_err(|e| TaskError::Validation {
+                    message: format!("Invalid lease expiry: {e}"),
+                })?
+                .with_timezone(&Utc),
+        )
+    } else {
+        None
+    };
+
+    let until_date = if let Some(date_str) = until_date_str {
+        Some(
+            DateTime::parse_from_rfc3339(&date_str)
+                .map_err(|e| TaskError::Validation {
+                    message: format!("Invalid until date: {e}"),
+                })?
+                .with_timezone(&Utc),
+        )
+    } else {
+        None
+    };
+
     let entry_date = DateTime::parse_from_rfc3339(&entry_date_str)
         .map_err(|e| TaskError::Validation {
             message: format!("Invalid entry date: {e}"),
@@ -489,6 +1329,10 @@ fn row_to_task(row: sqlx::sqlite::SqliteRow) -> EddaResult<Task> {
             message: format!("Invalid depends JSON: {e}"),
         })?;
 
+    let udas = serde_json::from_str(&udas_json).map_err(|e| TaskError::Validation {
+        message: format!("Invalid udas JSON: {e}"),
+    })?;
+
     Ok(Task {
         id: Some(id),
         uuid,
@@ -498,6 +1342,7 @@ fn row_to_task(row: sqlx::sqlite::SqliteRow) -> EddaResult<Task> {
         project,
         due_date,
         scheduled_date,
+        wait_date,
         start_date,
         end_date,
         entry_date,
@@ -507,14 +1352,120 @@ fn row_to_task(row: sqlx::sqlite::SqliteRow) -> EddaResult<Task> {
         parent_uuid,
         depends,
         recurrence,
+        until_date,
+        recurrence_mask,
+        recurrence_index: recurrence_index.map(|i| i as u32),
         effort: effort.map(|e| e as u32),
         effort_spent: effort_spent.map(|e| e as u32),
+        udas,
+        sync_id,
+        claimed_by,
+        lease_expires,
+    })
+}
+
+/// Words of context around the matched terms in a search snippet
+const SNIPPET_TOKENS: u32 = 12;
+
+/// bm25 weights of the search index columns (entity, title, body): a match
+/// in a description or title counts for more than one in the body
+const SEARCH_WEIGHTS: &str = "0.0, 4.0, 1.0";
+
+/// Convert a search result row to a SearchHit
+fn row_to_search_hit(row: sqlx::sqlite::SqliteRow) -> EddaResult<SearchHit> {
+    let entity_str: String = row.get("entity");
+    let rowid: i64 = row.get("rowid");
+    let uuid_str: Option<String> = row.get("uuid");
+    let status_str: Option<String> = row.get("status");
+    let rank: f64 = row.get("rank");
+
+    let entity = match entity_str.as_str() {
+        "task" => SearchEntity::Task,
+        _ => SearchEntity::Document,
+    };
+    let uuid = uuid_str
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| TaskError::Validation {
+            message: format!("Invalid UUID: {e}"),
+        })?
+        .unwrap_or_default();
+    let status = status_str
+        .map(|status| status.parse::<TaskStatus>())
+        .transpose()?;
+
+    Ok(SearchHit {
+        entity,
+        id: rowid.abs(),
+        uuid,
+        title: row.get("title"),
+        snippet: row.get("snippet"),
+        status,
+        // bm25 scores better matches lower
+        score: -rank,
+    })
+}
+
+/// Convert a database row to a TimeInterval
+fn row_to_interval(row: sqlx::sqlite::SqliteRow) -> EddaResult<TimeInterval> {
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|d| d.with_timezone(&Utc))
+            .map_err(|e| TaskError::Validation {
+                message: format!("Invalid interval time: {e}"),
+            })
+    };
+
+    let task_uuid: String = row.get("task_uuid");
+    let start_time: String = row.get("start_time");
+    let end_time: Option<String> = row.get("end_time");
+
+    Ok(TimeInterval {
+        id: Some(row.get("id")),
+        task_uuid: Uuid::parse_str(&task_uuid).map_err(|e| TaskError::Validation {
+            message: format!("Invalid interval task UUID: {e}"),
+        })?,
+        start: parse_time(&start_time)?,
+        end: end_time.as_deref().map(parse_time).transpose()?,
+    })
+}
+
+/// Convert a database row to an Operation
+fn row_to_operation(row: sqlx::sqlite::SqliteRow) -> EddaResult<Operation> {
+    let from_json = |json: Option<String>| {
+        json.map(|json| serde_json::from_str::<Task>(&json))
+            .transpose()
+            .map_err(|e| TaskError::Validation {
+                message: format!("Invalid task snapshot JSON: {e}"),
+            })
+    };
+
+    let task_uuid: String = row.get("task_uuid");
+    let kind: String = row.get("operation");
+    let created_at: String = row.get("created_at");
+
+    Ok(Operation {
+        id: Some(row.get("id")),
+        task_uuid: Uuid::parse_str(&task_uuid).map_err(|e| TaskError::Validation {
+            message: format!("Invalid operation task UUID: {e}"),
+        })?,
+        kind: kind.parse::<OperationKind>()?,
+        before: from_json(row.get("before_json"))?,
+        after: from_json(row.get("after_json"))?,
+        undoes: row.get("undoes"),
+        timestamp: DateTime::parse_from_rfc3339(&created_at)
+            .map(|d| d.with_timezone(&Utc))
+            .map_err(|e| TaskError::Validation {
+                message: format!("Invalid operation timestamp: {e}"),
+            })?,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::date::DateParser;
     use serial_test::serial;
     use sqlx::sqlite::SqlitePoolOptions;
 
@@ -632,6 +1583,282 @@ mod tests {
         assert_eq!(tasks.len(), 3); // All tasks should be pending by default
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_list_tasks_with_filter_expression() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        // Run migrations
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+
+        let storage = SqliteTaskStorage::new(pool);
+
+        let mut task1 = Task::new("Fix login bug".to_string());
+        task1.project = Some("web".to_string());
+        task1.add_tag("bug".to_string());
+        task1.priority = Some(Priority::High);
+        task1.due_date = Some("2026-10-20T12:00:00Z".parse().unwrap());
+
+        let mut task2 = Task::new("Write API docs".to_string());
+        task2.project = Some("api".to_string());
+        task2.due_date = Some("2026-12-01T12:00:00Z".parse().unwrap());
+
+        let mut task3 = Task::new("Refactor 100% of the login_form".to_string());
+        task3.add_tag("urgent".to_string());
+
+        storage.create_task(task1).await.unwrap();
+        storage.create_task(task2).await.unwrap();
+        storage.create_task(task3).await.unwrap();
+
+        let list = |query: &str| {
+            let storage = &storage;
+            let filter = TaskFilter {
+                expression: Some(query.parse().unwrap()),
+                ..Default::default()
+            };
+            async move {
+                let mut descriptions: Vec<String> = storage
+                    .list_tasks(Some(filter))
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| t.description)
+                    .collect();
+                descriptions.sort();
+                descriptions
+            }
+        };
+
+        assert_eq!(list("project:web").await, vec!["Fix login bug"]);
+        assert_eq!(list("+bug").await, vec!["Fix login bug"]);
+        assert_eq!(list("-bug").await.len(), 2);
+        assert_eq!(list("priority:H").await, vec!["Fix login bug"]);
+        assert_eq!(list("due.before:2026-11-01").await, vec!["Fix login bug"]);
+        assert_eq!(list("due.after:2026-11-01").await, vec!["Write API docs"]);
+        assert_eq!(
+            list("project: +urgent").await,
+            vec!["Refactor 100% of the login_form"]
+        );
+        assert_eq!(
+            list("description.contains:login").await,
+            vec!["Fix login bug", "Refactor 100% of the login_form"]
+        );
+        assert_eq!(
+            list("description.contains:100%").await,
+            vec!["Refactor 100% of the login_form"]
+        );
+        assert_eq!(
+            list("(project:web or project:api) and not +bug").await,
+            vec!["Write API docs"]
+        );
+        assert_eq!(list("status:pending project:api").await.len(), 1);
+        assert!(list("status:completed").await.is_empty());
+
+        // The expression combines with the structured filter fields
+        let filter = TaskFilter {
+            project: Some("web".to_string()),
+            expression: Some("+urgent".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(storage.count_tasks(Some(filter)).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_project_filter_includes_subprojects() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+
+        let storage = SqliteTaskStorage::new(pool);
+        for (description, project) in [
+            ("Site", "web"),
+            ("Login", "web.auth.login"),
+            ("Styles", "web-ui"),
+            ("Shouting", "WEB.auth"),
+        ] {
+            let mut task = Task::new(description.to_string());
+            task.project = Some(project.to_string());
+            storage.create_task(task).await.unwrap();
+        }
+
+        let list = |query: &str| {
+            let storage = &storage;
+            let filter = TaskFilter {
+                expression: Some(query.parse().unwrap()),
+                ..Default::default()
+            };
+            async move {
+                let mut descriptions: Vec<String> = storage
+                    .list_tasks(Some(filter))
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| t.description)
+                    .collect();
+                descriptions.sort();
+                descriptions
+            }
+        };
+
+        assert_eq!(list("project:web").await, ["Login", "Site"]);
+        assert_eq!(list("project:web.auth").await, ["Login"]);
+        assert_eq!(list("project.not:web").await, ["Shouting", "Styles"]);
+
+        let filter = TaskFilter {
+            project: Some("web.auth".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(storage.count_tasks(Some(filter)).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_virtual_tag_filters() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+
+        let storage = SqliteTaskStorage::new(pool);
+        let now = Utc::now();
+
+        let mut release = Task::new("Release".to_string());
+        release.due_date = Some(now - chrono::Duration::days(2));
+        release.add_tag("ops".to_string());
+        let release = storage.create_task(release).await.unwrap();
+        let mut notes = Task::new("Notes".to_string());
+        notes.parent_uuid = Some(release.uuid);
+        notes.add_annotation("Draft in the wiki".to_string());
+        notes.due_date = Some(now + chrono::Duration::days(3));
+        storage.create_task(notes).await.unwrap();
+        let mut issue = Task::new("Issue".to_string());
+        issue.status = TaskStatus::InProgress;
+        issue.depends.insert(release.uuid);
+        issue.sync_id = Some("https://github.com/acme/web/issues/7".to_string());
+        storage.create_task(issue).await.unwrap();
+
+        let list = |query: &str| {
+            let storage = &storage;
+            let expression = FilterExpr::from_args_with(
+                &[query.to_string()],
+                DateParser::new(chrono_tz::Tz::UTC),
+                &Default::default(),
+            )
+            .unwrap();
+            let filter = TaskFilter {
+                expression: Some(expression),
+                ..Default::default()
+            };
+            async move {
+                let mut descriptions: Vec<String> = storage
+                    .list_tasks(Some(filter))
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| t.description)
+                    .collect();
+                descriptions.sort();
+                descriptions
+            }
+        };
+
+        assert_eq!(list("+OVERDUE").await, ["Release"]);
+        assert_eq!(list("-OVERDUE").await, ["Issue", "Notes"]);
+        assert_eq!(list("+DUE").await, ["Notes"]);
+        assert_eq!(list("+BLOCKED").await, ["Issue"]);
+        assert_eq!(list("+BLOCKING").await, ["Release"]);
+        assert_eq!(list("+ACTIVE").await, ["Issue"]);
+        assert_eq!(list("+PARENT").await, ["Release"]);
+        assert_eq!(list("+CHILD").await, ["Notes"]);
+        assert_eq!(list("+ANNOTATED").await, ["Notes"]);
+        assert_eq!(list("+UNTAGGED").await, ["Issue", "Notes"]);
+        assert_eq!(list("+SYNCED").await, ["Issue"]);
+        assert!(list("+WAITING or +TODAY").await.is_empty());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_tasks_by_dependency_state() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+
+        let storage = SqliteTaskStorage::new(pool);
+
+        // deploy -> review (open), docs -> spelling (completed)
+        let review = storage
+            .create_task(Task::new("Review".to_string()))
+            .await
+            .unwrap();
+        let mut spelling = Task::new("Spelling".to_string());
+        spelling.status = TaskStatus::Completed;
+        let spelling = storage.create_task(spelling).await.unwrap();
+        let mut deploy = Task::new("Deploy".to_string());
+        deploy.depends.insert(review.uuid);
+        storage.create_task(deploy).await.unwrap();
+        let mut docs = Task::new("Docs".to_string());
+        docs.depends.insert(spelling.uuid);
+        storage.create_task(docs).await.unwrap();
+
+        let descriptions = |state| {
+            let storage = &storage;
+            async move {
+                let filter = TaskFilter {
+                    dependency: Some(state),
+                    ..Default::default()
+                };
+                let mut names: Vec<String> = storage
+                    .list_tasks(Some(filter))
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| t.description)
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+
+        assert_eq!(descriptions(DependencyState::Blocked).await, ["Deploy"]);
+        assert_eq!(descriptions(DependencyState::Blocking).await, ["Review"]);
+        assert_eq!(
+            descriptions(DependencyState::Unblocked).await,
+            ["Docs", "Review"]
+        );
+
+        let filter = TaskFilter {
+            depends_on: Some(review.uuid),
+            ..Default::default()
+        };
+        let dependents = storage.list_tasks(Some(filter)).await.unwrap();
+        assert_eq!(dependents.len(), 1);
+        assert_eq!(dependents[0].description, "Deploy");
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_delete_task() {
@@ -663,4 +1890,212 @@ mod tests {
             .unwrap();
         assert!(retrieved_task.is_none());
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_claim_task_once() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+        let storage = SqliteTaskStorage::new(pool);
+
+        let task = storage
+            .create_task(Task::new("Queued".to_string()))
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let expires = now + chrono::Duration::minutes(15);
+        assert!(
+            storage
+                .claim_task(task.uuid, "a", expires, now)
+                .await
+                .unwrap()
+        );
+        // The first claim wins; later ones see the task taken
+        assert!(
+            !storage
+                .claim_task(task.uuid, "b", expires, now)
+                .await
+                .unwrap()
+        );
+
+        let claimed = storage.get_task_by_uuid(task.uuid).await.unwrap().unwrap();
+        assert_eq!(claimed.status, TaskStatus::InProgress);
+        assert_eq!(claimed.claimed_by.as_deref(), Some("a"));
+        assert_eq!(
+            claimed.lease_expires.map(|t| t.timestamp()),
+            Some(expires.timestamp())
+        );
+
+        let later = expires + chrono::Duration::minutes(15);
+        assert!(!storage.renew_lease(task.uuid, "b", later).await.unwrap());
+        assert!(storage.renew_lease(task.uuid, "a", later).await.unwrap());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_tag_and_dependency_filters_use_indexes() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+        let storage = SqliteTaskStorage::new(pool.clone());
+
+        let review = storage
+            .create_task(Task::new("Review".to_string()))
+            .await
+            .unwrap();
+        let mut deploy = Task::new("Deploy".to_string());
+        deploy.add_tag("ops".to_string());
+        deploy.depends.insert(review.uuid);
+        deploy.add_annotation("Needs a window".to_string());
+        let mut deploy = storage.create_task(deploy).await.unwrap();
+
+        let stored = storage
+            .get_task_by_uuid(deploy.uuid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.tags, deploy.tags);
+        assert_eq!(stored.depends, deploy.depends);
+        assert_eq!(stored.annotations[0].description, "Needs a window");
+
+        // Updates replace the related rows rather than adding to them
+        deploy.tags.clear();
+        deploy.add_tag("release".to_string());
+        deploy.annotations.clear();
+        let deploy = storage.update_task(deploy).await.unwrap();
+        let stored = storage
+            .get_task_by_uuid(deploy.uuid)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.tags, deploy.tags);
+        assert!(stored.annotations.is_empty());
+
+        let filters = [
+            (
+                TaskFilter {
+                    tags: Some(vec!["release".to_string()]),
+                    ..Default::default()
+                },
+                "idx_task_tags_tag",
+            ),
+            (
+                TaskFilter {
+                    depends_on: Some(review.uuid),
+                    ..Default::default()
+                },
+                "idx_task_dependencies_depends_on",
+            ),
+        ];
+        for (filter, index) in filters {
+            let tasks = storage.list_tasks(Some(filter.clone())).await.unwrap();
+            assert_eq!(tasks.len(), 1);
+            assert_eq!(tasks[0].uuid, deploy.uuid);
+
+            let (where_clause, binds) = build_where_clause(&filter);
+            let sql = format!("EXPLAIN QUERY PLAN SELECT id FROM tasks WHERE 1=1{where_clause}");
+            let mut query = sqlx::query(&sql);
+            for bind in &binds {
+                query = query.bind(bind);
+            }
+            let plan: Vec<String> = query
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .iter()
+                .map(|row| row.get("detail"))
+                .collect();
+            assert!(
+                plan.iter().any(|step| step.contains(index)),
+                "{index} not used: {plan:?}"
+            );
+        }
+
+        storage.delete_task(deploy.id.unwrap()).await.unwrap();
+        let tags: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM task_tags")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, 0);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_search_index() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::storage::database::run_migrations(&pool)
+            .await
+            .unwrap();
+        let storage = SqliteTaskStorage::new(pool.clone());
+
+        let login = storage
+            .create_task(Task::new("Fix login redirect".to_string()))
+            .await
+            .unwrap();
+        let mut tokens = Task::new("Rotate API keys".to_string());
+        tokens.add_annotation("Logins fail once the token expires".to_string());
+        let tokens = storage.create_task(tokens).await.unwrap();
+        let mut stale = Task::new("Old login spike".to_string());
+        stale.status = TaskStatus::Deleted;
+        storage.create_task(stale).await.unwrap();
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO documents (uuid, title, content, created_at, updated_at) \
+             VALUES (?, 'Auth runbook', 'Steps when login breaks', ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&now)
+        .bind(&now)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Stemming matches "Logins"; a match in the description ranks first
+        let hits = storage.search(r#""login""#, 10).await.unwrap();
+        let found: Vec<(SearchEntity, &str)> = hits
+            .iter()
+            .map(|hit| (hit.entity, hit.title.as_str()))
+            .collect();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0], (SearchEntity::Task, "Fix login redirect"));
+        assert!(found.contains(&(SearchEntity::Task, "Rotate API keys")));
+        assert!(found.contains(&(SearchEntity::Document, "Auth runbook")));
+        assert_eq!(hits[0].id, login.id.unwrap());
+        assert_eq!(hits[0].status, Some(TaskStatus::Pending));
+        assert!(hits[0].snippet.contains("**login**"));
+
+        // Triggers keep the index current as tasks and annotations change
+        let mut tokens = tokens;
+        tokens.annotations.clear();
+        tokens.description = "Rotate signing keys".to_string();
+        storage.update_task(tokens).await.unwrap();
+        assert_eq!(storage.search(r#""login""#, 10).await.unwrap().len(), 2);
+        assert_eq!(storage.search(r#""signing""#, 10).await.unwrap().len(), 1);
+
+        let filter = TaskFilter {
+            expression: Some(r#"description.matches:"fix log*""#.parse().unwrap()),
+            ..Default::default()
+        };
+        let tasks = storage.list_tasks(Some(filter)).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].uuid, login.uuid);
+
+        storage.delete_task(login.id.unwrap()).await.unwrap();
+        assert_eq!(storage.search(r#""redirect""#, 10).await.unwrap().len(), 0);
+    }
 }
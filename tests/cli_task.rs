@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use predicates::prelude::*;
 use predicates::str::contains;
 use std::fs;
 use std::path::Path;
@@ -125,3 +126,1676 @@ fn test_task_operations_with_config() {
         .stdout(contains("First task"))
         .stdout(contains("Second task"));
 }
+
+#[test]
+fn test_task_list_with_filter() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Web task"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Other task"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "project", "web"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "tag", "2", "bug"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "project:web"])
+        .assert()
+        .success()
+        .stdout(contains("Web task"))
+        .stdout(contains("Other task").not());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "-bug"])
+        .assert()
+        .success()
+        .stdout(contains("Web task"))
+        .stdout(contains("Other task").not());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "project:web", "or", "+bug"])
+        .assert()
+        .success()
+        .stdout(contains("Web task"))
+        .stdout(contains("Other task"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "status:bogus"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid filter"));
+}
+
+#[test]
+fn test_task_start_stop_in_progress() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Long running task"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "start", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Started task 1"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "status:in_progress"])
+        .assert()
+        .success()
+        .stdout(contains("Long running task"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "stop", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Stopped task 1"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "stop", "1"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid status transition"));
+}
+
+#[test]
+fn test_task_recurring_generates_instances() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Pay rent"]).assert().success();
+
+    // All at once: a plain task past its until date would expire before
+    // becoming a recurring template
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "modify",
+        "1",
+        "due:2026-01-01",
+        "until:2026-01-20",
+        "recur:weekly",
+    ])
+    .assert()
+    .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "status:pending"])
+        .assert()
+        .success()
+        .stdout(contains("Pay rent").count(3));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "2"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("\"status\": \"Recurring\""))
+        .stdout(contains("\"recurrence_mask\": \"+--\""));
+
+    // The template only shows up when asked for by status
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(contains("Pay rent").count(3));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "status:recurring"])
+        .assert()
+        .success()
+        .stdout(contains("Pay rent").count(1));
+}
+
+#[test]
+fn test_task_dependencies() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for description in ["Deploy release", "Review release", "Write notes"] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(["task", "add", description]).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "depend", "1", "2"]).assert().success();
+
+    // A bad reference saves none of the others
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "depend", "3", "2", "99"])
+        .assert()
+        .failure()
+        .stderr(contains("not found"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "depend", "2", "1"])
+        .assert()
+        .failure()
+        .stderr(contains("Dependency cycle"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "blocked"]).assert().success().stdout(
+        contains("Deploy release")
+            .and(contains("Review release").not())
+            .and(contains("Write notes").not()),
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "unblocked"])
+        .assert()
+        .success()
+        .stdout(contains("Review release").and(contains("Deploy release").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "1"])
+        .assert()
+        .failure()
+        .stderr(contains("blocked by open dependencies"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "1", "--force"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_task_timesheet() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Write report"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "interval",
+        "add",
+        "1",
+        "2026-03-02T09:00:00Z",
+        "2026-03-02T10:30:00Z",
+    ])
+    .assert()
+    .success()
+    .stdout(contains("Added interval 1"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Time spent: 1:30"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "interval",
+        "modify",
+        "1",
+        "--end",
+        "2026-03-02T11:00:00Z",
+    ])
+    .assert()
+    .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "timesheet", "--by", "day"])
+        .assert()
+        .success()
+        .stdout(contains("2026-03-02").and(contains("2:00")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "timesheet", "--by", "project"])
+        .assert()
+        .success()
+        .stdout(contains("\"total_minutes\": 120"));
+}
+
+#[test]
+fn test_task_undo_and_history() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Agent task"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "project", "agents"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "history", "1"])
+        .assert()
+        .success()
+        .stdout(contains("create").and(contains("project: null -> \"agents\"")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "undo"])
+        .assert()
+        .success()
+        .stdout(contains("Undid update of task 1: Agent task"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "project:agents"])
+        .assert()
+        .success()
+        .stdout(contains("No tasks found."));
+}
+
+#[test]
+fn test_task_natural_dates() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "add",
+        "Ship release",
+        "--due",
+        "tomorrow",
+        "--wait",
+        "2026-01-01",
+    ])
+    .assert()
+    .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Plan next year", "--due", "2099-01-01"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "scheduled", "eow"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "due.before:+2d"])
+        .assert()
+        .success()
+        .stdout(contains("Ship release").and(contains("Plan next year").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Wait: 2026-01-01 00:00 UTC").and(contains("Scheduled:")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "wait", ""])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("\"wait_date\": null"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Bad date", "--due", "someday"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_task_inline_attributes() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Write", "schema"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "add",
+        "Fix",
+        "login",
+        "project:web",
+        "+bug",
+        "priority:H",
+        "due:2026-11-01",
+        "depends:1",
+    ])
+    .assert()
+    .success()
+    .stdout(contains("Created task 2: Fix login"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "get", "2"])
+        .assert()
+        .success()
+        .stdout(
+            contains("\"project\": \"web\"")
+                .and(contains("\"bug\""))
+                .and(contains("\"priority\": \"High\""))
+                .and(contains("\"due_date\": \"2026-11-01T00:00:00Z\"")),
+        );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "blocked"])
+        .assert()
+        .success()
+        .stdout(contains("Fix login"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "modify",
+        "2",
+        "priority:L",
+        "-bug",
+        "+frontend",
+        "depends:-1",
+        "description:Fix login form",
+    ])
+    .assert()
+    .success()
+    .stdout(contains("Updated task 2: Fix login form"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "2"]).assert().success().stdout(
+        contains("Priority: L")
+            .and(contains("+frontend"))
+            .and(contains("+bug").not())
+            .and(contains("Depends").not()),
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "2", "depends:2"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_task_bulk_operations() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for description in ["Triage one", "Triage two", "Triage three", "Triage four"] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(["task", "add", description, "project:triage"])
+            .assert()
+            .success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "tag", "1,2", "bug"])
+        .assert()
+        .success()
+        .stdout(contains("Added tag to task 1").and(contains("Added tag to task 2")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "--dry-run", "+bug", "priority:H"])
+        .assert()
+        .success()
+        .stdout(contains("Would modify task 1").and(contains("Dry run")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "priority:H"])
+        .assert()
+        .success()
+        .stdout(contains("No tasks found."));
+
+    // More tasks than the confirmation threshold: declining changes nothing
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "project:triage"])
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stdout(contains("Continue? [y/N]").and(contains("No changes made.")));
+
+    // A blocked task makes the whole batch roll back
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "depend", "4", "1"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "2-4"]).assert().failure();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "status:completed"])
+        .assert()
+        .success()
+        .stdout(contains("No tasks found."));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "--yes", "project:triage"])
+        .assert()
+        .success()
+        .stdout(contains("Completed task 4"));
+}
+
+#[test]
+fn test_task_user_defined_attributes() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str(
+        r#"
+[uda.estimate_points]
+type = "number"
+
+[uda.agent_model]
+type = "enum"
+values = ["opus", "sonnet", "haiku"]
+"#,
+    );
+    fs::write(&config_path, config).unwrap();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args([
+        "task",
+        "add",
+        "Refactor parser",
+        "estimate_points:5",
+        "agent_model:opus",
+    ])
+    .assert()
+    .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Write docs", "estimate_points:2"])
+        .assert()
+        .success();
+
+    // Values outside the declared type or allowed set are rejected
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "2", "agent_model:gpt"])
+        .assert()
+        .failure();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "2", "estimate_points:lots"])
+        .assert()
+        .failure();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "2", "agent_model:haiku"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "estimate_points.over:3"])
+        .assert()
+        .success()
+        .stdout(contains("Refactor parser").and(contains("Write docs").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "agent_model:haiku"])
+        .assert()
+        .success()
+        .stdout(contains("Write docs").and(contains("Refactor parser").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("estimate_points: 5"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains(r#""estimate_points": 5"#).and(contains(r#""agent_model": "opus""#)));
+
+    // An empty value removes the attribute
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "agent_model:"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "agent_model:"])
+        .assert()
+        .success()
+        .stdout(contains("Refactor parser"));
+}
+
+#[test]
+fn test_task_taskwarrior_import_export() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let export_path = temp.path().join("taskwarrior.json");
+    fs::write(
+        &export_path,
+        r#"[
+{"id":1,"description":"Write migration guide","entry":"20250105T090000Z","modified":"20250106T120000Z","status":"pending","uuid":"7d1c2a4e-0b7e-4c55-9f1e-5a9d0c3b2e01","project":"docs","tags":["migration"],"annotations":[{"entry":"20250105T091500Z","description":"Cover recurring tasks"}],"depends":["3f2e1d0c-9b8a-4f6e-8d7c-1a2b3c4d5e6f"],"customer":"acme","urgency":4.2},
+{"id":0,"description":"Collect old exports","entry":"20250101T080000Z","modified":"20250102T080000Z","end":"20250102T080000Z","status":"completed","uuid":"3f2e1d0c-9b8a-4f6e-8d7c-1a2b3c4d5e6f"}
+]"#,
+    )
+    .unwrap();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "import"])
+        .arg(&export_path)
+        .assert()
+        .success()
+        .stdout(contains(
+            "Imported 2 tasks: 2 added, 0 updated, 0 unchanged",
+        ));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Write migration guide").and(contains("3f2e1d0c")));
+
+    let output = cli_with_config(&config_path)
+        .args(["task", "export"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let exported: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let guide = exported
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|task| task["uuid"] == "7d1c2a4e-0b7e-4c55-9f1e-5a9d0c3b2e01")
+        .unwrap();
+    assert_eq!(guide["entry"], "20250105T090000Z");
+    assert_eq!(
+        guide["annotations"][0]["description"],
+        "Cover recurring tasks"
+    );
+    assert_eq!(
+        guide["depends"],
+        serde_json::json!(["3f2e1d0c-9b8a-4f6e-8d7c-1a2b3c4d5e6f"])
+    );
+    assert_eq!(guide["customer"], "acme");
+    assert!(guide.get("urgency").is_none());
+
+    // Re-importing our own export changes nothing
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "import", "-"])
+        .write_stdin(output.stdout)
+        .assert()
+        .success()
+        .stdout(contains("0 added, 0 updated, 2 unchanged"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "export", "status:completed"])
+        .assert()
+        .success()
+        .stdout(contains("Collect old exports").and(contains("migration guide").not()));
+}
+
+#[test]
+fn test_task_urgency() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str(
+        r#"
+[urgency]
+blocking = 10.0
+
+[urgency.user_project]
+ops = 3.0
+"#,
+    );
+    fs::write(&config_path, config).unwrap();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec!["task", "add", "Tidy notes"],
+        vec!["task", "add", "Rotate keys", "project:ops.security"],
+        vec!["task", "add", "Ship release", "priority:H"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "depend", "3", "2"]).assert().success();
+
+    // Task 2 blocks task 3, and its project scores through `ops`
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "urgency", "2", "--explain"])
+        .assert()
+        .success()
+        .stdout(
+            contains("blocking")
+                .and(contains("project ops"))
+                .and(contains("Total")),
+        );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "urgency", "3"])
+        .assert()
+        .success()
+        .stdout(contains("Task 3 urgency: 1.00"));
+
+    let output = cli_with_config(&config_path)
+        .args(["--format", "json", "task", "list", "--sort", "urgency"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let order: Vec<&str> = listed["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["description"].as_str().unwrap())
+        .collect();
+    assert_eq!(order, ["Rotate keys", "Ship release", "Tidy notes"]);
+    assert_eq!(listed["tasks"][0]["urgency"], 14.0);
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "--sort", "colour"])
+        .assert()
+        .failure();
+
+    // Coefficients can be changed through the config command
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "config", "get", "urgency.blocking"])
+        .assert()
+        .success()
+        .stdout(contains("10"));
+}
+
+#[test]
+fn test_task_named_reports() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str(
+        r#"
+[report.bugs]
+description = "Open bugs"
+filter = "+bug status:pending"
+columns = ["id", "priority", "description"]
+sort = ["priority-"]
+widths = { description = 12 }
+"#,
+    );
+    fs::write(&config_path, config).unwrap();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec![
+            "task",
+            "add",
+            "Fix login",
+            "+bug",
+            "priority:L",
+            "due:yesterday",
+        ],
+        vec!["task", "add", "Fix checkout flow", "+bug", "priority:H"],
+        vec!["task", "add", "Plan sprint", "due:+3d"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "overdue"])
+        .assert()
+        .success()
+        .stdout(
+            contains("Fix login")
+                .and(contains("1d ago"))
+                .and(contains("Plan sprint").not()),
+        );
+
+    // Custom report: its own filter, sort order and column widths
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "bugs"])
+        .assert()
+        .success()
+        .stdout(
+            contains("ID Pri Description\n 2 H   Fix checkou…\n 1 L   Fix login")
+                .and(contains("Plan sprint").not()),
+        );
+
+    // A command-line filter narrows the report; --limit caps it
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "next", "--limit", "1", "-bug"])
+        .assert()
+        .success()
+        .stdout(contains("Plan sprint").and(contains("1 of 1").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "next", "--limit", "2"])
+        .assert()
+        .success()
+        .stdout(contains("2 of 3 tasks shown"));
+
+    // Narrow terminals squeeze the description first
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env("COLUMNS", "30")
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::function(|out: &str| {
+            out.lines().all(|line| line.chars().count() <= 30)
+        }));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "reports"]).assert().success().stdout(
+        contains("bugs")
+            .and(contains("Open bugs"))
+            .and(contains("waiting")),
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "missing"])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown report"));
+}
+
+#[test]
+fn test_task_wait_and_until_lifecycle() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Chase invoice", "wait:tomorrow"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Status: waiting"));
+
+    // Waiting tasks stay out of the next report
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "next"])
+        .assert()
+        .success()
+        .stdout(contains("No tasks found."));
+
+    // Clearing the wait date brings the task back
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "wait:"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "status:pending"])
+        .assert()
+        .success()
+        .stdout(contains("Chase invoice"));
+
+    // A task past its until date is deleted on the next command
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Use voucher", "until:yesterday"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "2"])
+        .assert()
+        .success()
+        .stdout(contains("Status: deleted"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "history", "2"])
+        .assert()
+        .success()
+        .stdout(contains(r#"status: "Pending" -> "Deleted""#));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "cleanup"])
+        .assert()
+        .success()
+        .stdout(contains("Woke 0 waiting tasks").and(contains("Expired 0 tasks")));
+}
+
+#[test]
+fn test_task_contexts() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec!["task", "add", "Ship release", "project:work"],
+        vec!["task", "add", "Fix the roof", "+urgent"],
+        vec!["task", "add", "Water plants", "project:home"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "define", "work", "project:work or +urgent"])
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "define", "home", "project:home"])
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "missing"]).assert().failure();
+
+    // The active context narrows listings and reports
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "work"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"]).assert().success().stdout(
+        contains("Ship release")
+            .and(contains("Fix the roof"))
+            .and(contains("Water plants").not()),
+    );
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "report", "next", "+urgent"])
+        .assert()
+        .success()
+        .stdout(contains("Fix the roof").and(contains("Ship release").not()));
+
+    // Overridden per command, or per agent through the environment
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "--context", "none"])
+        .assert()
+        .success()
+        .stdout(contains("Water plants").and(contains("Ship release")));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env("EDDA_CONTEXT", "home")
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(contains("Water plants").and(contains("Ship release").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "list"])
+        .assert()
+        .success()
+        .stdout(contains("* work").and(contains("home")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "none"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(contains("Water plants").and(contains("Ship release")));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["context", "show"])
+        .assert()
+        .success()
+        .stdout(contains("No context is active"));
+}
+
+#[test]
+fn test_project_hierarchy_and_summary() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec!["task", "add", "Fix login", "project:web.auth", "effort:2h"],
+        vec!["task", "add", "Reset password", "project:web.auth.reset"],
+        vec!["task", "add", "Write copy", "project:web", "due:yesterday"],
+        vec!["task", "add", "Tune queries", "project:webapp"],
+        vec!["task", "done", "2"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Bad", "project:web..auth"])
+        .assert()
+        .failure();
+
+    // `project:web` takes in its subprojects but not `webapp`
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "project:web"])
+        .assert()
+        .success()
+        .stdout(
+            contains("Fix login")
+                .and(contains("Write copy"))
+                .and(contains("Tune queries").not()),
+        );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["project", "list"]).assert().success().stdout(
+        contains("web ")
+            .and(contains("  auth "))
+            .and(contains("    reset")),
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["project", "summary"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "web                                  2         1      33%      2:00       1",
+        ));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "project", "summary", "project:web.auth"])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#""name": "web.auth""#)
+                .and(contains(r#""completion": 50.0"#))
+                .and(contains(r#""remaining_minutes": 120"#))
+                .and(contains("webapp").not()),
+        );
+}
+
+#[test]
+fn test_task_virtual_tags() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec!["task", "add", "Renew certificate", "due:yesterday", "+ops"],
+        vec!["task", "add", "Rotate keys", "depends:1"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "+OVERDUE"])
+        .assert()
+        .success()
+        .stdout(contains("Renew certificate").and(contains("Rotate keys").not()));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "-BLOCKED"])
+        .assert()
+        .success()
+        .stdout(contains("Renew certificate").and(contains("Rotate keys").not()));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Virtual tags: BLOCKING OVERDUE"));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "get", "2"])
+        .assert()
+        .success()
+        .stdout(contains(
+            r#""virtual_tags": [
+    "BLOCKED",
+    "UNTAGGED"
+  ]"#,
+        ));
+
+    // Virtual tags can't be stored
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "2", "+OVERDUE"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_task_subtasks() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec!["task", "add", "Release", "effort:1h"],
+        vec!["task", "add", "Build", "--parent", "1", "effort:30"],
+        vec!["task", "add", "Docs", "--parent", "1", "effort:45"],
+        vec!["task", "add", "Changelog", "--parent", "3", "effort:15"],
+        vec!["task", "add", "Unrelated"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "tree", "1"]).assert().success().stdout(
+        contains("1 Release [pending] effort 2:30, spent 0:00, remaining 2:30")
+            .and(contains("\n  3 Docs [pending] effort 1:00"))
+            .and(contains("\n    4 Changelog [pending]"))
+            .and(contains("Unrelated").not()),
+    );
+
+    // Subtasks are listed under their parent, indented
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "--sort", "description+"])
+        .assert()
+        .success()
+        .stdout(contains("Release\n").and(contains("    Changelog")));
+
+    // A task can't become a subtask of its own subtask
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "1", "parent:4"])
+        .assert()
+        .failure();
+
+    // Open subtasks block completing the parent unless forced
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "1"])
+        .assert()
+        .failure()
+        .stderr(contains("open subtasks: 2, 3"));
+
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str("\n[subtasks]\ncomplete_parent = true\n");
+    fs::write(&config_path, config).unwrap();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "done", "--yes", "1-4"])
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains("Status: completed"));
+
+    // Deleting a parent deletes its subtasks
+    for args in [
+        vec!["task", "add", "Cleanup"],
+        vec!["task", "add", "Step", "--parent", "6"],
+        vec!["task", "delete", "6"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "7"])
+        .assert()
+        .success()
+        .stdout(contains("Status: deleted"));
+}
+
+#[test]
+fn test_task_annotations() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    for args in [
+        vec!["task", "add", "Fix login"],
+        vec!["task", "annotate", "1", "Seen on staging"],
+        vec![
+            "task",
+            "annotate",
+            "1",
+            "Root cause in auth",
+            "--author",
+            "agent-a",
+        ],
+        vec!["task", "annotate", "1", "Stale note"],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "denotate", "1", "Stale"])
+        .assert()
+        .success()
+        .stdout(contains("Removed annotation from task 1: Stale note"));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "reannotate", "1", "1", "Seen on staging and prod"])
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "denotate", "1", "7"])
+        .assert()
+        .failure()
+        .stderr(contains("has no annotation 7"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "get", "1"]).assert().success().stdout(
+        contains("  Annotations:")
+            .and(contains("1. "))
+            .and(contains("UTC Seen on staging and prod"))
+            .and(contains("[agent-a] Root cause in auth"))
+            .and(contains("Stale").not()),
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "task", "get", "1"])
+        .assert()
+        .success()
+        .stdout(contains(r#""author": "agent-a""#));
+}
+
+#[test]
+fn test_task_claims() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    for description in ["Write tests", "Fix login", "Update docs"] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(["task", "add", description]).assert().success();
+    }
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "modify", "2", "priority:H"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "next"])
+        .assert()
+        .success()
+        .stdout(contains("Task 2: Fix login"));
+
+    // Agents claiming at the same time each get a different task
+    let claims: Vec<serde_json::Value> = std::thread::scope(|scope| {
+        let workers: Vec<_> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|agent| {
+                let config_path = &config_path;
+                scope.spawn(move || {
+                    let output = cli_with_config(config_path)
+                        .args(["task", "next", "--claim", "--agent", agent])
+                        .args(["--lease", "15m"])
+                        .output()
+                        .unwrap();
+                    assert!(output.status.success());
+                    serde_json::from_slice(&output.stdout).unwrap()
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+    let mut ids: Vec<i64> = claims.iter().filter_map(|c| c["id"].as_i64()).collect();
+    ids.sort();
+    assert_eq!(ids, [1, 2, 3]);
+    assert_eq!(claims.iter().filter(|c| c.is_null()).count(), 1);
+    let holder = claims
+        .iter()
+        .find(|c| c["id"] == 2)
+        .and_then(|c| c["claimed_by"].as_str())
+        .unwrap()
+        .to_string();
+    let other = if holder == "a" { "b" } else { "a" };
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "heartbeat", "2", "--agent", &holder])
+        .assert()
+        .success()
+        .stdout(contains(format!("Task 2 held by {holder} until")));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "release", "2", "--agent", other])
+        .assert()
+        .failure()
+        .stderr(contains(format!("Task 2 is not claimed by {other}")));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "release", "2", "--agent", &holder])
+        .assert()
+        .success()
+        .stdout(contains("Released task 2: Fix login"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "next", "--claim", "--agent", "e"])
+        .assert()
+        .success()
+        .stdout(contains(r#""claimed_by": "e""#).and(contains(r#""description": "Fix login""#)));
+}
+
+#[test]
+fn test_search() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    for args in [
+        vec!["task", "add", "Fix login redirect"],
+        vec!["task", "add", "Rotate API keys"],
+        vec![
+            "task",
+            "annotate",
+            "2",
+            "Logins fail when the token expires",
+        ],
+    ] {
+        let mut cmd = cli_with_config(&config_path);
+        cmd.args(args).assert().success();
+    }
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["search", "login"]).assert().success().stdout(
+        contains("task 1: Fix login redirect [pending]")
+            .and(contains("task 2: Rotate API keys"))
+            .and(contains("**Logins**")),
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["--format", "json", "search", "token", "expir*"])
+        .assert()
+        .success()
+        .stdout(contains(r#""entity": "task""#).and(contains(r#""id": 2"#)));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["search", "payroll"])
+        .assert()
+        .success()
+        .stdout(contains("No matches found."));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "description.matches:login"])
+        .assert()
+        .success()
+        .stdout(contains("Fix login redirect").and(contains("Rotate API keys").not()));
+}
+
+#[test]
+fn test_system_migrate() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Keep", "me", "+work"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "status"])
+        .assert()
+        .success()
+        .stdout(contains("Schema version: 11").and(contains("applied")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "down", "--to", "9", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(contains("Would revert 11:").and(contains("Would revert 10:")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "down", "--to", "9"])
+        .assert()
+        .success()
+        .stdout(contains("Backed up database to").and(contains("Reverted 10:")));
+    assert_eq!(
+        fs::read_dir(temp.path().join("backups")).unwrap().count(),
+        1
+    );
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "status"])
+        .assert()
+        .success()
+        .stdout(contains("Schema version: 9").and(contains("pending")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "up", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(contains("Would apply 10:").and(contains("Would apply 11:")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "up"])
+        .assert()
+        .success()
+        .stdout(contains("Applied 11:"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list", "+work"])
+        .assert()
+        .success()
+        .stdout(contains("Keep me"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "down", "--to", "0"])
+        .assert()
+        .failure()
+        .stderr(contains("can't be reverted"));
+}
+
+#[test]
+fn test_refuses_newer_database() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+
+    let db_path = temp.path().join("edda.db");
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", db_path.display()))
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO schema_version (version, applied_at, description) VALUES (999, datetime('now'), 'Future')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+    });
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"])
+        .assert()
+        .failure()
+        .stderr(contains("newer than this build of edda"));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "up"])
+        .assert()
+        .failure()
+        .stderr(contains("newer than this build of edda"));
+}
+
+#[test]
+fn test_system_backup_and_restore() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+    let backup_dir = temp.path().join("backups");
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "First task"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "backup"])
+        .assert()
+        .success()
+        .stdout(contains("Backed up database to"));
+
+    // Only the newest backup of the day is kept
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Second task"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "backup", "--compress"])
+        .assert()
+        .success()
+        .stdout(contains("Removed 1 expired backups"));
+    let backups: Vec<String> = fs::read_dir(&backup_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].ends_with(".db.gz"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "backup", "list"])
+        .assert()
+        .success()
+        .stdout(contains("regular").and(contains(backups[0].as_str())));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Third task"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "restore", backups[0].as_str()])
+        .assert()
+        .success()
+        .stdout(contains("Saved current database to").and(contains("Restored database from")));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(contains("Second task").and(contains("Third task").not()));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "backup", "list"])
+        .assert()
+        .success()
+        .stdout(contains("pre-restore"));
+
+    // A damaged backup is refused and the database left alone
+    fs::write(backup_dir.join("damaged.db"), "not a database").unwrap();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "restore", "damaged.db"])
+        .assert()
+        .failure();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(contains("Second task"));
+}
+
+#[test]
+fn test_encrypted_backups() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+    let backup_dir = temp.path().join("backups");
+    let key_file = temp.path().join("backup.key");
+    fs::write(&key_file, [42u8; 32]).unwrap();
+    let wrong_key_file = temp.path().join("wrong.key");
+    fs::write(&wrong_key_file, [7u8; 32]).unwrap();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Call", "Acme", "about", "invoice"])
+        .assert()
+        .success();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env_remove("EDDA_BACKUP_PASSPHRASE")
+        .env_remove("EDDA_BACKUP_KEY_FILE")
+        .args(["system", "backup", "--encrypt"])
+        .assert()
+        .failure()
+        .stderr(contains("Backup encryption needs a key"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env("EDDA_BACKUP_KEY_FILE", &key_file)
+        .args(["system", "backup", "--encrypt"])
+        .assert()
+        .success();
+    let backup = fs::read_dir(&backup_dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(backup.to_string_lossy().ends_with(".db.gz.enc"));
+    // Nothing unencrypted is left behind, in the backups directory or the
+    // private staging directory next to the database
+    assert_eq!(fs::read_dir(&backup_dir).unwrap().count(), 1);
+    assert!(fs::read_dir(temp.path()).unwrap().all(|entry| {
+        !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(".edda-backup-")
+    }));
+    let bytes = fs::read(&backup).unwrap();
+    assert!(bytes.starts_with(b"EDDAENC"));
+    assert!(!bytes.windows(4).any(|window| window == b"Acme"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Later", "task"])
+        .assert()
+        .success();
+
+    // The key is needed, and must be the right one
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env_remove("EDDA_BACKUP_PASSPHRASE")
+        .env_remove("EDDA_BACKUP_KEY_FILE")
+        .arg("system")
+        .arg("restore")
+        .arg(&backup)
+        .assert()
+        .failure()
+        .stderr(contains("EDDA_BACKUP_KEY_FILE"));
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env("EDDA_BACKUP_KEY_FILE", &wrong_key_file)
+        .arg("system")
+        .arg("restore")
+        .arg(&backup)
+        .assert()
+        .failure()
+        .stderr(contains("wrong key"));
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env("EDDA_BACKUP_KEY_FILE", &key_file)
+        .arg("system")
+        .arg("restore")
+        .arg(&backup)
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(contains("Acme").and(contains("Later task").not()));
+
+    // The safety copy taken before restoring is encrypted too
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "backup", "list"])
+        .assert()
+        .success()
+        .stdout(contains("encrypted   pre-restore"));
+}
+
+#[test]
+fn test_passphrase_encrypted_backup() {
+    let temp = TempDir::new().unwrap();
+    let config_path = create_config_file(temp.path());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["task", "add", "Renew", "contract"])
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env_remove("EDDA_BACKUP_KEY_FILE")
+        .env("EDDA_BACKUP_PASSPHRASE", "correct horse battery staple")
+        .args(["system", "backup", "--encrypt"])
+        .assert()
+        .success();
+    let backup = fs::read_dir(temp.path().join("backups"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env_remove("EDDA_BACKUP_KEY_FILE")
+        .env("EDDA_BACKUP_PASSPHRASE", "wrong")
+        .arg("system")
+        .arg("restore")
+        .arg(&backup)
+        .assert()
+        .failure();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.env_remove("EDDA_BACKUP_KEY_FILE")
+        .env("EDDA_BACKUP_PASSPHRASE", "correct horse battery staple")
+        .arg("system")
+        .arg("restore")
+        .arg(&backup)
+        .assert()
+        .success()
+        .stdout(contains("Restored database from"));
+}
+
+#[test]
+fn test_pre_migration_backups_in_data_dir() {
+    // The database lives outside the data directory
+    let temp = TempDir::new().unwrap();
+    let data_dir = temp.path().join("data");
+    let db_path = temp.path().join("db").join("edda.db");
+    let config_path = temp.path().join("edda.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "data_dir = \"{}\"\n\n[database]\nurl = \"sqlite:{}\"\n",
+            data_dir.to_string_lossy().replace('\\', "/"),
+            db_path.to_string_lossy().replace('\\', "/")
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "init"]).assert().success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "down", "--to", "9"])
+        .assert()
+        .success();
+    assert!(!temp.path().join("db").join("backups").exists());
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "backup", "list"])
+        .assert()
+        .success()
+        .stdout(contains("pre-migration-v11"));
+    let backup = fs::read_dir(data_dir.join("backups"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .file_name();
+
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "restore"])
+        .arg(&backup)
+        .assert()
+        .success();
+    let mut cmd = cli_with_config(&config_path);
+    cmd.args(["system", "migrate", "status"])
+        .assert()
+        .success()
+        .stdout(contains("Schema version: 11"));
+}